@@ -1,14 +1,22 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::rate_limit::RateLimiter;
 use super::{
     error::ApiError,
-    requests::{ApiRequest, StreamableApiRequest},
+    requests::{capabilities::ReadCapabilities, ApiRequest, StreamableApiRequest},
 };
 use bytes::Bytes;
+use colored::Colorize;
 use futures_core::stream::Stream;
+use rand::Rng;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
-    Client as ReqwestClient, Url,
+    Client as ReqwestClient, Method, RequestBuilder, Response, Url,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Debug, Display},
+    time::Duration,
 };
-use std::fmt::Debug;
 use tomb_crypt::prelude::{ApiToken, EcSignatureKey};
 use uuid::Uuid;
 
@@ -43,6 +51,96 @@ impl Credentials {
 
 /// The audience for the API token
 const AUDIENCE: &str = "banyan-platform";
+/// Default time allowed for establishing a connection
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default time allowed for a request which does not stream content to complete
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Default number of idle connections kept open per host for parallel uploads
+pub const DEFAULT_POOL_SIZE: usize = 8;
+/// Endpoint of the core service probed by health checks
+const CORE_HEALTH_PATH: &str = "/api/v1/auth/who_am_i";
+/// Endpoint of data hosts probed by health checks
+const DATA_HEALTH_PATH: &str = "/api/v1/client_grant";
+
+#[derive(Debug, Clone, PartialEq)]
+/// How idempotent requests are retried after connection errors and 5xx responses
+pub struct RetryPolicy {
+    /// Number of attempts made after the first one fails
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every retry after it
+    pub base_delay: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_delay: Duration,
+    /// Randomize each delay between half and all of its value, so that clients which failed
+    /// together do not retry together
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before retrying after the given number of failed retries
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if self.jitter {
+            delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Reachability of the remote services
+pub struct HealthStatus {
+    /// Whether or not the core service responded successfully
+    pub core_ok: bool,
+    /// Whether or not the data host responded successfully, if one was checked
+    pub data_ok: Option<bool>,
+    /// Round trip time of the core health check in milliseconds, if it responded
+    pub latency_ms: Option<u128>,
+}
+
+impl Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = |ok: bool| if ok { "OK".green() } else { "DOWN".red() };
+        let data_status = match self.data_ok {
+            Some(ok) => status(ok),
+            None => "UNKNOWN".yellow(),
+        };
+        let latency = match self.latency_ms {
+            Some(latency_ms) => format!("{latency_ms}ms"),
+            None => "-".to_string(),
+        };
+        f.write_fmt(format_args!(
+            "{}\ncore:\t\t{}\ndata:\t\t{}\nlatency:\t{}",
+            "| HEALTH INFO |".yellow(),
+            status(self.core_ok),
+            data_status,
+            latency
+        ))
+    }
+}
 
 #[derive(Debug, Clone)]
 /// Client for interacting with our API
@@ -55,6 +153,19 @@ pub struct Client {
     pub signing_key: Option<EcSignatureKey>,
     /// The current bearer token
     pub bearer_token: Option<String>,
+    /// Time allowed for establishing a connection
+    connect_timeout: Duration,
+    /// Time allowed for a request which does not stream content to complete
+    request_timeout: Duration,
+    /// Number of idle connections kept open per host
+    pool_size: usize,
+    /// How failed idempotent requests are retried
+    retry_policy: RetryPolicy,
+    /// Cap on how fast content is uploaded and downloaded, if any
+    #[cfg(not(target_arch = "wasm32"))]
+    rate_limit: Option<RateLimiter>,
+    /// Optional parts of the API each host has said it supports
+    capabilities: BTreeMap<String, BTreeSet<String>>,
     /// The reqwest client
     reqwest_client: ReqwestClient,
 }
@@ -66,21 +177,101 @@ impl Client {
     /// # Returns
     /// * `Self` - The client
     pub fn new(remote_core: &str) -> Result<Self, ApiError> {
-        let mut default_headers = HeaderMap::new();
-        default_headers.insert("Content-Type", HeaderValue::from_static("application/json"));
-        let reqwest_client = ReqwestClient::builder()
-            .default_headers(default_headers)
-            .build()?;
-
         Ok(Self {
             remote_core: Url::parse(remote_core)?,
             claims: None,
             signing_key: None,
             bearer_token: None,
-            reqwest_client,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            pool_size: DEFAULT_POOL_SIZE,
+            retry_policy: RetryPolicy::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            rate_limit: None,
+            capabilities: BTreeMap::new(),
+            reqwest_client: Self::build_reqwest_client(DEFAULT_CONNECT_TIMEOUT, DEFAULT_POOL_SIZE)?,
         })
     }
 
+    /// Set the timeouts used for every request. Uploads of content may take as long as they
+    /// need to once connected, as their size is up to the user, but fail once they go as long
+    /// as the request timeout without making progress.
+    /// # Arguments
+    /// * `connect` - Time allowed for establishing a connection
+    /// * `request` - Time allowed for a request which does not stream content to complete
+    pub fn with_timeouts(&mut self, connect: Duration, request: Duration) -> Result<(), ApiError> {
+        self.reqwest_client = Self::build_reqwest_client(connect, self.pool_size)?;
+        self.connect_timeout = connect;
+        self.request_timeout = request;
+        Ok(())
+    }
+
+    /// Get the time allowed for a request which does not stream content to complete, which is
+    /// also how long one which does may go without making progress
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout
+    }
+
+    /// Set the number of idle connections kept open per host
+    /// # Arguments
+    /// * `pool_size` - Number of connections
+    pub fn with_pool_size(&mut self, pool_size: usize) -> Result<(), ApiError> {
+        self.reqwest_client = Self::build_reqwest_client(self.connect_timeout, pool_size)?;
+        self.pool_size = pool_size;
+        Ok(())
+    }
+
+    /// Set how idempotent requests are retried after connection errors and 5xx responses
+    /// # Arguments
+    /// * `policy` - The retry policy, or `RetryPolicy::none()` to make a single attempt
+    pub fn with_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Cap how fast content is uploaded and downloaded. Every clone of this Client shares the
+    /// same budget.
+    /// # Arguments
+    /// * `bytes_per_sec` - Bytes allowed per second, or zero to remove the cap
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_rate_limit(&mut self, bytes_per_sec: u64) {
+        self.rate_limit = (bytes_per_sec > 0).then(|| RateLimiter::new(bytes_per_sec));
+    }
+
+    /// Get the cap on how fast content is transferred, if there is one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn rate_limit(&self) -> Option<RateLimiter> {
+        self.rate_limit.clone()
+    }
+
+    /// Build the underlying reqwest client
+    #[allow(unused_variables)]
+    fn build_reqwest_client(
+        connect_timeout: Duration,
+        pool_size: usize,
+    ) -> Result<ReqwestClient, ApiError> {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+        let builder = ReqwestClient::builder().default_headers(default_headers);
+        // Timeouts and pooling are left to the browser in WASM
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = builder
+            .connect_timeout(connect_timeout)
+            .pool_max_idle_per_host(pool_size);
+        Ok(builder.build()?)
+    }
+
+    /// Bound how long a request may take to complete. Only requests which do not stream content
+    /// are bounded, as the time content takes to transfer depends on its size.
+    #[allow(unused_mut)]
+    fn with_request_timeout(&self, mut request_builder: RequestBuilder) -> RequestBuilder {
+        // Timeouts are left to the browser in WASM
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            request_builder = request_builder.timeout(self.request_timeout);
+        }
+        request_builder
+    }
+
     /// Set a new remote endpoint
     /// # Arguments
     /// * `remote` - The base URL for the API
@@ -168,6 +359,60 @@ impl Client {
         }
     }
 
+    /// Probe the core service and, if provided, a data host. Each host is checked
+    /// independently so that one being down does not hide the other.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn health(&self, data_host: Option<&Url>) -> HealthStatus {
+        let start = std::time::Instant::now();
+        let core_ok = self.probe(&self.remote_core, CORE_HEALTH_PATH).await;
+        let latency_ms = core_ok.then(|| start.elapsed().as_millis());
+        let data_ok = match data_host {
+            Some(data_host) => Some(self.probe(data_host, DATA_HEALTH_PATH).await),
+            None => None,
+        };
+        HealthStatus {
+            core_ok,
+            data_ok,
+            latency_ms,
+        }
+    }
+
+    /// Whether or not a host answers an unauthenticated request to one of its endpoints. Even
+    /// a refusal shows that the host is up, but a server error does not.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn probe(&self, host: &Url, path: &str) -> bool {
+        let Ok(url) = host.join(path) else {
+            return false;
+        };
+        match self
+            .with_request_timeout(self.reqwest_client.get(url))
+            .send()
+            .await
+        {
+            Ok(response) => !response.status().is_server_error(),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether or not a host, or the core service if none is given, supports an optional part
+    /// of the API. Each host is only asked once, and hosts which can not say support nothing.
+    pub async fn supports(&mut self, host: Option<&Url>, capability: &str) -> bool {
+        let host = host.unwrap_or(&self.remote_core).clone();
+        if !self.capabilities.contains_key(host.as_str()) {
+            let capabilities = self
+                .call(ReadCapabilities {
+                    host_url: Some(host.clone()),
+                })
+                .await
+                .map(|response| response.capabilities)
+                .unwrap_or_default();
+            self.capabilities.insert(host.to_string(), capabilities);
+        }
+        self.capabilities
+            .get(host.as_str())
+            .is_some_and(|capabilities| capabilities.contains(capability))
+    }
+
     /// Call a method that implements ApiRequest on the core server
     pub async fn call<T: ApiRequest>(&mut self, request: T) -> Result<T::ResponseType, ApiError> {
         // Determine if this request requires authentication
@@ -180,7 +425,9 @@ impl Client {
         }
 
         // Send the request and obtain the response
-        let response = request_builder.send().await?;
+        let response = self
+            .send_with_retries(self.with_request_timeout(request_builder))
+            .await?;
 
         // If the call succeeded
         if response.status().is_success() {
@@ -190,6 +437,7 @@ impl Client {
                 .await
                 .map_err(ApiError::format)
         } else {
+            record_api_error(response.status());
             // If we got a 404
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Return a HTTP response error
@@ -215,11 +463,14 @@ impl Client {
             request_builder = request_builder.bearer_auth(bearer_token);
         }
 
-        let response = request_builder.send().await?;
+        let response = self
+            .send_with_retries(self.with_request_timeout(request_builder))
+            .await?;
 
         if response.status().is_success() {
             Ok(())
         } else {
+            record_api_error(response.status());
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Handle 404 specifically
                 // You can extend this part to handle other status codes differently if needed
@@ -235,6 +486,42 @@ impl Client {
         }
     }
 
+    /// Send a request, retrying it according to the retry policy if it is idempotent.
+    /// Requests which change state on every call, like pushing metadata, are only sent once.
+    async fn send_with_retries(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> Result<Response, ApiError> {
+        let request = request_builder.build()?;
+        let max_retries = if is_idempotent(request.method()) {
+            self.retry_policy.max_retries
+        } else {
+            0
+        };
+        let mut retry = 0;
+        loop {
+            // Streaming bodies can not be replayed, so they are only ever sent once
+            let attempt = match request.try_clone() {
+                Some(attempt) if retry < max_retries => attempt,
+                _ => return Ok(self.reqwest_client.execute(request).await?),
+            };
+            match self.reqwest_client.execute(attempt).await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Err(err) if !(err.is_request() || err.is_timeout()) => return Err(err.into()),
+                Ok(response) => {
+                    tracing::warn!(
+                        "retrying after {} from {}",
+                        response.status(),
+                        request.url()
+                    )
+                }
+                Err(err) => tracing::warn!("retrying after {err}"),
+            }
+            sleep(self.retry_policy.delay(retry)).await;
+            retry += 1;
+        }
+    }
+
     /// Call a multipart method that implements ApiRequest
     // #[cfg(not(target_arch = "wasm32"))]
     pub async fn multipart<T: ApiRequest>(
@@ -256,6 +543,7 @@ impl Client {
                 .await
                 .map_err(ApiError::format)
         } else {
+            record_api_error(response.status());
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Handle 404 specifically
                 // You can extend this part to handle other status codes differently if needed
@@ -286,6 +574,7 @@ impl Client {
         if response.status().is_success() {
             Ok(())
         } else {
+            record_api_error(response.status());
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Handle 404 specifically
                 // You can extend this part to handle other status codes differently if needed
@@ -317,6 +606,7 @@ impl Client {
         if response.status().is_success() {
             Ok(response.bytes_stream())
         } else {
+            record_api_error(response.status());
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Handle 404 specifically
                 // You can extend this part to handle other status codes differently if needed
@@ -331,6 +621,28 @@ impl Client {
     }
 }
 
+/// Whether or not sending a request more than once has the same effect as sending it once
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Wait before the next attempt of a request
+async fn sleep(delay: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::time::sleep(delay).await;
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(delay).await;
+}
+
+/// Count an unsuccessful response from the API
+fn record_api_error(_status: reqwest::StatusCode) {
+    #[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+    crate::native::metrics::api_error(_status.as_u16());
+}
+
 // #[cfg(not(target_arch = "wasm32"))]
 // fn multipart_headers(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
 //     // Don't do anything!
@@ -347,3 +659,217 @@ impl Client {
 //         .header("Content-Type", "");
 //     request
 // }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::{Client, RetryPolicy};
+    use crate::{
+        api::{
+            error::ApiError,
+            models::bucket::{BucketType, StorageClass},
+            requests::{
+                core::buckets::{
+                    create::CreateBucket, metadata::pull::PullMetadata, read::ReadAllBuckets,
+                },
+                staging::upload::content::UploadContent,
+            },
+        },
+        blockstore::CarV2MemoryBlockStore,
+        utils::testing::mock_server::{free_address, mock_server, MockResponse},
+    };
+    use futures_util::StreamExt;
+    use reqwest::Url;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use uuid::Uuid;
+
+    /// Spawn a server which answers every request with an empty 200
+    async fn healthy_server() -> Url {
+        let url = mock_server(|_| MockResponse::empty("200 OK")).await;
+        Url::parse(&url).expect("bad url")
+    }
+
+    /// Spawn a server which answers the first `failures` requests with a 503 and every one after
+    /// with an empty JSON list, counting the requests it receives
+    async fn flaky_server(failures: usize) -> (Url, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let url = mock_server(move |_| {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                MockResponse::empty("503 Service Unavailable")
+            } else {
+                MockResponse::ok("[]")
+            }
+        })
+        .await;
+        (Url::parse(&url).expect("bad url"), requests)
+    }
+
+    /// Find an address which nothing is listening on
+    async fn down_server() -> Url {
+        Url::parse(&format!("http://{}", free_address().await)).expect("bad url")
+    }
+
+    #[tokio::test]
+    async fn health_both_up() -> Result<(), ApiError> {
+        let core = healthy_server().await;
+        let data = healthy_server().await;
+        let client = Client::new(core.as_str())?;
+        let health = client.health(Some(&data)).await;
+        assert!(health.core_ok);
+        assert_eq!(health.data_ok, Some(true));
+        assert!(health.latency_ms.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn health_refused_is_up() -> Result<(), ApiError> {
+        // The core service refuses the unauthenticated probe, while the data host is failing
+        let core = mock_server(|_| MockResponse::empty("401 Unauthorized")).await;
+        let data = mock_server(|_| MockResponse::empty("503 Service Unavailable")).await;
+        let client = Client::new(&core)?;
+        let health = client
+            .health(Some(&Url::parse(&data).expect("bad url")))
+            .await;
+        assert!(health.core_ok);
+        assert_eq!(health.data_ok, Some(false));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn health_core_down() -> Result<(), ApiError> {
+        let core = down_server().await;
+        let data = healthy_server().await;
+        let client = Client::new(core.as_str())?;
+        let health = client.health(Some(&data)).await;
+        assert!(!health.core_ok);
+        assert_eq!(health.data_ok, Some(true));
+        assert!(health.latency_ms.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stalled_request_times_out() -> Result<(), ApiError> {
+        // A server which accepts connections but never responds
+        let url = mock_server(|_| MockResponse::stalled()).await;
+        let mut client = Client::new(&url)?;
+        client.with_bearer_token("token".to_string());
+        client.with_timeouts(Duration::from_secs(1), Duration::from_millis(200))?;
+        let err = client
+            .call(ReadAllBuckets)
+            .await
+            .expect_err("stalled request succeeded");
+        assert!(err.is_timeout());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stalled_upload_times_out() -> Result<(), ApiError> {
+        // A staging host which reads the whole upload but never responds
+        let url = mock_server(|_| MockResponse::stalled()).await;
+        let mut client = Client::new(&url)?;
+        client.with_bearer_token("token".to_string());
+        client.with_timeouts(Duration::from_secs(1), Duration::from_millis(200))?;
+        let store = CarV2MemoryBlockStore::new().expect("failed to create store");
+        let err = store
+            .upload(url, Uuid::new_v4(), &mut client)
+            .await
+            .expect_err("stalled upload succeeded");
+        assert!(err.is_timeout());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn slow_stream_does_not_time_out() -> Result<(), ApiError> {
+        // A server which trickles its response out for longer than the request timeout
+        let url =
+            mock_server(|_| MockResponse::ok("meow").trickled(Duration::from_millis(100))).await;
+        let url = Url::parse(&url).expect("bad url");
+        let mut client = Client::new(url.as_str())?;
+        client.with_bearer_token("token".to_string());
+        client.with_timeouts(Duration::from_secs(1), Duration::from_millis(200))?;
+        let request = PullMetadata {
+            id: Uuid::new_v4(),
+            bucket_id: Uuid::new_v4(),
+        };
+        let mut stream = Box::pin(client.stream(request, &url).await?);
+        let mut body = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        assert_eq!(body, b"meow");
+        Ok(())
+    }
+
+    #[test]
+    fn retry_delays() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            jitter: false,
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(300));
+        assert_eq!(policy.delay(40), Duration::from_millis(300));
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        for retry in 0..5 {
+            let delay = jittered.delay(retry);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(300));
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() -> Result<(), ApiError> {
+        let (url, requests) = flaky_server(2).await;
+        let mut client = Client::new(url.as_str())?;
+        client.with_bearer_token("token".to_string());
+        client.with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        });
+        let buckets = client.call(ReadAllBuckets).await?;
+        assert!(buckets.0.is_empty());
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        // Without retries the first failure is final
+        let (url, requests) = flaky_server(1).await;
+        let mut client = Client::new(url.as_str())?;
+        client.with_bearer_token("token".to_string());
+        client.with_retry_policy(RetryPolicy::none());
+        assert!(client.call(ReadAllBuckets).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_non_idempotent() -> Result<(), ApiError> {
+        let (url, requests) = flaky_server(1).await;
+        let mut client = Client::new(url.as_str())?;
+        client.with_bearer_token("token".to_string());
+        client.with_retry_policy(RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        });
+        let create = CreateBucket {
+            name: "bucket".to_string(),
+            r#type: BucketType::Interactive,
+            storage_class: StorageClass::Hot,
+            initial_bucket_key_pem: String::new(),
+        };
+        assert!(client.call(create).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
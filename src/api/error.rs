@@ -70,6 +70,47 @@ impl ApiError {
             kind: ApiErrorKind::MissingData(String::from(msg)),
         }
     }
+
+    /// Request did not complete in time
+    pub fn timeout(err: reqwest::Error) -> Self {
+        Self {
+            kind: ApiErrorKind::Timeout(err),
+        }
+    }
+
+    /// Streamed transfer made no progress for `idle`
+    pub fn stalled(idle: std::time::Duration) -> Self {
+        Self {
+            kind: ApiErrorKind::Stalled(idle),
+        }
+    }
+
+    /// Whether or not this error was caused by a timeout, and the request may be retried
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self.kind,
+            ApiErrorKind::Timeout(_) | ApiErrorKind::Stalled(_)
+        )
+    }
+
+    /// Whether or not the remote answered that the requested resource does not exist
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self.kind,
+            ApiErrorKind::HttpResponse(reqwest::StatusCode::NOT_FOUND)
+        )
+    }
+
+    /// Whether or not a connection to the remote could not be made at all, so that the request
+    /// was never received, as opposed to having failed partway or been answered
+    pub fn is_unreachable(&self) -> bool {
+        match &self.kind {
+            ApiErrorKind::Timeout(err)
+            | ApiErrorKind::ReqwestGeneral(err)
+            | ApiErrorKind::HttpClient(err) => err.is_connect(),
+            _ => false,
+        }
+    }
 }
 
 impl From<Box<dyn std::error::Error + Send + Sync + 'static>> for ApiError {
@@ -102,6 +143,13 @@ impl Display for ApiError {
             }
             ApiErrorKind::Parse(err) => format!("{} {err}", "PARSING ERROR:".underline()),
             ApiErrorKind::MissingData(msg) => format!("{} {msg}", "MISSING DATA:".underline()),
+            ApiErrorKind::Timeout(err) => format!("{} {err}", "TIMEOUT:".underline()),
+            ApiErrorKind::Stalled(idle) => {
+                format!(
+                    "{} no progress was made for {idle:?}",
+                    "TIMEOUT:".underline()
+                )
+            }
             #[cfg(test)]
             #[cfg(feature = "integration-tests")]
             ApiErrorKind::Filesystem(err) => format!("{} {err}", "FILESYSTEM ERROR:".underline()),
@@ -150,6 +198,10 @@ enum ApiErrorKind {
     Parse(ParseError),
     /// Missing data for performing a request
     MissingData(String),
+    /// Request did not complete in time
+    Timeout(reqwest::Error),
+    /// Streamed transfer made no progress for this long
+    Stalled(std::time::Duration),
     /// When we're performing integration tests we also want Filesystem Errors
     #[cfg(test)]
     #[cfg(feature = "integration-tests")]
@@ -164,7 +216,11 @@ impl From<TombCryptError> for ApiError {
 
 impl From<reqwest::Error> for ApiError {
     fn from(value: reqwest::Error) -> Self {
-        Self::reqwest_general(value)
+        if value.is_timeout() {
+            Self::timeout(value)
+        } else {
+            Self::reqwest_general(value)
+        }
     }
 }
 
@@ -0,0 +1,149 @@
+use super::{error::ApiError, rate_limit::RateLimiter};
+use bytes::Bytes;
+use futures_util::stream;
+use std::{
+    future::Future,
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Largest chunk content is read in when it is streamed
+const MAX_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Watches a streamed transfer, failing it once it has gone too long without making progress.
+/// Transfers of content may take as long as their size needs, so long as they keep moving.
+#[derive(Debug, Clone)]
+pub struct IdleTimeout {
+    /// Time allowed without progress
+    idle: Duration,
+    /// When the transfer last made progress
+    last_progress: Arc<Mutex<Instant>>,
+}
+
+impl IdleTimeout {
+    /// Fail transfers which make no progress for `idle`
+    pub fn new(idle: Duration) -> Self {
+        Self {
+            idle,
+            last_progress: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Record that the transfer made progress
+    fn touch(&self) {
+        *self.last_progress.lock().expect("idle timeout poisoned") = Instant::now();
+    }
+
+    /// When the transfer counts as stalled, unless it makes progress before then
+    fn deadline(&self) -> Instant {
+        *self.last_progress.lock().expect("idle timeout poisoned") + self.idle
+    }
+
+    fn is_idle(&self) -> bool {
+        Instant::now() >= self.deadline()
+    }
+
+    /// Stream the content of a reader as a request body, recording progress as each chunk is
+    /// sent and no faster than `rate_limit` allows, if given. Reads which take longer than the
+    /// idle timeout fail the body.
+    pub fn body<R>(&self, reader: R, rate_limit: Option<RateLimiter>) -> reqwest::Body
+    where
+        R: AsyncRead + Unpin + Send + Sync + 'static,
+    {
+        let chunk_size = rate_limit.as_ref().map_or(MAX_CHUNK_SIZE, |limiter| {
+            limiter.bytes_per_sec().min(MAX_CHUNK_SIZE)
+        }) as usize;
+        let chunks = stream::unfold(
+            (Some(reader), self.clone(), rate_limit),
+            move |(reader, idle, rate_limit)| async move {
+                let mut reader = reader?;
+                let mut chunk = vec![0; chunk_size];
+                let read = match tokio::time::timeout(idle.idle, reader.read(&mut chunk)).await {
+                    Ok(read) => read,
+                    Err(_) => Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "reading content timed out",
+                    )),
+                };
+                match read {
+                    Ok(0) => None,
+                    Ok(len) => {
+                        chunk.truncate(len);
+                        idle.touch();
+                        if let Some(limiter) = &rate_limit {
+                            limiter.acquire(len as u64).await;
+                            // Waiting on the rate limit is not a stall
+                            idle.touch();
+                        }
+                        Some((Ok(Bytes::from(chunk)), (Some(reader), idle, rate_limit)))
+                    }
+                    // Nothing more is read after an error
+                    Err(err) => Some((Err(err), (None, idle, rate_limit))),
+                }
+            },
+        );
+        reqwest::Body::wrap_stream(chunks)
+    }
+
+    /// Run a transfer whose body was made by [`IdleTimeout::body`], failing it with a timeout
+    /// once it has made no progress for the idle timeout. This covers both a host which stops
+    /// reading the body and one which never responds once it has read all of it.
+    pub async fn run<T>(
+        &self,
+        transfer: impl Future<Output = Result<T, ApiError>>,
+    ) -> Result<T, ApiError> {
+        self.touch();
+        tokio::pin!(transfer);
+        loop {
+            let deadline = tokio::time::Instant::from_std(self.deadline());
+            tokio::select! {
+                result = &mut transfer => {
+                    return match result {
+                        // Reads of the body which timed out surface as errors from the transfer
+                        Err(_) if self.is_idle() => Err(ApiError::stalled(self.idle)),
+                        result => result,
+                    };
+                }
+                _ = tokio::time::sleep_until(deadline) => {
+                    if self.is_idle() {
+                        return Err(ApiError::stalled(self.idle));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IdleTimeout;
+    use crate::api::error::ApiError;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn steady_transfer_does_not_time_out() -> Result<(), ApiError> {
+        let idle = IdleTimeout::new(Duration::from_millis(200));
+        // Takes longer than the idle timeout in all, but keeps making progress
+        let transfer = async {
+            for _ in 0..5 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                idle.touch();
+            }
+            Ok(())
+        };
+        idle.run(transfer).await
+    }
+
+    #[tokio::test]
+    async fn stalled_transfer_times_out() {
+        let idle = IdleTimeout::new(Duration::from_millis(200));
+        let transfer = futures_util::future::pending::<Result<(), ApiError>>();
+        let err = idle
+            .run(transfer)
+            .await
+            .expect_err("stalled transfer succeeded");
+        assert!(err.is_timeout());
+    }
+}
@@ -2,8 +2,14 @@
 pub mod client;
 /// Error
 pub(crate) mod error;
+/// Failing transfers which stop making progress
+#[cfg(not(target_arch = "wasm32"))]
+pub mod idle;
 /// Our API models, along with CRUD implementations
 pub mod models;
+/// Capping how fast content is transferred
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rate_limit;
 /// Our API request implementation
 pub mod requests;
 mod utils;
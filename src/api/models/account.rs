@@ -9,6 +9,7 @@ use crate::api::{
             who_am_i::read::{ReadWhoAmI, ReadWhoAmIResponse},
         },
         buckets::usage::{GetTotalUsage, GetUsageLimit},
+        users::current::{ReadCurrentUser, ReadCurrentUserResponse},
     },
     utils::generate_api_key,
 };
@@ -33,6 +34,36 @@ impl Display for Account {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Identity, plan and usage of an Account, gathered in one place
+pub struct AccountInfo {
+    /// The unique identifier for the account
+    pub id: uuid::Uuid,
+    /// Email address the account is registered with, if the server shares it
+    pub email: Option<String>,
+    /// Tier of the plan the account is on, if the server shares it
+    pub tier: Option<String>,
+    /// Total bytes stored by the account
+    pub usage: u64,
+    /// Maximum number of bytes the account may store
+    pub usage_limit: u64,
+}
+
+impl Display for AccountInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unknown = || format!("{}", "Unknown".red());
+        f.write_fmt(format_args!(
+            "{}\nuser_id:\t{}\nemail:\t\t{}\ntier:\t\t{}\nusage:\t\t{} bytes\nusage_limit:\t{} bytes",
+            "| ACCOUNT INFO |".yellow(),
+            self.id,
+            self.email.clone().unwrap_or_else(unknown),
+            self.tier.clone().unwrap_or_else(unknown),
+            self.usage,
+            self.usage_limit
+        ))
+    }
+}
+
 impl Account {
     /// Create a new instance of this model or data structure. Attaches the associated credentials to the client.
     pub async fn create_fake(client: &mut Client) -> Result<(Self, EcSignatureKey), ApiError> {
@@ -76,6 +107,91 @@ impl Account {
             .await
             .map(|response| response.size)
     }
+
+    /// Get the identity, plan and usage of the account associated with the current credentials
+    /// in the Client. Servers which don't share the user's profile, answering its request with a
+    /// 404, leave its email and tier out.
+    pub async fn read_current(client: &mut Client) -> Result<AccountInfo, ApiError> {
+        let Self { id } = Self::who_am_i(client).await?;
+        let (email, tier) = match client.call(ReadCurrentUser).await {
+            Ok(ReadCurrentUserResponse {
+                email,
+                account_tier,
+            }) => (email, account_tier),
+            Err(err) if err.is_not_found() => (None, None),
+            Err(err) => return Err(err),
+        };
+        Ok(AccountInfo {
+            id,
+            email,
+            tier,
+            usage: Self::usage(client).await?,
+            usage_limit: Self::usage_limit(client).await?,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod mock_test {
+    use super::Account;
+    use crate::{
+        api::{
+            client::{Client, RetryPolicy},
+            error::ApiError,
+        },
+        utils::testing::mock_server::{mock_server, MockResponse},
+    };
+    use uuid::Uuid;
+
+    /// Serve an account whose profile is answered with `profile`
+    async fn account_server(id: Uuid, profile: MockResponse) -> Client {
+        let url = mock_server(move |request| {
+            if request.is("GET", "/api/v1/auth/who_am_i") {
+                MockResponse::ok(format!(r#"{{"user_id":"{id}"}}"#))
+            } else if request.is("GET", "/api/v1/users/current") {
+                profile.clone()
+            } else {
+                MockResponse::ok(r#"{"size":10}"#)
+            }
+        })
+        .await;
+        let mut client = Client::new(&url).unwrap();
+        client.with_bearer_token("token".to_string());
+        client.with_retry_policy(RetryPolicy::none());
+        client
+    }
+
+    #[tokio::test]
+    async fn read_current_profile() -> Result<(), ApiError> {
+        let id = Uuid::new_v4();
+        let profile =
+            MockResponse::ok(r#"{"email":"kitty@banyan.computer","account_tier":"free"}"#);
+        let info = Account::read_current(&mut account_server(id, profile).await).await?;
+        assert_eq!(info.id, id);
+        assert_eq!(info.email.as_deref(), Some("kitty@banyan.computer"));
+        assert_eq!(info.tier.as_deref(), Some("free"));
+        assert_eq!(info.usage, 10);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_current_unshared_profile() -> Result<(), ApiError> {
+        let id = Uuid::new_v4();
+        let profile = MockResponse::empty("404 Not Found");
+        let info = Account::read_current(&mut account_server(id, profile).await).await?;
+        assert_eq!(info.id, id);
+        assert_eq!(info.email, None);
+        assert_eq!(info.tier, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_current_profile_failure() {
+        let profile = MockResponse::new("500 Internal Server Error", r#"{"msg":"boom"}"#);
+        let mut client = account_server(Uuid::new_v4(), profile).await;
+        assert!(Account::read_current(&mut client).await.is_err());
+    }
 }
 
 #[cfg(feature = "integration-tests")]
@@ -111,6 +227,17 @@ pub mod test {
         let _ = Account::who_am_i(&mut client).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn read_current() -> Result<(), ApiError> {
+        let mut client = authenticated_client().await;
+        let subject = uuid::Uuid::parse_str(&client.subject().unwrap()).unwrap();
+        let info = Account::read_current(&mut client).await?;
+        assert_eq!(info.id, subject);
+        assert_eq!(info.usage, 0);
+        assert_eq!(info.usage_limit, 50 * 1024 * 1024 * 1024);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn usage() -> Result<(), ApiError> {
         let mut client = authenticated_client().await;
@@ -2,34 +2,71 @@ use serde::{Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr};
 use uuid::Uuid;
 
-use crate::api::{
-    client::Client,
-    error::ApiError,
-    models::bucket_key::BucketKey,
-    requests::{
-        core::buckets::{
-            create::{CreateBucket, CreateBucketResponse},
-            delete::DeleteBucket,
-            read::{ReadAllBuckets, ReadAllBucketsResponse, ReadBucket, ReadBucketResponse},
-            snapshots::read::ReadAllSnapshots,
-            update::UpdateBucket,
-            usage::GetBucketUsage,
+use crate::{
+    api::{
+        client::Client,
+        error::ApiError,
+        models::bucket_key::BucketKey,
+        requests::{
+            core::buckets::{
+                create::{CreateBucket, CreateBucketResponse},
+                delete::DeleteBucket,
+                read::{ReadAllBuckets, ReadAllBucketsResponse, ReadBucket, ReadBucketResponse},
+                snapshots::read::ReadAllSnapshots,
+                storage_class::SetStorageClass,
+                update::UpdateBucket,
+                usage::GetBucketUsage,
+            },
+            staging::client_grant::authorization::AuthorizationGrants,
         },
-        staging::client_grant::authorization::AuthorizationGrants,
     },
+    filesystem::wnfsio::{CompressionCodec, DEFAULT_ZSTD_LEVEL},
 };
 
 use super::snapshot::Snapshot;
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 /// Possible types of Bucket
 pub enum BucketType {
-    /// A bucket for storing backups (Cold)
+    /// A bucket for storing backups (Cold). Archival data is written rarely and read
+    /// even less, so content goes to cold storage and is compressed before it is encrypted.
+    /// The types don't differ in chunk size, which WNFS fixes for all content, or in how
+    /// much history is kept, as every Bucket keeps previous versions of its files.
     Backup,
-    /// A bucket for storing interactive data (Hot)
+    /// A bucket for storing interactive data (Hot). Content is kept in hot storage
+    /// uncompressed so that it can be read back quickly.
+    #[default]
     Interactive,
 }
+
+impl BucketType {
+    /// The defaults a Bucket of this type is created with
+    pub fn defaults(&self) -> BucketDefaults {
+        match self {
+            BucketType::Backup => BucketDefaults {
+                storage_class: StorageClass::Cold,
+                compression: CompressionCodec::Zstd {
+                    level: DEFAULT_ZSTD_LEVEL,
+                },
+            },
+            BucketType::Interactive => BucketDefaults {
+                storage_class: StorageClass::Hot,
+                compression: CompressionCodec::None,
+            },
+        }
+    }
+}
+
+/// Defaults which a Bucket is created with, determined by its BucketType
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketDefaults {
+    /// Storage class the remote Bucket is created in
+    pub storage_class: StorageClass,
+    /// Codec file content is compressed with before it is encrypted, until another is chosen
+    /// when preparing
+    pub compression: CompressionCodec,
+}
 impl Display for BucketType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -92,6 +129,9 @@ pub struct Bucket {
     pub r#type: BucketType,
     /// The storage class of the bucket
     pub storage_class: StorageClass,
+    /// Whether the bucket is protected from modification
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 impl Display for Bucket {
@@ -126,6 +166,7 @@ impl Bucket {
                 name: response.name,
                 r#type: response.r#type,
                 storage_class: response.storage_class,
+                read_only: false,
             },
             BucketKey {
                 id: response.initial_bucket_key.id,
@@ -133,6 +174,7 @@ impl Bucket {
                 approved: response.initial_bucket_key.approved,
                 pem: initial_bucket_key_pem,
                 fingerprint: response.initial_bucket_key.fingerprint,
+                created_at: None,
             },
         ))
     }
@@ -148,6 +190,7 @@ impl Bucket {
                 name: bucket.name,
                 r#type: bucket.r#type,
                 storage_class: bucket.storage_class,
+                read_only: bucket.read_only,
             });
         }
         Ok(buckets)
@@ -161,6 +204,7 @@ impl Bucket {
             name: response.name,
             r#type: response.r#type,
             storage_class: response.storage_class,
+            read_only: response.read_only,
         })
     }
 
@@ -170,11 +214,27 @@ impl Bucket {
         let update_request = UpdateBucket {
             bucket_id: String::from(self.id),
             name: self.name.clone(),
+            read_only: self.read_only,
         };
 
         client.call_no_content(update_request).await
     }
 
+    /// Transition the data of a bucket to another storage class. Reads from cold storage may be
+    /// slower, and data may need to be restored to a warmer class before it can be read.
+    pub async fn set_storage_class(
+        client: &mut Client,
+        bucket_id: Uuid,
+        storage_class: StorageClass,
+    ) -> Result<(), ApiError> {
+        client
+            .call_no_content(SetStorageClass {
+                bucket_id,
+                storage_class,
+            })
+            .await
+    }
+
     /// Get the snapshots for the bucket
     pub async fn list_snapshots(&self, client: &mut Client) -> Result<Vec<Snapshot>, ApiError> {
         let response = client.call(ReadAllSnapshots { bucket_id: self.id }).await?;
@@ -210,6 +270,17 @@ impl Bucket {
             .collect())
     }
 
+    /// List all snapshots for a bucket which were created after a timestamp
+    pub async fn list_snapshots_by_bucket_id_since(
+        client: &mut Client,
+        bucket_id: Uuid,
+        since: i64,
+    ) -> Result<Vec<Snapshot>, ApiError> {
+        let mut snapshots = Self::list_snapshots_by_bucket_id(client, bucket_id).await?;
+        snapshots.retain(|snapshot| snapshot.created_at > since);
+        Ok(snapshots)
+    }
+
     /// Get the usage for the bucket
     pub async fn usage(&self, client: &mut Client) -> Result<u64, ApiError> {
         client
@@ -287,6 +358,7 @@ pub mod test {
             name: "fake-bucket".to_string(),
             r#type: BucketType::Interactive,
             storage_class: StorageClass::Hot,
+            read_only: false,
         }
     }
     #[tokio::test]
@@ -390,3 +462,107 @@ pub mod test {
         Bucket::delete_by_id(&mut client, fake_id).await.unwrap();
     }
 }
+
+#[cfg(test)]
+mod defaults_test {
+    use super::{BucketType, StorageClass};
+    use crate::filesystem::wnfsio::CompressionCodec;
+
+    #[test]
+    fn backup_defaults_are_archival() {
+        let backup = BucketType::Backup.defaults();
+        let interactive = BucketType::Interactive.defaults();
+        assert_eq!(backup.storage_class, StorageClass::Cold);
+        assert!(matches!(backup.compression, CompressionCodec::Zstd { .. }));
+        // Interactive Buckets are still created in hot storage, uncompressed
+        assert_eq!(interactive.storage_class, StorageClass::Hot);
+        assert_eq!(interactive.compression, CompressionCodec::None);
+        assert_eq!(BucketType::default(), BucketType::Interactive);
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod mock_test {
+    use super::{Bucket, StorageClass};
+    use crate::{
+        api::{client::Client, error::ApiError},
+        utils::testing::mock_server::{mock_server, MockResponse},
+    };
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    /// Serve a single Bucket whose storage class can be changed
+    async fn bucket_server(id: Uuid) -> String {
+        let storage_class = Arc::new(Mutex::new("hot".to_string()));
+        mock_server(move |request| {
+            if request.is("PUT", "/") && request.request_line().contains("/storage_class") {
+                let request: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("bad request body");
+                *storage_class.lock().unwrap() =
+                    request["storage_class"].as_str().unwrap().to_string();
+                MockResponse::empty("200 OK")
+            } else {
+                MockResponse::ok(format!(
+                    r#"{{"id":"{id}","name":"kitty","type":"interactive","storage_class":"{}"}}"#,
+                    storage_class.lock().unwrap()
+                ))
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn set_storage_class() -> Result<(), ApiError> {
+        let id = Uuid::new_v4();
+        let mut client = Client::new(&bucket_server(id).await)?;
+        client.with_bearer_token("token".to_string());
+        assert_eq!(
+            Bucket::read(&mut client, id).await?.storage_class,
+            StorageClass::Hot
+        );
+        Bucket::set_storage_class(&mut client, id, StorageClass::Cold).await?;
+        assert_eq!(
+            Bucket::read(&mut client, id).await?.storage_class,
+            StorageClass::Cold
+        );
+        Ok(())
+    }
+
+    /// Serve a fixed list of snapshots with increasing creation times
+    async fn snapshots_server(metadata_id: Uuid) -> String {
+        mock_server(move |_| {
+            MockResponse::ok(format!(
+                "[{}]",
+                [100, 200, 300]
+                    .iter()
+                    .map(|created_at| format!(
+                        r#"{{"id":"{}","metadata_id":"{metadata_id}","size":1,"created_at":{created_at}}}"#,
+                        Uuid::new_v4()
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ))
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn list_snapshots_since() -> Result<(), ApiError> {
+        let mut client = Client::new(&snapshots_server(Uuid::new_v4()).await)?;
+        client.with_bearer_token("token".to_string());
+        let bucket_id = Uuid::new_v4();
+        assert_eq!(
+            Bucket::list_snapshots_by_bucket_id(&mut client, bucket_id)
+                .await?
+                .len(),
+            3
+        );
+        // Only snapshots created strictly after the timestamp are returned
+        let snapshots =
+            Bucket::list_snapshots_by_bucket_id_since(&mut client, bucket_id, 200).await?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].created_at, 300);
+        Ok(())
+    }
+}
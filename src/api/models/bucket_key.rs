@@ -28,6 +28,8 @@ pub struct BucketKey {
     pub fingerprint: String,
     /// Whether or not the Bucket Key has been approved
     pub approved: bool,
+    /// When the Bucket Key was created, if known
+    pub created_at: Option<i64>,
 }
 
 impl Display for BucketKey {
@@ -66,6 +68,7 @@ impl BucketKey {
             pem,
             fingerprint: response.fingerprint,
             approved: response.approved,
+            created_at: None,
         })
     }
 
@@ -81,6 +84,7 @@ impl BucketKey {
                 pem: key.pem,
                 fingerprint: key.fingerprint,
                 approved: key.approved,
+                created_at: key.created_at,
             });
         }
         Ok(bucket_keys)
@@ -95,6 +99,7 @@ impl BucketKey {
             pem: response.pem,
             fingerprint: response.fingerprint,
             approved: response.approved,
+            created_at: response.created_at,
         })
     }
 
@@ -280,6 +285,8 @@ mod test {
                 previous_cid: None,
                 valid_keys: vec![initial_bucket_key.fingerprint, bucket_key.fingerprint],
                 deleted_block_cids: BTreeSet::new(),
+                metadata_encrypted: true,
+                delta_base_cid: None,
                 metadata_stream: Body::from("metadata_stream"),
             },
             &mut client,
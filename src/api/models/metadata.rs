@@ -16,9 +16,10 @@ use {
                 push::PushMetadata,
                 read::{ReadAllMetadata, ReadCurrentMetadata, ReadMetadata},
             },
-            snapshots::create::CreateSnapshot,
+            snapshots::{create::CreateSnapshot, manifest::AttachManifest},
         },
     },
+    crate::filesystem::SignedManifest,
     bytes::Bytes,
     futures_core::stream::Stream,
 };
@@ -75,6 +76,8 @@ pub struct Metadata {
     pub state: MetadataState,
     /// The snapshot id of the metadata (if any)
     pub snapshot_id: Option<Uuid>,
+    /// The timestamp when the metadata was created
+    pub created_at: i64,
 }
 
 impl Display for Metadata {
@@ -92,11 +95,28 @@ impl Display for Metadata {
 
 impl Metadata {
     // TODO: This should probably take a generic trait related to Tomb in order to restore these arguments
-    /// Push new Metadata for a bucket. Creates a new metadata records and returns a storage ticket
+    /// Push new Metadata for a bucket. Creates a new metadata records and returns a storage ticket.
+    /// If the same metadata was already pushed, as when a sync is interrupted before its content
+    /// finished uploading, the existing record is returned without a storage ticket instead.
     pub async fn push(
         push_metadata: PushMetadata,
         client: &mut Client,
     ) -> Result<(Self, Option<String>, Option<String>), ApiError> {
+        if let Some(existing) = Self::read_all(push_metadata.bucket_id, client)
+            .await?
+            .into_iter()
+            .find(|metadata| {
+                metadata.metadata_cid == push_metadata.metadata_cid
+                    && metadata.root_cid == push_metadata.root_cid
+                    && !matches!(
+                        metadata.state,
+                        MetadataState::Outdated | MetadataState::Deleted
+                    )
+            })
+        {
+            return Ok((existing, None, None));
+        }
+
         let mut metadata = Self {
             id: Uuid::default(),
             bucket_id: push_metadata.bucket_id,
@@ -106,6 +126,7 @@ impl Metadata {
             data_size: 0,
             state: MetadataState::UploadFailed,
             snapshot_id: None,
+            created_at: chrono::Utc::now().timestamp(),
         };
 
         let response = client.multipart(push_metadata).await?;
@@ -152,6 +173,17 @@ impl Metadata {
             .collect())
     }
 
+    /// Read all the metadata for a bucket which was created after a timestamp
+    pub async fn read_all_since(
+        bucket_id: Uuid,
+        since: i64,
+        client: &mut Client,
+    ) -> Result<Vec<Self>, ApiError> {
+        let mut metadatas = Self::read_all(bucket_id, client).await?;
+        metadatas.retain(|metadata| metadata.created_at > since);
+        Ok(metadatas)
+    }
+
     /// Read the current metadata for a bucket
     pub async fn read_current(bucket_id: Uuid, client: &mut Client) -> Result<Self, ApiError> {
         let response = client.call(ReadCurrentMetadata { bucket_id }).await?;
@@ -175,6 +207,22 @@ impl Metadata {
         Ok(snapshot_resp.id)
     }
 
+    /// Attach the signed manifest of what a snapshot of this Metadata contains to it
+    pub async fn attach_manifest(
+        &self,
+        snapshot_id: Uuid,
+        manifest: &SignedManifest,
+        client: &mut Client,
+    ) -> Result<(), ApiError> {
+        client
+            .call_no_content(AttachManifest {
+                bucket_id: self.bucket_id,
+                snapshot_id,
+                manifest: manifest.clone(),
+            })
+            .await
+    }
+
     /// Given a bucket id and a ReadMetadataResponse, create a new Metadata object
     fn from_read_response(bucket_id: Uuid, response: ReadMetadataResponse) -> Self {
         Self {
@@ -186,6 +234,7 @@ impl Metadata {
             data_size: response.data_size as u64,
             state: response.state,
             snapshot_id: response.snapshot_id,
+            created_at: response.created_at,
         }
     }
 }
@@ -233,6 +282,8 @@ pub(crate) mod test {
                 previous_cid: None,
                 valid_keys: vec![],
                 deleted_block_cids: BTreeSet::new(),
+                metadata_encrypted: true,
+                delta_base_cid: None,
                 metadata_stream: Body::from("metadata_stream".as_bytes()),
             },
             client,
@@ -318,6 +369,8 @@ pub(crate) mod test {
                 previous_cid: None,
                 valid_keys: fs.share_manager.public_fingerprints(),
                 deleted_block_cids: BTreeSet::new(),
+                metadata_encrypted: true,
+                delta_base_cid: None,
                 metadata_stream: content_store.get_data().into(),
             },
             &mut client,
@@ -409,4 +462,54 @@ pub(crate) mod test {
             .await?;
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn push_twice() -> Result<(), ApiError> {
+        // Push the metadata, then crash before uploading any content
+        let mut setup = setup_and_push_metadata("push_twice").await?;
+        assert_eq!(setup.metadata.state, MetadataState::Pending);
+
+        // Syncing again finds the metadata that was already pushed
+        let (metadata, host, authorization) = Metadata::push(
+            PushMetadata {
+                bucket_id: setup.bucket.id,
+                expected_data_size: setup.content_store.data_size(),
+                root_cid: setup.metadata.root_cid.clone(),
+                metadata_cid: setup.metadata.metadata_cid.clone(),
+                previous_cid: None,
+                valid_keys: setup.fs.share_manager.public_fingerprints(),
+                deleted_block_cids: BTreeSet::new(),
+                metadata_encrypted: true,
+                delta_base_cid: None,
+                metadata_stream: setup.content_store.get_data().into(),
+            },
+            &mut setup.client,
+        )
+        .await?;
+        assert_eq!(metadata.id, setup.metadata.id);
+        assert_eq!(host, None);
+        assert_eq!(authorization, None);
+        let all_metadata = Metadata::read_all(setup.bucket.id, &mut setup.client).await?;
+        assert_eq!(all_metadata.len(), 1);
+
+        // The content can still be uploaded against the original storage ticket
+        setup
+            .storage_ticket
+            .clone()
+            .create_grant(&mut setup.client)
+            .await?;
+        setup
+            .content_store
+            .upload(
+                setup.storage_ticket.host.clone(),
+                metadata.id,
+                &mut setup.client,
+            )
+            .await?;
+        std::thread::sleep(Duration::new(3, 0));
+        let current = Metadata::read_current(setup.bucket.id, &mut setup.client).await?;
+        assert_eq!(current.id, setup.metadata.id);
+        Ok(())
+    }
 }
@@ -0,0 +1,77 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Token bucket capping the rate content is transferred at. Clones share the same bucket, so
+/// every clone of a Client draws from one budget, however many transfers it runs at once.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Bytes allowed per second
+    bytes_per_sec: u64,
+    /// Bytes which may be transferred right away, negative when transfers are owed for
+    state: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `bytes_per_sec` bytes per second, with bursts of at most one second's worth
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            state: Arc::new(Mutex::new(Bucket {
+                tokens: bytes_per_sec as f64,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// Get the number of bytes allowed per second
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Wait until `bytes` more may be transferred
+    pub async fn acquire(&self, bytes: u64) {
+        let rate = self.bytes_per_sec as f64;
+        let wait = {
+            let mut bucket = self.state.lock().expect("rate limiter poisoned");
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * rate;
+            bucket.tokens = (bucket.tokens + refill).min(rate) - bytes as f64;
+            bucket.updated = now;
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn acquire() {
+        let limiter = RateLimiter::new(1000);
+        // A second's worth can be sent straight away
+        let start = Instant::now();
+        limiter.acquire(1000).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+        // Anything more waits for the bucket to refill, even through a clone
+        limiter.clone().acquire(250).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -2,6 +2,8 @@ use reqwest::{Client, RequestBuilder, Url};
 use serde::de::DeserializeOwned;
 use std::{error::Error, fmt::Debug};
 
+/// Request for the optional parts of the API a host supports
+pub mod capabilities;
 /// API Request implementations for routes managed by the core service
 pub mod core;
 /// API Request implementations for managed by a storage host
@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+
+use crate::api::requests::ApiRequest;
+
+/// Staging hosts which can report how much of a content upload they received, and accept the
+/// rest of it starting from that offset
+pub const RESUMABLE_UPLOAD: &str = "resumable_upload";
+/// Core services which accept pushed metadata as just the blocks missing from a version they
+/// already hold
+pub const METADATA_DELTA: &str = "metadata_delta";
+/// Core services which keep the signed manifest of a snapshot alongside it
+pub const SNAPSHOT_MANIFEST: &str = "snapshot_manifest";
+
+/// Ask a host which optional parts of the API it supports. Hosts which predate this request
+/// answer it with a 404, and support none of them.
+#[derive(Debug)]
+pub struct ReadCapabilities {
+    /// Host to ask, or the core service if unset
+    pub host_url: Option<Url>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadCapabilitiesResponse {
+    pub capabilities: BTreeSet<String>,
+}
+
+impl ApiRequest for ReadCapabilities {
+    type ResponseType = ReadCapabilitiesResponse;
+    type ErrorType = ReadCapabilitiesError;
+
+    fn build_request(self, base_url: &Url, client: &Client) -> RequestBuilder {
+        let mut full_url = self.host_url.unwrap_or_else(|| base_url.clone());
+        full_url.set_path("/api/v1/capabilities");
+        client.get(full_url)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct ReadCapabilitiesError {
+    #[serde(rename = "msg")]
+    message: String,
+}
+
+impl Display for ReadCapabilitiesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message.as_ref())
+    }
+}
+
+impl Error for ReadCapabilitiesError {}
@@ -3,3 +3,5 @@ pub mod auth;
 pub mod blocks;
 /// Bucket requests for the Banyan Core API.
 pub mod buckets;
+/// User requests for the Banyan Core API.
+pub mod users;
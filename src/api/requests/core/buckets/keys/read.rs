@@ -24,6 +24,8 @@ pub struct ReadBucketKeyResponse {
     pub approved: bool,
     pub pem: String,
     pub fingerprint: String,
+    #[serde(default)]
+    pub created_at: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
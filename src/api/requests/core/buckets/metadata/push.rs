@@ -26,6 +26,8 @@ pub struct PushMetadata {
     pub previous_cid: Option<String>,
     pub valid_keys: Vec<String>,
     pub deleted_block_cids: BTreeSet<String>,
+    pub metadata_encrypted: bool,
+    pub delta_base_cid: Option<String>,
 
     pub metadata_stream: MetadataStreamType,
 }
@@ -38,6 +40,10 @@ struct PushMetadataData {
     pub previous_cid: Option<String>,
     pub valid_keys: Vec<String>,
     pub deleted_block_cids: BTreeSet<String>,
+    pub metadata_encrypted: bool,
+    /// Only set for services which support `METADATA_DELTA`, so no other sees the field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_base_cid: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,6 +71,8 @@ impl ApiRequest for PushMetadata {
             previous_cid: self.previous_cid,
             valid_keys: self.valid_keys,
             deleted_block_cids: self.deleted_block_cids,
+            metadata_encrypted: self.metadata_encrypted,
+            delta_base_cid: self.delta_base_cid,
         };
 
         // Attach the form data to the request as json
@@ -119,6 +127,8 @@ impl ApiRequest for PushMetadata {
             previous_cid: self.previous_cid,
             valid_keys: self.valid_keys,
             deleted_block_cids: self.deleted_block_cids,
+            metadata_encrypted: self.metadata_encrypted,
+            delta_base_cid: self.delta_base_cid,
         };
 
         // Serialize JSON part
@@ -177,3 +187,25 @@ impl Display for PushMetadataError {
 }
 
 impl Error for PushMetadataError {}
+
+#[cfg(test)]
+mod test {
+    use super::PushMetadataData;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn metadata_encrypted_is_sent() {
+        let data = PushMetadataData {
+            expected_data_size: 0,
+            root_cid: String::from("root_cid"),
+            metadata_cid: String::from("metadata_cid"),
+            previous_cid: None,
+            valid_keys: vec![],
+            deleted_block_cids: BTreeSet::new(),
+            metadata_encrypted: true,
+            delta_base_cid: None,
+        };
+        let json = serde_json::to_value(&data).expect("serialize");
+        assert_eq!(json["metadata_encrypted"], serde_json::Value::Bool(true));
+    }
+}
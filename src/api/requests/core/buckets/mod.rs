@@ -5,5 +5,6 @@ pub mod snapshots;
 pub mod create;
 pub mod delete;
 pub mod read;
+pub mod storage_class;
 pub mod update;
 pub mod usage;
@@ -23,6 +23,8 @@ pub struct ReadBucketResponse {
     pub name: String,
     pub r#type: BucketType,
     pub storage_class: StorageClass,
+    #[serde(default)]
+    pub read_only: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{api::requests::ApiRequest, filesystem::SignedManifest};
+
+/// Attach a signed manifest of what a snapshot contains to it, so that anyone with access to
+/// the snapshot can verify it. Only sent to hosts which support
+/// [`SNAPSHOT_MANIFEST`](crate::api::requests::capabilities::SNAPSHOT_MANIFEST).
+#[derive(Debug, Serialize)]
+pub struct AttachManifest {
+    pub bucket_id: Uuid,
+    pub snapshot_id: Uuid,
+    pub manifest: SignedManifest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachManifestResponse {}
+
+impl ApiRequest for AttachManifest {
+    type ResponseType = AttachManifestResponse;
+    type ErrorType = AttachManifestError;
+
+    fn build_request(self, base_url: &Url, client: &Client) -> RequestBuilder {
+        let path = format!(
+            "/api/v1/buckets/{}/snapshots/{}/manifest",
+            self.bucket_id, self.snapshot_id
+        );
+        let full_url = base_url.join(&path).unwrap();
+        client.put(full_url).json(&self.manifest)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachManifestError {
+    msg: String,
+}
+
+impl Display for AttachManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Error for AttachManifestError {}
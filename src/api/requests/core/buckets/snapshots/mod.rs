@@ -1,3 +1,4 @@
 pub mod create;
+pub mod manifest;
 pub mod read;
 pub mod restore;
@@ -0,0 +1,50 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::{models::bucket::StorageClass, requests::ApiRequest};
+
+/// Transition the data of a Bucket to another storage class
+#[derive(Debug, Serialize)]
+pub struct SetStorageClass {
+    #[serde(skip)]
+    pub bucket_id: Uuid,
+    pub storage_class: StorageClass,
+}
+
+#[derive(Deserialize)]
+pub struct SetStorageClassResponse;
+
+impl ApiRequest for SetStorageClass {
+    type ResponseType = SetStorageClassResponse;
+    type ErrorType = SetStorageClassError;
+
+    fn build_request(self, base_url: &Url, client: &Client) -> RequestBuilder {
+        let url = base_url
+            .join(&format!("/api/v1/buckets/{}/storage_class", self.bucket_id))
+            .unwrap();
+
+        client.put(url).json(&self)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct SetStorageClassError {
+    msg: String,
+}
+
+impl Display for SetStorageClassError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl Error for SetStorageClassError {}
@@ -11,6 +11,7 @@ pub struct UpdateBucket {
     #[serde(skip)]
     pub bucket_id: String,
     pub name: String,
+    pub read_only: bool,
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,44 @@
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::api::requests::ApiRequest;
+
+#[derive(Debug)]
+pub struct ReadCurrentUser;
+
+#[derive(Debug, Deserialize)]
+pub struct ReadCurrentUserResponse {
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub account_tier: Option<String>,
+}
+
+impl ApiRequest for ReadCurrentUser {
+    type ErrorType = ReadCurrentUserError;
+    type ResponseType = ReadCurrentUserResponse;
+
+    fn build_request(self, base_url: &Url, client: &Client) -> RequestBuilder {
+        let mut full_url = base_url.clone();
+        full_url.set_path("/api/v1/users/current");
+        client.get(full_url)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadCurrentUserError {
+    msg: String,
+}
+
+impl Error for ReadCurrentUserError {}
+
+impl std::fmt::Display for ReadCurrentUserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
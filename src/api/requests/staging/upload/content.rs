@@ -2,6 +2,8 @@ use async_trait::async_trait;
 use uuid::Uuid;
 
 use super::push::PushContent;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::api::{idle::IdleTimeout, rate_limit::RateLimiter};
 use crate::{
     api::{client::Client, error::ApiError},
     blockstore::CarV2MemoryBlockStore,
@@ -17,7 +19,17 @@ pub trait UploadContent {
     type UploadError: From<ApiError>;
 
     fn get_hash(&self) -> Result<String, Self::UploadError>;
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError>;
+    /// The content, starting `offset` bytes in
+    async fn get_body(&self, offset: u64) -> Result<ContentType, Self::UploadError>;
+    /// The content, starting `offset` bytes in, streamed through `idle` so that the upload fails
+    /// if it stalls, and sent no faster than `rate_limit` allows, if given
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get_streamed_body(
+        &self,
+        offset: u64,
+        rate_limit: Option<RateLimiter>,
+        idle: &IdleTimeout,
+    ) -> Result<ContentType, Self::UploadError>;
     fn get_length(&self) -> Result<u64, Self::UploadError>;
 
     async fn upload(
@@ -26,18 +38,46 @@ pub trait UploadContent {
         metadata_id: Uuid,
         client: &mut Client,
     ) -> Result<(), Self::UploadError> {
+        self.upload_from(host_url, metadata_id, 0, client).await
+    }
+
+    /// Upload the content from `offset` onwards, continuing an upload of which the staging
+    /// service already received the first `offset` bytes
+    async fn upload_from(
+        &self,
+        host_url: String,
+        metadata_id: Uuid,
+        offset: u64,
+        client: &mut Client,
+    ) -> Result<(), Self::UploadError> {
+        let offset = offset.min(self.get_length()?);
+        #[cfg(not(target_arch = "wasm32"))]
+        let idle = IdleTimeout::new(client.request_timeout());
+        #[cfg(not(target_arch = "wasm32"))]
+        let content = self
+            .get_streamed_body(offset, client.rate_limit(), &idle)
+            .await?;
+        #[cfg(target_arch = "wasm32")]
+        let content = self.get_body(offset).await?;
         let push_content = PushContent {
             host_url,
             metadata_id,
-            content: self.get_body().await?,
-            content_len: self.get_length()?,
+            content,
+            content_len: self.get_length()? - offset,
             content_hash: self.get_hash()?,
+            offset,
         };
 
-        client
-            .multipart_no_content(push_content)
-            .await
-            .map_err(|err| err.into())
+        #[cfg(not(target_arch = "wasm32"))]
+        idle.run(client.multipart_no_content(push_content)).await?;
+        #[cfg(target_arch = "wasm32")]
+        client.multipart_no_content(push_content).await?;
+        #[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+        crate::native::metrics::bytes_transferred(
+            crate::native::metrics::Direction::Upload,
+            self.get_length()? - offset,
+        );
+        Ok(())
     }
 }
 
@@ -52,12 +92,27 @@ impl UploadContent for CarV2MemoryBlockStore {
         Ok(hasher.finalize().to_string())
     }
 
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError> {
+    async fn get_body(&self, offset: u64) -> Result<ContentType, Self::UploadError> {
+        let mut data = self.get_data();
+        data.drain(..(offset as usize).min(data.len()));
+
         #[cfg(target_arch = "wasm32")]
-        return Ok(std::io::Cursor::new(self.get_data()));
+        return Ok(std::io::Cursor::new(data));
 
         #[cfg(not(target_arch = "wasm32"))]
-        return Ok(self.get_data().into());
+        return Ok(data.into());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn get_streamed_body(
+        &self,
+        offset: u64,
+        rate_limit: Option<RateLimiter>,
+        idle: &IdleTimeout,
+    ) -> Result<ContentType, Self::UploadError> {
+        let mut data = self.get_data();
+        data.drain(..(offset as usize).min(data.len()));
+        Ok(idle.body(std::io::Cursor::new(data), rate_limit))
     }
 
     fn get_length(&self) -> Result<u64, Self::UploadError> {
@@ -93,3 +148,93 @@ mod test {
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod mock_test {
+    use super::UploadContent;
+    use crate::{
+        api::{
+            client::Client,
+            error::ApiError,
+            requests::{capabilities::RESUMABLE_UPLOAD, staging::upload::progress::UploadProgress},
+        },
+        blockstore::{BanyanBlockStore, CarV2MemoryBlockStore},
+        utils::testing::mock_server::{mock_server, MockResponse},
+    };
+    use reqwest::Url;
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+    use wnfs::libipld::IpldCodec;
+
+    /// Serve a staging host which has received the first `received` bytes of an upload, and
+    /// which only says it can take the rest if it is `resumable`. Uploads it is sent are kept.
+    async fn staging_server(resumable: bool, received: u64) -> (Url, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let uploads = Arc::new(Mutex::new(Vec::new()));
+        let kept = uploads.clone();
+        let url = mock_server(move |request| {
+            if request.is("GET", "/api/v1/capabilities") {
+                if resumable {
+                    MockResponse::ok(format!(r#"{{"capabilities":["{RESUMABLE_UPLOAD}"]}}"#))
+                } else {
+                    MockResponse::empty("404 Not Found")
+                }
+            } else if request.is("GET", "/api/v1/upload/") {
+                MockResponse::ok(format!(r#"{{"bytes_received":{received}}}"#))
+            } else {
+                kept.lock().unwrap().push(request.body);
+                MockResponse::empty("200 OK")
+            }
+        })
+        .await;
+        (Url::parse(&url).expect("bad url"), uploads)
+    }
+
+    #[tokio::test]
+    async fn resume_from_offset() -> Result<(), ApiError> {
+        let store = CarV2MemoryBlockStore::new()?;
+        for i in 0..8u8 {
+            store.put_block(vec![i; 256], IpldCodec::Raw).await?;
+        }
+        let data = store.get_data();
+        let metadata_id = Uuid::new_v4();
+        let (host, uploads) = staging_server(true, 512).await;
+        let mut client = Client::new(host.as_str())?;
+        client.with_bearer_token("token".to_string());
+
+        // The host says it can resume, and how far the interrupted upload got
+        assert!(client.supports(Some(&host), RESUMABLE_UPLOAD).await);
+        let offset = client
+            .call(UploadProgress {
+                host_url: host.clone(),
+                metadata_id,
+            })
+            .await?
+            .bytes_received;
+        assert_eq!(offset, 512);
+
+        // Only the rest of the content is sent, along with where it starts
+        store
+            .upload_from(host.to_string(), metadata_id, offset, &mut client)
+            .await?;
+        let uploads = uploads.lock().unwrap();
+        assert_eq!(uploads.len(), 1);
+        let contains = |needle: &[u8]| {
+            uploads[0]
+                .windows(needle.len())
+                .any(|window| window == needle)
+        };
+        assert!(contains(br#""offset":512"#));
+        assert!(contains(&data[offset as usize..]));
+        assert!(!contains(&data[..offset as usize]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resume_unsupported() -> Result<(), ApiError> {
+        let (host, _) = staging_server(false, 512).await;
+        let mut client = Client::new(host.as_str())?;
+        assert!(!client.supports(Some(&host), RESUMABLE_UPLOAD).await);
+        Ok(())
+    }
+}
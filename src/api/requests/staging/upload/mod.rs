@@ -1,3 +1,5 @@
 pub mod content;
+/// Request for how much of an upload the staging area has received.
+pub mod progress;
 /// Request for pushing new content to the staging area.
 pub mod push;
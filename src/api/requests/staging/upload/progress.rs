@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::api::requests::ApiRequest;
+
+/// Ask a staging host how much of the content uploaded for a metadata it received. Only hosts
+/// which support [`RESUMABLE_UPLOAD`](crate::api::requests::capabilities::RESUMABLE_UPLOAD)
+/// answer this.
+#[derive(Debug)]
+pub struct UploadProgress {
+    pub host_url: Url,
+    pub metadata_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadProgressResponse {
+    /// Number of bytes of the content CAR the staging service has received
+    pub bytes_received: u64,
+}
+
+impl ApiRequest for UploadProgress {
+    type ResponseType = UploadProgressResponse;
+    type ErrorType = UploadProgressError;
+
+    fn build_request(self, _base_url: &Url, client: &Client) -> RequestBuilder {
+        let mut full_url = self.host_url;
+        full_url.set_path(&format!("/api/v1/upload/{}", self.metadata_id));
+        client.get(full_url)
+    }
+
+    fn requires_authentication(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct UploadProgressError {
+    #[serde(rename = "msg")]
+    message: String,
+}
+
+impl Display for UploadProgressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message.as_ref())
+    }
+}
+
+impl Error for UploadProgressError {}
@@ -19,14 +19,25 @@ pub struct PushContent {
     pub content: ContentType,
     pub content_len: u64,
     pub content_hash: String,
+    /// Byte offset into the CAR at which `content` starts, when resuming an upload. Only ever
+    /// nonzero for hosts which support
+    /// [`RESUMABLE_UPLOAD`](crate::api::requests::capabilities::RESUMABLE_UPLOAD).
+    pub offset: u64,
 }
 
 #[derive(Debug, Serialize)]
 struct PushContentData {
     pub metadata_id: Uuid,
     pub content_hash: String,
+    /// Left out of full uploads, so hosts which can't resume them see the request they expect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
 }
 
+/// Size of the multipart framing around the request data and the CAR
+#[cfg(not(target_arch = "wasm32"))]
+const MULTIPART_FRAMING_LEN: u64 = 410;
+
 #[derive(Debug, Deserialize)]
 pub struct PushContentResponse {}
 
@@ -43,6 +54,7 @@ impl ApiRequest for PushContent {
         let pc_req = PushContentData {
             metadata_id: self.metadata_id,
             content_hash: self.content_hash,
+            offset: (self.offset > 0).then_some(self.offset),
         };
 
         // Attach the form data to the request as json
@@ -62,10 +74,10 @@ impl ApiRequest for PushContent {
             .part("car-upload", multipart_car);
 
         // post
-        client
-            .post(full_url)
-            .multipart(multipart_form)
-            .header(reqwest::header::CONTENT_LENGTH, self.content_len + 546)
+        client.post(full_url).multipart(multipart_form).header(
+            reqwest::header::CONTENT_LENGTH,
+            self.content_len + MULTIPART_FRAMING_LEN + multipart_json_data.len() as u64,
+        )
     }
 
     fn requires_authentication(&self) -> bool {
@@ -98,6 +110,7 @@ impl ApiRequest for PushContent {
         let pc_req = PushContentData {
             metadata_id: self.metadata_id,
             content_hash: self.content_hash,
+            offset: (self.offset > 0).then_some(self.offset),
         };
 
         // Serialize JSON part
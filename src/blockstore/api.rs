@@ -4,7 +4,7 @@ use crate::api::{
     requests::{core::blocks::locate::LocationRequest, staging::pull_blocks::PullBlock},
 };
 use async_trait::async_trait;
-use futures_util::StreamExt;
+use futures_util::{stream, StreamExt};
 use reqwest::Url;
 use std::{
     borrow::Cow,
@@ -15,6 +15,11 @@ use wnfs::libipld::{Cid, IpldCodec};
 
 use super::{BanyanBlockStore, BlockStoreError};
 
+/// Default number of CIDs located per request by `find_cids_with_progress`
+pub const LOCATE_BATCH_SIZE: usize = 1000;
+/// Number of blocks `get_blocks` pulls from storage hosts at once
+pub const GET_BLOCKS_CONCURRENCY: usize = 16;
+
 /// A network-based BlockStore designed to interface with a Kubo node or an API which mirrors it
 
 #[derive(Debug, Clone)]
@@ -43,6 +48,72 @@ impl BanyanApiBlockStore {
         block_locations.extend(response);
         Ok(())
     }
+
+    /// Find the locations associated with a set of CIDs in batches of `batch_size`, calling
+    /// `progress` with the number of CIDs resolved so far and the total after each batch
+    pub async fn find_cids_with_progress(
+        &self,
+        cids: BTreeSet<Cid>,
+        batch_size: usize,
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<(), ApiError> {
+        let total = cids.len() as u64;
+        let cids = cids.into_iter().collect::<Vec<Cid>>();
+        let mut resolved = 0;
+        for batch in cids.chunks(batch_size.max(1)) {
+            let request = LocationRequest {
+                cids: batch.iter().copied().collect(),
+            };
+            let mut client = self.client.clone();
+            let response = client.call(request).await?;
+            // Several batches may name the same host, so merge rather than replace
+            let mut block_locations = self.block_locations.borrow_mut();
+            for (url, located) in response {
+                block_locations.entry(url).or_default().extend(located);
+            }
+            resolved += batch.len() as u64;
+            progress(resolved, total);
+        }
+        Ok(())
+    }
+
+    /// Whether a storage host is already known for a CID
+    fn is_located(&self, cid: &Cid) -> bool {
+        let cid = cid.to_string();
+        self.block_locations
+            .borrow()
+            .values()
+            .any(|cids| cids.contains(&cid))
+    }
+
+    /// Retrieve many blocks at once. CIDs without a known location are located together first,
+    /// then up to [`GET_BLOCKS_CONCURRENCY`] blocks are pulled at a time. Blocks which could not
+    /// be retrieved are left out of the result.
+    pub async fn get_blocks(&self, cids: &[Cid]) -> Result<HashMap<Cid, Vec<u8>>, BlockStoreError> {
+        let unlocated = cids
+            .iter()
+            .filter(|cid| !self.is_located(cid))
+            .copied()
+            .collect::<BTreeSet<Cid>>();
+        if !unlocated.is_empty() {
+            self.find_cids_with_progress(unlocated, LOCATE_BATCH_SIZE, |_, _| {})
+                .await
+                .map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
+        }
+
+        let blocks = stream::iter(cids)
+            .map(|cid| async move {
+                BanyanBlockStore::get_block(self, cid)
+                    .await
+                    .ok()
+                    .map(|content| (*cid, content.into_owned()))
+            })
+            .buffer_unordered(GET_BLOCKS_CONCURRENCY)
+            .filter_map(|block| async move { block })
+            .collect::<HashMap<Cid, Vec<u8>>>()
+            .await;
+        Ok(blocks)
+    }
 }
 
 #[async_trait(?Send)]
@@ -81,6 +152,133 @@ impl BanyanBlockStore for BanyanApiBlockStore {
         while let Some(chunk) = stream.next().await {
             data.extend_from_slice(&chunk.unwrap());
         }
+        #[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+        crate::native::metrics::bytes_transferred(
+            crate::native::metrics::Direction::Download,
+            data.len() as u64,
+        );
         Ok(Cow::Owned(data))
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod test {
+    use super::BanyanApiBlockStore;
+    use crate::{
+        api::{client::Client, error::ApiError},
+        blockstore::BlockStoreError,
+        utils::testing::mock_server::{mock_server, MockResponse, MockServer},
+    };
+    use std::{
+        collections::{BTreeSet, HashMap},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+    use wnfs::libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid, IpldCodec,
+    };
+
+    /// Serve block locations, placing every requested CID at the same host
+    async fn locate_server() -> String {
+        mock_server(|request| {
+            let cids: Vec<String> =
+                serde_json::from_slice(&request.body).expect("bad request body");
+            MockResponse::ok(
+                serde_json::to_string(&HashMap::from([("http://storage".to_string(), cids)]))
+                    .expect("failed to serialize"),
+            )
+        })
+        .await
+    }
+
+    /// Serve both block locations and blocks, placing every requested CID at this server and
+    /// counting the location requests made
+    async fn block_server(blocks: HashMap<String, Vec<u8>>) -> (String, Arc<AtomicUsize>) {
+        let server = MockServer::bind().await;
+        let host = server.url.clone();
+        let locates = Arc::new(AtomicUsize::new(0));
+        let server_locates = locates.clone();
+        let url = server.serve(move |request| {
+            if request.is("POST", "/") {
+                server_locates.fetch_add(1, Ordering::SeqCst);
+                let cids: Vec<String> =
+                    serde_json::from_slice(&request.body).expect("bad request body");
+                return MockResponse::ok(
+                    serde_json::to_string(&HashMap::from([(host.clone(), cids)]))
+                        .expect("failed to serialize"),
+                );
+            }
+            let cid = request
+                .request_line()
+                .split_whitespace()
+                .nth(1)
+                .and_then(|path| path.rsplit('/').next())
+                .unwrap_or_default();
+            match blocks.get(cid) {
+                Some(content) => MockResponse::ok(content.clone()),
+                None => MockResponse::empty("404 Not Found"),
+            }
+        });
+        (url, locates)
+    }
+
+    #[tokio::test]
+    async fn get_blocks() -> Result<(), BlockStoreError> {
+        let cids = (0..5u8)
+            .map(|i| Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(&[i])))
+            .collect::<Vec<Cid>>();
+        // The last block is nowhere to be found
+        let blocks = cids[..4]
+            .iter()
+            .enumerate()
+            .map(|(i, cid)| (cid.to_string(), vec![i as u8; 64]))
+            .collect::<HashMap<String, Vec<u8>>>();
+        let (url, locates) = block_server(blocks).await;
+        let mut client = Client::new(&url).map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
+        client.with_bearer_token("token".to_string());
+        let store = BanyanApiBlockStore::from(client);
+
+        // Every block that exists is returned, having been located all at once
+        let retrieved = store.get_blocks(&cids).await?;
+        assert_eq!(retrieved.len(), 4);
+        for (i, cid) in cids[..4].iter().enumerate() {
+            assert_eq!(retrieved.get(cid), Some(&vec![i as u8; 64]));
+        }
+        assert_eq!(locates.load(Ordering::SeqCst), 1);
+
+        // Blocks already located are not located again
+        store.get_blocks(&cids[..4]).await?;
+        assert_eq!(locates.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find_cids_reports_progress() -> Result<(), ApiError> {
+        let mut client = Client::new(&locate_server().await)?;
+        client.with_bearer_token("token".to_string());
+        let store = BanyanApiBlockStore::from(client);
+        let cids = (0..5u8)
+            .map(|i| Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(&[i])))
+            .collect::<BTreeSet<Cid>>();
+
+        let mut events = Vec::new();
+        store
+            .find_cids_with_progress(cids.clone(), 2, |resolved, total| {
+                events.push((resolved, total))
+            })
+            .await?;
+        // One event per batch, always moving forward
+        assert_eq!(events, vec![(2, 5), (4, 5), (5, 5)]);
+        // Every batch was remembered
+        let block_locations = store.block_locations.borrow();
+        let located = block_locations.get("http://storage").expect("no locations");
+        for cid in cids {
+            assert!(located.contains(&cid.to_string()));
+        }
+        Ok(())
+    }
+}
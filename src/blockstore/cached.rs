@@ -0,0 +1,236 @@
+use super::{BanyanBlockStore, BlockStoreError, RootedBlockStore};
+use crate::LibipldError;
+use async_trait::async_trait;
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+};
+use wnfs::libipld::{Cid, IpldCodec};
+
+/// BlockStore which keeps the most recently used blocks of an inner BlockStore in memory,
+/// up to a total number of bytes. Blocks which are written are cached as well.
+#[derive(Debug)]
+pub struct CachedBlockStore<BS: BanyanBlockStore> {
+    /// BlockStore that is read from on cache misses and written through to
+    inner: BS,
+    /// Recently used blocks
+    cache: RefCell<Lru>,
+    /// Number of reads served from memory
+    hits: Cell<u64>,
+    /// Number of reads which went to the inner BlockStore
+    misses: Cell<u64>,
+}
+
+/// Blocks ordered by when they were last used
+#[derive(Debug, Default)]
+struct Lru {
+    /// Block content and the tick it was last used at
+    blocks: HashMap<Cid, (Vec<u8>, u64)>,
+    /// Cids by the tick they were last used at, oldest first
+    recency: BTreeMap<u64, Cid>,
+    /// Total size of the cached content
+    size: u64,
+    /// Maximum total size of the cached content
+    capacity: u64,
+    /// Counter ordering uses
+    tick: u64,
+}
+
+impl Lru {
+    /// Get a block, marking it as the most recently used
+    fn get(&mut self, cid: &Cid) -> Option<Vec<u8>> {
+        self.tick += 1;
+        let (content, used) = self.blocks.get_mut(cid)?;
+        self.recency.remove(used);
+        self.recency.insert(self.tick, *cid);
+        *used = self.tick;
+        Some(content.clone())
+    }
+
+    /// Insert a block, evicting the least recently used blocks to make room for it
+    fn insert(&mut self, cid: Cid, content: Vec<u8>) {
+        let len = content.len() as u64;
+        // Blocks which would not fit even on their own are never cached
+        if len > self.capacity || self.get(&cid).is_some() {
+            return;
+        }
+        while self.size + len > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some((evicted, _)) = self.blocks.remove(&oldest) {
+                self.size -= evicted.len() as u64;
+            }
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, cid);
+        self.blocks.insert(cid, (content, self.tick));
+        self.size += len;
+    }
+}
+
+impl<BS: BanyanBlockStore> CachedBlockStore<BS> {
+    /// Cache up to `capacity_bytes` of block content from an inner BlockStore
+    pub fn new(inner: BS, capacity_bytes: u64) -> Self {
+        Self {
+            inner,
+            cache: RefCell::new(Lru {
+                capacity: capacity_bytes,
+                ..Default::default()
+            }),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    /// Get a reference to the underlying BlockStore
+    pub fn inner(&self) -> &BS {
+        &self.inner
+    }
+
+    /// Get the size of the block content currently held in memory
+    pub fn cache_size(&self) -> u64 {
+        self.cache.borrow().size
+    }
+
+    /// Fraction of reads served from memory, or zero if nothing has been read
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits.get();
+        let total = hits + self.misses.get();
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<BS: BanyanBlockStore> BanyanBlockStore for CachedBlockStore<BS> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        let cached = self.cache.borrow_mut().get(cid);
+        if let Some(content) = cached {
+            self.hits.set(self.hits.get() + 1);
+            return Ok(Cow::Owned(content));
+        }
+
+        self.misses.set(self.misses.get() + 1);
+        let content = BanyanBlockStore::get_block(&self.inner, cid)
+            .await?
+            .to_vec();
+        self.cache.borrow_mut().insert(*cid, content.clone());
+        Ok(Cow::Owned(content))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        let cid = BanyanBlockStore::put_block(&self.inner, bytes.clone(), codec).await?;
+        self.cache.borrow_mut().insert(cid, bytes);
+        Ok(cid)
+    }
+}
+
+#[async_trait(?Send)]
+impl<BS: RootedBlockStore> RootedBlockStore for CachedBlockStore<BS> {
+    fn get_root(&self) -> Option<Cid> {
+        self.inner.get_root()
+    }
+
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
+        self.inner.set_root(root)
+    }
+
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.inner.all_cids().await
+    }
+}
+
+#[async_trait(?Send)]
+impl<BS: BanyanBlockStore> wnfs::common::BlockStore for CachedBlockStore<BS> {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::CachedBlockStore;
+    use crate::blockstore::{BanyanBlockStore, BlockStoreError, MemoryBlockStore};
+    use wnfs::{
+        common::blockstore::{bs_duplication_test, bs_retrieval_test},
+        libipld::IpldCodec,
+    };
+
+    #[tokio::test]
+    async fn cached_retrieval() -> Result<(), BlockStoreError> {
+        let store = CachedBlockStore::new(MemoryBlockStore::default(), 1024);
+        bs_retrieval_test(&store)
+            .await
+            .map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
+        bs_duplication_test(&store)
+            .await
+            .map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hit_rate() -> Result<(), BlockStoreError> {
+        let inner = MemoryBlockStore::default();
+        let kitty_cid = inner
+            .put_block("Hello Kitty!".as_bytes().to_vec(), IpldCodec::Raw)
+            .await?;
+        let store = CachedBlockStore::new(inner, 1024);
+        assert_eq!(store.hit_rate(), 0.0);
+
+        // The first read misses, the rest are served from memory
+        for _ in 0..4 {
+            assert_eq!(
+                store.get_block(&kitty_cid).await?.to_vec(),
+                "Hello Kitty!".as_bytes()
+            );
+        }
+        assert_eq!(store.hit_rate(), 0.75);
+
+        // Written blocks are cached straight away
+        let puppy_cid = store
+            .put_block("Hello Puppy!".as_bytes().to_vec(), IpldCodec::Raw)
+            .await?;
+        store.get_block(&puppy_cid).await?;
+        assert_eq!(store.hit_rate(), 0.8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() -> Result<(), BlockStoreError> {
+        // Room for exactly two of these blocks
+        let store = CachedBlockStore::new(MemoryBlockStore::default(), 16);
+        let first = store.put_block(vec![1; 8], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![2; 8], IpldCodec::Raw).await?;
+        // Using the first block makes the second the oldest
+        store.get_block(&first).await?;
+        let third = store.put_block(vec![3; 8], IpldCodec::Raw).await?;
+        assert_eq!(store.cache_size(), 16);
+
+        store.get_block(&first).await?;
+        store.get_block(&third).await?;
+        assert_eq!(store.hit_rate(), 1.0);
+        // The second block has to be read from the inner store again
+        assert_eq!(store.get_block(&second).await?.to_vec(), vec![2; 8]);
+        assert_eq!(store.hit_rate(), 0.75);
+
+        // Blocks larger than the whole cache are never held
+        let big = store.put_block(vec![4; 32], IpldCodec::Raw).await?;
+        store.get_block(&big).await?;
+        assert!(store.cache_size() <= 16);
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 use crate::{
-    blockstore::{BlockStoreError, RootedBlockStore},
-    car::{error::CarError, v1::Block, v2::CarV2},
+    blockstore::{BlockStoreError, CarV2MemoryBlockStore, RootedBlockStore},
+    car::{
+        error::CarError,
+        v1::Block,
+        v2::{index::indexable::Indexable, CarV2, Header, HEADER_SIZE, PRAGMA_SIZE},
+        Streamable,
+    },
     utils::{get_read, get_read_write, get_write},
 };
 use async_trait::async_trait;
@@ -8,19 +13,75 @@ use serde::{de::Error as DeError, Deserialize, Serialize};
 use std::{
     borrow::Cow,
     fs::File,
+    io::{Cursor, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 use wnfs::libipld::{Cid, IpldCodec};
 
 use super::BanyanBlockStore;
 
-/// CarV2DiskBlockStore implementation using File IO
-#[derive(Debug, PartialEq, Clone)]
+/// Header a CarV2 is about to be written with, recorded beside it before its index and header
+/// are rewritten in place, so that a save which is interrupted can be finished or undone the
+/// next time it is opened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Journal(Header);
+
+impl Journal {
+    /// Location of the journal for the CarV2 at `path`
+    fn path(path: &Path) -> PathBuf {
+        let mut journal = path.as_os_str().to_owned();
+        journal.push(".journal");
+        PathBuf::from(journal)
+    }
+
+    /// The journal for a CarV2 about to be written with `header`
+    fn from_header(header: &Header) -> Self {
+        Self(*header)
+    }
+
+    /// Durably record the journal at `path`
+    fn write(self, path: &Path) -> Result<(), CarError> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.to_bytes()?)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn to_bytes(self) -> Result<Vec<u8>, CarError> {
+        let mut bytes = Cursor::new(Vec::with_capacity(HEADER_SIZE));
+        self.0.write_bytes(&mut bytes)?;
+        Ok(bytes.into_inner())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != HEADER_SIZE {
+            return None;
+        }
+        Header::read_bytes(&mut Cursor::new(bytes)).ok().map(Self)
+    }
+}
+
+/// CarV2DiskBlockStore implementation using File IO.
+///
+/// Every read opens its own handle on the CarV2, so concurrent reads never share a file cursor,
+/// and every write runs to completion without yielding, so concurrent async callers can never
+/// interleave an append with another. The store is also `Send` and `Sync`: writes from several
+/// threads take turns, shared with every clone of the store, while reads go on alongside them.
+#[derive(Debug, Clone)]
 pub struct CarV2DiskBlockStore {
     /// CarV2 file path
     pub path: PathBuf,
     /// CarV2v2
     pub car: CarV2,
+    /// Held for the duration of every write to the file
+    writing: Arc<Mutex<()>>,
+}
+
+impl PartialEq for CarV2DiskBlockStore {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.car == other.car
+    }
 }
 
 impl CarV2DiskBlockStore {
@@ -35,6 +96,7 @@ impl CarV2DiskBlockStore {
         let store = CarV2DiskBlockStore {
             path: path.to_path_buf(),
             car: CarV2::new(&mut rw)?,
+            writing: Arc::default(),
         };
         // Return Ok
         Ok(store)
@@ -47,24 +109,213 @@ impl CarV2DiskBlockStore {
             return Err(BlockStoreError::missing_file(path));
         }
 
+        // Finish or undo any write that was interrupted
+        Self::recover(path)?;
+
         // If the file is already a valid CARv2
         let mut file = File::open(path)?;
         let car = CarV2::read_bytes(&mut file)?;
         Ok(Self {
             path: path.to_path_buf(),
             car,
+            writing: Arc::default(),
         })
     }
 
-    /// Save the CarV2 CarV2DiskBlockStore to disk
+    /// Rebuild the index of a CARv2 at a given path by scanning its data payload.
+    /// Returns the repaired store and the number of blocks found.
+    pub fn repair(path: &Path) -> Result<(Self, usize), BlockStoreError> {
+        let mut rw = get_read_write(path)?;
+        let car = CarV2::repair(&mut rw)?;
+        // Drop whatever remained of the old index payload
+        let mut index_bytes = Cursor::new(<Vec<u8>>::new());
+        car.car.index.borrow().write_bytes(&mut index_bytes)?;
+        rw.set_len(car.header.borrow().index_offset + index_bytes.into_inner().len() as u64)?;
+        let blocks = car.car.index.borrow().get_all_cids().len();
+        Ok((
+            Self {
+                path: path.to_path_buf(),
+                car,
+                writing: Arc::default(),
+            },
+            blocks,
+        ))
+    }
+
+    /// Recover from a write that was interrupted before the header of the CARv2 at `path` was
+    /// updated. If the journaled index was written in full, meaning every Block it lists can be
+    /// found where it says, the header is rolled forward to it; otherwise the CARv2 is truncated
+    /// back to the data its header describes and reindexed.
+    fn recover(path: &Path) -> Result<(), BlockStoreError> {
+        let journal_path = Journal::path(path);
+        let Ok(bytes) = std::fs::read(&journal_path) else {
+            return Ok(());
+        };
+        if let Some(journal) = Journal::from_bytes(&bytes) {
+            let mut rw = get_read_write(path)?;
+            rw.seek(SeekFrom::Start(PRAGMA_SIZE as u64))?;
+            let header = Header::read_bytes(&mut rw)?;
+            let write_header = |rw: &mut File, header: &Header| -> Result<(), CarError> {
+                rw.seek(SeekFrom::Start(PRAGMA_SIZE as u64))?;
+                header.write_bytes(rw)
+            };
+
+            // Roll forward
+            write_header(&mut rw, &journal.0)?;
+            rw.seek(SeekFrom::Start(0))?;
+            if Self::index_is_complete(&mut rw) {
+                warn!("Finished an interrupted write to {}", path.display());
+            }
+            // Roll back
+            else {
+                write_header(&mut rw, &header)?;
+                rw.set_len(header.data_offset + header.data_size)?;
+                CarV2::repair(&mut rw)?;
+                warn!("Undid an interrupted write to {}", path.display());
+            }
+        }
+        std::fs::remove_file(journal_path)?;
+        Ok(())
+    }
+
+    /// Whether the CARv2 in `rw` can be read, and every Block its index lists is found within the
+    /// data payload at the offset it is listed at
+    fn index_is_complete(rw: &mut File) -> bool {
+        let Ok(car) = CarV2::read_bytes(&mut *rw) else {
+            return false;
+        };
+        let header = *car.header.borrow();
+        let data_end = header.data_offset.saturating_add(header.data_size);
+        let index = car.car.index.borrow();
+        index.buckets.iter().all(|bucket| {
+            bucket.map.iter().all(|(cid, offset)| {
+                (header.data_offset..data_end).contains(offset)
+                    && rw.seek(SeekFrom::Start(*offset)).is_ok()
+                    && Block::read_bytes(&mut *rw).is_ok_and(|block| block.cid == *cid)
+            })
+        })
+    }
+
+    /// Wait for any other thread writing to the file to finish
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Overwrite the copy of a Block held by this store, persisting the updated index
+    pub fn overwrite_block(&self, block: &Block) -> Result<(), BlockStoreError> {
+        let _writing = self.lock();
+        self.car.replace_block(block, &mut get_write(&self.path)?)?;
+        self.save()?;
+        Ok(())
+    }
+
+    /// Append a Block to the CarV2v1 unless it is already held. This never yields, so nothing
+    /// else can run between checking the index and appending and write the same Block twice.
+    pub(crate) fn append_block(&self, block: &Block) -> Result<(), BlockStoreError> {
+        // If this needs to be appended to the CarV2v1
+        if self.car.car.index.borrow().get_offset(&block.cid).is_none() {
+            let _writing = self.lock();
+            // Open the file in append mode
+            let mut file = get_write(&self.path)?;
+            // Put the block
+            self.car.put_block(block, &mut file)?;
+        }
+        Ok(())
+    }
+
+    /// Save the CarV2 CarV2DiskBlockStore to disk. Blocks are already on disk once they are
+    /// put, so only the CarV1 header, index and Header are rewritten, in place, after the Header
+    /// they are written with is journaled. A save cut short is then finished or undone the next
+    /// time the CarV2 is loaded. Should the CarV1 header change length, which moves every Block,
+    /// the CarV2 is instead written to a copy beside the original which only replaces it once
+    /// complete.
     pub fn to_disk(&self) -> Result<(), CarError> {
-        self.car.write_bytes(&mut get_read_write(&self.path)?)
+        let _writing = self.lock();
+        self.save()
+    }
+
+    /// `to_disk`, for callers already holding the write lock
+    fn save(&self) -> Result<(), CarError> {
+        let journal_path = Journal::path(&self.path);
+        let mut file = get_read_write(&self.path)?;
+        let written = self.car.write_index_journaled(&mut file, |header| {
+            Journal::from_header(header).write(&journal_path)
+        })?;
+        match written {
+            Some(len) => {
+                // Drop whatever remained of the old index payload
+                file.set_len(len)?;
+                file.sync_all()?;
+                std::fs::remove_file(&journal_path)?;
+            }
+            None => {
+                drop(file);
+                let writing = self.path.with_extension("writing");
+                std::fs::copy(&self.path, &writing)?;
+                let mut file = get_read_write(&writing)?;
+                self.car.write_bytes(&mut file)?;
+                file.sync_all()?;
+                std::fs::rename(&writing, &self.path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a Block from the CARv2. Its content takes up space until the CARv2 is compacted.
+    pub fn remove_block(&self, cid: &Cid) -> Result<(), BlockStoreError> {
+        self.remove_blocks(std::slice::from_ref(cid))
+    }
+
+    /// Remove several Blocks from the CARv2, persisting the updated index only once
+    pub fn remove_blocks(&self, cids: &[Cid]) -> Result<(), BlockStoreError> {
+        let _writing = self.lock();
+        let mut file = get_read_write(&self.path)?;
+        let len = self.car.remove_blocks(cids, &mut file)?;
+        // Drop whatever remained of the old index payload
+        file.set_len(len)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Rewrite the CARv2 without the content of removed Blocks, returning the number of bytes
+    /// reclaimed. The compacted CARv2 is written beside the original and only replaces it once
+    /// complete, so an interrupted compaction leaves the original untouched.
+    pub fn compact(&mut self) -> Result<u64, BlockStoreError> {
+        let writing = self.writing.clone();
+        let _writing = writing.lock().unwrap_or_else(PoisonError::into_inner);
+        let compacting = self.path.with_extension("compacting");
+        std::fs::copy(&self.path, &compacting)?;
+        let car = self.car.clone();
+        let mut file = get_read_write(&compacting)?;
+        let len = car.compact(&mut file)?;
+        file.set_len(len)?;
+        file.sync_all()?;
+        let reclaimed = std::fs::metadata(&self.path)?.len().saturating_sub(len);
+        std::fs::rename(&compacting, &self.path)?;
+        self.car = car;
+        Ok(reclaimed)
     }
 
     /// Get the size of the underlying CARv1
     pub fn data_size(&self) -> u64 {
         self.car.data_size()
     }
+
+    /// Copy every Block written after the first `data_size` bytes of the CARv1 into a new
+    /// in memory CARv2 which shares this store's root
+    pub fn delta_since(&self, data_size: u64) -> Result<CarV2MemoryBlockStore, BlockStoreError> {
+        let delta = CarV2MemoryBlockStore::new()?;
+        let offset = self.car.header.borrow().data_offset + data_size;
+        let cids = self.car.car.index.borrow().get_cids_since(offset);
+        let mut file = get_read(&self.path)?;
+        for cid in cids {
+            delta.put_existing_block(&self.car.get_block(&cid, &mut file)?)?;
+        }
+        if let Some(root) = self.get_root() {
+            delta.set_root(&root)?;
+        }
+        Ok(delta)
+    }
 }
 
 #[async_trait(?Send)]
@@ -81,33 +332,27 @@ impl BanyanBlockStore for CarV2DiskBlockStore {
     async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
         // Create a block with this content
         let block = Block::new(bytes, codec)?;
-        // If this CID already exists in the store
-        if self.get_block(&block.cid).await.is_ok() {
-            // Return OK
-            Ok(block.cid)
-        }
-        // If this needs to be appended to the CarV2v1
-        else {
-            // Open the file in append mode
-            let mut file = get_write(&self.path)?;
-            // Put the block
-            self.car.put_block(&block, &mut file)?;
-            // Return Ok with block CID
-            Ok(block.cid)
-        }
+        self.append_block(&block)?;
+        // Return Ok with block CID
+        Ok(block.cid)
     }
 }
 
 #[async_trait(?Send)]
 impl RootedBlockStore for CarV2DiskBlockStore {
-    fn set_root(&self, root: &Cid) {
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
         self.car.set_root(root);
-        self.to_disk().expect("failed to write to disk");
+        self.to_disk()?;
+        Ok(())
     }
 
     fn get_root(&self) -> Option<Cid> {
         self.car.get_root()
     }
+
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.car.car.index.borrow().get_all_cids()
+    }
 }
 
 impl Serialize for CarV2DiskBlockStore {
@@ -140,12 +385,25 @@ impl<'de> Deserialize<'de> for CarV2DiskBlockStore {
 
 #[cfg(test)]
 mod test {
+    use super::Journal;
     use crate::{
         blockstore::{BanyanBlockStore, BlockStoreError, CarV2DiskBlockStore, RootedBlockStore},
-        utils::testing::blockstores::car_test_setup,
+        car::{
+            error::CarError,
+            v1::Block,
+            v2::{Header, PH_SIZE, PRAGMA_SIZE},
+            Streamable,
+        },
+        utils::{get_read_write, testing::blockstores::car_test_setup, varint::encode_varint_u128},
     };
+    use futures::future::join_all;
     use serial_test::serial;
-    use std::{fs::remove_file, path::Path, str::FromStr};
+    use std::{
+        fs::{remove_file, OpenOptions},
+        io::{Seek, SeekFrom, Write},
+        path::Path,
+        str::FromStr,
+    };
     use wnfs::common::blockstore::{bs_duplication_test, bs_retrieval_test};
     use wnfs::libipld::{Cid, IpldCodec};
 
@@ -191,7 +449,7 @@ mod test {
             .put_block(kitty_bytes.clone(), IpldCodec::Raw)
             .await?;
         // Insert root
-        original.set_root(&kitty_cid);
+        original.set_root(&kitty_cid)?;
         // Save
         original.to_disk()?;
 
@@ -207,6 +465,314 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn concurrent_access() -> Result<(), BlockStoreError> {
+        let path = Path::new("test")
+            .join("car")
+            .join("carv2_carv2blockstore_concurrent_access.car");
+        if path.exists() {
+            remove_file(&path)?;
+        }
+        let store = CarV2DiskBlockStore::new(&path)?;
+        let data_size = store.data_size();
+        // The same Block is put several times over while the others are being put
+        let contents = (0..32u8)
+            .chain([7, 7, 7])
+            .map(|i| vec![i; 256])
+            .collect::<Vec<_>>();
+        let cids = join_all(
+            contents
+                .iter()
+                .map(|content| store.put_block(content.clone(), IpldCodec::Raw)),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<Cid>, BlockStoreError>>()?;
+        // Each distinct Block was appended exactly once
+        assert_eq!(store.car.car.index.borrow().get_all_cids().len(), 32);
+        let appended = contents[..32]
+            .iter()
+            .map(|content| {
+                let block = Block::new(content.clone(), IpldCodec::Raw)?;
+                Ok::<u64, BlockStoreError>(
+                    encode_varint_u128(block.varint).len() as u64 + block.varint as u64,
+                )
+            })
+            .sum::<Result<u64, BlockStoreError>>()?;
+        assert_eq!(store.data_size(), data_size + appended);
+
+        let blocks = join_all(cids.iter().map(|cid| store.get_block(cid))).await;
+        for (block, content) in blocks.into_iter().zip(&contents) {
+            assert_eq!(&block?.to_vec(), content);
+        }
+        store.to_disk()?;
+        assert_eq!(store, CarV2DiskBlockStore::load(&path)?);
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn shared_between_threads() -> Result<(), BlockStoreError> {
+        fn assert_sync<T: Send + Sync>(_: &T) {}
+
+        let path = Path::new("test")
+            .join("car")
+            .join("carv2_carv2blockstore_shared_between_threads.car");
+        if path.exists() {
+            remove_file(&path)?;
+        }
+        let store = CarV2DiskBlockStore::new(&path)?;
+        assert_sync(&store);
+        let data_size = store.data_size();
+        // Every thread puts the same Block alongside its own
+        let contents = (0..4u8)
+            .map(|thread| {
+                (0..8u8)
+                    .map(|i| vec![thread * 8 + i; 256])
+                    .chain([vec![255; 256]])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let cids = std::thread::scope(|scope| {
+            let threads = contents
+                .iter()
+                .map(|contents| {
+                    let store = &store;
+                    scope.spawn(move || {
+                        futures::executor::block_on(async {
+                            let mut cids = Vec::new();
+                            for content in contents {
+                                cids.push(store.put_block(content.clone(), IpldCodec::Raw).await?);
+                                store.to_disk()?;
+                            }
+                            Ok::<Vec<Cid>, BlockStoreError>(cids)
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().expect("thread panicked"))
+                .collect::<Result<Vec<Vec<Cid>>, BlockStoreError>>()
+        })?;
+
+        // Each distinct Block was appended exactly once, and none overwrote another
+        assert_eq!(store.car.car.index.borrow().get_all_cids().len(), 33);
+        let distinct = contents
+            .concat()
+            .into_iter()
+            .collect::<std::collections::BTreeSet<_>>();
+        let appended = distinct
+            .iter()
+            .map(|content| {
+                let block = Block::new(content.clone(), IpldCodec::Raw)?;
+                Ok::<u64, BlockStoreError>(
+                    encode_varint_u128(block.varint).len() as u64 + block.varint as u64,
+                )
+            })
+            .sum::<Result<u64, BlockStoreError>>()?;
+        assert_eq!(store.data_size(), data_size + appended);
+        let reconstructed = CarV2DiskBlockStore::load(&path)?;
+        assert_eq!(store, reconstructed);
+        for (cids, contents) in cids.iter().zip(&contents) {
+            for (cid, content) in cids.iter().zip(contents) {
+                let block = futures::executor::block_on(reconstructed.get_block(cid))?;
+                assert_eq!(&block.to_vec(), content);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn remove_and_compact() -> Result<(), BlockStoreError> {
+        let path = Path::new("test")
+            .join("car")
+            .join("carv2_carv2blockstore_remove_and_compact.car");
+        if path.exists() {
+            remove_file(&path)?;
+        }
+        let mut store = CarV2DiskBlockStore::new(&path)?;
+        let root = store.put_block(vec![0; 512], IpldCodec::Raw).await?;
+        let removed = store.put_block(vec![1; 512], IpldCodec::Raw).await?;
+        let kept = store.put_block(vec![2; 512], IpldCodec::Raw).await?;
+        store.set_root(&root)?;
+        store.to_disk()?;
+
+        assert!(store.remove_block(&root).is_err());
+        store.remove_block(&removed)?;
+        assert!(store.get_block(&removed).await.is_err());
+        assert!(store.compact()? >= 512);
+        assert!(!path.with_extension("compacting").exists());
+
+        // The compacted CARv2 reads back in with everything that was kept
+        let reconstructed = CarV2DiskBlockStore::load(&path)?;
+        assert_eq!(store, reconstructed);
+        assert_eq!(reconstructed.get_root(), Some(root));
+        assert!(reconstructed.get_block(&removed).await.is_err());
+        assert_eq!(reconstructed.get_block(&kept).await?.to_vec(), vec![2; 512]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn repair_broken_index() -> Result<(), BlockStoreError> {
+        let path = car_test_setup(2, "indexless", "carv2blockstore_repair_broken_index")?;
+        // Write out an index payload
+        CarV2DiskBlockStore::load(&path)?.to_disk()?;
+        let original = CarV2DiskBlockStore::load(&path)?;
+        let original_cids = original.car.car.index.borrow().get_all_cids();
+        // Corrupt the index payload
+        let index_offset = original.car.header.borrow().index_offset;
+        let mut file = OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start(index_offset))?;
+        file.write_all(&[0x00; 8])?;
+        assert!(CarV2DiskBlockStore::load(&path).is_err());
+
+        // Repair and reload
+        let (_, blocks) = CarV2DiskBlockStore::repair(&path)?;
+        assert_eq!(blocks, original_cids.len());
+        let repaired = CarV2DiskBlockStore::load(&path)?;
+        // Ensure every CID known before the damage still resolves
+        for cid in &original_cids {
+            assert_eq!(
+                original.get_block(cid).await?.to_vec(),
+                repaired.get_block(cid).await?.to_vec()
+            );
+        }
+        Ok(())
+    }
+
+    /// Durably record the header a CarV2 is about to be written with, as a save does
+    fn write_journal(path: &Path, header: &Header) -> Result<(), CarError> {
+        Journal::from_header(header).write(&Journal::path(path))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn atomic_save() -> Result<(), BlockStoreError> {
+        let path = Path::new("test")
+            .join("car")
+            .join("carv2_carv2blockstore_atomic_save.car");
+        if path.exists() {
+            remove_file(&path)?;
+        }
+        let store = CarV2DiskBlockStore::new(&path)?;
+        let kitty_cid = store
+            .put_block(b"Hello Kitty!".to_vec(), IpldCodec::Raw)
+            .await?;
+        store.set_root(&kitty_cid)?;
+        // Nothing is left beside the CarV2 once it is saved
+        assert!(!path.with_extension("writing").exists());
+        assert!(!Journal::path(&path).exists());
+        // Later saves leave the Blocks where they are
+        let data_size = store.data_size();
+        let len = path.metadata()?.len();
+        store.set_root(&kitty_cid)?;
+        assert_eq!(store.data_size(), data_size);
+        assert_eq!(path.metadata()?.len(), len);
+        assert_eq!(store, CarV2DiskBlockStore::load(&path)?);
+        assert_eq!(store, CarV2DiskBlockStore::load(&path)?);
+
+        // A CarV2 shorter than its Header describes is an error rather than a panic
+        let file = get_read_write(&path)?;
+        file.set_len(file.metadata()?.len() - 16)?;
+        assert!(CarV2DiskBlockStore::load(&path).is_err());
+        // As is one without a valid pragma
+        let mut file = get_read_write(&path)?;
+        file.write_all(&[0x00; 4])?;
+        assert!(CarV2DiskBlockStore::load(&path).is_err());
+        Ok(())
+    }
+
+    /// Create a CarV2 holding one block, then add another and save it again without ever
+    /// updating the header, as though the process died partway through
+    async fn interrupted_write(
+        name: &str,
+    ) -> Result<(std::path::PathBuf, Cid, Cid), BlockStoreError> {
+        let path = Path::new("test").join("car").join(format!("{name}.car"));
+        if path.exists() {
+            remove_file(&path)?;
+        }
+        let store = CarV2DiskBlockStore::new(&path)?;
+        let first = store.put_block(b"first".to_vec(), IpldCodec::Raw).await?;
+        store.set_root(&first)?;
+        // Keep the consistent header
+        let mut header = [0; PH_SIZE as usize];
+        std::io::Read::read_exact(&mut std::fs::File::open(&path)?, &mut header)?;
+
+        let second = store.put_block(b"second".to_vec(), IpldCodec::Raw).await?;
+        store.to_disk()?;
+        // Put back the old header, leaving behind the journal entry which preceded it
+        write_journal(&path, &store.car.header.borrow())?;
+        let mut file = get_read_write(&path)?;
+        file.write_all(&header)?;
+        Ok((path, first, second))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn journal_roll_forward() -> Result<(), BlockStoreError> {
+        let (path, first, second) =
+            interrupted_write("carv2blockstore_journal_roll_forward").await?;
+        // The index was written in full, so nothing is lost
+        let store = CarV2DiskBlockStore::load(&path)?;
+        assert!(!Journal::path(&path).exists());
+        assert_eq!(store.get_block(&first).await?.to_vec(), b"first".to_vec());
+        assert_eq!(store.get_block(&second).await?.to_vec(), b"second".to_vec());
+        assert_eq!(store, CarV2DiskBlockStore::load(&path)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn journal_roll_back() -> Result<(), BlockStoreError> {
+        let (path, first, second) = interrupted_write("carv2blockstore_journal_roll_back").await?;
+        // Lose the end of the index
+        let file = get_read_write(&path)?;
+        file.set_len(file.metadata()?.len() - 4)?;
+        // Only what the old header described remains
+        let store = CarV2DiskBlockStore::load(&path)?;
+        assert!(!Journal::path(&path).exists());
+        assert_eq!(store.get_block(&first).await?.to_vec(), b"first".to_vec());
+        assert!(store.get_block(&second).await.is_err());
+        assert_eq!(store, CarV2DiskBlockStore::load(&path)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn journal_roll_back_misplaced_block() -> Result<(), BlockStoreError> {
+        let (path, first, second) =
+            interrupted_write("carv2blockstore_journal_roll_back_misplaced_block").await?;
+        // The index can still be read, but the Block it lists last is not where it says
+        let mut file = get_read_write(&path)?;
+        file.seek(SeekFrom::Start(PRAGMA_SIZE as u64))?;
+        let header = Header::read_bytes(&mut file)?;
+        file.seek(SeekFrom::Start(header.data_offset + header.data_size + 2))?;
+        file.write_all(&[0xff; 4])?;
+        // So only what the old header described remains
+        let store = CarV2DiskBlockStore::load(&path)?;
+        assert!(!Journal::path(&path).exists());
+        assert_eq!(store.get_block(&first).await?.to_vec(), b"first".to_vec());
+        assert!(store.get_block(&second).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn journal_characteristics() -> Result<(), BlockStoreError> {
+        let (path, ..) = interrupted_write("carv2blockstore_journal_characteristics").await?;
+        // Whatever the header was about to be written with is what it is rolled forward to
+        let mut journaled = *CarV2DiskBlockStore::load(&path)?.car.header.borrow();
+        journaled.characteristics = 0;
+        write_journal(&path, &journaled)?;
+        let store = CarV2DiskBlockStore::load(&path)?;
+        assert_eq!(*store.car.header.borrow(), journaled);
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn carv2blockstore() -> Result<(), BlockStoreError> {
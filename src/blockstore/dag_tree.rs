@@ -0,0 +1,144 @@
+use super::{BanyanBlockStore, BlockStoreError};
+use crate::car::v2::block_links;
+use async_recursion::async_recursion;
+use serde::Serialize;
+use std::{collections::HashSet, fmt::Display};
+use wnfs::libipld::{Cid, IpldCodec};
+
+/// The Blocks reachable from a root, each listed with its codec, size and links
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DagTree {
+    /// Cid of the Block
+    pub cid: Cid,
+    /// Name of the codec the Cid names
+    pub codec: String,
+    /// Size of the Block content in bytes, or None if the Block could not be found
+    pub size: Option<u64>,
+    /// Whether the Block was already listed elsewhere in the tree, in which case its links
+    /// are not listed again
+    pub repeated: bool,
+    /// Trees of the Blocks this one links to, in the order they appear
+    pub links: Vec<DagTree>,
+}
+
+impl DagTree {
+    /// Follow the DAG-PB and DAG-CBOR links of every Block reachable from `root`. Missing
+    /// Blocks are listed without a size rather than treated as errors, so that the rest of
+    /// the DAG can still be inspected.
+    pub async fn build(root: &Cid, store: &impl BanyanBlockStore) -> Result<Self, BlockStoreError> {
+        Self::visit(root, store, &mut HashSet::new()).await
+    }
+
+    #[async_recursion(?Send)]
+    async fn visit(
+        cid: &Cid,
+        store: &impl BanyanBlockStore,
+        visited: &mut HashSet<Cid>,
+    ) -> Result<Self, BlockStoreError> {
+        let mut tree = Self {
+            cid: *cid,
+            codec: codec_name(cid),
+            size: None,
+            repeated: !visited.insert(*cid),
+            links: Vec::new(),
+        };
+        let content = match BanyanBlockStore::get_block(store, cid).await {
+            Ok(content) => content.to_vec(),
+            Err(_) => return Ok(tree),
+        };
+        tree.size = Some(content.len() as u64);
+        if !tree.repeated {
+            for link in block_links(cid, &content)? {
+                tree.links.push(Self::visit(&link, store, visited).await?);
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Number of distinct Blocks in the tree
+    pub fn block_count(&self) -> usize {
+        1 + self
+            .links
+            .iter()
+            .filter(|link| !link.repeated)
+            .map(DagTree::block_count)
+            .sum::<usize>()
+    }
+
+    fn write_lines(&self, depth: usize, lines: &mut Vec<String>) {
+        let size = match self.size {
+            Some(size) => format!("{size} B"),
+            None => "missing".to_string(),
+        };
+        let repeated = if self.repeated { " (see above)" } else { "" };
+        lines.push(format!(
+            "{}{} [{}, {size}]{repeated}",
+            "  ".repeat(depth),
+            self.cid,
+            self.codec
+        ));
+        for link in &self.links {
+            link.write_lines(depth + 1, lines);
+        }
+    }
+}
+
+impl Display for DagTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines = Vec::new();
+        self.write_lines(0, &mut lines);
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// Name of the codec of a Cid, as it is known in the multicodec table
+fn codec_name(cid: &Cid) -> String {
+    match IpldCodec::try_from(cid.codec()) {
+        Ok(IpldCodec::Raw) => "raw".to_string(),
+        Ok(IpldCodec::DagPb) => "dag-pb".to_string(),
+        Ok(IpldCodec::DagCbor) => "dag-cbor".to_string(),
+        Ok(IpldCodec::DagJson) => "dag-json".to_string(),
+        _ => format!("{:#x}", cid.codec()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::DagTree;
+    use crate::blockstore::{BanyanBlockStore, BlockStoreError, MemoryBlockStore};
+    use wnfs::{
+        common::dagcbor,
+        libipld::{Cid, Ipld, IpldCodec},
+    };
+
+    #[tokio::test]
+    async fn build() -> Result<(), BlockStoreError> {
+        let store = MemoryBlockStore::new();
+        let leaf = store.put_block(vec![1, 2, 3], IpldCodec::Raw).await?;
+        // A leaf linked to twice, and a Block which is not in the store
+        let missing = Cid::new_v1(IpldCodec::DagJson.into(), *leaf.hash());
+        let ipld = Ipld::List(vec![
+            Ipld::Link(leaf),
+            Ipld::Link(leaf),
+            Ipld::Link(missing),
+        ]);
+        let root = store
+            .put_block(
+                dagcbor::encode(&ipld).map_err(Box::from)?,
+                IpldCodec::DagCbor,
+            )
+            .await?;
+
+        let tree = DagTree::build(&root, &store).await?;
+        assert_eq!(tree.codec, "dag-cbor");
+        assert_eq!(tree.links.len(), 3);
+        assert_eq!(tree.links[0].size, Some(3));
+        assert!(!tree.links[0].repeated);
+        assert!(tree.links[1].repeated);
+        assert_eq!(tree.links[2].size, None);
+        assert_eq!(tree.block_count(), 3);
+        assert!(tree.to_string().contains("(see above)"));
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use super::{BanyanApiBlockStore, BanyanBlockStore, BlockStoreError, CarV2DiskBlockStore};
+use crate::{car::v1::Block, LibipldError};
+use async_trait::async_trait;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fs::remove_file,
+    path::{Path, PathBuf},
+};
+use wnfs::libipld::{Cid, IpldCodec};
+
+/// Default maximum size of the on disk cache (1 GiB)
+pub const DEFAULT_CACHE_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+
+/// Read-through BlockStore which persists every block fetched from a source
+/// into a CarV2DiskBlockStore and serves subsequent reads locally
+#[derive(Debug)]
+pub struct DiskCachedApiBlockStore<S: BanyanBlockStore = BanyanApiBlockStore> {
+    /// BlockStore that is read from on cache misses
+    source: S,
+    /// CarV2 file path of the cache
+    path: PathBuf,
+    /// Local cache of blocks
+    cache: RefCell<CarV2DiskBlockStore>,
+    /// Maximum size of the cache's data in bytes before it is evicted
+    size_limit: u64,
+}
+
+impl<S: BanyanBlockStore> DiskCachedApiBlockStore<S> {
+    /// Create a new cache over a source, loading an existing cache CAR at `path` if one exists
+    pub fn new(source: S, path: &Path, size_limit: u64) -> Result<Self, BlockStoreError> {
+        Ok(Self {
+            source,
+            path: path.to_path_buf(),
+            cache: RefCell::new(Self::open_cache(path)?),
+            size_limit,
+        })
+    }
+
+    /// Get a reference to the underlying source BlockStore
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Get the size of the data currently held by the cache
+    pub fn cache_size(&self) -> u64 {
+        self.cache.borrow().data_size()
+    }
+
+    /// Whether or not a block is already present in the cache
+    #[allow(clippy::await_holding_refcell_ref)]
+    pub async fn is_cached(&self, cid: &Cid) -> bool {
+        let cache = self.cache.borrow();
+        BanyanBlockStore::get_block(&*cache, cid).await.is_ok()
+    }
+
+    /// Remove every block from the cache
+    pub fn clear(&self) -> Result<(), BlockStoreError> {
+        Self::remove_cache(&self.path)?;
+        *self.cache.borrow_mut() = CarV2DiskBlockStore::new(&self.path)?;
+        Ok(())
+    }
+
+    /// Delete the cache CAR at a given path, if it exists
+    pub fn remove_cache(path: &Path) -> Result<(), BlockStoreError> {
+        if path.exists() {
+            remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Load the cache CAR if it is valid, otherwise start a fresh one
+    fn open_cache(path: &Path) -> Result<CarV2DiskBlockStore, BlockStoreError> {
+        if path.exists() {
+            if let Ok(cache) = CarV2DiskBlockStore::load(path) {
+                return Ok(cache);
+            }
+            // The cache is not a readable CAR, start over
+            remove_file(path)?;
+        } else if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        CarV2DiskBlockStore::new(path)
+    }
+
+    /// Write a block into the cache, evicting everything if the size limit would be exceeded
+    async fn insert(&self, block: Block) -> Result<Cid, BlockStoreError> {
+        if self.is_cached(&block.cid).await {
+            return Ok(block.cid);
+        }
+        self.append(&block)?;
+        // Persist the updated index
+        self.cache.borrow().to_disk()?;
+        Ok(block.cid)
+    }
+
+    /// Write a block into the cache without persisting the index, evicting everything first if
+    /// the size limit would be exceeded
+    fn append(&self, block: &Block) -> Result<(), BlockStoreError> {
+        if self.cache_size() + block.varint as u64 > self.size_limit {
+            self.clear()?;
+        }
+        // Append the block under the CID it was requested by
+        self.cache.borrow().append_block(block)
+    }
+}
+
+impl DiskCachedApiBlockStore<BanyanApiBlockStore> {
+    /// Fill the cache with blocks ahead of reading them, retrieving `batch_size` at a time.
+    /// Prefetching stops before the cache would have to be evicted, leaving any remaining
+    /// blocks to be fetched as they are read. Returns how many blocks were prefetched.
+    pub async fn prefetch(
+        &self,
+        cids: &[Cid],
+        batch_size: usize,
+    ) -> Result<usize, BlockStoreError> {
+        let mut missing = Vec::new();
+        for cid in cids {
+            if !self.is_cached(cid).await {
+                missing.push(*cid);
+            }
+        }
+
+        let mut prefetched = 0;
+        let fetched = self
+            .append_batches(&missing, batch_size, &mut prefetched)
+            .await;
+        // Persist the index once, for every block appended before any failure
+        if prefetched > 0 {
+            self.cache.borrow().to_disk()?;
+        }
+        fetched.map(|_| prefetched)
+    }
+
+    /// Append the blocks of `cids` to the cache `batch_size` at a time without persisting the
+    /// index, until the cache would have to be evicted. Counts the blocks in `appended`.
+    async fn append_batches(
+        &self,
+        cids: &[Cid],
+        batch_size: usize,
+        appended: &mut usize,
+    ) -> Result<(), BlockStoreError> {
+        for batch in cids.chunks(batch_size.max(1)) {
+            for (cid, content) in self.source.get_blocks(batch).await? {
+                let block = Block {
+                    varint: (cid.encoded_len() + content.len()) as u128,
+                    cid,
+                    content,
+                };
+                if self.cache_size() + block.varint as u64 > self.size_limit {
+                    return Ok(());
+                }
+                self.append(&block)?;
+                *appended += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: BanyanBlockStore> BanyanBlockStore for DiskCachedApiBlockStore<S> {
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        // Serve the block locally if we already have it
+        {
+            let cache = self.cache.borrow();
+            if let Ok(block) = BanyanBlockStore::get_block(&*cache, cid).await {
+                return Ok(Cow::Owned(block.to_vec()));
+            }
+        }
+
+        // Otherwise fetch it from the source and remember it
+        let content = BanyanBlockStore::get_block(&self.source, cid)
+            .await?
+            .to_vec();
+        self.insert(Block {
+            varint: (cid.encoded_len() + content.len()) as u128,
+            cid: *cid,
+            content: content.clone(),
+        })
+        .await?;
+        Ok(Cow::Owned(content))
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        self.insert(Block::new(bytes, codec)?).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: BanyanBlockStore> wnfs::common::BlockStore for DiskCachedApiBlockStore<S> {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DiskCachedApiBlockStore;
+    use crate::{
+        blockstore::{BanyanBlockStore, BlockStoreError, CarV2MemoryBlockStore},
+        LibipldError,
+    };
+    use async_trait::async_trait;
+    use serial_test::serial;
+    use std::{borrow::Cow, cell::RefCell, path::Path};
+    use wnfs::libipld::{Cid, IpldCodec};
+
+    /// CarV2MemoryBlockStore which counts the number of reads made against it
+    #[derive(Debug)]
+    struct CountingBlockStore {
+        store: CarV2MemoryBlockStore,
+        reads: RefCell<usize>,
+    }
+
+    impl CountingBlockStore {
+        fn new() -> Result<Self, BlockStoreError> {
+            Ok(Self {
+                store: CarV2MemoryBlockStore::new()?,
+                reads: RefCell::new(0),
+            })
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl BanyanBlockStore for CountingBlockStore {
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+            *self.reads.borrow_mut() += 1;
+            BanyanBlockStore::get_block(&self.store, cid).await
+        }
+
+        async fn put_block(
+            &self,
+            bytes: Vec<u8>,
+            codec: IpldCodec,
+        ) -> Result<Cid, BlockStoreError> {
+            BanyanBlockStore::put_block(&self.store, bytes, codec).await
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl wnfs::common::BlockStore for CountingBlockStore {
+        async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+            BanyanBlockStore::put_block(self, bytes, codec)
+                .await
+                .map_err(|err| LibipldError::msg(err.to_string()))
+        }
+
+        async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+            BanyanBlockStore::get_block(self, cid)
+                .await
+                .map_err(|err| LibipldError::msg(err.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn second_fetch_is_local() -> Result<(), BlockStoreError> {
+        let path = Path::new("test")
+            .join("car")
+            .join("disk_cache_second_fetch.car");
+        DiskCachedApiBlockStore::<CountingBlockStore>::remove_cache(&path)?;
+
+        let source = CountingBlockStore::new()?;
+        let kitty_bytes = "Hello Kitty!".as_bytes().to_vec();
+        let kitty_cid =
+            BanyanBlockStore::put_block(&source, kitty_bytes.clone(), IpldCodec::Raw).await?;
+
+        let store = DiskCachedApiBlockStore::new(source, &path, u64::MAX)?;
+        // First fetch reads through to the source
+        assert_eq!(store.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        assert_eq!(*store.source().reads.borrow(), 1);
+        assert!(store.is_cached(&kitty_cid).await);
+        // Second fetch is served from disk
+        assert_eq!(store.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        assert_eq!(*store.source().reads.borrow(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn evicts_over_limit() -> Result<(), BlockStoreError> {
+        let path = Path::new("test").join("car").join("disk_cache_evicts.car");
+        DiskCachedApiBlockStore::<CountingBlockStore>::remove_cache(&path)?;
+
+        let store = DiskCachedApiBlockStore::new(CountingBlockStore::new()?, &path, 64)?;
+        let first = store.put_block(vec![0; 48], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![1; 48], IpldCodec::Raw).await?;
+        // Inserting the second block evicted the first
+        assert!(!store.is_cached(&first).await);
+        assert!(store.is_cached(&second).await);
+        Ok(())
+    }
+}
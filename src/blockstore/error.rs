@@ -4,6 +4,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use wnfs::libipld::{Cid, IpldCodec};
+
 use crate::{car::error::CarError, WnfsError};
 
 #[derive(Debug)]
@@ -32,6 +34,33 @@ impl BlockStoreError {
         }
     }
 
+    pub fn capacity_exceeded(size: u64, remaining: u64) -> Self {
+        Self {
+            kind: BlockStoreErrorKind::CapacityExceeded { size, remaining },
+        }
+    }
+
+    pub fn malformed_block(codec: IpldCodec) -> Self {
+        Self {
+            kind: BlockStoreErrorKind::MalformedBlock(codec),
+        }
+    }
+
+    pub fn codec_mismatch(cid: &Cid, expected: IpldCodec) -> Self {
+        Self {
+            kind: BlockStoreErrorKind::CodecMismatch {
+                cid: *cid,
+                expected,
+            },
+        }
+    }
+
+    pub fn rolled_over(count: usize) -> Self {
+        Self {
+            kind: BlockStoreErrorKind::RolledOver(count),
+        }
+    }
+
     pub fn car(err: CarError) -> Self {
         Self {
             kind: BlockStoreErrorKind::Car(err),
@@ -60,6 +89,18 @@ impl Display for BlockStoreError {
                     dir.display()
                 )
             }
+            BlockStoreErrorKind::CapacityExceeded { size, remaining } => format!(
+                "A block of {size} bytes does not fit in the {remaining} bytes left in the BlockStore"
+            ),
+            BlockStoreErrorKind::MalformedBlock(codec) => {
+                format!("Block content is not valid {codec:?}")
+            }
+            BlockStoreErrorKind::CodecMismatch { cid, expected } => {
+                format!("Block {cid} was not encoded as {expected:?}")
+            }
+            BlockStoreErrorKind::RolledOver(count) => format!(
+                "Content rolled over into {count} more CAR(s) since it was last pushed, and only one CAR can be uploaded at a time"
+            ),
             BlockStoreErrorKind::Car(err) => format!("{} {err}", "CAR ERROR:".underline()),
             BlockStoreErrorKind::Wnfs(err) => format!("{} {err}", "WNFS ERROR:".underline()),
         };
@@ -73,6 +114,20 @@ pub enum BlockStoreErrorKind {
     MissingFile(PathBuf),
     MissingDirectory(PathBuf),
     Exists(PathBuf),
+    /// A block was put into a BlockStore without room for it
+    CapacityExceeded {
+        size: u64,
+        remaining: u64,
+    },
+    /// Block content could not be decoded with the codec it was put with
+    MalformedBlock(IpldCodec),
+    /// A block was read expecting a different codec than its CID names
+    CodecMismatch {
+        cid: Cid,
+        expected: IpldCodec,
+    },
+    /// Content spilled over into new CARs that were never pushed
+    RolledOver(usize),
     Car(CarError),
     Wnfs(WnfsError),
 }
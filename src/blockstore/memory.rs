@@ -1,10 +1,17 @@
 use crate::blockstore::RootedBlockStore;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, cell::RefCell};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+};
 use wnfs::{
     common::{BlockStore, MemoryBlockStore as WnfsMemoryBlockStore},
-    libipld::{Cid, IpldCodec},
+    libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid, IpldCodec,
+    },
 };
 
 use super::{BanyanBlockStore, BlockStoreError};
@@ -14,6 +21,15 @@ use super::{BanyanBlockStore, BlockStoreError};
 pub struct MemoryBlockStore {
     root: RefCell<Option<Cid>>,
     store: WnfsMemoryBlockStore,
+    /// Total size of the stored block content
+    #[serde(default)]
+    size: Cell<u64>,
+    /// Maximum total size of the stored block content, if any
+    #[serde(default)]
+    max_bytes: Option<u64>,
+    /// Cids of the stored blocks
+    #[serde(default)]
+    cids: RefCell<BTreeSet<Cid>>,
 }
 
 impl MemoryBlockStore {
@@ -21,6 +37,26 @@ impl MemoryBlockStore {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Creates a new in-memory block store which refuses blocks once `limit` bytes of block
+    /// content are held, rather than growing without bound
+    pub fn with_max_bytes(limit: u64) -> Self {
+        Self {
+            max_bytes: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Get the total size of the stored block content
+    pub fn current_bytes(&self) -> u64 {
+        self.size.get()
+    }
+
+    /// Get the number of bytes of block content which can still be stored, if there is a limit
+    pub fn remaining(&self) -> Option<u64> {
+        self.max_bytes
+            .map(|max_bytes| max_bytes.saturating_sub(self.size.get()))
+    }
 }
 
 #[async_trait(?Send)]
@@ -35,10 +71,25 @@ impl BanyanBlockStore for MemoryBlockStore {
 
     /// Stores an array of bytes in the block store.
     async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
-        self.store
+        let len = bytes.len() as u64;
+        let cid = Cid::new_v1(codec.into(), Code::Sha2_256.digest(&bytes));
+        // Blocks which are already stored take up no more room
+        if self.store.get_block(&cid).await.is_ok() {
+            return Ok(cid);
+        }
+        if let Some(remaining) = self.remaining() {
+            if len > remaining {
+                return Err(BlockStoreError::capacity_exceeded(len, remaining));
+            }
+        }
+        let cid = self
+            .store
             .put_block(bytes, codec)
             .await
-            .map_err(|err| BlockStoreError::wnfs(Box::from(err)))
+            .map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
+        self.size.set(self.size.get() + len);
+        self.cids.borrow_mut().insert(cid);
+        Ok(cid)
     }
 }
 
@@ -48,8 +99,13 @@ impl RootedBlockStore for MemoryBlockStore {
         *self.root.borrow()
     }
 
-    fn set_root(&self, root: &Cid) {
-        *self.root.borrow_mut() = Some(*root)
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
+        *self.root.borrow_mut() = Some(*root);
+        Ok(())
+    }
+
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.cids.borrow().iter().copied().collect()
     }
 }
 
@@ -82,11 +138,88 @@ mod test {
             .await
             .map_err(Box::from)?;
         // Set the root
-        store.set_root(&cid);
+        store.set_root(&cid).map_err(Box::from)?;
         // Get the root
         let root = store.get_root();
         // Assert that the root is the same as the cid
         assert_eq!(root, Some(cid));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn max_bytes() -> Result<(), BlockStoreError> {
+        let store = MemoryBlockStore::with_max_bytes(16);
+        assert_eq!(store.remaining(), Some(16));
+        let cid = store.put_block(vec![1; 12], IpldCodec::Raw).await?;
+        assert_eq!(store.current_bytes(), 12);
+        assert_eq!(store.remaining(), Some(4));
+
+        // Storing the same block again takes up no more room
+        assert_eq!(store.put_block(vec![1; 12], IpldCodec::Raw).await?, cid);
+        assert_eq!(store.current_bytes(), 12);
+
+        // A block which doesn't fit is refused rather than stored
+        let err = store
+            .put_block(vec![2; 8], IpldCodec::Raw)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not fit"));
+        assert_eq!(store.current_bytes(), 12);
+        store.put_block(vec![3; 4], IpldCodec::Raw).await?;
+        assert_eq!(store.remaining(), Some(0));
+
+        // Without a limit blocks are only tallied
+        let unbounded = MemoryBlockStore::new();
+        unbounded.put_block(vec![1; 12], IpldCodec::Raw).await?;
+        assert_eq!(unbounded.current_bytes(), 12);
+        assert_eq!(unbounded.remaining(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn all_cids() -> Result<(), BlockStoreError> {
+        let store = MemoryBlockStore::new();
+        assert!(store.all_cids().await.is_empty());
+        let first = store.put_block(vec![1, 2, 3], IpldCodec::Raw).await?;
+        let second = store.put_block(vec![4, 5, 6], IpldCodec::DagCbor).await?;
+        // Duplicates are only listed once
+        store.put_block(vec![1, 2, 3], IpldCodec::Raw).await?;
+
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(store.all_cids().await, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn codecs() -> Result<(), BlockStoreError> {
+        let store = MemoryBlockStore::new();
+        let json = br#"{"indexed":true}"#.to_vec();
+        let cid = store
+            .put_block_with_codec(json.clone(), IpldCodec::DagJson)
+            .await?;
+        assert_eq!(cid.codec(), u64::from(IpldCodec::DagJson));
+        assert_eq!(
+            store
+                .get_block_with_codec(&cid, IpldCodec::DagJson)
+                .await?
+                .to_vec(),
+            json
+        );
+
+        // Reading it back as another codec is refused
+        let err = store
+            .get_block_with_codec(&cid, IpldCodec::DagCbor)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("was not encoded as"));
+
+        // As is content which does not decode with the codec it is put with
+        let err = store
+            .put_block_with_codec(b"not json".to_vec(), IpldCodec::DagJson)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not valid"));
+        Ok(())
+    }
 }
@@ -1,23 +1,35 @@
 mod api;
+mod cached;
 #[cfg(not(target_arch = "wasm32"))]
 mod carv2_disk;
 mod carv2_memory;
+mod dag_tree;
+#[cfg(not(target_arch = "wasm32"))]
+mod disk_cache;
 mod error;
 mod memory;
 #[cfg(not(target_arch = "wasm32"))]
 mod multi_carv2_disk;
+#[cfg(not(target_arch = "wasm32"))]
+mod read_repair;
 mod split;
 mod wnfs_trait;
 
-pub use api::BanyanApiBlockStore;
+pub use api::{BanyanApiBlockStore, GET_BLOCKS_CONCURRENCY, LOCATE_BATCH_SIZE};
+pub use cached::CachedBlockStore;
 #[cfg(not(target_arch = "wasm32"))]
 pub use carv2_disk::CarV2DiskBlockStore;
 pub use carv2_memory::CarV2MemoryBlockStore;
+pub use dag_tree::DagTree;
+#[cfg(not(target_arch = "wasm32"))]
+pub use disk_cache::{DiskCachedApiBlockStore, DEFAULT_CACHE_SIZE_LIMIT};
 pub(crate) use error::BlockStoreError;
 pub use memory::MemoryBlockStore;
 #[cfg(not(target_arch = "wasm32"))]
 pub use multi_carv2_disk::MultiCarV2DiskBlockStore;
-pub use split::DoubleSplitStore;
+#[cfg(not(target_arch = "wasm32"))]
+pub use read_repair::ReadRepairStore;
+pub use split::{DoubleSplitStore, ReadOrder};
 /// Makes it so that downstream crates don't need to know about the underlying trait
 pub use wnfs_trait::BanyanBlockStore;
 
@@ -28,6 +40,8 @@ use wnfs::libipld::Cid;
 pub trait RootedBlockStore: BanyanBlockStore {
     /// Get the root CID
     fn get_root(&self) -> Option<Cid>;
-    /// Set the root CID
-    fn set_root(&self, root: &Cid);
+    /// Set the root CID, persisting it if the store is persistent
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError>;
+    /// Get the CID of every Block held, in sorted order
+    async fn all_cids(&self) -> Vec<Cid>;
 }
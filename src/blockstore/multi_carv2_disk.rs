@@ -1,8 +1,12 @@
 use super::{BanyanBlockStore, CarV2DiskBlockStore};
 use crate::{
-    api::requests::staging::upload::content::{ContentType, UploadContent},
+    api::{
+        idle::IdleTimeout,
+        rate_limit::RateLimiter,
+        requests::staging::upload::content::{ContentType, UploadContent},
+    },
     blockstore::{BlockStoreError, RootedBlockStore},
-    car::error::CarError,
+    car::{error::CarError, v1::Block, v2::index::indexable::Indexable},
     WnfsError,
 };
 use async_trait::async_trait;
@@ -10,17 +14,32 @@ use serde::{Deserialize, Serialize};
 use std::fs::{self, create_dir_all};
 use std::{
     borrow::Cow,
+    cell::{Cell, Ref, RefCell},
+    io::SeekFrom,
     path::{Path, PathBuf},
 };
+use tokio::io::AsyncSeekExt;
 use wnfs::libipld::{Cid, IpldCodec};
 
+/// Upper bound on the space each Block takes up in the index payload of a CARv2
+const INDEX_ENTRY_SIZE: u64 = 128;
+
 /// CARv2 MultiCarV2DiskBlockStore across multiple CAR files using File IO
 #[derive(Debug, PartialEq, Clone)]
 pub struct MultiCarV2DiskBlockStore {
     /// CAR directory path
     pub path: PathBuf,
     /// CARv2 BlockStores inside this BlockStore
-    pub deltas: Vec<CarV2DiskBlockStore>,
+    pub deltas: RefCell<Vec<CarV2DiskBlockStore>>,
+    /// Size in bytes no CAR file should grow past. Once a Block would take the most recent CAR
+    /// past this size, a new CAR is started to hold it. Defaults to no limit.
+    ///
+    /// Only the most recent CAR is pushed when syncing, so content which rolls over into more
+    /// than one new CAR can't be synced. This is meant for Drives whose CARs are exported
+    /// directly, e.g. as pieces of a storage deal.
+    pub max_car_size: u64,
+    /// Number of CARs started to hold overflowing Blocks since the last delta was added
+    rolled_over: Cell<usize>,
 }
 
 impl MultiCarV2DiskBlockStore {
@@ -36,10 +55,30 @@ impl MultiCarV2DiskBlockStore {
         // Ok
         Ok(Self {
             path: dir.to_path_buf(),
-            deltas: Vec::new(),
+            deltas: RefCell::new(Vec::new()),
+            max_car_size: u64::MAX,
+            rolled_over: Cell::new(0),
         })
     }
 
+    /// The paths of every CAR in a directory, sorted so that the most recent delta is last,
+    /// such that 10.car follows 9.car
+    pub fn car_paths(dir: &Path) -> Result<Vec<PathBuf>, BlockStoreError> {
+        let mut paths = fs::read_dir(dir)?
+            .flatten()
+            .map(|dir_entry| dir_entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "car"))
+            .collect::<Vec<PathBuf>>();
+        paths.sort_by_key(|path| {
+            let number = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u64>().ok());
+            (number, path.clone())
+        });
+        Ok(paths)
+    }
+
     /// Load a MultiCarV2DiskBlockStore from a directory
     pub fn load(dir: &Path) -> Result<Self, BlockStoreError> {
         if dir.is_file() {
@@ -47,59 +86,113 @@ impl MultiCarV2DiskBlockStore {
         }
 
         let mut deltas = Vec::new();
-        for dir_entry in fs::read_dir(dir)?.flatten() {
-            if dir_entry
-                .file_name()
-                .to_str()
-                .expect("no file name str")
-                .ends_with(".car")
-            {
-                if let Ok(car) = CarV2DiskBlockStore::load(&dir_entry.path()) {
-                    deltas.push(car);
-                }
+        for path in Self::car_paths(dir)? {
+            match CarV2DiskBlockStore::load(&path) {
+                Ok(car) => deltas.push(car),
+                Err(err) => warn!(
+                    "Skipping {}, which could not be loaded: {err}. Repairing the Drive may recover it.",
+                    path.display()
+                ),
             }
         }
 
-        // Sort so that the most recent delta is last in the list
-        deltas.sort_by(|a, b| a.path.cmp(&b.path));
-
         // Ok
         Ok(Self {
             path: dir.to_path_buf(),
-            deltas,
+            deltas: RefCell::new(deltas),
+            max_car_size: u64::MAX,
+            rolled_over: Cell::new(0),
         })
     }
 
     /// Add a new delta file / CAR file
     pub fn add_delta(&mut self) -> Result<(), BlockStoreError> {
+        self.push_delta()?;
+        self.rolled_over.set(0);
+        Ok(())
+    }
+
+    /// Ensure that all content written since the last delta was added is in the most recent
+    /// CAR, as that is the only one which is uploaded
+    pub fn check_uploadable(&self) -> Result<(), BlockStoreError> {
+        match self.rolled_over.get() {
+            0 => Ok(()),
+            count => Err(BlockStoreError::rolled_over(count)),
+        }
+    }
+
+    /// Start a new CAR file which carries over the root of the most recent one
+    fn push_delta(&self) -> Result<(), BlockStoreError> {
+        let count = self.deltas.borrow().len();
         // Create a new delta for writing
-        let new_store =
-            CarV2DiskBlockStore::new(&self.path.join(format!("{}.car", self.deltas.len() + 1)))?;
+        let new_store = CarV2DiskBlockStore::new(&self.path.join(format!("{}.car", count + 1)))?;
 
         // Set the root depending on previous deltas
-        if !self.deltas.is_empty() {
+        if count > 0 {
             new_store.set_root(
                 &self
                     .get_delta()?
                     .get_root()
                     .ok_or(BlockStoreError::car(CarError::missing_root()))?,
-            );
+            )?;
         } else {
-            new_store.set_root(&Cid::default());
+            new_store.set_root(&Cid::default())?;
         }
 
         // Add the new store
-        self.deltas.push(new_store);
+        self.deltas.borrow_mut().push(new_store);
 
         // Ok
         Ok(())
     }
 
-    /// Get the most recent delta
-    pub fn get_delta(&self) -> Result<&CarV2DiskBlockStore, BlockStoreError> {
+    /// Whether any delta holds a block, without reading it
+    pub fn contains(&self, cid: &Cid) -> bool {
         self.deltas
-            .last()
-            .ok_or(BlockStoreError::missing_file(&self.path.join("1.car")))
+            .borrow()
+            .iter()
+            .any(|delta| delta.car.car.index.borrow().get_offset(cid).is_some())
+    }
+
+    /// Get the most recent delta
+    pub fn get_delta(&self) -> Result<Ref<'_, CarV2DiskBlockStore>, BlockStoreError> {
+        Ref::filter_map(self.deltas.borrow(), |deltas| deltas.last())
+            .map_err(|_| BlockStoreError::missing_file(&self.path.join("1.car")))
+    }
+
+    /// Whether or not `block` would take the most recent delta past the maximum CAR size
+    fn overflows(&self, delta: &CarV2DiskBlockStore, block: &Block) -> bool {
+        let index = delta.car.car.index.borrow();
+        let blocks = index.get_all_cids().len() as u64;
+        // Never leave a CAR empty, even if the Block alone is too large
+        if blocks == 0 || index.get_offset(&block.cid).is_some() {
+            return false;
+        }
+        let header = *delta.car.header.borrow();
+        // The Block, its length prefix, and its entry in the index
+        let block_size = block.varint as u64 + 10;
+        let projected =
+            header.data_offset + header.data_size + block_size + (blocks + 1) * INDEX_ENTRY_SIZE;
+        projected > self.max_car_size
+    }
+
+    /// Overwrite the copy of a Block in the most recent delta which holds it,
+    /// or write it into the current delta if none do
+    pub fn overwrite_block(&self, block: &Block) -> Result<(), BlockStoreError> {
+        let deltas = self.deltas.borrow();
+        let holder = deltas.iter().rev().find(|store| {
+            store
+                .car
+                .car
+                .index
+                .borrow()
+                .get_offset(&block.cid)
+                .is_some()
+        });
+        match holder {
+            Some(store) => store.overwrite_block(block),
+            None => self.get_delta()?.overwrite_block(block),
+        }
     }
 }
 
@@ -107,11 +200,11 @@ impl MultiCarV2DiskBlockStore {
 impl BanyanBlockStore for MultiCarV2DiskBlockStore {
     async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
         // Iterate in reverse order
-        for store in self.deltas.iter().rev() {
+        for store in self.deltas.borrow().iter().rev() {
             // If block is retrieved
             if let Ok(data) = store.get_block(cid).await {
-                // Ok
-                return Ok(data);
+                // Copy it out of the borrow of the deltas
+                return Ok(Cow::Owned(data.into_owned()));
             }
         }
 
@@ -120,10 +213,14 @@ impl BanyanBlockStore for MultiCarV2DiskBlockStore {
     }
 
     async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
-        // If there is a delta
-        let current_delta = self.get_delta()?;
-        let cid = current_delta.put_block(bytes, codec).await?;
-        Ok(cid)
+        let block = Block::new(bytes, codec)?;
+        // Start a new CAR if this Block would not fit in the current one
+        if self.overflows(&self.get_delta()?, &block) {
+            self.push_delta()?;
+            self.rolled_over.set(self.rolled_over.get() + 1);
+        }
+        self.get_delta()?.append_block(&block)?;
+        Ok(block.cid)
     }
 }
 
@@ -137,13 +234,26 @@ impl RootedBlockStore for MultiCarV2DiskBlockStore {
         }
     }
 
-    fn set_root(&self, root: &Cid) {
-        if !self.deltas.is_empty() {
-            let current_delta = self.get_delta().unwrap();
-            current_delta.set_root(root);
-            current_delta.to_disk().expect("failed to write to disk");
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
+        match self.get_delta() {
+            Ok(current_delta) => current_delta.set_root(root),
+            // Without a delta there is nothing to root yet
+            Err(_) => Ok(()),
         }
     }
+
+    /// Cids held across every delta
+    async fn all_cids(&self) -> Vec<Cid> {
+        let mut cids: Vec<Cid> = self
+            .deltas
+            .borrow()
+            .iter()
+            .flat_map(|delta| delta.car.car.index.borrow().get_all_cids())
+            .collect();
+        cids.sort();
+        cids.dedup();
+        cids
+    }
 }
 
 #[async_trait(?Send)]
@@ -151,27 +261,84 @@ impl UploadContent for MultiCarV2DiskBlockStore {
     type UploadError = WnfsError;
 
     fn get_hash(&self) -> Result<String, Self::UploadError> {
+        self.check_uploadable()?;
         let reader = std::fs::File::open(&self.get_delta()?.path)?;
         let mut hasher = blake3::Hasher::new();
         hasher.update_reader(&reader)?;
         Ok(hasher.finalize().to_string())
     }
 
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError> {
-        Ok(tokio::fs::File::open(&self.get_delta()?.path).await?.into())
+    async fn get_body(&self, offset: u64) -> Result<ContentType, Self::UploadError> {
+        self.check_uploadable()?;
+        let path = self.get_delta()?.path.clone();
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(file.into())
+    }
+
+    async fn get_streamed_body(
+        &self,
+        offset: u64,
+        rate_limit: Option<RateLimiter>,
+        idle: &IdleTimeout,
+    ) -> Result<ContentType, Self::UploadError> {
+        self.check_uploadable()?;
+        let path = self.get_delta()?.path.clone();
+        let mut file = tokio::fs::File::open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(idle.body(file, rate_limit))
     }
 
     fn get_length(&self) -> Result<u64, Self::UploadError> {
+        self.check_uploadable()?;
         Ok(self.get_delta()?.path.metadata()?.len())
     }
 }
 
+/// How a MultiCarV2DiskBlockStore is recorded in configuration. Stores without a maximum CAR
+/// size are recorded as just their path, as they always have been.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredMultiCarV2 {
+    Path(PathBuf),
+    Sized {
+        path: PathBuf,
+        max_car_size: u64,
+        #[serde(default)]
+        rolled_over: usize,
+    },
+}
+
 impl Serialize for MultiCarV2DiskBlockStore {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        self.path.serialize(serializer)
+        let path = self.path.clone();
+        if self.max_car_size == u64::MAX && self.rolled_over.get() == 0 {
+            StoredMultiCarV2::Path(path)
+        } else {
+            StoredMultiCarV2::Sized {
+                path,
+                max_car_size: self.max_car_size,
+                rolled_over: self.rolled_over.get(),
+            }
+        }
+        .serialize(serializer)
+    }
+}
+
+impl MultiCarV2DiskBlockStore {
+    /// Read where a MultiCarV2DiskBlockStore is recorded to be without loading any of its CARs
+    pub(crate) fn deserialize_path<'de, D>(
+        deserializer: D,
+    ) -> std::result::Result<PathBuf, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match StoredMultiCarV2::deserialize(deserializer)? {
+            StoredMultiCarV2::Path(path) | StoredMultiCarV2::Sized { path, .. } => path,
+        })
     }
 }
 
@@ -180,10 +347,20 @@ impl<'de> Deserialize<'de> for MultiCarV2DiskBlockStore {
     where
         D: serde::Deserializer<'de>,
     {
-        let path = PathBuf::deserialize(deserializer)?;
-        Self::load(&path).map_err(|err| {
+        let (path, max_car_size, rolled_over) = match StoredMultiCarV2::deserialize(deserializer)? {
+            StoredMultiCarV2::Path(path) => (path, u64::MAX, 0),
+            StoredMultiCarV2::Sized {
+                path,
+                max_car_size,
+                rolled_over,
+            } => (path, max_car_size, rolled_over),
+        };
+        let mut store = Self::load(&path).map_err(|err| {
             serde::de::Error::custom(format!("MultiCARv2 Deserialization error: {err}"))
-        })
+        })?;
+        store.max_car_size = max_car_size;
+        store.rolled_over.set(rolled_over);
+        Ok(store)
     }
 }
 
@@ -196,7 +373,12 @@ mod test {
         libipld::IpldCodec,
     };
 
-    use crate::blockstore::{BanyanBlockStore, BlockStoreError, MultiCarV2DiskBlockStore};
+    use crate::{
+        api::requests::staging::upload::content::UploadContent,
+        blockstore::{
+            BanyanBlockStore, BlockStoreError, MultiCarV2DiskBlockStore, RootedBlockStore,
+        },
+    };
 
     #[tokio::test]
     #[serial]
@@ -212,7 +394,7 @@ mod test {
         store.add_delta()?;
 
         // Assert that there are now two delta CARs
-        assert_eq!(store.deltas.len(), 1);
+        assert_eq!(store.deltas.borrow().len(), 1);
 
         let hello_kitty = "Hello Kitty!".as_bytes().to_vec();
         let hello_kitty_cid = store.put_block(hello_kitty.clone(), IpldCodec::Raw).await?;
@@ -220,7 +402,7 @@ mod test {
         // Create a new delta
         store.add_delta()?;
         // Assert that there are now two delta CARs
-        assert_eq!(store.deltas.len(), 2);
+        assert_eq!(store.deltas.borrow().len(), 2);
 
         let goodbye_kitty = "Goodbye Kitty!".as_bytes().to_vec();
         let goodbye_kitty_cid = store
@@ -254,7 +436,7 @@ mod test {
         // Create a new delta
         store.add_delta()?;
         // Assert that there is one CAR
-        assert_eq!(store.deltas.len(), 1);
+        assert_eq!(store.deltas.borrow().len(), 1);
 
         let hello_kitty = "Hello Kitty!".as_bytes().to_vec();
         let hello_kitty_cid = store.put_block(hello_kitty.clone(), IpldCodec::Raw).await?;
@@ -277,6 +459,50 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn max_car_size() -> Result<(), BlockStoreError> {
+        let path = &Path::new("test").join("max_car_size");
+        if path.exists() {
+            remove_dir_all(path)?;
+        }
+        let max_car_size = 4096;
+        let mut store = MultiCarV2DiskBlockStore::new(path)?;
+        store.add_delta()?;
+        store.max_car_size = max_car_size;
+
+        let mut blocks = Vec::new();
+        for i in 0..32u8 {
+            let bytes = vec![i; 500];
+            let cid = store.put_block(bytes.clone(), IpldCodec::Raw).await?;
+            blocks.push((cid, bytes));
+        }
+        store.set_root(&blocks[0].0)?;
+
+        // The Blocks were spread across several CARs, none of which are too large
+        assert!(store.deltas.borrow().len() > 1);
+        for delta in store.deltas.borrow().iter() {
+            delta.to_disk()?;
+            assert!(delta.path.metadata()?.len() <= max_car_size);
+        }
+
+        // Every Block resolves, before and after reloading the store
+        let stored = serde_json::to_string(&store).expect("serialize");
+        let reloaded: MultiCarV2DiskBlockStore =
+            serde_json::from_str(&stored).expect("deserialize");
+        assert_eq!(reloaded.max_car_size, max_car_size);
+        // None of the content written since the first delta is uploaded
+        assert!(store.check_uploadable().is_err());
+        assert!(reloaded.check_uploadable().is_err());
+        assert!(reloaded.get_length().is_err());
+        assert_eq!(reloaded.deltas.borrow().len(), store.deltas.borrow().len());
+        for (cid, bytes) in &blocks {
+            assert_eq!(&store.get_block(cid).await?.to_vec(), bytes);
+            assert_eq!(&reloaded.get_block(cid).await?.to_vec(), bytes);
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn multifileblockstore() -> Result<(), BlockStoreError> {
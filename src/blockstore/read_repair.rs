@@ -0,0 +1,152 @@
+use super::{BanyanBlockStore, BlockStoreError, MultiCarV2DiskBlockStore, RootedBlockStore};
+use crate::{
+    car::{error::CarError, v1::Block},
+    LibipldError,
+};
+use async_trait::async_trait;
+use std::borrow::Cow;
+use wnfs::libipld::{Cid, IpldCodec};
+
+/// BlockStore over local CAR deltas backed by a remote. Every local read is checked
+/// against its Cid, and corrupt blocks are re-fetched from the remote and overwritten
+/// locally before being served.
+#[derive(Debug)]
+pub struct ReadRepairStore<'a, R: BanyanBlockStore> {
+    /// Local copy of the blocks, which is repaired in place
+    local: &'a MultiCarV2DiskBlockStore,
+    /// BlockStore holding known good copies of the blocks
+    remote: &'a R,
+}
+
+impl<'a, R: BanyanBlockStore> ReadRepairStore<'a, R> {
+    /// Create a new read-repair BlockStore
+    pub fn new(local: &'a MultiCarV2DiskBlockStore, remote: &'a R) -> Self {
+        Self { local, remote }
+    }
+
+    /// Re-fetch a block from the remote and overwrite the local copy with it
+    async fn repair(&self, cid: &Cid) -> Result<Vec<u8>, BlockStoreError> {
+        let content = BanyanBlockStore::get_block(self.remote, cid)
+            .await?
+            .to_vec();
+        let block = Block {
+            varint: (cid.encoded_len() + content.len()) as u128,
+            cid: *cid,
+            content,
+        };
+        // Never replace one bad copy with another
+        if !block.verify() {
+            return Err(BlockStoreError::car(CarError::corrupt_block(cid)));
+        }
+        self.local.overwrite_block(&block)?;
+        info!("🩹 Repaired block {cid}");
+        Ok(block.content)
+    }
+}
+
+#[async_trait(?Send)]
+impl<R: BanyanBlockStore> BanyanBlockStore for ReadRepairStore<'_, R> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        match BanyanBlockStore::get_block(self.local, cid).await {
+            Ok(content) => {
+                let block = Block {
+                    varint: (cid.encoded_len() + content.len()) as u128,
+                    cid: *cid,
+                    content: content.to_vec(),
+                };
+                if block.verify() {
+                    Ok(Cow::Owned(block.content))
+                } else {
+                    warn!("Local copy of block {cid} does not match its CID, re-fetching it");
+                    Ok(Cow::Owned(self.repair(cid).await?))
+                }
+            }
+            // Blocks we don't have locally are served straight from the remote
+            Err(_) => BanyanBlockStore::get_block(self.remote, cid).await,
+        }
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        BanyanBlockStore::put_block(self.local, bytes, codec).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<R: BanyanBlockStore> RootedBlockStore for ReadRepairStore<'_, R> {
+    fn get_root(&self) -> Option<Cid> {
+        self.local.get_root()
+    }
+
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
+        self.local.set_root(root)
+    }
+
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.local.all_cids().await
+    }
+}
+
+#[async_trait(?Send)]
+impl<R: BanyanBlockStore> wnfs::common::BlockStore for ReadRepairStore<'_, R> {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReadRepairStore;
+    use crate::blockstore::{
+        BanyanBlockStore, BlockStoreError, CarV2MemoryBlockStore, MultiCarV2DiskBlockStore,
+    };
+    use serial_test::serial;
+    use std::{
+        fs::{remove_dir_all, OpenOptions},
+        io::{Seek, SeekFrom, Write},
+        path::Path,
+    };
+    use wnfs::libipld::IpldCodec;
+
+    #[tokio::test]
+    #[serial]
+    async fn repairs_corrupt_block() -> Result<(), BlockStoreError> {
+        let dir = Path::new("test").join("car").join("read_repair");
+        if dir.exists() {
+            remove_dir_all(&dir)?;
+        }
+        let mut local = MultiCarV2DiskBlockStore::new(&dir)?;
+        local.add_delta()?;
+        let remote = CarV2MemoryBlockStore::new()?;
+
+        let kitty_bytes = "Hello Kitty!".as_bytes().to_vec();
+        let kitty_cid = local.put_block(kitty_bytes.clone(), IpldCodec::Raw).await?;
+        BanyanBlockStore::put_block(&remote, kitty_bytes.clone(), IpldCodec::Raw).await?;
+        local.get_delta()?.to_disk()?;
+
+        // Flip the last byte of the block's content on disk
+        let delta = local.get_delta()?;
+        let header = *delta.car.header.borrow();
+        let last_byte = header.data_offset + header.data_size - 1;
+        let mut file = OpenOptions::new().write(true).open(&delta.path)?;
+        file.seek(SeekFrom::Start(last_byte))?;
+        file.write_all(&[kitty_bytes[kitty_bytes.len() - 1] ^ 0xff])?;
+        assert_ne!(local.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+
+        // Reading through the repair store serves and restores the good copy
+        let store = ReadRepairStore::new(&local, &remote);
+        assert_eq!(store.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        assert_eq!(local.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        // The repair survives reloading from disk
+        let reloaded = MultiCarV2DiskBlockStore::load(&dir)?;
+        assert_eq!(reloaded.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        Ok(())
+    }
+}
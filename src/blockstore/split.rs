@@ -7,31 +7,56 @@ use wnfs::{
     libipld::{Cid, IpldCodec},
 };
 
-/// Blockstore built over two
+/// Which half of a DoubleSplitStore is read from first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadOrder {
+    /// Read from the primary store, falling back to the secondary
+    #[default]
+    PrimaryFirst,
+    /// Read from the secondary store, falling back to the primary
+    SecondaryFirst,
+}
+
+/// Blockstore built over two. Writes always go to both, and must succeed on the primary.
 #[derive(Debug)]
 pub struct DoubleSplitStore<'a, M: BanyanBlockStore, D: BanyanBlockStore> {
     primary: &'a M,
     secondary: &'a D,
+    read_order: ReadOrder,
 }
 
+#[async_trait(?Send)]
 impl<M: RootedBlockStore, D: BanyanBlockStore> RootedBlockStore for DoubleSplitStore<'_, M, D> {
     fn get_root(&self) -> Option<Cid> {
         self.primary.get_root()
     }
 
-    fn set_root(&self, root: &Cid) {
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
         self.primary.set_root(root)
     }
+
+    /// Only the Blocks of the primary store are listed
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.primary.all_cids().await
+    }
 }
 
 #[async_trait(?Send)]
 impl<M: BanyanBlockStore, D: BanyanBlockStore> BanyanBlockStore for DoubleSplitStore<'_, M, D> {
     async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
-        match BlockStore::get_block(self.primary, cid).await {
-            Ok(blk) => Ok(blk),
-            Err(_) => BlockStore::get_block(self.secondary, cid)
-                .await
-                .map_err(|err| BlockStoreError::wnfs(Box::from(err))),
+        match self.read_order {
+            ReadOrder::PrimaryFirst => match BlockStore::get_block(self.primary, cid).await {
+                Ok(blk) => Ok(blk),
+                Err(_) => BlockStore::get_block(self.secondary, cid)
+                    .await
+                    .map_err(|err| BlockStoreError::wnfs(Box::from(err))),
+            },
+            ReadOrder::SecondaryFirst => match BlockStore::get_block(self.secondary, cid).await {
+                Ok(blk) => Ok(blk),
+                Err(_) => BlockStore::get_block(self.primary, cid)
+                    .await
+                    .map_err(|err| BlockStoreError::wnfs(Box::from(err))),
+            },
         }
     }
 
@@ -48,9 +73,19 @@ impl<M: BanyanBlockStore, D: BanyanBlockStore> BanyanBlockStore for DoubleSplitS
 }
 
 impl<'a, M: BanyanBlockStore, D: BanyanBlockStore> DoubleSplitStore<'a, M, D> {
-    /// Create a new split BlockStore
+    /// Create a new split BlockStore which reads from the primary store first
     pub fn new(primary: &'a M, secondary: &'a D) -> Self {
-        Self { primary, secondary }
+        Self::with_read_order(primary, secondary, ReadOrder::PrimaryFirst)
+    }
+
+    /// Create a new split BlockStore which reads from its stores in the given order, only
+    /// consulting the second if the first does not have a block
+    pub fn with_read_order(primary: &'a M, secondary: &'a D, read_order: ReadOrder) -> Self {
+        Self {
+            primary,
+            secondary,
+            read_order,
+        }
     }
 }
 
@@ -70,3 +105,38 @@ impl<M: BanyanBlockStore, D: BanyanBlockStore> wnfs::common::BlockStore
             .map_err(|err| LibipldError::msg(err.to_string()))
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod test {
+    use super::{DoubleSplitStore, ReadOrder};
+    use crate::blockstore::{
+        BanyanBlockStore, BlockStoreError, CachedBlockStore, MemoryBlockStore,
+    };
+    use wnfs::libipld::IpldCodec;
+
+    #[tokio::test]
+    async fn read_order() -> Result<(), BlockStoreError> {
+        let primary = CachedBlockStore::new(MemoryBlockStore::default(), 1024);
+        let secondary = CachedBlockStore::new(MemoryBlockStore::default(), 1024);
+        let kitty_bytes = "Hello Kitty!".as_bytes().to_vec();
+        let kitty_cid = DoubleSplitStore::new(&primary, &secondary)
+            .put_block(kitty_bytes.clone(), IpldCodec::Raw)
+            .await?;
+
+        // Blocks found in the first store never touch the second
+        let store =
+            DoubleSplitStore::with_read_order(&primary, &secondary, ReadOrder::SecondaryFirst);
+        assert_eq!(store.get_block(&kitty_cid).await?.to_vec(), kitty_bytes);
+        assert_eq!(secondary.hit_rate(), 1.0);
+        assert_eq!(primary.hit_rate(), 0.0);
+
+        // Blocks missing from the first store are read from the second
+        let puppy_bytes = "Hello Puppy!".as_bytes().to_vec();
+        let puppy_cid = primary
+            .put_block(puppy_bytes.clone(), IpldCodec::Raw)
+            .await?;
+        assert_eq!(store.get_block(&puppy_cid).await?.to_vec(), puppy_bytes);
+        Ok(())
+    }
+}
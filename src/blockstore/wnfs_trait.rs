@@ -1,7 +1,10 @@
 use std::borrow::Cow;
 
 use async_trait::async_trait;
-use wnfs::libipld::{Cid, IpldCodec};
+use wnfs::{
+    common::dagcbor,
+    libipld::{Cid, Ipld, IpldCodec},
+};
 
 use crate::LibipldError;
 
@@ -16,6 +19,36 @@ use crate::blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore};
 pub trait BanyanBlockStore: wnfs::common::BlockStore {
     async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError>;
     async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError>;
+
+    /// Put a block after checking that its bytes are well formed for the codec, so that tools
+    /// outside this crate can parse DAG-CBOR and DAG-JSON blocks by their CID alone
+    async fn put_block_with_codec(
+        &self,
+        bytes: Vec<u8>,
+        codec: IpldCodec,
+    ) -> Result<Cid, BlockStoreError> {
+        let well_formed = match codec {
+            IpldCodec::DagCbor => dagcbor::decode::<Ipld>(&bytes).is_ok(),
+            IpldCodec::DagJson => serde_json::from_slice::<serde_json::Value>(&bytes).is_ok(),
+            _ => true,
+        };
+        if !well_formed {
+            return Err(BlockStoreError::malformed_block(codec));
+        }
+        BanyanBlockStore::put_block(self, bytes, codec).await
+    }
+
+    /// Get a block, failing if its CID does not name the expected codec
+    async fn get_block_with_codec(
+        &self,
+        cid: &Cid,
+        codec: IpldCodec,
+    ) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        if cid.codec() != u64::from(codec) {
+            return Err(BlockStoreError::codec_mismatch(cid, codec));
+        }
+        BanyanBlockStore::get_block(self, cid).await
+    }
 }
 
 macro_rules! impl_wnfs_blockstore {
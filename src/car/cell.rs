@@ -0,0 +1,64 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt::{Debug, Formatter},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Interior mutability for the headers and indexes of CARs which, unlike a `RefCell`, can be
+/// shared between threads. Borrows block rather than panic while another thread holds them, so
+/// the same borrow may never be held twice on one thread if either of them is mutable.
+#[derive(Default)]
+pub struct SyncCell<T>(RwLock<T>);
+
+impl<T> SyncCell<T> {
+    /// Wrap a value
+    pub fn new(value: T) -> Self {
+        Self(RwLock::new(value))
+    }
+
+    /// Immutably borrow the value, waiting for any mutable borrow on another thread to end
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        // A panic mid-write leaves the value no less consistent than a RefCell would
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Mutably borrow the value, waiting for every other borrow on another thread to end
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Unwrap the value
+    pub fn into_inner(self) -> T {
+        self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Clone> Clone for SyncCell<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.borrow().clone())
+    }
+}
+
+impl<T: PartialEq> PartialEq for SyncCell<T> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl<T: Debug> Debug for SyncCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SyncCell").field(&*self.borrow()).finish()
+    }
+}
+
+impl<T: Serialize> Serialize for SyncCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.borrow().serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SyncCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
@@ -0,0 +1,161 @@
+use super::{
+    error::CarError,
+    v1,
+    v2::{
+        index::{indexable::Indexable, indexsorted::Bucket, Index},
+        CarV2,
+    },
+};
+use crate::utils::{get_read, get_read_write};
+use std::{
+    fs::remove_file,
+    io::{copy, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+use wnfs::libipld::Cid;
+
+/// Combine the data payloads of several CarV2 files into a single CarV2 at `output`.
+/// Blocks shared between inputs are only stored once. The combined CarV2 has `root` as its
+/// root if one is given, otherwise the root of the first input which has one.
+pub fn concat(inputs: &[PathBuf], output: &Path, root: Option<Cid>) -> Result<CarV2, CarError> {
+    if output.exists() {
+        remove_file(output)?;
+    }
+    let mut out = get_read_write(output)?;
+    let car = CarV2::new(&mut out)?;
+    let mut first_root = None;
+
+    for input in inputs {
+        let mut r = get_read(input)?;
+        let input_car = CarV2::read_bytes(&mut r)?;
+        first_root = first_root.or(input_car.get_root());
+        let input_index = input_car.car.index.borrow().clone();
+
+        let shared = input_index
+            .get_all_cids()
+            .iter()
+            .any(|cid| car.car.index.borrow().get_offset(cid).is_some());
+        if shared {
+            // Copy blocks one by one, in the order they were written, skipping duplicates
+            let mut blocks = input_index
+                .buckets
+                .iter()
+                .flat_map(|bucket| bucket.map.iter().map(|(cid, offset)| (*offset, *cid)))
+                .collect::<Vec<(u64, Cid)>>();
+            blocks.sort();
+            for (_, cid) in blocks {
+                car.put_block(&input_car.get_block(&cid, &mut r)?, &mut out)?;
+            }
+        } else {
+            // Nothing to deduplicate, so append the whole block payload at once
+            let header = *input_car.header.borrow();
+            r.seek(SeekFrom::Start(header.data_offset))?;
+            v1::Header::read_bytes(&mut r)?;
+            let blocks_start = r.stream_position()?;
+            let blocks_end = header.data_offset + header.data_size;
+
+            let out_header = *car.header.borrow();
+            let next_block = out_header.data_offset + out_header.data_size;
+            out.seek(SeekFrom::Start(next_block))?;
+            copy(&mut (&mut r).take(blocks_end - blocks_start), &mut out)?;
+
+            car.car
+                .index
+                .borrow_mut()
+                .merge(&rebase(&input_index, blocks_start), next_block);
+            car.update_header(out.stream_position()?)?;
+        }
+    }
+
+    if let Some(root) = root.or(first_root) {
+        car.set_root(&root);
+    }
+    car.write_bytes(&mut out)?;
+    Ok(car)
+}
+
+/// Copy of an Index with every offset made relative to `start`
+fn rebase(index: &Index<Bucket>, start: u64) -> Index<Bucket> {
+    let mut rebased = index.clone();
+    for bucket in &mut rebased.buckets {
+        for offset in bucket.map.values_mut() {
+            *offset -= start;
+        }
+    }
+    rebased
+}
+
+#[cfg(test)]
+mod test {
+    use super::concat;
+    use crate::{
+        car::{error::CarError, v1::Block, v2::CarV2},
+        utils::{get_read, get_read_write},
+    };
+    use serial_test::serial;
+    use std::{
+        fs::{create_dir_all, remove_file},
+        path::{Path, PathBuf},
+    };
+    use wnfs::libipld::{Cid, IpldCodec};
+
+    /// Write a CarV2 containing one Block per content at a given path
+    fn write_car(path: &Path, contents: &[&str]) -> Result<Vec<Cid>, CarError> {
+        if path.exists() {
+            remove_file(path)?;
+        }
+        let mut file = get_read_write(path)?;
+        let car = CarV2::new(&mut file)?;
+        let mut cids = Vec::new();
+        for content in contents {
+            let block = Block::new(content.as_bytes().to_vec(), IpldCodec::Raw)?;
+            car.put_block(&block, &mut file)?;
+            cids.push(block.cid);
+        }
+        car.set_root(&cids[0]);
+        car.write_bytes(&mut file)?;
+        Ok(cids)
+    }
+
+    #[test]
+    #[serial]
+    fn concat_two() -> Result<(), CarError> {
+        let dir = Path::new("test").join("car").join("concat");
+        create_dir_all(&dir)?;
+        let first = dir.join("first.car");
+        let second = dir.join("second.car");
+        let third = dir.join("third.car");
+        let first_cids = write_car(&first, &["kitty", "puppy"])?;
+        let second_cids = write_car(&second, &["bunny", "birdy"])?;
+        // Shares a block with the first CAR
+        let third_cids = write_car(&third, &["lizard", "kitty"])?;
+
+        let output = dir.join("combined.car");
+        concat(&[first, second, third], &output, None)?;
+
+        let mut file = get_read(&output)?;
+        let combined = CarV2::read_bytes(&mut file)?;
+        assert!(combined.verify(&mut file)?.is_empty());
+        // The root of the first input is kept
+        assert_eq!(combined.get_root(), Some(first_cids[0]));
+        let all_cids = [first_cids, second_cids, third_cids].concat();
+        for cid in &all_cids {
+            assert_eq!(combined.get_block(cid, &mut file)?.cid, *cid);
+        }
+        // The shared block was only stored once
+        assert_eq!(combined.car.index.borrow().get_all_cids().len(), 5);
+
+        // A chosen root replaces the inputs' roots
+        let chosen = all_cids[2];
+        let output = dir.join("combined_rooted.car");
+        let inputs: Vec<PathBuf> = ["first", "second"]
+            .iter()
+            .map(|name| dir.join(format!("{name}.car")))
+            .collect();
+        assert_eq!(
+            concat(&inputs, &output, Some(chosen))?.get_root(),
+            Some(chosen)
+        );
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt::Display;
 use colored::Colorize;
 use wnfs::libipld::Cid;
 
-use crate::utils::UtilityError;
+use crate::{car::v2::PH_SIZE, utils::UtilityError};
 
 #[derive(Debug)]
 pub struct CarError {
@@ -31,6 +31,24 @@ impl CarError {
         }
     }
 
+    pub fn bad_pragma() -> Self {
+        Self {
+            kind: CarErrorKind::BadPragma,
+        }
+    }
+
+    pub fn bad_header_length(length: u64) -> Self {
+        Self {
+            kind: CarErrorKind::BadHeaderLength(length),
+        }
+    }
+
+    pub fn truncated(expected: u64, actual: u64) -> Self {
+        Self {
+            kind: CarErrorKind::Truncated { expected, actual },
+        }
+    }
+
     pub fn index() -> Self {
         Self {
             kind: CarErrorKind::Index,
@@ -49,6 +67,30 @@ impl CarError {
         }
     }
 
+    pub fn corrupt_block(cid: &Cid) -> Self {
+        Self {
+            kind: CarErrorKind::CorruptBlock(cid.to_owned()),
+        }
+    }
+
+    pub fn malformed_node(cid: &Cid) -> Self {
+        Self {
+            kind: CarErrorKind::MalformedNode(cid.to_owned()),
+        }
+    }
+
+    pub fn root_removal(cid: &Cid) -> Self {
+        Self {
+            kind: CarErrorKind::RootRemoval(cid.to_owned()),
+        }
+    }
+
+    pub fn block_too_large(size: u128, limit: u64) -> Self {
+        Self {
+            kind: CarErrorKind::BlockTooLarge { size, limit },
+        }
+    }
+
     pub fn io_error(err: std::io::Error) -> Self {
         Self {
             kind: CarErrorKind::Io(err),
@@ -74,9 +116,28 @@ impl Display for CarError {
             CarErrorKind::MissingRoot => "Missing Root CID".to_owned(),
             CarErrorKind::MissingBlock(cid) => format!("Missing Block with CID: {}", cid),
             CarErrorKind::V1Header => "Malformed CARv1 Header".to_owned(),
+            CarErrorKind::BadPragma => "Missing or malformed CARv2 pragma".to_owned(),
+            CarErrorKind::BadHeaderLength(length) => {
+                format!("CARv2 pragma and Header took up {length} bytes rather than {PH_SIZE}")
+            }
+            CarErrorKind::Truncated { expected, actual } => format!(
+                "CARv2 Header describes {expected} bytes, but only {actual} bytes are present"
+            ),
             CarErrorKind::Index => "Malformed CARv2 Index".to_owned(),
             CarErrorKind::Codec => "Invalid Index Codec".to_owned(),
             CarErrorKind::EndOfData => "Expected more data in CAR than was present".to_owned(),
+            CarErrorKind::CorruptBlock(cid) => {
+                format!("Block content does not match its CID: {}", cid)
+            }
+            CarErrorKind::MalformedNode(cid) => {
+                format!("Block can not be decoded as a DAG node: {}", cid)
+            }
+            CarErrorKind::RootRemoval(cid) => {
+                format!("The root Block can not be removed: {}", cid)
+            }
+            CarErrorKind::BlockTooLarge { size, limit } => format!(
+                "Block declares a length of {size} bytes, exceeding the maximum of {limit} bytes"
+            ),
             CarErrorKind::Cid(err) => format!("{} {err}", "CID ERROR:".underline()),
             CarErrorKind::Io(err) => format!("{} {err}", "IO ERROR:".underline()),
             CarErrorKind::Utility(err) => format!("{} {err}", "UTILITY ERROR:".underline()),
@@ -93,12 +154,32 @@ pub enum CarErrorKind {
     MissingBlock(Cid),
     /// The CARv1 Header was not correct
     V1Header,
+    /// The CARv2 pragma was not correct
+    BadPragma,
+    /// The CARv2 pragma and Header did not end where expected
+    BadHeaderLength(u64),
+    /// The CARv2 is shorter than its Header describes
+    Truncated {
+        expected: u64,
+        actual: u64,
+    },
     /// The CARv2 Index was not correct
     Index,
     /// Index codec
     Codec,
     /// Index codec
     EndOfData,
+    /// A Block's content does not hash to its Cid
+    CorruptBlock(Cid),
+    /// A Block's content could not be decoded according to its codec
+    MalformedNode(Cid),
+    /// The root Block was asked to be removed
+    RootRemoval(Cid),
+    /// A Block declared a length greater than the maximum block size
+    BlockTooLarge {
+        size: u128,
+        limit: u64,
+    },
     Io(std::io::Error),
     Cid(wnfs::libipld::cid::Error),
     Utility(UtilityError),
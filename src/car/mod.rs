@@ -1,3 +1,8 @@
+/// Thread-safe interior mutability
+mod cell;
+/// Concatenation of CARs
+#[cfg(not(target_arch = "wasm32"))]
+mod concat;
 /// CAR Errors
 pub(crate) mod error;
 /// Streamable Trait and testing Macro
@@ -7,5 +12,8 @@ pub mod v1;
 /// CARv2
 pub mod v2;
 
+pub use cell::SyncCell;
+#[cfg(not(target_arch = "wasm32"))]
+pub use concat::concat;
 #[allow(unused)]
 pub(crate) use streamable::{streamable_tests, Streamable};
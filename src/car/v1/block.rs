@@ -2,12 +2,31 @@ use crate::{
     car::{error::CarError, Streamable},
     utils::varint::{encode_varint_u128, read_varint_u128},
 };
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    sync::atomic::{AtomicU64, Ordering},
+};
 use wnfs::libipld::{
     multihash::{Code, MultihashDigest},
     Cid, IpldCodec,
 };
 
+/// Default maximum length of a single Block, matching the section size limit of go-car
+pub const MAX_BLOCK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Maximum length of a Block that will be read from a CAR
+static BLOCK_SIZE_LIMIT: AtomicU64 = AtomicU64::new(MAX_BLOCK_SIZE);
+
+/// Set the maximum length of a Block that will be read from a CAR
+pub fn set_max_block_size(limit: u64) {
+    BLOCK_SIZE_LIMIT.store(limit, Ordering::SeqCst);
+}
+
+/// Get the maximum length of a Block that will be read from a CAR
+pub fn max_block_size() -> u64 {
+    BLOCK_SIZE_LIMIT.load(Ordering::SeqCst)
+}
+
 /// CARv1 Data Block
 /// | 19-byte varint | x-byte Cid | x-byte content |
 #[derive(PartialEq, Debug)]
@@ -23,10 +42,17 @@ pub struct Block {
 impl Block {
     /// Given some data, create a Cid and varint to match
     pub fn new(content: Vec<u8>, codec: IpldCodec) -> Result<Self, CarError> {
+        Self::new_with_codec(content, codec.into())
+    }
+
+    /// Given some data, create a Block whose Cid carries any multicodec code. Use this for
+    /// DAGs whose codec has no `IpldCodec` variant; the content is stored as is and hashed
+    /// with SHA2-256 like every other Block.
+    pub fn new_with_codec(content: Vec<u8>, codec: u64) -> Result<Self, CarError> {
         // Compute the SHA256 hash of the bytes
         let hash = Code::Sha2_256.digest(&content);
         // Represent the hash as a CID V1
-        let cid = Cid::new_v1(codec.into(), hash);
+        let cid = Cid::new_v1(codec, hash);
         let varint = (cid.encoded_len() + content.len()) as u128;
         // Create new
         Ok(Self {
@@ -36,6 +62,16 @@ impl Block {
         })
     }
 
+    /// Whether the content hashes to the Cid, using the hash function the Cid names
+    pub fn verify(&self) -> bool {
+        let hash = self.cid.hash();
+        match Code::try_from(hash.code()) {
+            Ok(code) => code.digest(&self.content) == *hash,
+            // Content hashed with a function we do not know can not be verified
+            Err(_) => false,
+        }
+    }
+
     /// Read the Varint and Cid from stream only
     pub fn start_read<R: Read + Seek>(mut r: R) -> Result<(u128, Cid), CarError> {
         // Read the varint
@@ -53,6 +89,11 @@ impl Block {
 
     /// If start read was just called, grab the data that follows it and return a Block
     pub fn finish_read<R: Read + Seek>(varint: u128, cid: Cid, mut r: R) -> Result<Self, CarError> {
+        // Refuse to allocate for lengths no honest CAR would declare
+        let limit = max_block_size();
+        if varint > limit as u128 {
+            return Err(CarError::block_too_large(varint, limit));
+        }
         // Determine how much data has yet to be read from this block
         let content_length = varint as usize - cid.to_bytes().len();
         // Create a content vector with the specified capacity
@@ -105,4 +146,39 @@ mod test {
             crate::car::v1::Block::new(data_example, wnfs::libipld::IpldCodec::Raw).expect("unable to create new Block")
         },
     }
+
+    use crate::{
+        car::{error::CarError, v1::CarV1, Streamable},
+        utils::varint::encode_varint_u128,
+    };
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+    use wnfs::libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid, IpldCodec,
+    };
+
+    #[test]
+    fn absurd_block_length() -> Result<(), CarError> {
+        // Start with a valid CARv1
+        let mut car_bytes = Cursor::new(<Vec<u8>>::new());
+        CarV1::new(None, &mut car_bytes)?;
+        // Append a Block which claims to be a petabyte long but is only a CID
+        let cid = Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(b"kitty"));
+        car_bytes.seek(SeekFrom::End(0))?;
+        car_bytes.write_all(&encode_varint_u128(1 << 50))?;
+        car_bytes.write_all(&cid.to_bytes())?;
+        // Indexing the CAR only peeks at the length
+        car_bytes.seek(SeekFrom::Start(0))?;
+        let car = CarV1::read_bytes(None, &mut car_bytes)?;
+        // Reading the Block fails cleanly instead of allocating
+        let err = car
+            .get_block(&cid, &mut car_bytes)
+            .expect_err("read an absurdly large block");
+        assert!(err.to_string().contains("exceeding the maximum"));
+        car_bytes.seek(SeekFrom::Start(0))?;
+        crate::car::v1::Header::read_bytes(&mut car_bytes)?;
+        let err = super::Block::read_bytes(&mut car_bytes).expect_err("read block");
+        assert!(err.to_string().contains("exceeding the maximum"));
+        Ok(())
+    }
 }
@@ -1,11 +1,10 @@
 use crate::{
-    car::{error::CarError, Streamable},
+    car::{error::CarError, Streamable, SyncCell},
     utils::varint::{encode_varint_u64, read_varint_u64},
 };
 
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::RefCell,
     collections::BTreeMap,
     io::{Read, Seek, Write},
 };
@@ -21,7 +20,7 @@ pub struct Header {
     /// The version of the CAR (1 or 2)
     pub version: u64,
     /// The deserialized IPLD encoding the roots of the filesystem
-    pub roots: RefCell<Vec<Cid>>,
+    pub roots: SyncCell<Vec<Cid>>,
 }
 
 impl Header {
@@ -60,7 +59,7 @@ impl Header {
         // Return Ok with new Self
         Ok(Self {
             version: *int as u64,
-            roots: RefCell::new(roots),
+            roots: SyncCell::new(roots),
         })
     }
 
@@ -87,7 +86,7 @@ impl Header {
     pub(crate) fn default(version: u64) -> Self {
         Self {
             version,
-            roots: RefCell::new(Vec::new()),
+            roots: SyncCell::new(Vec::new()),
         }
     }
 }
@@ -115,8 +114,10 @@ impl Streamable for Header {
         let mut ipld_buf: Vec<u8> = vec![0; ipld_len as usize];
         // Read that IPLD in as DAGCBOR bytes
         r.read_exact(&mut ipld_buf)?;
-        // Assert
-        assert_eq!(ipld_buf.len() as u64, ipld_len);
+        // Ensure the whole Header was read
+        if ipld_buf.len() as u64 != ipld_len {
+            return Err(CarError::v1_header());
+        }
         // Reconstruct this object from those IPLD bytes
         Self::from_ipld_bytes(&ipld_buf)
     }
@@ -126,10 +127,9 @@ impl Streamable for Header {
 #[cfg(not(target_arch = "wasm32"))]
 mod test {
     use super::Header;
-    use crate::car::{error::CarError, Streamable};
+    use crate::car::{error::CarError, Streamable, SyncCell};
     use serial_test::serial;
     use std::{
-        cell::RefCell,
         fs::File,
         io::{BufReader, Cursor},
         path::Path,
@@ -166,7 +166,7 @@ mod test {
 
         let header = Header {
             version: 1,
-            roots: RefCell::new(vec![cid1, cid2]),
+            roots: SyncCell::new(vec![cid1, cid2]),
         };
 
         let mut bytes = Cursor::new(<Vec<u8>>::new());
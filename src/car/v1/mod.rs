@@ -3,16 +3,15 @@ mod block;
 /// CARv1 Header
 mod header;
 
-pub use block::Block;
+pub use block::{max_block_size, set_max_block_size, Block, MAX_BLOCK_SIZE};
 pub use header::Header;
 
 use crate::car::{
     v2::index::{indexable::Indexable, indexsorted::Bucket, Index, INDEX_SORTED_CODEC},
-    Streamable,
+    Streamable, SyncCell,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::RefCell,
     collections::HashMap,
     io::{Cursor, Read, Seek, SeekFrom, Write},
 };
@@ -26,7 +25,7 @@ pub struct CarV1 {
     /// The CARv1 Header
     pub header: Header,
     /// The CARv1 Index
-    pub index: RefCell<Index<Bucket>>,
+    pub index: SyncCell<Index<Bucket>>,
 }
 
 impl CarV1 {
@@ -45,14 +44,14 @@ impl CarV1 {
             let index = <Index<Bucket>>::read_bytes(&mut r)?;
             Ok(Self {
                 header,
-                index: RefCell::new(index),
+                index: SyncCell::new(index),
             })
         } else {
             // If we're in a CARv1, we have to create an Index by scanning over the Blocks
             let index = Index::read_from_carv1(&mut r)?;
             Ok(Self {
                 header,
-                index: RefCell::new(index),
+                index: SyncCell::new(index),
             })
         }
     }
@@ -161,11 +160,21 @@ impl CarV1 {
         Self::read_bytes(index_offset, rw)
     }
 
-    /// Set the singular root of the CarV1
+    /// Set the singular root of the CarV1, replacing any others
     pub fn set_root(&self, root: &Cid) {
         *self.header.roots.borrow_mut() = vec![*root];
     }
 
+    /// Set every root of the CarV1, in order
+    pub fn set_roots(&self, roots: &[Cid]) {
+        *self.header.roots.borrow_mut() = roots.to_vec();
+    }
+
+    /// Get every root of the CarV1, in order
+    pub fn get_roots(&self) -> Vec<Cid> {
+        self.header.roots.borrow().clone()
+    }
+
     /// Get the singular root of the CarV1
     pub fn get_root(&self) -> Option<Cid> {
         let roots = self.header.roots.borrow();
@@ -192,7 +201,7 @@ impl CarV1 {
             .expect("failed to write header as bytes");
         Self {
             header,
-            index: RefCell::new(Index {
+            index: SyncCell::new(Index {
                 codec: INDEX_SORTED_CODEC,
                 buckets: vec![],
             }),
@@ -305,6 +314,46 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[serial]
+    fn set_roots() -> Result<(), CarError> {
+        let car_path = &car_test_setup(1, "basic", "set_roots_original")?;
+        let mut rw = get_read_write(car_path)?;
+        let car = CarV1::read_bytes(None, &mut rw)?;
+        // Every root written by another tool is kept, not just the first
+        let original_roots = vec![
+            Cid::from_str("bafyreihyrpefhacm6kkp4ql6j6udakdit7g3dmkzfriqfykhjw6cad5lrm")?,
+            Cid::from_str("bafyreidj5idub6mapiupjwjsyyxhyhedxycv4vihfsicm2vt46o7morwlm")?,
+        ];
+        assert_eq!(car.get_roots(), original_roots);
+        assert_eq!(car.get_root(), Some(original_roots[0]));
+
+        // Roots of mixed codecs, and more of them than before, survive a rewrite
+        let roots = vec![
+            Cid::from_str("QmNX6Tffavsya4xgBi2VJQnSuqy9GsxongxZZ9uZBqp16d")?,
+            Cid::from_str("bafkreifw7plhl6mofk6sfvhnfh64qmkq73oeqwl6sloru6rehaoujituke")?,
+            original_roots[0],
+            original_roots[1],
+        ];
+        car.set_roots(&roots);
+        rw.seek(SeekFrom::Start(0))?;
+        car.write_bytes(&mut rw)?;
+        let rewritten = CarV1::read_bytes(None, &mut File::open(car_path)?)?;
+        assert_eq!(rewritten.get_roots(), roots);
+        assert_eq!(rewritten, car);
+
+        // As does having none at all, once the blocks left behind by the shrinking header are cut
+        car.set_roots(&[]);
+        rw.seek(SeekFrom::Start(0))?;
+        let len = car.write_bytes(&mut rw)?;
+        rw.set_len(len)?;
+        let rewritten = CarV1::read_bytes(None, &mut File::open(car_path)?)?;
+        assert!(rewritten.get_roots().is_empty());
+        assert_eq!(rewritten.get_root(), None);
+        assert_eq!(rewritten, car);
+        Ok(())
+    }
+
     #[test]
     #[serial]
     fn put_get_block() -> Result<(), CarError> {
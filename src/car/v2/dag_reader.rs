@@ -0,0 +1,161 @@
+use super::CarV2;
+use crate::car::error::CarError;
+use std::io::{self, Read, Seek};
+use wnfs::{
+    common::dagcbor,
+    libipld::{Cid, Ipld, IpldCodec},
+};
+
+/// Reader over the bytes of a DAG within a CarV2, produced by a depth-first traversal from a
+/// root. Raw leaves are yielded as they are, DAG-PB nodes yield the data of their UnixFS
+/// payload before that of their children, and DAG-CBOR nodes only contribute their links.
+/// Only one Block is held in memory at a time.
+pub struct DagReader<'a, R> {
+    car: &'a CarV2,
+    r: R,
+    /// Cids still to be visited, the next one last
+    pending: Vec<Cid>,
+    /// Data of the Block currently being read
+    buffer: Vec<u8>,
+    /// How much of the buffer has already been read
+    position: usize,
+}
+
+impl<'a, R: Read + Seek> DagReader<'a, R> {
+    pub(crate) fn new(car: &'a CarV2, root: &Cid, r: R) -> Self {
+        Self {
+            car,
+            r,
+            pending: vec![*root],
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Load the data of a Block and queue its links to be visited next
+    fn visit(&mut self, cid: &Cid) -> Result<(), CarError> {
+        let block = self.car.get_block(cid, &mut self.r)?;
+        let (data, links) = match IpldCodec::try_from(cid.codec()) {
+            Ok(IpldCodec::Raw) => (block.content, Vec::new()),
+            Ok(IpldCodec::DagPb) => {
+                decode_dag_pb(&block.content).ok_or(CarError::malformed_node(cid))?
+            }
+            Ok(IpldCodec::DagCbor) => {
+                let ipld: Ipld =
+                    dagcbor::decode(&block.content).map_err(|_| CarError::malformed_node(cid))?;
+                let mut links = Vec::new();
+                collect_links(&ipld, &mut links);
+                (Vec::new(), links)
+            }
+            _ => return Err(CarError::malformed_node(cid)),
+        };
+        self.pending.extend(links.into_iter().rev());
+        self.buffer = data;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for DagReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Skip over Blocks which contribute no data until one does, or the DAG is exhausted
+        while self.position >= self.buffer.len() {
+            let Some(cid) = self.pending.pop() else {
+                return Ok(0);
+            };
+            self.visit(&cid)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        }
+        let remaining = &self.buffer[self.position..];
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Every link within some IPLD, in the order they appear
+pub(crate) fn collect_links(ipld: &Ipld, links: &mut Vec<Cid>) {
+    match ipld {
+        Ipld::Link(cid) => links.push(*cid),
+        Ipld::List(list) => list.iter().for_each(|item| collect_links(item, links)),
+        Ipld::Map(map) => map.values().for_each(|value| collect_links(value, links)),
+        _ => {}
+    }
+}
+
+/// Cids a Block links to, in the order they appear. Only DAG-PB and DAG-CBOR Blocks have
+/// links; Blocks of any other codec are leaves.
+pub(crate) fn block_links(cid: &Cid, content: &[u8]) -> Result<Vec<Cid>, CarError> {
+    match IpldCodec::try_from(cid.codec()) {
+        Ok(IpldCodec::DagPb) => decode_dag_pb(content)
+            .map(|(_, links)| links)
+            .ok_or(CarError::malformed_node(cid)),
+        Ok(IpldCodec::DagCbor) => {
+            let ipld: Ipld = dagcbor::decode(content).map_err(|_| CarError::malformed_node(cid))?;
+            let mut links = Vec::new();
+            collect_links(&ipld, &mut links);
+            Ok(links)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// A protobuf field value
+enum Field<'a> {
+    Varint,
+    Bytes(&'a [u8]),
+}
+
+/// Split protobuf encoded bytes into their field numbers and values. Only the wire types used
+/// by DAG-PB and UnixFS are understood.
+fn fields(mut bytes: &[u8]) -> Option<Vec<(u64, Field<'_>)>> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let (key, rest) = unsigned_varint::decode::u64(bytes).ok()?;
+        let field = match key & 0b111 {
+            0 => {
+                let (_, rest) = unsigned_varint::decode::u64(rest).ok()?;
+                bytes = rest;
+                Field::Varint
+            }
+            2 => {
+                let (len, rest) = unsigned_varint::decode::u64(rest).ok()?;
+                let len = usize::try_from(len).ok().filter(|len| *len <= rest.len())?;
+                bytes = &rest[len..];
+                Field::Bytes(&rest[..len])
+            }
+            _ => return None,
+        };
+        fields.push((key >> 3, field));
+    }
+    Some(fields)
+}
+
+/// Decode a DAG-PB node into the file data of its UnixFS payload and its links
+fn decode_dag_pb(bytes: &[u8]) -> Option<(Vec<u8>, Vec<Cid>)> {
+    let mut data = Vec::new();
+    let mut links = Vec::new();
+    for (number, field) in fields(bytes)? {
+        match (number, field) {
+            // PBNode.Data holds a UnixFS message, whose own Data field is the file content
+            (1, Field::Bytes(unixfs)) => {
+                for (number, field) in fields(unixfs)? {
+                    if let (2, Field::Bytes(content)) = (number, field) {
+                        data.extend_from_slice(content);
+                    }
+                }
+            }
+            // PBNode.Links, each of which has its Cid in PBLink.Hash
+            (2, Field::Bytes(link)) => {
+                let hash = fields(link)?.into_iter().find_map(|field| match field {
+                    (1, Field::Bytes(hash)) => Some(hash),
+                    _ => None,
+                })?;
+                links.push(Cid::try_from(hash).ok()?);
+            }
+            _ => {}
+        }
+    }
+    Some((data, links))
+}
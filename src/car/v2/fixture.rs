@@ -3,11 +3,12 @@ mod test {
         error::CarError,
         v1::Header,
         v2::{CarV2, HEADER_SIZE, PH_SIZE},
+        SyncCell,
     };
     use base58::ToBase58;
     use sha2::Digest;
     use std::io::{Cursor, Seek, SeekFrom};
-    use std::{cell::RefCell, str::FromStr};
+    use std::str::FromStr;
     use wnfs::libipld::Cid;
 
     /// Quick Specification Reference Links:
@@ -389,7 +390,7 @@ mod test {
         // CARv1 Header
         let header = Header {
             version: 2,
-            roots: RefCell::new(vec![
+            roots: SyncCell::new(vec![
                 Cid::from_str(&binary_cid_to_base58_cid(&block_zero_cid))
                     .expect("failed to represent binary as CID"),
                 Cid::from_str(&binary_cid_to_base58_cid(&block_five_cid))
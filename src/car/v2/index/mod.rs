@@ -123,6 +123,30 @@ impl Index<Bucket> {
         Ok(new_index)
     }
 
+    /// Incorporate the entries of another Index, such as that of a second CAR whose data was
+    /// appended at `offset_shift` bytes into this one. Offsets from `other` are shifted by that
+    /// amount. Cids already present in this Index keep their existing offset.
+    pub fn merge(&mut self, other: &Index<Bucket>, offset_shift: u64) {
+        for bucket in &other.buckets {
+            for (cid, offset) in &bucket.map {
+                if self.get_offset(cid).is_none() {
+                    self.insert_offset(cid, offset + offset_shift);
+                }
+            }
+        }
+    }
+
+    /// Remove a Cid from whichever Bucket holds it, returning the offset it had.
+    /// Buckets left empty are dropped, as they can not be read back in.
+    pub fn remove_offset(&mut self, cid: &Cid) -> Option<u64> {
+        let offset = self
+            .buckets
+            .iter_mut()
+            .find_map(|bucket| bucket.map.remove(cid));
+        self.buckets.retain(|bucket| !bucket.map.is_empty());
+        offset
+    }
+
     /// Accumulate a vec of all Cids in all Buckets
     pub fn get_all_cids(&self) -> Vec<Cid> {
         let mut cids = <Vec<Cid>>::new();
@@ -132,13 +156,30 @@ impl Index<Bucket> {
         cids.sort();
         cids
     }
+
+    /// Accumulate a vec of all Cids whose Blocks begin at or beyond a given offset
+    pub fn get_cids_since(&self, offset: u64) -> Vec<Cid> {
+        let mut cids = <Vec<Cid>>::new();
+        for bucket in &self.buckets {
+            cids.extend(
+                bucket
+                    .map
+                    .iter()
+                    .filter(|(_, block_offset)| **block_offset >= offset)
+                    .map(|(cid, _)| *cid),
+            )
+        }
+        cids.sort();
+        cids
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Bucket, Index, INDEX_SORTED_CODEC};
+    use super::{Bucket, Index, Indexable, INDEX_SORTED_CODEC};
+    use crate::car::{error::CarError, v1::Block};
     use std::{collections::HashMap, str::FromStr};
-    use wnfs::libipld::Cid;
+    use wnfs::libipld::{Cid, IpldCodec};
 
     /// Generate example data for Bucket
     #[allow(dead_code)]
@@ -161,6 +202,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn merge() -> Result<(), CarError> {
+        let cid = |content: &str| -> Result<Cid, CarError> {
+            Ok(Block::new(content.as_bytes().to_vec(), IpldCodec::Raw)?.cid)
+        };
+        let (kitty, puppy, bunny) = (cid("kitty")?, cid("puppy")?, cid("bunny")?);
+
+        let mut first = Index {
+            codec: INDEX_SORTED_CODEC,
+            buckets: vec![],
+        };
+        first.insert_offset(&kitty, 0);
+        first.insert_offset(&puppy, 50);
+        let mut second = Index {
+            codec: INDEX_SORTED_CODEC,
+            buckets: vec![],
+        };
+        second.insert_offset(&puppy, 0);
+        second.insert_offset(&bunny, 40);
+
+        // The second CAR's data was appended 100 bytes in
+        first.merge(&second, 100);
+        assert_eq!(first.get_offset(&kitty), Some(0));
+        // Duplicates keep the offset they already had
+        assert_eq!(first.get_offset(&puppy), Some(50));
+        assert_eq!(first.get_offset(&bunny), Some(140));
+        assert_eq!(first.get_all_cids().len(), 3);
+        Ok(())
+    }
+
     crate::car::streamable_tests! {
         <crate::car::v2::Bucket, crate::car::error::CarError>:
         indexsorted: crate::car::v2::index::test::index_sorted_example(),
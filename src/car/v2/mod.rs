@@ -1,3 +1,5 @@
+/// Reading file bytes out of a DAG
+mod dag_reader;
 /// Fixture
 #[cfg(test)]
 #[cfg(not(target_arch = "wasm32"))]
@@ -6,8 +8,13 @@ pub(crate) mod fixture;
 pub mod header;
 /// CarV2 Index
 pub mod index;
+/// Reading a CarV2 without seeking
+mod streaming;
 
+pub use dag_reader::DagReader;
+pub(crate) use dag_reader::{block_links, collect_links};
 pub use header::{Header, HEADER_SIZE};
+pub use streaming::StreamingReader;
 
 // Code
 use self::index::indexable::Indexable;
@@ -15,14 +22,11 @@ use super::error::CarError;
 use crate::car::{
     v1::{Block, CarV1},
     v2::index::{indexsorted::Bucket, Index},
-    Streamable,
+    Streamable, SyncCell,
 };
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
-use std::{
-    cell::RefCell,
-    io::{Read, Seek, SeekFrom, Write},
-};
+use std::io::{Read, Seek, SeekFrom, Write};
 use wnfs::libipld::Cid;
 
 // | 11-byte fixed pragma | 40-byte header | optional padding | CarV1 data payload | optional padding | optional index payload |
@@ -38,7 +42,7 @@ pub(crate) const PRAGMA: [u8; PRAGMA_SIZE] = [
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct CarV2 {
     /// The header
-    pub(crate) header: RefCell<Header>,
+    pub(crate) header: SyncCell<Header>,
     /// The CarV1 internal to the CarV2
     pub car: CarV1, // Note that the index is actually stored internally to the CarV1 struct
 }
@@ -55,8 +59,21 @@ impl CarV2 {
         Self::verify_pragma(&mut r)?;
         // Load in the header
         let header = Header::read_bytes(&mut r)?;
-        // Assert we're at the right spot
-        assert_eq!(r.stream_position()?, PH_SIZE);
+        // Ensure we're at the right spot
+        let position = r.stream_position()?;
+        if position != PH_SIZE {
+            return Err(CarError::bad_header_length(position));
+        }
+        // Ensure the payloads the Header describes are all present
+        let actual = r.seek(SeekFrom::End(0))?;
+        let expected = header
+            .data_offset
+            .checked_add(header.data_size)
+            .ok_or(CarError::truncated(u64::MAX, actual))?
+            .max(header.index_offset);
+        if actual < expected {
+            return Err(CarError::truncated(expected, actual));
+        }
         // Seek to the data offset
         r.seek(SeekFrom::Start(header.data_offset))?;
         // Load in the CarV1
@@ -72,13 +89,30 @@ impl CarV2 {
         r.seek(SeekFrom::Start(header.index_offset))?;
         // Create the new object
         Ok(Self {
-            header: RefCell::new(header),
+            header: SyncCell::new(header),
             car,
         })
     }
 
+    /// Read a CarV2 front to back from a stream which can not seek, such as a socket or stdin.
+    /// The pragma and Headers are read straight away; the returned reader then yields each
+    /// Block of the data payload in turn, rebuilding the Index as it goes.
+    pub fn read_streaming<R: Read>(r: R) -> Result<StreamingReader<R>, CarError> {
+        StreamingReader::new(r)
+    }
+
     /// Write the CarV2 out to a writer, reading in the content required to write as we go
-    pub fn write_bytes<RW: Read + Write + Seek>(&self, mut rw: RW) -> Result<(), CarError> {
+    pub fn write_bytes<RW: Read + Write + Seek>(&self, rw: RW) -> Result<(), CarError> {
+        self.write_bytes_journaled(rw, |_| Ok(()))
+    }
+
+    /// Write the CarV2 out like `write_bytes`, calling `journal` with the updated Header once the
+    /// data and index payloads are written, but before the Header itself is
+    pub fn write_bytes_journaled<RW, J>(&self, mut rw: RW, journal: J) -> Result<(), CarError>
+    where
+        RW: Read + Write + Seek,
+        J: FnOnce(&Header) -> Result<(), CarError>,
+    {
         // Determine part where the CarV1 will go
         let data_offset = self.header.borrow().data_offset;
         // Skip to it
@@ -92,6 +126,9 @@ impl CarV2 {
         rw.seek(SeekFrom::Start(self.header.borrow().index_offset))?;
         // Write out the index
         self.car.index.borrow().write_bytes(&mut rw)?;
+        rw.flush()?;
+        // Record the Header about to be written
+        journal(&self.header.borrow())?;
         // Move back to the start
         rw.seek(SeekFrom::Start(0))?;
         // Write the PRAGMA
@@ -103,6 +140,77 @@ impl CarV2 {
         Ok(())
     }
 
+    /// Write the CarV1 header, index and Header in place, leaving every Block where it is.
+    /// `journal` is called with the updated Header before anything is written. Returns the
+    /// position at which the index ends, or `None` without writing anything if the CarV1
+    /// header changed length, as that moves every Block.
+    pub fn write_index_journaled<RW, J>(
+        &self,
+        mut rw: RW,
+        journal: J,
+    ) -> Result<Option<u64>, CarError>
+    where
+        RW: Read + Write + Seek,
+        J: FnOnce(&Header) -> Result<(), CarError>,
+    {
+        let header = *self.header.borrow();
+        rw.seek(SeekFrom::Start(header.data_offset))?;
+        crate::car::v1::Header::read_bytes(&mut rw)?;
+        let old_header_len = rw.stream_position()? - header.data_offset;
+        let mut current_header = Cursor::new(<Vec<u8>>::new());
+        self.car.header.write_bytes(&mut current_header)?;
+        let current_header = current_header.into_inner();
+        if current_header.len() as u64 != old_header_len {
+            return Ok(None);
+        }
+        // Make sure the index goes after the data payload, even if it was never written
+        self.update_header(header.data_offset + header.data_size)?;
+        journal(&self.header.borrow())?;
+        rw.seek(SeekFrom::Start(header.data_offset))?;
+        rw.write_all(&current_header)?;
+        self.write_index_and_header(&mut rw).map(Some)
+    }
+
+    /// Rebuild the Index by scanning the data payload, ignoring any existing index payload
+    pub fn repair_index<R: Read + Seek>(mut r: R) -> Result<Index<Bucket>, CarError> {
+        // Verify the pragma
+        Self::verify_pragma(&mut r)?;
+        // Load in the header
+        let header = Header::read_bytes(&mut r)?;
+        // Seek to the data offset and skip past the CarV1 header
+        r.seek(SeekFrom::Start(header.data_offset))?;
+        crate::car::v1::Header::read_bytes(&mut r)?;
+        // Scan the blocks that follow
+        let mut index = Index::read_from_carv1(&mut r)?;
+        // Anything found beyond the data payload is not a real block
+        let data_end = header.data_offset.saturating_add(header.data_size);
+        for bucket in &mut index.buckets {
+            bucket.map.retain(|_, offset| *offset < data_end);
+        }
+        index.buckets.retain(|bucket| !bucket.map.is_empty());
+        Ok(index)
+    }
+
+    /// Rebuild the Index of a CarV2 and write it back out, returning the repaired CarV2
+    pub fn repair<RW: Read + Write + Seek>(mut rw: RW) -> Result<Self, CarError> {
+        let index = Self::repair_index(&mut rw)?;
+        // Reread the headers, which are still intact
+        Self::verify_pragma(&mut rw)?;
+        let header = Header::read_bytes(&mut rw)?;
+        rw.seek(SeekFrom::Start(header.data_offset))?;
+        let car = CarV1 {
+            header: crate::car::v1::Header::read_bytes(&mut rw)?,
+            index: SyncCell::new(index),
+        };
+        let repaired = Self {
+            header: SyncCell::new(header),
+            car,
+        };
+        // Write out the rebuilt index
+        repaired.write_bytes(&mut rw)?;
+        Ok(repaired)
+    }
+
     /// Export the CarV2 as bytes to a Vec<u8>
     pub fn to_bytes(&self) -> Result<Vec<u8>, CarError> {
         // Create a new vec
@@ -122,9 +230,11 @@ impl CarV2 {
         let mut pragma: [u8; PRAGMA_SIZE] = [0; PRAGMA_SIZE];
         r.read_exact(&mut pragma)?;
         // Ensure correctness
-        assert_eq!(pragma, PRAGMA);
-        // Return Ok
-        Ok(())
+        if pragma == PRAGMA {
+            Ok(())
+        } else {
+            Err(CarError::bad_pragma())
+        }
     }
 
     /// Get a Block directly from the CarV2
@@ -140,15 +250,30 @@ impl CarV2 {
         }
     }
 
+    /// Re-hash every indexed Block, whatever codec it was written with, returning the Cids whose
+    /// content no longer matches them. Blocks which can not be read in at all, such as those cut
+    /// off by a truncated download, are reported as well.
+    pub fn verify<R: Read + Seek>(&self, mut r: R) -> Result<Vec<Cid>, CarError> {
+        let cids = self.car.index.borrow().get_all_cids();
+        let mut mismatched = Vec::new();
+        for cid in cids {
+            match self.get_block(&cid, &mut r) {
+                Ok(block) if block.verify() => {}
+                _ => mismatched.push(cid),
+            }
+        }
+        Ok(mismatched)
+    }
+
     /// Set a Block directly in the CarV2
     pub fn put_block<W: Write + Seek>(&self, block: &Block, mut w: W) -> Result<(), CarError> {
+        // Grab index first, so that no other append can move the end of the data payload
+        let index: &mut Index<Bucket> = &mut self.car.index.borrow_mut();
         // Grab the header
         let header = *self.header.borrow();
         // Determine offset of the next block
         let next_block = header.data_offset + header.data_size;
 
-        // Grab index
-        let index: &mut Index<Bucket> = &mut self.car.index.borrow_mut();
         // If the index does not contain the Cid
         if index.get_offset(&block.cid).is_none() {
             // Insert offset
@@ -166,16 +291,153 @@ impl CarV2 {
         Ok(())
     }
 
+    /// Append a Block to the CarV2 and point its Cid at the new copy, even if one is already
+    /// indexed. Any previous copy is left in the data payload but is no longer reachable.
+    pub fn replace_block<W: Write + Seek>(&self, block: &Block, mut w: W) -> Result<(), CarError> {
+        // Grab index first, so that no other append can move the end of the data payload
+        let mut index = self.car.index.borrow_mut();
+        // Grab the header
+        let header = *self.header.borrow();
+        // Determine offset of the next block
+        let next_block = header.data_offset + header.data_size;
+        // Point the Cid at the new copy
+        index.insert_offset(&block.cid, next_block);
+        // Move to the end
+        w.seek(SeekFrom::Start(next_block))?;
+        // Write the bytes
+        block.write_bytes(&mut w)?;
+        // Update the data size
+        self.update_header(w.stream_position()?)?;
+        // Flush
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Remove a Block from the index of the CarV2 and persist the updated index. The content of
+    /// the Block stays in the data payload until the CarV2 is compacted. Returns the length of
+    /// the CarV2, beyond which anything left over from a previous index is stale.
+    pub fn remove_block<RW: Write + Seek>(&self, cid: &Cid, rw: RW) -> Result<u64, CarError> {
+        self.remove_blocks(std::slice::from_ref(cid), rw)
+    }
+
+    /// Remove several Blocks like `remove_block`, writing out the updated index only once.
+    /// Nothing is removed if any of them is a root or is not in the CarV2.
+    pub fn remove_blocks<RW: Write + Seek>(
+        &self,
+        cids: &[Cid],
+        mut rw: RW,
+    ) -> Result<u64, CarError> {
+        let roots = self.get_roots();
+        {
+            let mut index = self.car.index.borrow_mut();
+            for cid in cids {
+                // The roots are what make the rest of the CarV2 reachable
+                if roots.contains(cid) {
+                    return Err(CarError::root_removal(cid));
+                }
+                if index.get_offset(cid).is_none() {
+                    return Err(CarError::missing_block(cid));
+                }
+            }
+            for cid in cids {
+                index.remove_offset(cid);
+            }
+        }
+        // Make sure the index goes after the data payload, even if it was never written
+        let header = *self.header.borrow();
+        self.update_header(header.data_offset + header.data_size)?;
+        self.write_index_and_header(&mut rw)
+    }
+
+    /// Rewrite the data payload in place so that it holds only the Blocks which are still
+    /// indexed, in the order they were written, then write the rebuilt index after them.
+    /// Returns the length of the compacted CarV2, beyond which anything remaining is stale.
+    pub fn compact<RW: Read + Write + Seek>(&self, mut rw: RW) -> Result<u64, CarError> {
+        let data_offset = self.header.borrow().data_offset;
+        // Blocks start right after the CarV1 header, so make sure the one written is current
+        rw.seek(SeekFrom::Start(data_offset))?;
+        if crate::car::v1::Header::read_bytes(&mut rw)? != self.car.header {
+            self.write_bytes(&mut rw)?;
+            rw.seek(SeekFrom::Start(data_offset))?;
+            crate::car::v1::Header::read_bytes(&mut rw)?;
+        }
+        let mut next_block = rw.stream_position()?;
+
+        let mut offsets = self
+            .car
+            .index
+            .borrow()
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.map.iter().map(|(cid, offset)| (*offset, *cid)))
+            .collect::<Vec<(u64, Cid)>>();
+        offsets.sort();
+
+        let mut index = self.car.index.borrow_mut();
+        for (offset, cid) in offsets {
+            rw.seek(SeekFrom::Start(offset))?;
+            let block = Block::read_bytes(&mut rw)?;
+            // Blocks only ever move towards the start, so nothing unread is overwritten
+            if offset != next_block {
+                rw.seek(SeekFrom::Start(next_block))?;
+                block.write_bytes(&mut rw)?;
+                index.insert_offset(&cid, next_block);
+            }
+            next_block = rw.stream_position()?;
+        }
+        drop(index);
+
+        self.update_header(next_block)?;
+        self.write_index_and_header(&mut rw)
+    }
+
+    /// Write the index at the index offset followed by the PRAGMA and Header, returning the
+    /// position at which the index ends
+    fn write_index_and_header<W: Write + Seek>(&self, mut w: W) -> Result<u64, CarError> {
+        w.seek(SeekFrom::Start(self.header.borrow().index_offset))?;
+        self.car.index.borrow().write_bytes(&mut w)?;
+        let end = w.stream_position()?;
+        w.seek(SeekFrom::Start(0))?;
+        w.write_all(&PRAGMA)?;
+        self.header.borrow().clone().write_bytes(&mut w)?;
+        w.flush()?;
+        Ok(end)
+    }
+
     /// Create a new CarV2 struct by writing into a stream, then deserializing it
-    pub fn new<RW: Read + Write + Seek>(mut rw: RW) -> Result<Self, CarError> {
-        // Move to CarV1 no padding
+    pub fn new<RW: Read + Write + Seek>(rw: RW) -> Result<Self, CarError> {
+        Self::new_aligned(rw, 0)
+    }
+
+    /// Create a new CarV2 whose data payload starts on a multiple of `alignment` bytes,
+    /// padding after the header as needed. An alignment of 0 or 1 adds no padding.
+    pub fn new_aligned<RW: Read + Write + Seek>(rw: RW, alignment: u64) -> Result<Self, CarError> {
+        // Determine where the CarV1 will start
+        let data_offset = if alignment > 1 {
+            PH_SIZE.div_ceil(alignment) * alignment
+        } else {
+            PH_SIZE
+        };
+        Self::new_with_padding(rw, data_offset)
+    }
+
+    /// Create a new CarV2 whose data payload starts at `data_offset`, zero padding the space
+    /// between the header and the payload. Offsets within the header place the payload
+    /// directly after it.
+    pub fn new_with_padding<RW: Read + Write + Seek>(
+        mut rw: RW,
+        data_offset: u64,
+    ) -> Result<Self, CarError> {
+        let data_offset = data_offset.max(PH_SIZE);
+        // Pad out to the CarV1
         rw.seek(SeekFrom::Start(PH_SIZE))?;
+        rw.write_all(&vec![0; (data_offset - PH_SIZE) as usize])?;
         // Construct a CarV1
         let car = CarV1::default(2);
         // Write CarV1 Header
         car.header.write_bytes(&mut rw)?;
         // Compute the data size
-        let data_size = rw.stream_position()? - PH_SIZE;
+        let data_size = rw.stream_position()? - data_offset;
 
         // Move to start
         rw.seek(SeekFrom::Start(0))?;
@@ -184,7 +446,7 @@ impl CarV2 {
         // Write header with correct data size
         let header = Header {
             characteristics: 0,
-            data_offset: PH_SIZE,
+            data_offset,
             data_size,
             index_offset: 0,
         };
@@ -195,14 +457,10 @@ impl CarV2 {
         Self::read_bytes(&mut rw)
     }
 
-    fn update_header(&self, data_end: u64) -> Result<(), CarError> {
+    pub(crate) fn update_header(&self, data_end: u64) -> Result<(), CarError> {
         let mut header = self.header.borrow_mut();
         // Update the data size
-        header.data_size = if data_end > PH_SIZE {
-            data_end - PH_SIZE
-        } else {
-            0
-        };
+        header.data_size = data_end.saturating_sub(header.data_offset);
 
         // Update the index offset
         header.index_offset = header.data_offset + header.data_size;
@@ -212,7 +470,7 @@ impl CarV2 {
         Ok(())
     }
 
-    /// Set the singular root of the CarV2
+    /// Set the singular root of the CarV2, replacing any others
     pub fn set_root(&self, root: &Cid) {
         self.car.set_root(root);
     }
@@ -221,21 +479,46 @@ impl CarV2 {
     pub fn get_root(&self) -> Option<Cid> {
         self.car.get_root()
     }
+
+    /// Set every root of the CarV2, in order
+    pub fn set_roots(&self, roots: &[Cid]) {
+        self.car.set_roots(roots);
+    }
+
+    /// Get every root of the CarV2, in order
+    pub fn get_roots(&self) -> Vec<Cid> {
+        self.car.get_roots()
+    }
+
+    /// Stream the bytes of the DAG beneath `root`, such as the content of a UnixFS file,
+    /// without reading every Block into memory
+    pub fn dag_reader<R: Read + Seek>(&self, root: &Cid, r: R) -> DagReader<'_, R> {
+        DagReader::new(self, root, r)
+    }
 }
 
 #[cfg(test)]
 #[cfg(not(target_arch = "wasm32"))]
 mod test {
     use crate::{
-        car::{error::CarError, v1::Block, v2::CarV2},
-        utils::{get_read_write, testing::blockstores::car_test_setup},
+        car::{
+            error::CarError,
+            v1::Block,
+            v2::{index::indexable::Indexable, CarV2, Header, PH_SIZE, PRAGMA},
+            Streamable,
+        },
+        utils::{get_read_write, testing::blockstores::car_test_setup, varint::encode_varint_u64},
     };
     use serial_test::serial;
     use std::{
+        collections::BTreeMap,
         fs::{File, OpenOptions},
-        io::{Seek, SeekFrom},
+        io::{Cursor, Read, Seek, SeekFrom},
+    };
+    use wnfs::{
+        common::dagcbor,
+        libipld::{Cid, Ipld, IpldCodec},
     };
-    use wnfs::libipld::{Cid, IpldCodec};
 
     #[test]
     #[serial]
@@ -246,6 +529,84 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn malformed_input() -> Result<(), CarError> {
+        // Garbage in place of the pragma
+        let err = CarV2::read_bytes(Cursor::new(vec![0xff; 128])).unwrap_err();
+        assert!(err.to_string().contains("pragma"));
+        // A valid pragma with only part of a Header after it
+        let mut bytes = PRAGMA.to_vec();
+        bytes.extend([0; 8]);
+        assert!(CarV2::read_bytes(Cursor::new(bytes)).is_err());
+        // Nothing at all
+        assert!(CarV2::read_bytes(Cursor::new(Vec::new())).is_err());
+        // A data payload which would end past the largest possible offset
+        let mut bytes = Cursor::new(PRAGMA.to_vec());
+        bytes.seek(SeekFrom::End(0))?;
+        Header {
+            characteristics: 0,
+            data_offset: PH_SIZE,
+            data_size: u64::MAX,
+            index_offset: 0,
+        }
+        .write_bytes(&mut bytes)?;
+        let bytes = bytes.into_inner();
+        assert!(CarV2::read_bytes(Cursor::new(bytes.clone())).is_err());
+        assert!(CarV2::read_streaming(Cursor::new(bytes)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn read_streaming() -> Result<(), CarError> {
+        let car_path = car_test_setup(2, "basic", "read_streaming")?;
+        let original = CarV2::read_bytes(&mut File::open(&car_path)?)?;
+        let mut bytes = Vec::new();
+        File::open(&car_path)?.read_to_end(&mut bytes)?;
+
+        // A byte slice can only be read forwards
+        let mut reader = CarV2::read_streaming(bytes.as_slice())?;
+        assert_eq!(reader.roots(), original.get_roots());
+        let mut cids = Vec::new();
+        for block in &mut reader {
+            let block = block?;
+            assert!(block.verify());
+            cids.push(block.cid);
+        }
+        cids.sort();
+        assert_eq!(cids, original.car.index.borrow().get_all_cids());
+        // The rebuilt Index points at the same Blocks as the stored one
+        for cid in &cids {
+            assert_eq!(
+                reader.index().get_offset(cid),
+                original.car.index.borrow().get_offset(cid)
+            );
+        }
+
+        // A stream cut off inside the data payload is reported rather than ending early
+        let data_end = original.header.borrow().data_offset + original.data_size();
+        let reader = CarV2::read_streaming(&bytes[..data_end as usize - 1])?;
+        let err = reader.last().expect("no blocks read").unwrap_err();
+        assert!(err.to_string().contains("only"));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn repair_broken_index() -> Result<(), CarError> {
+        let car_path = car_test_setup(2, "basic", "repair_broken_index")?;
+        let mut file = File::open(car_path)?;
+        let index = CarV2::repair_index(&mut file)?;
+        let all_cids = index.get_all_cids();
+        assert!(!all_cids.is_empty());
+        // Every block in the rebuilt index is where it claims to be
+        for cid in all_cids {
+            file.seek(SeekFrom::Start(index.get_offset(&cid).expect("no offset")))?;
+            assert_eq!(Block::read_bytes(&mut file)?.cid, cid);
+        }
+        Ok(())
+    }
+
     #[test]
     #[serial]
     fn put_get_block() -> Result<(), CarError> {
@@ -287,6 +648,172 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn aligned_data_offset() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new_aligned(&mut rw, 4096)?;
+        let blocks = (0..3u8)
+            .map(|i| Block::new(vec![i; 100], IpldCodec::Raw))
+            .collect::<Result<Vec<Block>, CarError>>()?;
+        for block in &blocks {
+            car.put_block(block, &mut rw)?;
+        }
+        car.write_bytes(&mut rw)?;
+
+        // The data payload starts on the boundary and every Block reads back
+        let reconstructed = CarV2::read_bytes(&mut rw)?;
+        assert_eq!(reconstructed.header.borrow().data_offset % 4096, 0);
+        for block in &blocks {
+            assert_eq!(&reconstructed.get_block(&block.cid, &mut rw)?, block);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn padded_round_trip() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new_with_padding(&mut rw, 4096)?;
+        let block = Block::new("Hello Kitty!".as_bytes().to_vec(), IpldCodec::Raw)?;
+        car.put_block(&block, &mut rw)?;
+        car.set_root(&block.cid);
+        car.write_bytes(&mut rw)?;
+        // Everything between the header and the data payload is padding
+        assert!(rw.get_ref()[PH_SIZE as usize..4096]
+            .iter()
+            .all(|byte| *byte == 0));
+
+        // Reading back in, as well as writing again, keeps the CarV1 where it is
+        let reconstructed = CarV2::read_bytes(&mut rw)?;
+        assert_eq!(reconstructed.header.borrow().data_offset, 4096);
+        assert_eq!(reconstructed.get_block(&block.cid, &mut rw)?, block);
+        let puppy = Block::new("Hello Puppy!".as_bytes().to_vec(), IpldCodec::Raw)?;
+        reconstructed.put_block(&puppy, &mut rw)?;
+        reconstructed.write_bytes(&mut rw)?;
+        let rewritten = CarV2::read_bytes(&mut rw)?;
+        assert_eq!(rewritten.header.borrow().data_offset, 4096);
+        assert_eq!(rewritten.get_root(), Some(block.cid));
+        for block in [&block, &puppy] {
+            assert_eq!(&rewritten.get_block(&block.cid, &mut rw)?, block);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn codecs_round_trip() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new(&mut rw)?;
+        // DagPb encoding of an empty PBNode with Data set to "Hello Kitty!"
+        let dag_pb = [vec![0x0a, 12], b"Hello Kitty!".to_vec()].concat();
+        let dag_json = br#"{"hello":"kitty"}"#.to_vec();
+        let blocks = [
+            Block::new(dag_pb, IpldCodec::DagPb)?,
+            Block::new(dag_json, IpldCodec::DagJson)?,
+        ];
+        for block in &blocks {
+            car.put_block(block, &mut rw)?;
+        }
+        car.write_bytes(&mut rw)?;
+
+        // Read the CarV2 back in and check that each Block kept its codec
+        let reconstructed = CarV2::read_bytes(&mut rw)?;
+        assert!(reconstructed.verify(&mut rw)?.is_empty());
+        for (block, codec) in blocks.iter().zip([IpldCodec::DagPb, IpldCodec::DagJson]) {
+            assert_eq!(block.cid.codec(), u64::from(codec));
+            assert!(block.verify());
+            assert_eq!(&reconstructed.get_block(&block.cid, &mut rw)?, block);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn verify_detects_corruption() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new(&mut rw)?;
+        let mut block = Block::new(br#"{"hello":"kitty"}"#.to_vec(), IpldCodec::DagJson)?;
+        // Keep the length but change the content
+        block.content[2] = b'j';
+        car.put_block(&block, &mut rw)?;
+        assert!(!block.verify());
+        assert_eq!(car.verify(&mut rw)?, vec![block.cid]);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_and_compact() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new(&mut rw)?;
+        let blocks = (0..4u8)
+            .map(|i| Block::new(vec![i; 100], IpldCodec::Raw))
+            .collect::<Result<Vec<Block>, CarError>>()?;
+        for block in &blocks {
+            car.put_block(block, &mut rw)?;
+        }
+        car.set_root(&blocks[0].cid);
+        car.write_bytes(&mut rw)?;
+        let original_len = rw.get_ref().len() as u64;
+
+        // The root stays put
+        let err = car
+            .remove_block(&blocks[0].cid, &mut rw)
+            .expect_err("removed the root");
+        assert!(err.to_string().contains("root"));
+        // Removed Blocks are no longer reachable, even after reading the CarV2 back in
+        car.remove_block(&blocks[1].cid, &mut rw)?;
+        car.remove_block(&blocks[2].cid, &mut rw)?;
+        assert!(car.get_block(&blocks[1].cid, &mut rw).is_err());
+        assert!(car.remove_block(&blocks[1].cid, &mut rw).is_err());
+        let reread = CarV2::read_bytes(&mut rw)?;
+        assert!(reread.get_block(&blocks[2].cid, &mut rw).is_err());
+        assert_eq!(reread.get_block(&blocks[3].cid, &mut rw)?, blocks[3]);
+
+        // Compacting reclaims their space and leaves the rest retrievable
+        let compacted_len = car.compact(&mut rw)?;
+        assert!(compacted_len < original_len);
+        rw.get_mut().truncate(compacted_len as usize);
+        let compacted = CarV2::read_bytes(&mut rw)?;
+        assert_eq!(compacted.get_root(), Some(blocks[0].cid));
+        assert_eq!(compacted.car.index.borrow().get_all_cids().len(), 2);
+        for block in [&blocks[0], &blocks[3]] {
+            assert_eq!(&compacted.get_block(&block.cid, &mut rw)?, block);
+        }
+        assert!(compacted.verify(&mut rw)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn verify_reports_damage() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new(&mut rw)?;
+        let blocks = (0..3u8)
+            .map(|i| Block::new(vec![i; 100], IpldCodec::Raw))
+            .collect::<Result<Vec<Block>, CarError>>()?;
+        for block in &blocks {
+            car.put_block(block, &mut rw)?;
+        }
+        car.write_bytes(&mut rw)?;
+        assert!(car.verify(&mut rw)?.is_empty());
+
+        let offset = |block: &Block| {
+            car.car
+                .index
+                .borrow()
+                .get_offset(&block.cid)
+                .expect("no offset")
+        };
+        // Flip the last content byte of the first Block
+        let last_byte = offset(&blocks[1]) as usize - 1;
+        rw.get_mut()[last_byte] ^= 0xff;
+        // Cut the last Block off partway through
+        rw.get_mut().truncate(offset(&blocks[2]) as usize + 10);
+
+        let mut damaged = car.verify(&mut rw)?;
+        damaged.sort();
+        let mut expected = vec![blocks[0].cid, blocks[2].cid];
+        expected.sort();
+        assert_eq!(damaged, expected);
+        Ok(())
+    }
+
     #[test]
     #[serial]
     fn to_from_disk_no_offset() -> Result<(), CarError> {
@@ -341,4 +868,69 @@ mod test {
 
         Ok(())
     }
+
+    /// A length delimited protobuf field
+    fn pb_field(number: u64, bytes: &[u8]) -> Vec<u8> {
+        let mut field = encode_varint_u64((number << 3) | 2);
+        field.extend(encode_varint_u64(bytes.len() as u64));
+        field.extend_from_slice(bytes);
+        field
+    }
+
+    #[test]
+    fn dag_reader() -> Result<(), CarError> {
+        let mut rw = Cursor::new(Vec::new());
+        let car = CarV2::new(&mut rw)?;
+        let hello = Block::new("hello ".as_bytes().to_vec(), IpldCodec::Raw)?;
+        let kitty = Block::new("kitty".as_bytes().to_vec(), IpldCodec::Raw)?;
+        let tail = Block::new(" :3".as_bytes().to_vec(), IpldCodec::Raw)?;
+        // A UnixFS file with some data of its own, followed by that of its leaves
+        let mut unixfs = vec![0x08, 0x02];
+        unixfs.extend(pb_field(2, "> ".as_bytes()));
+        let mut file_node = Vec::new();
+        for leaf in [&hello, &kitty] {
+            file_node.extend(pb_field(2, &pb_field(1, &leaf.cid.to_bytes())));
+        }
+        file_node.extend(pb_field(1, &unixfs));
+        let file = Block::new(file_node, IpldCodec::DagPb)?;
+        // Tied together under a DAG-CBOR root
+        let root = Block::new(
+            dagcbor::encode(&Ipld::Map(BTreeMap::from([
+                ("file".to_string(), Ipld::Link(file.cid)),
+                ("tail".to_string(), Ipld::Link(tail.cid)),
+            ])))
+            .expect("failed to encode root"),
+            IpldCodec::DagCbor,
+        )?;
+        for block in [&hello, &kitty, &tail, &file, &root] {
+            car.put_block(block, &mut rw)?;
+        }
+
+        // Read a few bytes at a time, to cross Block boundaries mid read
+        let mut reader = car.dag_reader(&root.cid, &mut rw);
+        let mut content = Vec::new();
+        let mut buf = [0; 3];
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            content.extend_from_slice(&buf[..read]);
+        }
+        assert_eq!(content, "> hello kitty :3".as_bytes());
+
+        // Any subtree can be read on its own
+        let mut content = String::new();
+        car.dag_reader(&file.cid, &mut rw)
+            .read_to_string(&mut content)?;
+        assert_eq!(content, "> hello kitty");
+
+        // Missing Blocks fail the read rather than truncating it
+        let missing = Block::new("missing".as_bytes().to_vec(), IpldCodec::Raw)?;
+        assert!(car
+            .dag_reader(&missing.cid, &mut rw)
+            .read_to_end(&mut Vec::new())
+            .is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,181 @@
+use super::{Header, HEADER_SIZE, PH_SIZE, PRAGMA, PRAGMA_SIZE};
+use crate::car::{
+    error::CarError,
+    v1::{self, max_block_size, Block},
+    v2::index::{indexable::Indexable, indexsorted::Bucket, Index, INDEX_SORTED_CODEC},
+    Streamable, SyncCell,
+};
+use crate::utils::UtilityError;
+use std::io::{self, Cursor, ErrorKind, Read};
+use unsigned_varint::{decode, encode};
+use wnfs::libipld::Cid;
+
+/// Reads the Blocks of a CarV2 one after another from a stream which can not seek, such as a
+/// socket or stdin. The index payload is never consulted; instead an Index is rebuilt from
+/// the Blocks as they are read.
+#[derive(Debug)]
+pub struct StreamingReader<R: Read> {
+    r: R,
+    /// Number of bytes read from the start of the CarV2
+    position: u64,
+    /// The CarV2 Header
+    header: Header,
+    /// The Header of the CarV1 data payload
+    car_header: v1::Header,
+    /// Offsets of the Blocks read so far
+    index: Index<Bucket>,
+    /// Whether the data payload has been read to its end, or reading it failed
+    done: bool,
+}
+
+impl<R: Read> StreamingReader<R> {
+    /// Read the pragma and both Headers, leaving the stream at the first Block
+    pub(crate) fn new(r: R) -> Result<Self, CarError> {
+        let mut reader = Self {
+            r,
+            position: 0,
+            // Until the real Header is read, only the pragma and Header are expected
+            header: Header {
+                characteristics: 0,
+                data_offset: PH_SIZE,
+                data_size: 0,
+                index_offset: 0,
+            },
+            car_header: v1::Header {
+                version: 1,
+                roots: SyncCell::new(vec![]),
+            },
+            index: Index {
+                codec: INDEX_SORTED_CODEC,
+                buckets: vec![],
+            },
+            done: false,
+        };
+
+        let mut pragma = [0; PRAGMA_SIZE];
+        reader.read_exact(&mut pragma)?;
+        if pragma != PRAGMA {
+            return Err(CarError::bad_pragma());
+        }
+        let mut header_bytes = [0; HEADER_SIZE];
+        reader.read_exact(&mut header_bytes)?;
+        reader.header = Header::read_bytes(&mut Cursor::new(header_bytes))?;
+        if reader.header.data_offset < PH_SIZE {
+            return Err(CarError::bad_header_length(reader.header.data_offset));
+        }
+        // A data payload which ends past the largest possible offset can never be read
+        if reader
+            .header
+            .data_offset
+            .checked_add(reader.header.data_size)
+            .is_none()
+        {
+            return Err(CarError::bad_header_length(reader.header.data_size));
+        }
+
+        // Skip any padding before the data payload
+        let padding = reader.header.data_offset - reader.position;
+        let skipped = io::copy(&mut (&mut reader.r).take(padding), &mut io::sink())?;
+        reader.position += skipped;
+        if skipped < padding {
+            return Err(reader.truncated());
+        }
+
+        let header_len = reader.read_varint()?;
+        let limit = max_block_size();
+        if header_len > limit {
+            return Err(CarError::block_too_large(header_len as u128, limit));
+        }
+        let mut header_bytes = vec![0; header_len as usize];
+        reader.read_exact(&mut header_bytes)?;
+        reader.car_header = v1::Header::from_ipld_bytes(&header_bytes)?;
+        reader.done = reader.position >= reader.data_end();
+        Ok(reader)
+    }
+
+    /// Get the CarV2 Header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Get the roots named by the CarV1 Header
+    pub fn roots(&self) -> Vec<Cid> {
+        self.car_header.roots.borrow().clone()
+    }
+
+    /// Get the Index of the Blocks read so far
+    pub fn index(&self) -> &Index<Bucket> {
+        &self.index
+    }
+
+    /// Offset of the end of the data payload
+    fn data_end(&self) -> u64 {
+        self.header
+            .data_offset
+            .saturating_add(self.header.data_size)
+    }
+
+    fn truncated(&self) -> CarError {
+        CarError::truncated(self.data_end(), self.position)
+    }
+
+    /// Fill `buf`, treating a stream which ends early as truncated
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), CarError> {
+        match self.r.read_exact(buf) {
+            Ok(()) => {
+                self.position += buf.len() as u64;
+                Ok(())
+            }
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(self.truncated()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Read a varint one byte at a time, so that nothing after it is consumed
+    fn read_varint(&mut self) -> Result<u64, CarError> {
+        let mut buf = encode::u64_buffer();
+        for i in 0..buf.len() {
+            self.read_exact(&mut buf[i..=i])?;
+            if decode::is_last(buf[i]) {
+                break;
+            }
+        }
+        Ok(decode::u64(&buf).map_err(UtilityError::from)?.0)
+    }
+
+    /// Read the Block which begins at the current position
+    fn read_block(&mut self) -> Result<Block, CarError> {
+        let offset = self.position;
+        let varint = self.read_varint()?;
+        // Refuse to allocate for lengths no honest CAR would declare
+        let limit = max_block_size();
+        if varint > limit {
+            return Err(CarError::block_too_large(varint as u128, limit));
+        }
+        let mut section = vec![0; varint as usize];
+        self.read_exact(&mut section)?;
+        let mut cursor = Cursor::new(section.as_slice());
+        let cid = Cid::read_bytes(&mut cursor)?;
+        let content = section[cursor.position() as usize..].to_vec();
+        self.index.insert_offset(&cid, offset);
+        Ok(Block {
+            varint: varint as u128,
+            cid,
+            content,
+        })
+    }
+}
+
+impl<R: Read> Iterator for StreamingReader<R> {
+    type Item = Result<Block, CarError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let block = self.read_block();
+        // Nothing past a bad Block can be trusted to line up
+        self.done = block.is_err() || self.position >= self.data_end();
+        Some(block)
+    }
+}
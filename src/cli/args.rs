@@ -1,6 +1,7 @@
 use clap::{command, Parser};
 
 use super::{commands::TombCommand, verbosity::MyVerbosity};
+use crate::native::utils::{OutputMode, ProgressMode};
 
 /// Arguments to tomb
 #[derive(Parser, Debug)]
@@ -12,4 +13,21 @@ pub struct Args {
     /// Verbosity level.
     #[arg(short, long, help = "verbosity level", default_value = "normal")]
     pub verbose: MyVerbosity,
+    /// How progress is reported. `jsonl` writes one JSON event per line to stderr, `hidden` reports nothing.
+    #[arg(long, value_enum, default_value_t = ProgressMode::Bar)]
+    pub progress: ProgressMode,
+    /// How command results are written. `json` prints a single JSON document to stdout, and
+    /// errors as `{"error": ...}`, for use in scripts.
+    #[arg(long, value_enum, default_value_t = OutputMode::Text)]
+    pub output: OutputMode,
+    /// Suppress all output other than errors
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Disable colored output. Colors are also disabled when the `NO_COLOR` environment variable is set.
+    #[arg(long)]
+    pub no_color: bool,
+    /// Serve Prometheus metrics at `/metrics` on this address for the lifetime of the command
+    #[cfg(feature = "metrics")]
+    #[arg(long)]
+    pub metrics_listen: Option<std::net::SocketAddr>,
 }
@@ -1,13 +1,17 @@
 use super::RunnableCommand;
 use crate::{
     api::{
-        client::Credentials,
+        client::{Client, Credentials},
         models::account::Account,
         requests::core::auth::device_api_key::regwait::start::{
             StartRegwait, StartRegwaitResponse,
         },
     },
-    native::{configuration::globalconfig::GlobalConfig, NativeError},
+    native::{
+        configuration::globalconfig::GlobalConfig,
+        utils::{claim_stdout, output_mode, render, OutputMode},
+        NativeError,
+    },
     prelude::api::requests::core::auth::who_am_i::read::ReadWhoAmI,
 };
 use async_trait::async_trait;
@@ -15,6 +19,10 @@ use base64::{engine::general_purpose, Engine as _};
 use bytesize::ByteSize;
 use clap::Subcommand;
 use colored::Colorize;
+use std::{
+    io::{IsTerminal, Write},
+    time::Duration,
+};
 use tokio::task::JoinHandle;
 use tomb_crypt::{
     hex_fingerprint,
@@ -33,8 +41,89 @@ pub enum AccountCommand {
     Register,
     /// Ask the server who I am
     WhoAmI,
+    /// Get the identity, plan and usage of the Account at once
+    Info,
     /// Get info about Account usage
-    Usage,
+    Usage {
+        /// Keep re-querying usage every this many seconds, redrawing it until Ctrl-C
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<u64>,
+    },
+}
+
+/// Format the usage of an Account against its limit as a single line,
+/// leaving out whichever of the two could not be obtained
+fn format_usage_line(usage_current: Option<u64>, usage_limit: Option<u64>) -> String {
+    match (usage_current, usage_limit) {
+        (Some(current), Some(limit)) if limit > 0 => format!(
+            "usage: {} / {} ({:.1}%)",
+            ByteSize(current),
+            ByteSize(limit),
+            current as f64 / limit as f64 * 100.0
+        ),
+        (Some(current), Some(limit)) => {
+            format!("usage: {} / {}", ByteSize(current), ByteSize(limit))
+        }
+        (Some(current), None) => format!("usage: {}", ByteSize(current)),
+        (None, Some(limit)) => format!("usage: unknown / {}", ByteSize(limit)),
+        (None, None) => "usage: unknown".to_string(),
+    }
+}
+
+/// Query the current usage and limit of the Account, failing only if neither is available
+async fn query_usage(client: &mut Client) -> Result<(Option<u64>, Option<u64>), NativeError> {
+    let usage_current = Account::usage(client).await.ok();
+    let usage_limit = Account::usage_limit(client).await.ok();
+    if usage_current.is_none() && usage_limit.is_none() {
+        return Err(NativeError::unauthorized("Unable to obtain usage stats"));
+    }
+    Ok((usage_current, usage_limit))
+}
+
+/// Format one update of a watched usage, as a line of prose or of JSON
+fn usage_update(mode: OutputMode, usage_current: Option<u64>, usage_limit: Option<u64>) -> String {
+    match mode {
+        OutputMode::Text => format_usage_line(usage_current, usage_limit),
+        OutputMode::Json => serde_json::json!({
+            "usage_current": usage_current,
+            "usage_limit": usage_limit,
+        })
+        .to_string(),
+    }
+}
+
+/// Redraw the usage of the Account every `interval` until interrupted. When stdout is not a
+/// terminal, each update is printed on its own line instead, and as a JSON object in JSON mode.
+async fn watch_usage(client: &mut Client, interval: Duration) -> Result<String, NativeError> {
+    let mode = output_mode();
+    let tty = std::io::stdout().is_terminal() && mode == OutputMode::Text;
+    claim_stdout();
+    let mut ticker = tokio::time::interval(interval);
+    // Listen for Ctrl-C once, so that it is not missed while usage is being queried
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = ticker.tick() => {
+                let (usage_current, usage_limit) = query_usage(client).await?;
+                let line = usage_update(mode, usage_current, usage_limit);
+                let mut stdout = std::io::stdout().lock();
+                if tty {
+                    // Return to the start of the line and clear it before redrawing
+                    write!(stdout, "\r\x1b[2K{line}")?;
+                } else {
+                    writeln!(stdout, "{line}")?;
+                }
+                stdout.flush()?;
+            }
+        }
+    }
+    // Leave the last redrawn line intact
+    if tty {
+        println!();
+    }
+    Ok(format!("{}", "<< STOPPED WATCHING USAGE >>".green()))
 }
 
 #[async_trait(?Send)]
@@ -155,22 +244,22 @@ impl RunnableCommand<NativeError> for AccountCommand {
                 .await
                 .map(|v| v.to_string())
                 .map_err(NativeError::api),
-            AccountCommand::Usage => {
+            AccountCommand::Info => Account::read_current(&mut client)
+                .await
+                .map(|info| render(&info))
+                .map_err(NativeError::api),
+            AccountCommand::Usage {
+                watch: Some(seconds),
+            } => watch_usage(&mut client, Duration::from_secs(seconds.max(1))).await,
+            AccountCommand::Usage { watch: None } => {
                 let mut output = format!("{}", "| ACCOUNT USAGE INFO |".yellow());
 
-                let usage_current_result = Account::usage(&mut client).await;
-                let usage_limit_result = Account::usage_limit(&mut client).await;
+                let (usage_current, usage_limit) = query_usage(&mut client).await?;
 
-                if usage_current_result.is_err() && usage_limit_result.is_err() {
-                    return Err(NativeError::custom_error(
-                        "Unable to obtain usage stats. Check your authentication!",
-                    ));
-                }
-
-                if let Ok(usage_current) = usage_current_result {
+                if let Some(usage_current) = usage_current {
                     output = format!("{}\nusage_current:\t{}", output, ByteSize(usage_current));
                 }
-                if let Ok(usage_limit) = usage_limit_result {
+                if let Some(usage_limit) = usage_limit {
                     output = format!("{}\nusage_limit:\t{}", output, ByteSize(usage_limit));
                 }
 
@@ -179,3 +268,34 @@ impl RunnableCommand<NativeError> for AccountCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{format_usage_line, usage_update};
+    use crate::native::utils::OutputMode;
+
+    #[test]
+    fn usage_line() {
+        assert_eq!(
+            format_usage_line(Some(250_000_000), Some(1_000_000_000)),
+            "usage: 250.0 MB / 1.0 GB (25.0%)"
+        );
+        assert_eq!(format_usage_line(Some(0), Some(0)), "usage: 0 B / 0 B");
+        assert_eq!(format_usage_line(Some(1_500), None), "usage: 1.5 KB");
+        assert_eq!(format_usage_line(None, None), "usage: unknown");
+    }
+
+    #[test]
+    fn usage_updates() {
+        assert_eq!(
+            usage_update(OutputMode::Text, Some(1_500), None),
+            "usage: 1.5 KB"
+        );
+        // Every JSON update is a whole document on a single line
+        let update = usage_update(OutputMode::Json, Some(1_500), None);
+        assert!(!update.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&update).expect("update is not JSON");
+        assert_eq!(value["usage_current"], 1_500);
+        assert!(value["usage_limit"].is_null());
+    }
+}
@@ -0,0 +1,42 @@
+use super::RunnableCommand;
+use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+use async_trait::async_trait;
+use bytesize::ByteSize;
+use clap::Subcommand;
+use colored::Colorize;
+
+/// Subcommand for the local block cache
+#[derive(Subcommand, Clone, Debug)]
+pub enum CacheCommand {
+    /// Display the location and size of the block cache
+    Info,
+    /// Remove all blocks from the block cache
+    Clear,
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for CacheCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        let global = GlobalConfig::from_disk().await?;
+        match self {
+            CacheCommand::Info => {
+                let size = if global.cache_path.exists() {
+                    std::fs::metadata(&global.cache_path)?.len()
+                } else {
+                    0
+                };
+                Ok(format!(
+                    "{}\npath:\t\t{}\nsize:\t\t{}\nlimit:\t\t{}",
+                    "| CACHE INFO |".yellow(),
+                    global.cache_path.display(),
+                    ByteSize(size),
+                    ByteSize(global.cache_size_limit)
+                ))
+            }
+            CacheCommand::Clear => {
+                global.clear_cache()?;
+                Ok(format!("{}", "<< CACHE CLEARED SUCCESSFULLY >>".green()))
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use super::RunnableCommand;
+use crate::{
+    car::{concat, error::CarError, v2::CarV2},
+    native::{utils::claim_stdout, NativeError},
+    utils::get_read,
+};
+use async_trait::async_trait;
+use clap::Subcommand;
+use std::{
+    io::{copy, stdout},
+    path::PathBuf,
+};
+use wnfs::libipld::Cid;
+
+/// Subcommand for working with CAR files
+#[derive(Subcommand, Clone, Debug)]
+pub enum CarCommand {
+    /// Combine several CARv2 files into one, storing shared blocks only once
+    Concat {
+        /// CARv2 files to combine, in order
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Path of the combined CARv2
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Root of the combined CARv2. Defaults to the root of the first input which has one.
+        #[arg(long)]
+        root: Option<Cid>,
+    },
+    /// Write the bytes of a DAG within a CARv2, such as a UnixFS file, to stdout as they are read
+    Cat {
+        /// CARv2 file to read from
+        path: PathBuf,
+
+        /// Root of the DAG to read. Defaults to the root of the CARv2.
+        #[arg(long)]
+        root: Option<Cid>,
+    },
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for CarCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        match self {
+            CarCommand::Concat {
+                inputs,
+                output,
+                root,
+            } => {
+                let car = concat(&inputs, &output, root)?;
+                Ok(format!(
+                    "📦 Combined {} CARs into {} with {} blocks",
+                    inputs.len(),
+                    output.display(),
+                    car.car.index.borrow().get_all_cids().len()
+                ))
+            }
+            CarCommand::Cat { path, root } => {
+                let mut r = get_read(&path)?;
+                let car = CarV2::read_bytes(&mut r)?;
+                let root = root.or(car.get_root()).ok_or(CarError::missing_root())?;
+                claim_stdout();
+                copy(&mut car.dag_reader(&root, &mut r), &mut stdout().lock())?;
+                Ok(String::new())
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use super::RunnableCommand;
+use crate::{cli::args::Args as TombArgs, native::NativeError};
+use async_trait::async_trait;
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+use std::io::Write;
+
+/// Name of the binary completions are generated for
+const BIN_NAME: &str = "banyan";
+
+/// Print a shell completion script to stdout
+#[derive(Args, Clone, Debug)]
+pub struct CompletionsCommand {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+impl CompletionsCommand {
+    /// Write the completion script for every command and argument of the CLI
+    pub fn generate(&self, out: &mut impl Write) {
+        clap_complete::generate(self.shell, &mut TombArgs::command(), BIN_NAME, out);
+    }
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for CompletionsCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        let mut script = Vec::new();
+        self.generate(&mut script);
+        Ok(String::from_utf8(script)?)
+    }
+}
@@ -0,0 +1,31 @@
+use super::RunnableCommand;
+use crate::{
+    cli::specifiers::DriveSpecifier,
+    native::{sync::OmniBucket, utils::render, NativeError},
+};
+use async_trait::async_trait;
+use clap::Args;
+use uuid::Uuid;
+
+/// Show the files which changed between two versions of a Drive's metadata
+#[derive(Args, Clone, Debug)]
+pub struct DiffCommand {
+    /// Drive in question
+    #[clap(flatten)]
+    pub drive_specifier: DriveSpecifier,
+
+    /// Id of the older Metadata
+    pub from: Uuid,
+
+    /// Id of the newer Metadata
+    pub to: Uuid,
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for DiffCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        let omni = OmniBucket::from_specifier(&self.drive_specifier).await;
+        let diff = omni.diff(self.from, self.to).await?;
+        Ok(render(&diff))
+    }
+}
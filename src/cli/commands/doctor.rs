@@ -0,0 +1,52 @@
+use super::RunnableCommand;
+use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+use async_trait::async_trait;
+use clap::Args;
+use colored::{ColoredString, Colorize};
+use reqwest::Url;
+
+/// Check the health of the remote services and the local configuration
+#[derive(Args, Clone, Debug)]
+pub struct DoctorCommand;
+
+/// Colorize the result of a check
+fn check(ok: bool) -> ColoredString {
+    if ok {
+        "OK".green()
+    } else {
+        "FAILED".red()
+    }
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for DoctorCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        // Fall back on the defaults so remote health is still reported
+        let (config_ok, global) = match GlobalConfig::from_disk().await {
+            Ok(global) => (true, global),
+            Err(_) => (false, GlobalConfig::default()),
+        };
+        let wrapping_key_ok = global.wrapping_key().await.is_ok();
+        // Check the storage host of the first drive which has one
+        let data_host = global
+            .buckets
+            .iter()
+            .filter_map(|bucket| bucket.storage_ticket.as_ref())
+            .find_map(|ticket| Url::parse(&ticket.host).ok());
+        // A Client which can't be built is reported like any other failed check
+        let health = match global.get_client().await {
+            Ok(client) => client.health(data_host.as_ref()).await.to_string(),
+            Err(err) => format!(
+                "{}\nclient:\t\t{} ({err})",
+                "| HEALTH INFO |".yellow(),
+                check(false)
+            ),
+        };
+        Ok(format!(
+            "{health}\n{}\nconfig:\t\t{}\nwrapping key:\t{}",
+            "| CONFIG INFO |".yellow(),
+            check(config_ok),
+            check(wrapping_key_ok)
+        ))
+    }
+}
@@ -1,26 +1,138 @@
 use crate::{
+    api::models::bucket::{BucketType, StorageClass},
+    blockstore::{CarV2DiskBlockStore, DoubleSplitStore, RootedBlockStore},
     cli::{
         commands::{prompt_for_bool, KeyCommand, MetadataCommand, RunnableCommand},
         specifiers::DriveSpecifier,
     },
+    filesystem::{
+        wnfsio::{path_to_segments, CompressionCodec},
+        FsMetadataEntryType,
+    },
     native::{
         configuration::globalconfig::GlobalConfig,
-        operations::{prepare, restore},
-        sync::OmniBucket,
+        file_scanning::LinkPolicy,
+        operations::{mv, prepare, remove, restore},
+        sync::{OmniBucket, SyncState},
+        utils::{claim_stdout, render},
         NativeError,
     },
+    utils::get_read,
 };
 use async_trait::async_trait;
 use bytesize::ByteSize;
 use clap::Subcommand;
 use colored::Colorize;
-use std::{env::current_dir, path::PathBuf};
+use serde::Serialize;
+use std::{
+    env::current_dir,
+    fmt::Display,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// Type of entry reported by `find`
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum EntryType {
+    /// Files
+    F,
+    /// Directories
+    D,
+}
+
+impl From<EntryType> for FsMetadataEntryType {
+    fn from(value: EntryType) -> Self {
+        match value {
+            EntryType::F => FsMetadataEntryType::File,
+            EntryType::D => FsMetadataEntryType::Dir,
+        }
+    }
+}
+
+/// Every Drive known locally or remotely
+#[derive(Debug, Serialize)]
+pub struct DriveList {
+    /// The Drives
+    pub drives: Vec<OmniBucket>,
+}
+
+impl Display for DriveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.drives.is_empty() {
+            return f.write_str("No known Drives locally or remotely.");
+        }
+        for drive in &self.drives {
+            f.write_fmt(format_args!("\n{drive}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A newly created Drive
+#[derive(Debug, Serialize)]
+pub struct DriveCreated {
+    /// The Drive
+    #[serde(flatten)]
+    pub drive: OmniBucket,
+}
+
+impl Display for DriveCreated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\n{}",
+            "<< NEW DRIVE CREATED >>".green(),
+            self.drive
+        ))
+    }
+}
+
+/// Data stored remotely by a Drive
+#[derive(Debug, Serialize)]
+pub struct DriveUsage {
+    /// Remote id of the Drive
+    pub drive_id: Uuid,
+    /// Bytes stored
+    pub usage: u64,
+}
+
+impl Display for DriveUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}drive_id:\t\t{}\nusage:\t\t{}",
+            "| USAGE INFO |".blue(),
+            self.drive_id,
+            ByteSize(self.usage)
+        ))
+    }
+}
+
+/// Outcome of syncing a Drive
+#[derive(Debug, Serialize)]
+pub struct SyncReport {
+    /// What was done
+    pub message: String,
+    /// How the local and remote copies relate afterwards
+    pub sync_state: SyncState,
+}
+
+impl Display for SyncReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
 
 /// Subcommand for Drive Management
 #[derive(Subcommand, Clone, Debug)]
 pub enum DrivesCommand {
     /// List all Drives
     Ls,
+    /// Sort Drives by whether they are tracked locally, remotely or both
+    Reconcile {
+        /// Track every remote only Drive in the local configuration
+        #[arg(long)]
+        adopt: bool,
+    },
     /// Initialize a new Drive
     Create {
         /// Drive Name
@@ -29,6 +141,14 @@ pub enum DrivesCommand {
         /// Drive Root
         #[arg(short, long)]
         origin: Option<PathBuf>,
+        /// Workflow the Drive is for: backup or interactive. Backup Drives are stored cold and
+        /// compress their content with zstd; interactive Drives are stored hot, uncompressed
+        #[arg(short = 't', long = "type", default_value_t = BucketType::Interactive)]
+        drive_type: BucketType,
+        /// Encrypt the names of files and directories, so that the layout of the Drive is hidden
+        /// from anyone who can't unlock it
+        #[arg(long)]
+        encrypt_names: bool,
     },
     /// Prepare a Drive for Pushing by encrypting new data
     Prepare {
@@ -36,24 +156,196 @@ pub enum DrivesCommand {
         #[clap(flatten)]
         drive_specifier: DriveSpecifier,
 
-        /// Follow symbolic links
+        /// Follow symbolic links, as long as they lead somewhere inside the Drive's origin
         #[arg(short, long)]
         follow_links: bool,
+
+        /// Follow symbolic links wherever they lead, even outside of the Drive's origin
+        #[arg(long)]
+        follow_links_unsafe: bool,
+
+        /// Number of files to read from disk concurrently. They are still encrypted one at a time
+        #[arg(short, long, default_value_t = 8)]
+        parallelism: usize,
+
+        /// Maximum number of files open at once. Defaults to the soft open file limit minus a margin
+        #[arg(long)]
+        max_open_files: Option<usize>,
+
+        /// Start a new content CAR whenever the current one would grow past this many bytes,
+        /// e.g. to keep each CAR within the size of a storage deal. Only the most recent CAR is
+        /// pushed when syncing, so a Drive whose content rolls over can't be synced.
+        #[arg(long, value_name = "BYTES")]
+        max_car_size: Option<u64>,
+
+        /// Compress file content before it is encrypted: none, lz4, gzip, zstd or zstd:<LEVEL>.
+        /// Remembered for later preparations of the Drive. Files too large to be read into
+        /// memory are always stored uncompressed.
+        #[arg(long, value_name = "CODEC")]
+        compression: Option<CompressionCodec>,
     },
     /// Reconstruct a Drive filesystem locally
     Restore {
         /// Drive in question
         #[clap(flatten)]
         drive_specifier: DriveSpecifier,
+
+        /// Only restore the file or directory at this path within the Drive
+        path: Option<PathBuf>,
+
+        /// Continue an interrupted restore, skipping files which were already restored
+        #[arg(long)]
+        resume: bool,
+
+        /// Number of blocks of a file to decrypt concurrently
+        #[arg(short, long, default_value_t = restore::DEFAULT_RESTORE_PARALLELISM)]
+        parallelism: usize,
+
+        /// Size in bytes of the write buffer in front of each restored file
+        #[arg(long, default_value_t = restore::DEFAULT_WRITE_BUFFER_SIZE)]
+        buffer_size: usize,
+
+        /// Periodically fsync restored files while writing them, and once they are complete.
+        /// Slower, but nothing is reported as restored before it is durably on disk.
+        #[arg(long)]
+        fsync: bool,
+    },
+    /// Display the metadata of a single file or directory in a Drive
+    Stat {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Path of the file or directory within the Drive
+        path: PathBuf,
+
+        /// Report the total size of a directory's contents instead of its number of children
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Write the content of a file in a Drive to stdout
+    Cat {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Path of the file within the Drive
+        path: PathBuf,
+    },
+    /// Remove files and directories from a Drive, saving it only once
+    Rm {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Paths of the files or directories within the Drive
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Move or rename a file or directory within a Drive, along with everything beneath it
+    Mv {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Path of the file or directory within the Drive
+        from: PathBuf,
+
+        /// Path within the Drive to move it to
+        to: PathBuf,
+
+        /// Replace whatever is already at the destination
+        #[arg(short = 'f', long)]
+        overwrite: bool,
+    },
+    /// Find files and directories in a Drive by name, without restoring any content
+    Find {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Glob pattern matched against the names of files and directories
+        pattern: String,
+
+        /// Only report entries of this type: f for files, d for directories
+        #[arg(short = 't', long = "type")]
+        entry_type: Option<EntryType>,
+
+        /// Match names regardless of case
+        #[arg(short, long)]
+        ignore_case: bool,
+    },
+    /// Stream a Drive filesystem to stdout as a tar archive
+    ExportTar(DriveSpecifier),
+    /// Write the encrypted blocks of a Drive into a plain CARv1 which IPFS tooling can import
+    ExportCar {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// File to write the CARv1 to
+        path: PathBuf,
     },
     /// Sync Drive data to or from remote
-    Sync(DriveSpecifier),
+    Sync {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Push metadata and content again, even if the Drive is already synced
+        #[arg(long)]
+        force: bool,
+
+        /// Report what syncing would do without changing anything locally or remotely
+        #[arg(long, conflicts_with = "force")]
+        dry_run: bool,
+    },
+    /// Pull the content of a single subdirectory of a Drive
+    Pull {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Subdirectory within the Drive to pull
+        #[arg(long)]
+        path: PathBuf,
+    },
+    /// Move the data of a Drive to another storage class. Cold storage costs less, but reads
+    /// may be slower and require the data to be rehydrated first.
+    SetClass {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Storage class to move to: hot, warm or cold
+        storage_class: StorageClass,
+    },
+    /// Mark a Drive read-only, refusing any add, remove or prepare until it is unlocked
+    Lock(DriveSpecifier),
+    /// Allow a read-only Drive to be modified again
+    Unlock(DriveSpecifier),
     /// Delete a Drive
-    Delete(DriveSpecifier),
+    Delete {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Report what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Delete both locally and remotely without prompting for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Drive info
     Info(DriveSpecifier),
     /// Drive data usage
     Usage(DriveSpecifier),
+    /// Rebuild the indexes of a Drive's local CAR files
+    Repair(DriveSpecifier),
+    /// Re-hash every block in a Drive's local CAR files, reporting any that are damaged
+    Verify(DriveSpecifier),
     /// Get information on Drive Metadata
     Metadata {
         /// Subcommand
@@ -74,69 +366,309 @@ impl RunnableCommand<NativeError> for DrivesCommand {
         match self {
             // List all Buckets tracked remotely and locally
             DrivesCommand::Ls => {
-                let omnis = OmniBucket::ls().await?;
-                if !omnis.is_empty() {
-                    Ok(omnis
-                        .iter()
-                        .fold(String::new(), |acc, bucket| format!("{acc}\n{bucket}")))
-                } else {
-                    Ok("No known Drives locally or remotely.".to_string())
+                let drives = OmniBucket::ls().await?;
+                Ok(render(&DriveList { drives }))
+            }
+            DrivesCommand::Reconcile { adopt } => {
+                let mut client = GlobalConfig::from_disk().await?.get_client().await?;
+                let mut report = OmniBucket::reconcile(&mut client).await?;
+                if adopt {
+                    report.adopt_remote_only().await?;
                 }
+                Ok(render(&report))
             }
-            // Create a new Bucket. This attempts to create the Bucket both locally and remotely, but settles for a simple local creation if remote permissions fail
-            DrivesCommand::Create { name, origin } => {
+            // Create a new Bucket. This creates the Bucket both locally and remotely, settling for a simple local creation if there are no credentials, and rolls back either half if the other fails
+            DrivesCommand::Create {
+                name,
+                origin,
+                drive_type,
+                encrypt_names,
+            } => {
                 let origin = origin.unwrap_or(current_dir()?);
-                let omni = OmniBucket::create(&name, &origin).await?;
-                let output = format!("{}\n{}", "<< NEW DRIVE CREATED >>".green(), omni);
-                Ok(output)
+                let drive =
+                    OmniBucket::create_with_type(&name, &origin, drive_type, encrypt_names).await?;
+                Ok(render(&DriveCreated { drive }))
             }
             DrivesCommand::Prepare {
                 drive_specifier,
                 follow_links,
+                follow_links_unsafe,
+                parallelism,
+                max_open_files,
+                max_car_size,
+                compression,
             } => {
-                prepare::pipeline(
+                let mut omni = OmniBucket::from_specifier(&drive_specifier).await;
+                if let Some(max_car_size) = max_car_size {
+                    let mut local = omni.get_local()?;
+                    local.content.max_car_size = max_car_size;
+                    omni.set_local(local);
+                }
+                if let Some(compression) = compression {
+                    let mut local = omni.get_local()?;
+                    local.compression = compression;
+                    omni.set_local(local);
+                }
+                let content_path = omni.get_local()?.content.path;
+                let stats = prepare::pipeline(
+                    omni,
+                    LinkPolicy::new(follow_links, follow_links_unsafe),
+                    parallelism,
+                    max_open_files,
+                    None,
+                )
+                .await?;
+                info!("{stats}");
+                Ok(format!(
+                    "Prepared data successfully; Encrypted in {}",
+                    content_path.display()
+                ))
+            }
+            DrivesCommand::Restore {
+                drive_specifier,
+                path,
+                resume,
+                parallelism,
+                buffer_size,
+                fsync,
+            } => {
+                restore::subtree(
                     OmniBucket::from_specifier(&drive_specifier).await,
-                    follow_links,
+                    path.as_deref().unwrap_or(Path::new("")),
+                    None,
+                    resume,
+                    parallelism,
+                    restore::WriteStrategy { buffer_size, fsync },
+                    None,
                 )
                 .await
             }
-            DrivesCommand::Restore { drive_specifier } => {
-                restore::pipeline(OmniBucket::from_specifier(&drive_specifier).await).await
+            DrivesCommand::Stat {
+                drive_specifier,
+                path,
+                recursive,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let fs = omni.unlock().await?;
+                let stat = fs
+                    .stat(
+                        &path_to_segments(&path)?,
+                        recursive,
+                        &omni.get_local()?.metadata,
+                    )
+                    .await?;
+                Ok(format!("{stat}"))
             }
-            DrivesCommand::Sync(drive_specifier) => {
+            DrivesCommand::Cat {
+                drive_specifier,
+                path,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                claim_stdout();
+                restore::cat(omni, &path, std::io::stdout().lock()).await?;
+                Ok(String::new())
+            }
+            DrivesCommand::Rm {
+                drive_specifier,
+                paths,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let missing = remove::pipeline_many(omni.get_local()?, &paths).await?;
+                let removed = paths.len() - missing.len();
+                let mut report = format!("{}", format!("<< REMOVED {removed} PATHS >>").green());
+                for path in missing {
+                    report.push_str(&format!("\n{} {}", "not found:".red(), path.display()));
+                }
+                Ok(report)
+            }
+            DrivesCommand::Mv {
+                drive_specifier,
+                from,
+                to,
+                overwrite,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                mv::pipeline(omni.get_local()?, &from, &to, overwrite).await?;
+                Ok(format!(
+                    "{}",
+                    format!("<< MOVED {} TO {} >>", from.display(), to.display()).green()
+                ))
+            }
+            DrivesCommand::Find {
+                drive_specifier,
+                pattern,
+                entry_type,
+                ignore_case,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let fs = omni.unlock().await?;
+                let paths = fs
+                    .find(
+                        &pattern,
+                        entry_type.map(FsMetadataEntryType::from),
+                        ignore_case,
+                        &omni.get_local()?.metadata,
+                    )
+                    .await?;
+                Ok(paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n"))
+            }
+            DrivesCommand::ExportTar(drive_specifier) => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                claim_stdout();
+                restore::to_tar(omni, std::io::stdout().lock()).await?;
+                Ok("📦 Drive has been exported as a tar archive".to_string())
+            }
+            DrivesCommand::ExportCar {
+                drive_specifier,
+                path,
+            } => {
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let fs = omni.unlock().await?;
+                let local = omni.get_local()?;
+                let root = local
+                    .metadata
+                    .get_root()
+                    .ok_or(NativeError::missing_root("metadata cid"))?;
+                let store = DoubleSplitStore::new(&local.metadata, &local.content);
+                let blocks = fs
+                    .export_carv1(&root, &store, &mut File::create(&path)?)
+                    .await?;
+                Ok(format!("📦 Exported {blocks} blocks to {}", path.display()))
+            }
+            DrivesCommand::Sync {
+                drive_specifier,
+                force,
+                dry_run,
+            } => {
+                let mut omni = OmniBucket::from_specifier(&drive_specifier).await;
+                if dry_run {
+                    return Ok(render(&omni.sync_bucket_dry_run()?));
+                }
+                let message = omni.sync_bucket(force).await?;
+                Ok(render(&SyncReport {
+                    message,
+                    sync_state: omni.sync_state,
+                }))
+            }
+            DrivesCommand::Pull {
+                drive_specifier,
+                path,
+            } => {
+                let mut omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let message = omni.sync_bucket_path(&path).await?;
+                Ok(render(&SyncReport {
+                    message,
+                    sync_state: omni.sync_state,
+                }))
+            }
+            DrivesCommand::SetClass {
+                drive_specifier,
+                storage_class,
+            } => {
                 OmniBucket::from_specifier(&drive_specifier)
                     .await
-                    .sync_bucket()
+                    .set_storage_class(storage_class)
                     .await
             }
-            DrivesCommand::Delete(drive_specifier) => {
+            DrivesCommand::Lock(drive_specifier) => {
+                OmniBucket::from_specifier(&drive_specifier)
+                    .await
+                    .set_read_only(true)
+                    .await
+            }
+            DrivesCommand::Unlock(drive_specifier) => {
+                OmniBucket::from_specifier(&drive_specifier)
+                    .await
+                    .set_read_only(false)
+                    .await
+            }
+            DrivesCommand::Delete {
+                drive_specifier,
+                dry_run,
+                yes,
+            } => {
                 let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                if dry_run {
+                    return Ok(render(&omni.deletion_report().await?));
+                }
+                if yes {
+                    return Ok(render(&omni.delete(true, true).await?));
+                }
                 let local_deletion = prompt_for_bool("Do you want to delete this Bucket locally?");
                 let remote_deletion =
                     prompt_for_bool("Do you want to delete this Bucket remotely?");
-                omni.delete(local_deletion, remote_deletion).await
+                // Deletion can not be undone, so make sure one last time
+                if (local_deletion || remote_deletion)
+                    && !prompt_for_bool(&format!(
+                        "{}\nThis permanently deletes the data above. Are you sure?",
+                        omni.deletion_report().await?.details()
+                    ))
+                {
+                    return Ok("Nothing was deleted".to_string());
+                }
+                Ok(render(&omni.delete(local_deletion, remote_deletion).await?))
             }
             DrivesCommand::Info(drive_specifier) => {
                 let omni = OmniBucket::from_specifier(&drive_specifier).await;
-                Ok(format!("{omni}"))
+                Ok(render(&omni))
             }
             DrivesCommand::Usage(drive_specifier) => {
                 let mut client = GlobalConfig::from_disk().await?.get_client().await?;
                 let remote = OmniBucket::from_specifier(&drive_specifier)
                     .await
                     .get_remote()?;
-                remote
-                    .usage(&mut client)
-                    .await
-                    .map(|v| {
-                        format!(
-                            "{}drive_id:\t\t{}\nusage:\t\t{}",
-                            "| USAGE INFO |".blue(),
-                            remote.id,
-                            ByteSize(v)
-                        )
+                let usage = remote.usage(&mut client).await.map_err(NativeError::api)?;
+                Ok(render(&DriveUsage {
+                    drive_id: remote.id,
+                    usage,
+                }))
+            }
+            DrivesCommand::Repair(drive_specifier) => {
+                // Find the Drive without loading its CARs, which may be too damaged to load
+                let stores = GlobalConfig::bucket_stores_from_disk()?
+                    .into_iter()
+                    .find(|stores| {
+                        let check_remote = stores.remote_id.is_some()
+                            && stores.remote_id == drive_specifier.drive_id;
+                        let check_origin = Some(&stores.origin) == drive_specifier.origin.as_ref();
+                        let check_name = Some(&stores.name) == drive_specifier.name.as_ref();
+                        check_remote || check_origin || check_name
                     })
-                    .map_err(NativeError::api)
+                    .ok_or(NativeError::missing_local_drive())?;
+                let mut output = format!("{}", "| REPAIR INFO |".yellow());
+                for path in stores.car_paths()? {
+                    let (_, blocks) = CarV2DiskBlockStore::repair(&path)?;
+                    output = format!("{output}\n{}:\t{blocks} blocks recovered", path.display());
+                }
+                Ok(output)
+            }
+            DrivesCommand::Verify(drive_specifier) => {
+                let local = OmniBucket::from_specifier(&drive_specifier)
+                    .await
+                    .get_local()?;
+                let deltas = local.content.deltas.borrow();
+                let stores = std::iter::once(&local.metadata).chain(deltas.iter());
+                let mut output = format!("{}", "| VERIFY INFO |".yellow());
+                for store in stores {
+                    let damaged = store.car.verify(get_read(&store.path)?)?;
+                    if damaged.is_empty() {
+                        output = format!("{output}\n{}:\tintact", store.path.display());
+                    } else {
+                        output = format!(
+                            "{output}\n{}:\t{} damaged blocks",
+                            store.path.display(),
+                            damaged.len()
+                        );
+                        for cid in damaged {
+                            output = format!("{output}\n\t{}", cid.to_string().red());
+                        }
+                    }
+                }
+                Ok(output)
             }
             DrivesCommand::Metadata { subcommand } => subcommand.run_internal().await,
             DrivesCommand::Keys { subcommand } => subcommand.run_internal().await,
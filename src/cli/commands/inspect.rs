@@ -0,0 +1,29 @@
+use super::RunnableCommand;
+use crate::{
+    cli::specifiers::DriveSpecifier,
+    native::{sync::OmniBucket, utils::render, NativeError},
+};
+use async_trait::async_trait;
+use clap::Args;
+use wnfs::libipld::Cid;
+
+/// Print the tree of Blocks in a Drive's local content, with their codecs, sizes and links
+#[derive(Args, Clone, Debug)]
+pub struct InspectCommand {
+    /// Drive in question
+    #[clap(flatten)]
+    pub drive_specifier: DriveSpecifier,
+
+    /// Block to start from. Defaults to the root of the Drive's content.
+    #[arg(long)]
+    pub root: Option<Cid>,
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for InspectCommand {
+    async fn run_internal(self) -> Result<String, NativeError> {
+        let omni = OmniBucket::from_specifier(&self.drive_specifier).await;
+        let tree = omni.inspect(self.root).await?;
+        Ok(render(&tree))
+    }
+}
@@ -8,11 +8,13 @@ use super::{
     RunnableCommand,
 };
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use clap::Subcommand;
 use colored::Colorize;
+use std::path::{Path, PathBuf};
 use tomb_crypt::{
     hex_fingerprint,
-    prelude::{PrivateKey, PublicKey},
+    prelude::{EcPublicEncryptionKey, PrivateKey, PublicKey},
 };
 use uuid::Uuid;
 
@@ -22,7 +24,24 @@ pub enum KeyCommand {
     /// Request Access to a Drive if you dont already have it
     RequestAccess(DriveSpecifier),
     /// List all Keys in a Drive
-    Ls(DriveSpecifier),
+    #[clap(alias = "list")]
+    Ls {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+        /// Only show Keys awaiting approval
+        #[arg(long)]
+        pending: bool,
+    },
+    /// Give several public keys access to a Drive in a single metadata update
+    Add {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+        /// PEM file containing a public key to share the Drive with. Repeat for more recipients.
+        #[arg(long = "pem", required = true)]
+        pems: Vec<PathBuf>,
+    },
     /// Get information about an individual Drive Key
     Info(KeySpecifier),
     /// Delete a given Key
@@ -34,7 +53,7 @@ pub enum KeyCommand {
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for KeyCommand {
     async fn run_internal(self) -> Result<String, NativeError> {
-        let global = GlobalConfig::from_disk().await?;
+        let mut global = GlobalConfig::from_disk().await?;
         let mut client = global.get_client().await?;
         match self {
             KeyCommand::RequestAccess(drive_specifier) => {
@@ -66,9 +85,12 @@ impl RunnableCommand<NativeError> for KeyCommand {
                     Err(NativeError::missing_remote_drive())
                 }
             }
-            KeyCommand::Ls(drive_specifier) => {
+            KeyCommand::Ls {
+                drive_specifier,
+                pending,
+            } => {
                 let omni = OmniBucket::from_specifier(&drive_specifier).await;
-                let id = omni.get_id().unwrap();
+                let id = omni.get_id()?;
                 let my_fingerprint = hex_fingerprint(
                     &global
                         .wrapping_key()
@@ -80,13 +102,45 @@ impl RunnableCommand<NativeError> for KeyCommand {
                 );
                 BucketKey::read_all(id, &mut client)
                     .await
-                    .map(|keys| {
-                        keys.iter().fold(String::new(), |acc, key| {
-                            format!("{}\n\n{}", acc, key.context_fmt(&my_fingerprint))
-                        })
-                    })
+                    .map(|keys| key_table(&keys, &my_fingerprint, pending))
                     .map_err(NativeError::api)
             }
+            KeyCommand::Add {
+                drive_specifier,
+                pems,
+            } => {
+                // Import every key, noting those which are invalid rather than giving up
+                let mut recipients = Vec::new();
+                let mut failures = Vec::new();
+                for pem in pems {
+                    match import_public_key(&pem).await {
+                        Ok(recipient) => recipients.push(recipient),
+                        Err(err) => failures.push(format!("{}: {err}", pem.display())),
+                    }
+                }
+                if recipients.is_empty() {
+                    return Err(NativeError::custom_error(&format!(
+                        "No valid public keys were provided:\n{}",
+                        failures.join("\n")
+                    )));
+                }
+
+                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let local = omni.get_local()?;
+                local.ensure_writable()?;
+                let mut fs = omni.unlock().await?;
+                fs.share_with_all(&recipients, &local.metadata).await?;
+                global.update_config(&local)?;
+
+                let mut report = format!(
+                    "{}\nSync the Drive to push the new access.",
+                    format!("<< SHARED WITH {} KEYS >>", recipients.len()).green()
+                );
+                for failure in failures {
+                    report.push_str(&format!("\n{} {failure}", "failed:".red()));
+                }
+                Ok(report)
+            }
             KeyCommand::Info(ks) => {
                 let (bucket_id, id) = get_key_info(&client, &ks).await?;
                 let my_fingerprint = hex_fingerprint(
@@ -121,6 +175,42 @@ impl RunnableCommand<NativeError> for KeyCommand {
     }
 }
 
+/// Read a public key from a PEM file
+async fn import_public_key(path: &Path) -> Result<EcPublicEncryptionKey, NativeError> {
+    let pem_bytes = std::fs::read(path)?;
+    Ok(EcPublicEncryptionKey::import(&pem_bytes).await?)
+}
+
+/// Render Drive Keys as a table, marking the Key belonging to this device
+fn key_table(keys: &[BucketKey], my_fingerprint: &str, pending: bool) -> String {
+    let mut table = format!(
+        "\n{:<36}  {:<40}  {:<10}  {:<19}",
+        "ID", "FINGERPRINT", "STATUS", "CREATED"
+    );
+    for key in keys.iter().filter(|key| !pending || !key.approved) {
+        let status = if key.approved {
+            format!("{:<10}", "Approved").green()
+        } else {
+            format!("{:<10}", "Pending").red()
+        };
+        let created = key
+            .created_at
+            .and_then(|created_at| NaiveDateTime::from_timestamp_opt(created_at, 0))
+            .map(|created_at| created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or("-".to_string());
+        let mine = if key.fingerprint == my_fingerprint {
+            "<< THIS IS YOUR KEY".green().to_string()
+        } else {
+            String::new()
+        };
+        table.push_str(&format!(
+            "\n{:<36}  {:<40}  {}  {:<19}  {}",
+            key.id, key.fingerprint, status, created, mine
+        ));
+    }
+    table
+}
+
 async fn get_key_info(
     client: &Client,
     key_specifier: &KeySpecifier,
@@ -1,7 +1,7 @@
 use std::collections::BTreeSet;
 
 use crate::{
-    api::models::metadata::Metadata,
+    api::{models::metadata::Metadata, requests::capabilities::SNAPSHOT_MANIFEST},
     filesystem::FilesystemError,
     native::{configuration::globalconfig::GlobalConfig, sync::OmniBucket, NativeError},
     prelude::blockstore::RootedBlockStore,
@@ -12,6 +12,7 @@ use super::{
     RunnableCommand,
 };
 use async_trait::async_trait;
+use chrono::DateTime;
 use clap::Subcommand;
 use wnfs::{libipld::Cid, private::PrivateNode};
 
@@ -19,7 +20,15 @@ use wnfs::{libipld::Cid, private::PrivateNode};
 #[derive(Subcommand, Clone, Debug)]
 pub enum MetadataCommand {
     /// List all Metadatas associated with Bucket
-    Ls(DriveSpecifier),
+    Ls {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Only list Metadatas created after this RFC 3339 timestamp, e.g. 2023-09-05T19:05:34Z
+        #[arg(long, value_parser = parse_timestamp)]
+        since: Option<i64>,
+    },
     /// Read an individual Metadata Id
     Read(MetadataSpecifier),
     /// Read the currently active Metadata
@@ -28,23 +37,35 @@ pub enum MetadataCommand {
     Snapshot(MetadataSpecifier),
 }
 
+/// Parse an RFC 3339 timestamp into seconds since the epoch
+fn parse_timestamp(timestamp: &str) -> Result<i64, String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|datetime| datetime.timestamp())
+        .map_err(|err| format!("invalid RFC 3339 timestamp: {err}"))
+}
+
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for MetadataCommand {
     async fn run_internal(self) -> Result<String, NativeError> {
         let mut client = GlobalConfig::from_disk().await?.get_client().await?;
         match self {
             // List all Metadata for a Bucket
-            MetadataCommand::Ls(drive_specifier) => {
+            MetadataCommand::Ls {
+                drive_specifier,
+                since,
+            } => {
                 let omni = OmniBucket::from_specifier(&drive_specifier).await;
                 let bucket_id = omni.get_id()?;
-                Metadata::read_all(bucket_id, &mut client)
-                    .await
-                    .map(|metadatas| {
-                        metadatas.iter().fold(String::from("\n"), |acc, metadata| {
-                            format!("{}\n\n{}", acc, metadata)
-                        })
+                match since {
+                    Some(since) => Metadata::read_all_since(bucket_id, since, &mut client).await,
+                    None => Metadata::read_all(bucket_id, &mut client).await,
+                }
+                .map(|metadatas| {
+                    metadatas.iter().fold(String::from("\n"), |acc, metadata| {
+                        format!("{}\n\n{}", acc, metadata)
                     })
-                    .map_err(NativeError::api)
+                })
+                .map_err(NativeError::api)
             }
             // Read an existing metadata
             MetadataCommand::Read(metadata_specifier) => {
@@ -80,7 +101,7 @@ impl RunnableCommand<NativeError> for MetadataCommand {
                 if local.metadata.get_root().map(|cid| cid.to_string())
                     != Some(metadata.metadata_cid.clone())
                 {
-                    return Err(NativeError::custom_error("this is the wrong metadata"));
+                    return Err(NativeError::not_synced());
                 }
 
                 // Finish loading the filesystem
@@ -106,11 +127,26 @@ impl RunnableCommand<NativeError> for MetadataCommand {
                     }
                 }
 
-                metadata
-                    .snapshot(active_cids, &mut client)
-                    .await
-                    .map(|snapshot| format!("{:?}", snapshot))
-                    .map_err(NativeError::api)
+                let snapshot_id = metadata.snapshot(active_cids, &mut client).await?;
+
+                // Attest to what the snapshot contains
+                let signing_key = GlobalConfig::from_disk().await?.api_key().await?;
+                let manifest = fs.sign_manifest(&signing_key, &local.metadata).await?;
+                let manifest_path = local.save_manifest(snapshot_id, &manifest)?;
+                // Keep the manifest with the snapshot too, where the server can hold it
+                let attached = if client.supports(None, SNAPSHOT_MANIFEST).await {
+                    metadata
+                        .attach_manifest(snapshot_id, &manifest, &mut client)
+                        .await?;
+                    "attached to snapshot"
+                } else {
+                    "only saved locally, the server can't hold it"
+                };
+                Ok(format!(
+                    "{:?}\nsigned manifest:\t{} ({attached})",
+                    snapshot_id,
+                    manifest_path.display()
+                ))
             }
         }
     }
@@ -1,6 +1,12 @@
 mod account;
 mod api;
+mod cache;
+mod car;
+mod completions;
+mod diff;
+mod doctor;
 mod drives;
+mod inspect;
 mod keys;
 mod metadata;
 mod runnable_command;
@@ -11,8 +17,14 @@ use crate::native::NativeError;
 pub use account::AccountCommand;
 pub use api::ApiCommand;
 use async_trait::async_trait;
+pub use cache::CacheCommand;
+pub use car::CarCommand;
 use clap::Subcommand;
-pub use drives::DrivesCommand;
+pub use completions::CompletionsCommand;
+pub use diff::DiffCommand;
+pub use doctor::DoctorCommand;
+pub use drives::{DriveCreated, DriveList, DriveUsage, DrivesCommand, SyncReport};
+pub use inspect::InspectCommand;
 pub use keys::KeyCommand;
 pub use metadata::MetadataCommand;
 pub use runnable_command::RunnableCommand;
@@ -52,6 +64,27 @@ pub enum TombCommand {
         #[clap(subcommand)]
         command: DrivesCommand,
     },
+    /// Local block cache management
+    Cache {
+        /// Subcommand
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+    /// Work with CAR files directly
+    Car {
+        /// Subcommand
+        #[clap(subcommand)]
+        command: CarCommand,
+    },
+    /// Check the health of the remote services and the local configuration
+    Doctor(DoctorCommand),
+    /// Show the files which changed between two versions of a Drive's metadata
+    Diff(DiffCommand),
+    /// Print the tree of Blocks in a Drive's local content
+    Inspect(InspectCommand),
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions(CompletionsCommand),
 }
 
 #[async_trait(?Send)]
@@ -61,6 +94,12 @@ impl RunnableCommand<NativeError> for TombCommand {
             TombCommand::Api { command } => Ok(command.run_internal().await?),
             TombCommand::Account { command } => Ok(command.run_internal().await?),
             TombCommand::Drives { command } => command.run_internal().await,
+            TombCommand::Cache { command } => command.run_internal().await,
+            TombCommand::Car { command } => command.run_internal().await,
+            TombCommand::Doctor(command) => command.run_internal().await,
+            TombCommand::Diff(command) => command.run_internal().await,
+            TombCommand::Inspect(command) => command.run_internal().await,
+            TombCommand::Completions(command) => command.run_internal().await,
         }
     }
 }
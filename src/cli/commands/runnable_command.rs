@@ -1,7 +1,10 @@
 use std::fmt::Display;
 
 use crate::{
-    native::configuration::{globalconfig::GlobalConfig, xdg::config_path},
+    native::{
+        configuration::{globalconfig::GlobalConfig, xdg::config_path},
+        utils::{output_mode, stdout_claimed, OutputMode},
+    },
     WnfsError,
 };
 use async_trait::async_trait;
@@ -25,6 +28,23 @@ where
 
         let result = self.run_internal().await;
 
+        // Scripts get exactly one JSON document on stdout, whether or not the command succeeded,
+        // unless the command wrote its own output there
+        if output_mode() == OutputMode::Json {
+            let document = match &result {
+                // Commands without a structured result still report their message
+                Ok(message) => serde_json::from_str::<serde_json::Value>(message)
+                    .unwrap_or_else(|_| serde_json::json!({ "message": message })),
+                Err(error) => serde_json::json!({ "error": error.to_string() }),
+            };
+            if stdout_claimed() {
+                eprintln!("{document:#}");
+            } else {
+                println!("{document:#}");
+            }
+            return result.map(|_| ());
+        }
+
         // Provide output based on that
         match result {
             Ok(message) => {
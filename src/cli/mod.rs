@@ -12,16 +12,24 @@ mod test {
     #[cfg(feature = "integration-tests")]
     use crate::cli::commands::AccountCommand;
     use crate::{
+        api::models::bucket::BucketType,
         cli::{
-            commands::{DrivesCommand, RunnableCommand, TombCommand},
+            args::Args,
+            commands::{DriveUsage, DrivesCommand, RunnableCommand, TombCommand},
             specifiers::DriveSpecifier,
         },
-        native::{configuration::globalconfig::GlobalConfig, NativeError},
+        native::{
+            configuration::globalconfig::GlobalConfig,
+            operations::restore,
+            utils::{configure_color, render_as, OutputMode},
+            NativeError,
+        },
         utils::{
             testing::local_operations::{test_setup, test_teardown},
             UtilityError,
         },
     };
+    use clap::Parser;
     use serial_test::serial;
     use std::path::Path;
 
@@ -38,6 +46,8 @@ mod test {
             command: DrivesCommand::Create {
                 name: "Bucket Name".to_string(),
                 origin: Some(origin.to_path_buf()),
+                drive_type: BucketType::Interactive,
+                encrypt_names: false,
             },
         }
     }
@@ -56,7 +66,12 @@ mod test {
         TombCommand::Drives {
             command: DrivesCommand::Prepare {
                 drive_specifier: DriveSpecifier::with_origin(origin),
-                follow_links: true,
+                follow_links: false,
+                follow_links_unsafe: false,
+                parallelism: 8,
+                max_open_files: None,
+                max_car_size: None,
+                compression: None,
             },
         }
     }
@@ -66,6 +81,11 @@ mod test {
         TombCommand::Drives {
             command: DrivesCommand::Restore {
                 drive_specifier: DriveSpecifier::with_origin(origin),
+                path: None,
+                resume: false,
+                parallelism: restore::DEFAULT_RESTORE_PARALLELISM,
+                buffer_size: restore::DEFAULT_WRITE_BUFFER_SIZE,
+                fsync: false,
             },
         }
     }
@@ -154,4 +174,161 @@ mod test {
         // Teardown test
         test_teardown(test_name).await
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_color() -> Result<(), UtilityError> {
+        let test_name = "cli_no_color";
+        test_setup(test_name).await?;
+        // The same command is colored when colors are forced on
+        colored::control::set_override(true);
+        let colored_args =
+            Args::try_parse_from(["banyan", "cache", "info"]).expect("failed to parse arguments");
+        assert!(colored_args.command.run_internal().await?.contains('\x1b'));
+        let args = Args::try_parse_from(["banyan", "--no-color", "cache", "info"])
+            .expect("failed to parse arguments");
+        assert!(!configure_color(args.no_color));
+        let output = args.command.run_internal().await;
+        // Leave colors as they were for other tests
+        colored::control::unset_override();
+        assert!(!output?.contains('\x1b'));
+        test_teardown(test_name).await
+    }
+
+    #[test]
+    fn json_output() {
+        let args = Args::try_parse_from(["banyan", "--output", "json", "cache", "info"])
+            .expect("failed to parse arguments");
+        assert_eq!(args.output, OutputMode::Json);
+        let usage = DriveUsage {
+            drive_id: uuid::Uuid::nil(),
+            usage: 1024,
+        };
+        let value: serde_json::Value =
+            serde_json::from_str(&render_as(OutputMode::Json, &usage)).expect("output is not JSON");
+        assert_eq!(value["usage"], 1024);
+        assert_eq!(value["drive_id"], uuid::Uuid::nil().to_string());
+        // Prose is unchanged
+        assert!(render_as(OutputMode::Text, &usage).contains("1.0 KB"));
+    }
+
+    #[test]
+    fn completions() {
+        for shell in ["bash", "zsh", "fish", "powershell"] {
+            let args = Args::try_parse_from(["banyan", "completions", shell])
+                .expect("failed to parse arguments");
+            let TombCommand::Completions(command) = args.command else {
+                panic!("parsed the wrong command");
+            };
+            let mut script = Vec::new();
+            command.generate(&mut script);
+            let script = String::from_utf8(script).expect("script is not UTF-8");
+            // Nested subcommands are completed too
+            assert!(script.contains("banyan"));
+            assert!(script.contains("export-car"));
+        }
+    }
+
+    #[test]
+    fn restore_resume() {
+        let args =
+            Args::try_parse_from(["banyan", "drives", "restore", "-o", "origin", "--resume"])
+                .expect("failed to parse arguments");
+        let TombCommand::Drives {
+            command: DrivesCommand::Restore { resume, .. },
+        } = args.command
+        else {
+            panic!("parsed the wrong command");
+        };
+        assert!(resume);
+    }
+
+    #[test]
+    fn mv_overwrite() {
+        let args = Args::try_parse_from([
+            "banyan", "drives", "mv", "-o", "origin", "kitty", "puppy", "-f",
+        ])
+        .expect("failed to parse arguments");
+        let TombCommand::Drives {
+            command:
+                DrivesCommand::Mv {
+                    drive_specifier,
+                    overwrite,
+                    ..
+                },
+        } = args.command
+        else {
+            panic!("parsed the wrong command");
+        };
+        assert_eq!(drive_specifier.origin, Some("origin".into()));
+        assert!(overwrite);
+    }
+
+    #[test]
+    fn prepare_max_open_files() {
+        let args = Args::try_parse_from([
+            "banyan",
+            "drives",
+            "prepare",
+            "-o",
+            "origin",
+            "--max-open-files",
+            "64",
+        ])
+        .expect("failed to parse arguments");
+        let TombCommand::Drives {
+            command: DrivesCommand::Prepare { max_open_files, .. },
+        } = args.command
+        else {
+            panic!("parsed the wrong command");
+        };
+        assert_eq!(max_open_files, Some(64));
+    }
+
+    #[test]
+    fn restore_parallelism() {
+        for (extra, expected) in [
+            (vec![], restore::DEFAULT_RESTORE_PARALLELISM),
+            (vec!["--parallelism", "3"], 3),
+        ] {
+            let args = Args::try_parse_from(
+                ["banyan", "drives", "restore", "-o", "origin"]
+                    .into_iter()
+                    .chain(extra),
+            )
+            .expect("failed to parse arguments");
+            let TombCommand::Drives {
+                command: DrivesCommand::Restore { parallelism, .. },
+            } = args.command
+            else {
+                panic!("parsed the wrong command");
+            };
+            assert_eq!(parallelism, expected);
+        }
+    }
+
+    #[test]
+    fn restore_write_strategy() {
+        let args = Args::try_parse_from([
+            "banyan",
+            "drives",
+            "restore",
+            "-o",
+            "origin",
+            "--buffer-size",
+            "4096",
+            "--fsync",
+        ])
+        .expect("failed to parse arguments");
+        let TombCommand::Drives {
+            command: DrivesCommand::Restore {
+                buffer_size, fsync, ..
+            },
+        } = args.command
+        else {
+            panic!("parsed the wrong command");
+        };
+        assert_eq!(buffer_size, 4096);
+        assert!(fsync);
+    }
 }
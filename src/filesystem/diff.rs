@@ -0,0 +1,227 @@
+//! Changes to the files of a FileSystem between two of its versions.
+use super::{content_hash, error::FilesystemError, FsMetadata};
+use colored::Colorize;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::PathBuf,
+};
+use wnfs::{common::BlockStore, libipld::Cid, private::PrivateNode};
+
+/// Files which changed between two versions of a FileSystem, each list in path order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FsDiff {
+    /// Files only in the newer version
+    pub added: Vec<PathBuf>,
+    /// Files only in the older version
+    pub removed: Vec<PathBuf>,
+    /// Files in both versions whose content differs
+    pub modified: Vec<PathBuf>,
+    /// Files whose unchanged content is at a new path, as (old path, new path)
+    pub moved: Vec<(PathBuf, PathBuf)>,
+}
+
+impl FsDiff {
+    /// Whether or not nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.moved.is_empty()
+    }
+}
+
+impl Display for FsDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no changes");
+        }
+        let mut lines = Vec::new();
+        lines.extend(
+            self.added
+                .iter()
+                .map(|path| format!("{} {}", "+".green(), path.display())),
+        );
+        lines.extend(
+            self.removed
+                .iter()
+                .map(|path| format!("{} {}", "-".red(), path.display())),
+        );
+        lines.extend(
+            self.modified
+                .iter()
+                .map(|path| format!("{} {}", "~".yellow(), path.display())),
+        );
+        lines.extend(
+            self.moved
+                .iter()
+                .map(|(from, to)| format!("{} {} -> {}", ">".blue(), from.display(), to.display())),
+        );
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// What identifies the content of a file across versions
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct FileContent {
+    /// CIDs of the content blocks
+    cids: BTreeSet<Cid>,
+    /// Plaintext hash of the content, for files written since hashes were recorded
+    hash: Option<String>,
+}
+
+impl FileContent {
+    /// Whether this says anything about the content. Files without content blocks or a hash
+    /// can't be matched up when they move.
+    fn is_known(&self) -> bool {
+        !self.cids.is_empty() || self.hash.is_some()
+    }
+}
+
+impl FsMetadata {
+    /// Every file in this FileSystem along with what identifies its content
+    async fn file_contents(
+        &self,
+        metadata_store: &impl BlockStore,
+    ) -> Result<BTreeMap<PathBuf, FileContent>, FilesystemError> {
+        let mut files = BTreeMap::new();
+        for (node, path) in self.get_all_nodes(metadata_store).await? {
+            if let PrivateNode::File(file) = node {
+                let cids = file
+                    .get_cids(&self.forest, metadata_store)
+                    .await
+                    .map_err(Box::from)?
+                    .into_iter()
+                    .collect();
+                let hash = content_hash(file.get_metadata()).map(str::to_string);
+                files.insert(path, FileContent { cids, hash });
+            }
+        }
+        Ok(files)
+    }
+
+    /// Compute the files which changed from this version of the FileSystem to a newer one.
+    /// Content is compared by its block CIDs, and by its plaintext hash where one was recorded.
+    /// A file removed from one path and added at another with the same content counts as moved.
+    pub async fn diff(
+        &self,
+        metadata_store: &impl BlockStore,
+        newer: &FsMetadata,
+        newer_metadata_store: &impl BlockStore,
+    ) -> Result<FsDiff, FilesystemError> {
+        let before = self.file_contents(metadata_store).await?;
+        let mut after = newer.file_contents(newer_metadata_store).await?;
+        let mut diff = FsDiff::default();
+
+        let mut removed = Vec::new();
+        for (path, content) in before {
+            match after.remove(&path) {
+                Some(new_content) if new_content != content => diff.modified.push(path),
+                Some(_) => {}
+                None => removed.push((path, content)),
+            }
+        }
+
+        // Whatever is left was added, unless it is where a removed file went
+        let mut added_by_content = BTreeMap::<FileContent, Vec<PathBuf>>::new();
+        for (path, content) in after {
+            added_by_content.entry(content).or_default().push(path);
+        }
+        for (path, content) in removed {
+            let destination = added_by_content
+                .get_mut(&content)
+                .filter(|paths| content.is_known() && !paths.is_empty())
+                .map(|paths| paths.remove(0));
+            match destination {
+                Some(destination) => diff.moved.push((path, destination)),
+                None => diff.removed.push(path),
+            }
+        }
+        diff.added = added_by_content.into_values().flatten().collect();
+        diff.added.sort();
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod test {
+    use super::FsDiff;
+    use crate::{
+        blockstore::CarV2MemoryBlockStore,
+        filesystem::{FilesystemError, FsMetadata},
+    };
+    use std::path::PathBuf;
+    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('/').map(String::from).collect()
+    }
+
+    #[tokio::test]
+    async fn diff() -> Result<(), FilesystemError> {
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let mut fs = FsMetadata::init(&wrapping_key).await?;
+        for (path, content) in [
+            ("cats/kitty.txt", "Hello Kitty!"),
+            ("cats/tom.txt", "Hello Tom!"),
+            ("dogs/puppy.txt", "Hello Puppy!"),
+            ("dogs/rex.txt", "Hello Rex!"),
+        ] {
+            fs.write(
+                &segments(path),
+                &metadata_store,
+                &content_store,
+                content.as_bytes().to_vec(),
+            )
+            .await?;
+        }
+        fs.save(&metadata_store, &content_store).await?;
+        let before = FsMetadata::unlock(&wrapping_key, &metadata_store).await?;
+        assert!(before
+            .diff(&metadata_store, &before, &metadata_store)
+            .await?
+            .is_empty());
+
+        fs.write(
+            &segments("cats/kitty.txt"),
+            &metadata_store,
+            &content_store,
+            "Goodbye Kitty!".as_bytes().to_vec(),
+        )
+        .await?;
+        fs.rm(&segments("cats/tom.txt"), &metadata_store).await?;
+        fs.mv(
+            &segments("dogs/rex.txt"),
+            &segments("cats/rex.txt"),
+            &metadata_store,
+            &content_store,
+        )
+        .await?;
+        fs.write(
+            &segments("birds/tweety.txt"),
+            &metadata_store,
+            &content_store,
+            "Hello Tweety!".as_bytes().to_vec(),
+        )
+        .await?;
+        fs.save(&metadata_store, &content_store).await?;
+        let after = FsMetadata::unlock(&wrapping_key, &metadata_store).await?;
+
+        assert_eq!(
+            before
+                .diff(&metadata_store, &after, &metadata_store)
+                .await?,
+            FsDiff {
+                added: vec![PathBuf::from("birds/tweety.txt")],
+                removed: vec![PathBuf::from("cats/tom.txt")],
+                modified: vec![PathBuf::from("cats/kitty.txt")],
+                moved: vec![(PathBuf::from("dogs/rex.txt"), PathBuf::from("cats/rex.txt"))],
+            }
+        );
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use tomb_crypt::prelude::TombCryptError;
 
 use crate::{blockstore::BlockStoreError, WnfsError};
 
-use super::sharing::SharingError;
+use super::sharing::{SharingError, UnlockFailure};
 
 #[derive(Debug)]
 pub struct FilesystemError {
@@ -21,11 +21,30 @@ impl Display for FilesystemError {
             FilesystemErrorKind::NodeNotFound(path) => {
                 format!("Unable to find node with path \"{path}\"")
             }
+            FilesystemErrorKind::NodeExists(path) => {
+                format!("A node already exists at path \"{path}\"")
+            }
+            FilesystemErrorKind::MoveIntoSelf(path) => {
+                format!("Unable to move node with path \"{path}\" inside of itself")
+            }
+            FilesystemErrorKind::BadName(name) => {
+                format!("Unable to decrypt node name \"{name}\"")
+            }
             FilesystemErrorKind::Sharing(err) => format!("{} {err}", "SHARING ERROR:".underline()),
             FilesystemErrorKind::Blockstore(err) => {
                 format!("{} {err}", "BLOCKSTORE ERROR:".underline())
             }
             FilesystemErrorKind::Wnfs(err) => format!("{} {err}", "WNFS ERROR:".underline()),
+            FilesystemErrorKind::BadPattern(pattern) => {
+                format!("Invalid glob pattern \"{pattern}\"")
+            }
+            FilesystemErrorKind::Manifest(msg) => {
+                format!("Unable to sign or verify manifest: {msg}")
+            }
+            FilesystemErrorKind::PlaintextMetadata(names) => format!(
+                "refusing to push metadata which exposes directory names: {}",
+                names.join(", ")
+            ),
         };
 
         f.write_str(&string)
@@ -47,6 +66,30 @@ impl FilesystemError {
         }
     }
 
+    pub fn node_exists(path: &str) -> Self {
+        Self {
+            kind: FilesystemErrorKind::NodeExists(path.to_string()),
+        }
+    }
+
+    pub fn move_into_self(path: &str) -> Self {
+        Self {
+            kind: FilesystemErrorKind::MoveIntoSelf(path.to_string()),
+        }
+    }
+
+    pub fn bad_name(name: &str) -> Self {
+        Self {
+            kind: FilesystemErrorKind::BadName(name.to_string()),
+        }
+    }
+
+    pub fn bad_pattern(pattern: &str) -> Self {
+        Self {
+            kind: FilesystemErrorKind::BadPattern(pattern.to_string()),
+        }
+    }
+
     pub fn sharing(err: SharingError) -> Self {
         Self {
             kind: FilesystemErrorKind::Sharing(err),
@@ -59,20 +102,47 @@ impl FilesystemError {
         }
     }
 
+    pub fn manifest(msg: &str) -> Self {
+        Self {
+            kind: FilesystemErrorKind::Manifest(msg.to_string()),
+        }
+    }
+
+    /// Metadata about to be pushed contains plaintext directory names
+    pub fn plaintext_metadata(names: &[String]) -> Self {
+        Self {
+            kind: FilesystemErrorKind::PlaintextMetadata(names.to_vec()),
+        }
+    }
+
     pub fn wnfs(err: WnfsError) -> Self {
         Self {
             kind: FilesystemErrorKind::Wnfs(err),
         }
     }
+
+    /// Why a key was unable to unlock the FileSystem, if that is what this error describes
+    pub fn unlock_failure(&self) -> Option<UnlockFailure> {
+        match &self.kind {
+            FilesystemErrorKind::Sharing(err) => err.unlock_failure(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum FilesystemErrorKind {
     MissingMetadata(String),
     NodeNotFound(String),
+    NodeExists(String),
+    MoveIntoSelf(String),
+    BadName(String),
+    BadPattern(String),
     Sharing(SharingError),
     Blockstore(BlockStoreError),
     Wnfs(WnfsError),
+    Manifest(String),
+    PlaintextMetadata(Vec<String>),
 }
 
 impl From<SharingError> for FilesystemError {
@@ -0,0 +1,219 @@
+//! Signed attestations of what a FileSystem contains at a point in time, which anyone holding
+//! the signer's public key can check for tampering.
+use super::{error::FilesystemError, FsMetadata};
+use crate::blockstore::RootedBlockStore;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::Utc;
+use p384::{
+    ecdsa::{
+        signature::{Signer, Verifier},
+        Signature, SigningKey, VerifyingKey,
+    },
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+};
+use serde::{Deserialize, Serialize};
+use tomb_crypt::prelude::{EcPublicSignatureKey, EcSignatureKey, PrivateKey, PublicKey};
+use wnfs::{libipld::Cid, private::PrivateNode};
+
+/// Prefix of the hashes of leaves of the merkle tree, so that they can't be mistaken for the
+/// interior nodes above them and the other way around
+const LEAF_PREFIX: u8 = 0;
+/// Prefix of the hashes of interior nodes of the merkle tree
+const NODE_PREFIX: u8 = 1;
+
+/// Summary of the content of a FileSystem
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Root CID of the metadata the FileSystem was loaded from
+    pub root_cid: String,
+    /// Hex encoded root of a merkle tree over every file path and its content CIDs
+    pub merkle_root: String,
+    /// Number of files summarized by the merkle root
+    pub file_count: usize,
+    /// Milliseconds since the epoch at which the Manifest was created
+    pub created: i64,
+}
+
+/// Manifest along with a signature over its JSON encoding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedManifest {
+    /// Manifest which was signed
+    pub manifest: Manifest,
+    /// Base64 encoded ECDSA signature
+    pub signature: String,
+}
+
+impl SignedManifest {
+    /// Whether the Manifest was signed by the private half of `public_key` and is unmodified
+    pub async fn verify(&self, public_key: &EcPublicSignatureKey) -> Result<bool, FilesystemError> {
+        let pem = String::from_utf8(public_key.export().await?)
+            .map_err(|_| FilesystemError::manifest("public key is not valid PEM"))?;
+        let verifying_key = VerifyingKey::from_public_key_pem(&pem)
+            .map_err(|err| FilesystemError::manifest(&err.to_string()))?;
+        let Ok(signature) = STANDARD
+            .decode(&self.signature)
+            .map_err(|_| ())
+            .and_then(|bytes| Signature::from_slice(&bytes).map_err(|_| ()))
+        else {
+            return Ok(false);
+        };
+        Ok(verifying_key
+            .verify(&manifest_bytes(&self.manifest)?, &signature)
+            .is_ok())
+    }
+}
+
+impl FsMetadata {
+    /// Summarize the content of this FileSystem and sign the summary
+    pub async fn sign_manifest(
+        &self,
+        signing_key: &EcSignatureKey,
+        metadata_store: &impl RootedBlockStore,
+    ) -> Result<SignedManifest, FilesystemError> {
+        let root_cid = metadata_store
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("root cid"))?;
+
+        // Every file contributes a leaf of its path and content CIDs, in path order
+        let mut files = Vec::new();
+        for (node, path) in self.get_all_nodes(metadata_store).await? {
+            if let PrivateNode::File(file) = node {
+                let cids = file
+                    .get_cids(&self.forest, metadata_store)
+                    .await
+                    .map_err(Box::from)?;
+                files.push((path, cids));
+            }
+        }
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let leaves = files
+            .iter()
+            .map(|(path, cids)| leaf_hash(&path.to_string_lossy(), cids))
+            .collect();
+
+        let manifest = Manifest {
+            root_cid: root_cid.to_string(),
+            merkle_root: merkle_root(leaves).to_hex().to_string(),
+            file_count: files.len(),
+            created: Utc::now().timestamp_millis(),
+        };
+
+        let pem = String::from_utf8(signing_key.export().await?.to_vec())
+            .map_err(|_| FilesystemError::manifest("signing key is not valid PEM"))?;
+        let signing_key = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|err| FilesystemError::manifest(&err.to_string()))?;
+        let signature: Signature = signing_key.sign(&manifest_bytes(&manifest)?);
+        Ok(SignedManifest {
+            manifest,
+            signature: STANDARD.encode(signature.to_bytes()),
+        })
+    }
+}
+
+/// Bytes of a Manifest which are signed
+fn manifest_bytes(manifest: &Manifest) -> Result<Vec<u8>, FilesystemError> {
+    serde_json::to_vec(manifest).map_err(|err| FilesystemError::manifest(&err.to_string()))
+}
+
+/// Hash of the leaf of the merkle tree for a file at `path` with content `cids`
+fn leaf_hash(path: &str, cids: &[Cid]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(path.as_bytes());
+    for cid in cids {
+        hasher.update(&[0]);
+        hasher.update(&cid.to_bytes());
+    }
+    hasher.finalize()
+}
+
+/// Hash of the interior node of the merkle tree above `left` and `right`
+fn node_hash(left: &blake3::Hash, right: &blake3::Hash) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[NODE_PREFIX]);
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    hasher.finalize()
+}
+
+/// Hash pairs of nodes until a single root remains, carrying odd nodes up a level
+fn merkle_root(mut level: Vec<blake3::Hash>) -> blake3::Hash {
+    if level.is_empty() {
+        return blake3::hash(&[]);
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [single] => *single,
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod test {
+    use super::{leaf_hash, merkle_root, node_hash};
+    use crate::{
+        blockstore::CarV2MemoryBlockStore,
+        filesystem::{FilesystemError, FsMetadata},
+    };
+    use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey};
+
+    async fn setup() -> Result<(FsMetadata, CarV2MemoryBlockStore), FilesystemError> {
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let mut fs = FsMetadata::init(&wrapping_key).await?;
+        fs.write(
+            &["cats".to_string(), "kitty.txt".to_string()],
+            &metadata_store,
+            &content_store,
+            "Hello Kitty!".as_bytes().to_vec(),
+        )
+        .await?;
+        fs.save(&metadata_store, &content_store).await?;
+        Ok((fs, metadata_store))
+    }
+
+    #[tokio::test]
+    async fn valid_signature() -> Result<(), FilesystemError> {
+        let (fs, metadata_store) = setup().await?;
+        let signing_key = EcSignatureKey::generate().await?;
+        let signed = fs.sign_manifest(&signing_key, &metadata_store).await?;
+        assert_eq!(signed.manifest.file_count, 1);
+        assert!(signed.verify(&signing_key.public_key()?).await?);
+        // Other keys did not sign it
+        let other_key = EcSignatureKey::generate().await?;
+        assert!(!signed.verify(&other_key.public_key()?).await?);
+        Ok(())
+    }
+
+    #[test]
+    fn nodes_are_not_leaves() {
+        let left = leaf_hash("left", &[]);
+        let right = leaf_hash("right", &[]);
+        // The same bytes hash differently as a leaf and as an interior node
+        let mut concatenated = left.as_bytes().to_vec();
+        concatenated.extend_from_slice(right.as_bytes());
+        let mut unprefixed = blake3::Hasher::new();
+        unprefixed.update(&concatenated);
+        assert_ne!(node_hash(&left, &right), unprefixed.finalize());
+        assert_ne!(leaf_hash("left", &[]), blake3::hash(b"left"));
+        assert_eq!(merkle_root(vec![left, right]), node_hash(&left, &right));
+    }
+
+    #[tokio::test]
+    async fn tampered_manifest() -> Result<(), FilesystemError> {
+        let (fs, metadata_store) = setup().await?;
+        let signing_key = EcSignatureKey::generate().await?;
+        let mut signed = fs.sign_manifest(&signing_key, &metadata_store).await?;
+        signed.manifest.file_count += 1;
+        assert!(!signed.verify(&signing_key.public_key()?).await?);
+        Ok(())
+    }
+}
@@ -1,28 +1,49 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::blockstore::CarV2DiskBlockStore;
 use crate::{
-    blockstore::{BanyanBlockStore, CarV2MemoryBlockStore, DoubleSplitStore, RootedBlockStore},
+    blockstore::{
+        BanyanBlockStore, BlockStoreError, CarV2MemoryBlockStore, DoubleSplitStore,
+        RootedBlockStore,
+    },
+    car::{
+        v1::{Block, Header},
+        v2::collect_links,
+        Streamable, SyncCell,
+    },
     filesystem::{
+        names::NameKey,
         serialize::{load_dir, load_forest, store_dir, store_forest, store_share_manager},
-        sharing::{manager::ShareManager, SharedFile},
-        wnfsio::path_to_segments,
+        sharing::{manager::ShareManager, SharedFile, SharingError},
+        wnfsio::{path_to_segments, CompressionCodec},
     },
+    utils::{Clock, SystemClock},
 };
+use aho_corasick::AhoCorasick;
 use async_recursion::async_recursion;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
+use colored::Colorize;
+use futures::AsyncRead;
 use futures_util::future::join_all;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::stream::{self, LocalBoxStream, StreamExt};
+use glob::{MatchOptions, Pattern};
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Display,
+    io::{Seek, Write},
     path::{Path, PathBuf},
     rc::Rc,
 };
 use tomb_crypt::prelude::{EcEncryptionKey, EcPublicEncryptionKey, PrivateKey};
 use wnfs::{
-    common::{BlockStore, Metadata},
-    libipld::{Cid, Ipld},
+    common::{dagcbor, BlockStore, Metadata},
+    libipld::{Cid, Ipld, IpldCodec},
     namefilter::Namefilter,
     private::{
-        share::SharePayload, PrivateDirectory, PrivateForest, PrivateNode, PrivateNodeOnPathHistory,
+        share::SharePayload, PrivateDirectory, PrivateFile, PrivateForest, PrivateNode,
+        PrivateNodeOnPathHistory,
     },
 };
 
@@ -33,6 +54,15 @@ const FOREST_LABEL: &str = "FOREST";
 const TOMB_BUILD_FEATURES_LABEL: &str = "TOMB_BUILD_FEATURES";
 const TOMB_BUILD_PROFILE_LABEL: &str = "TOMB_BUILD_PROFILE";
 const TOMB_REPO_VERSION_LABEL: &str = "TOMB_REPO_VERSION";
+const NAME_KEY_LABEL: &str = "NAME_KEY";
+const MODE_LABEL: &str = "mode";
+const MTIME_LABEL: &str = "mtime";
+const MTIME_NSEC_LABEL: &str = "mtime_nsec";
+const CTIME_LABEL: &str = "ctime";
+const CONTENT_HASH_LABEL: &str = "blake3";
+const HARDLINK_LABEL: &str = "hardlink";
+/// Names shorter than this are too likely to occur by chance in ciphertext to be checked for
+const MIN_PLAINTEXT_NAME_LEN: usize = 6;
 
 // TODO: Allow ser / de against a cbor file on disk -- that would be straight up easier to debug
 /// Describes how to serialize / deserialize metadata for a Wnfs Fs against
@@ -47,6 +77,9 @@ pub struct FsMetadata {
     pub share_manager: ShareManager,
     /// Loaded Metadata
     pub metadata: Option<BTreeMap<String, Ipld>>,
+    /// Key used to encrypt path segments before they become WNFS names.
+    /// Drives without one stored in their root directory use plaintext names.
+    pub name_key: Option<NameKey>,
 }
 
 impl FsMetadata {
@@ -74,9 +107,25 @@ impl FsMetadata {
             root_dir,
             share_manager,
             metadata: None,
+            name_key: None,
         })
     }
 
+    /// Initialize a new FsMetadata which encrypts the names of all of its Nodes.
+    /// The name key is kept in the metadata of the encrypted root directory.
+    pub async fn init_with_name_encryption(
+        wrapping_key: &EcEncryptionKey,
+    ) -> Result<Self, FilesystemError> {
+        let mut fs = Self::init(wrapping_key).await?;
+        let name_key = NameKey::generate();
+        Rc::make_mut(&mut fs.root_dir)
+            .content
+            .metadata
+            .put(NAME_KEY_LABEL, Ipld::Bytes(name_key.as_bytes().to_vec()));
+        fs.name_key = Some(name_key);
+        Ok(fs)
+    }
+
     /// Save our metadata as blocks and Link them to the root of the blockstore
     pub async fn save(
         &mut self,
@@ -153,8 +202,8 @@ impl FsMetadata {
             .map_err(Box::from)?;
         assert_eq!(root_cid_1, root_cid_2);
 
-        metadata_store.set_root(&root_cid_1);
-        content_store.set_root(&root_cid_1);
+        metadata_store.set_root(&root_cid_1)?;
+        content_store.set_root(&root_cid_1)?;
 
         let root_cid_3 = metadata_store
             .get_root()
@@ -210,7 +259,7 @@ impl FsMetadata {
         let mut share_manager = store
             .get_deserializable::<ShareManager>(share_manager_cid)
             .await
-            .map_err(Box::from)?;
+            .map_err(|err| SharingError::corrupt_share_manager(&err.to_string()))?;
         // Get our private Ref
         share_manager.load_refs(wrapping_key).await?;
         let current_private_ref = share_manager
@@ -220,12 +269,18 @@ impl FsMetadata {
 
         // Get the root directory
         let root_dir = load_dir(store, current_private_ref, &forest).await?;
+        // Get the name key, if this Fs encrypts names
+        let name_key = match root_dir.get_metadata().0.get(NAME_KEY_LABEL) {
+            Some(Ipld::Bytes(bytes)) => NameKey::from_bytes(bytes),
+            _ => None,
+        };
         // Return the new metadata
         Ok(Self {
             forest,
             root_dir,
             share_manager,
             metadata: Some(root_map),
+            name_key,
         })
     }
 
@@ -235,7 +290,91 @@ impl FsMetadata {
         recipient: &EcPublicEncryptionKey,
         store: &impl RootedBlockStore,
     ) -> Result<(), FilesystemError> {
-        self.share_manager.share_with(recipient).await?;
+        self.share_with_all(std::slice::from_ref(recipient), store)
+            .await
+    }
+
+    /// Revoke the access of the key with `fingerprint` and save. Every Node in the tree is
+    /// moved onto a fresh name and ratchet first, so the revoked key can not follow any of them
+    /// to future versions, even from a reference to a single file or directory.
+    /// This is forward secrecy only: versions from before the revocation, and any content
+    /// the revoked key already holds keys to, remain accessible to it.
+    pub async fn revoke(
+        &mut self,
+        fingerprint: &str,
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl RootedBlockStore,
+    ) -> Result<(), FilesystemError> {
+        self.share_manager.revoke(fingerprint).await?;
+        let ds_store = DoubleSplitStore::new(metadata_store, content_store);
+        // Rebuild the tree under a brand new root directory
+        let old_root = Rc::clone(&self.root_dir);
+        let mut new_root = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            &mut thread_rng(),
+        ));
+        Rc::make_mut(&mut new_root).content.metadata = old_root.content.metadata.clone();
+        self.rekey_children(&old_root, &mut new_root, &[], &ds_store)
+            .await?;
+        self.root_dir = new_root;
+        self.save(metadata_store, content_store).await
+    }
+
+    /// Recreate everything below `path` in `old_root` within `new_root`, such that each Node
+    /// takes the header of a brand new one, with a new name and ratchet. Files keep their
+    /// content, which is not encrypted again.
+    #[async_recursion(?Send)]
+    async fn rekey_children(
+        &mut self,
+        old_root: &PrivateDirectory,
+        new_root: &mut Rc<PrivateDirectory>,
+        path: &[String],
+        store: &impl BlockStore,
+    ) -> Result<(), FilesystemError> {
+        let mut rng = thread_rng();
+        let entries = old_root
+            .ls(path, true, &self.forest, store)
+            .await
+            .map_err(Box::from)?;
+        for (name, _) in entries {
+            let mut child = path.to_vec();
+            child.push(name);
+            match old_root
+                .get_node(&child, true, &self.forest, store)
+                .await
+                .map_err(Box::from)?
+            {
+                Some(PrivateNode::Dir(_)) => {
+                    new_root
+                        .mkdir(&child, true, Utc::now(), &self.forest, store, &mut rng)
+                        .await
+                        .map_err(Box::from)?;
+                    self.rekey_children(old_root, new_root, &child, store)
+                        .await?;
+                }
+                Some(PrivateNode::File(file)) => {
+                    let new_file = new_root
+                        .open_file_mut(&child, true, Utc::now(), &mut self.forest, store, &mut rng)
+                        .await
+                        .map_err(Box::from)?;
+                    let header = new_file.header.clone();
+                    *new_file = (*file).clone();
+                    new_file.header = header;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Share with several recipients, updating the metadata only once
+    pub async fn share_with_all(
+        &mut self,
+        recipients: &[EcPublicEncryptionKey],
+        store: &impl RootedBlockStore,
+    ) -> Result<(), FilesystemError> {
+        self.share_manager.share_with_all(recipients).await?;
         // Save the new share manager to the map, conserving all other links in the metadata
         let store_manager_cid = store_share_manager(&self.share_manager, store).await?;
         // Get the map
@@ -256,7 +395,7 @@ impl FsMetadata {
         // Put the metadata IPLD Map into BlockStores
         let metadata_cid = store.put_serializable(&metadata).await.map_err(Box::from)?;
         // Update the root CID
-        store.set_root(&metadata_cid);
+        store.set_root(&metadata_cid)?;
         // Update the metadata
         Ok(())
     }
@@ -309,6 +448,7 @@ impl FsMetadata {
             file_name,
             mime_type,
             size,
+            expires_at: None,
         })
     }
 
@@ -316,6 +456,18 @@ impl FsMetadata {
         shared_file: SharedFile,
         store: &impl BlockStore,
     ) -> Result<Vec<u8>, FilesystemError> {
+        Self::receive_file_content_at(shared_file, store, &SystemClock).await
+    }
+
+    /// Receive the content of a shared file, refusing it if it has expired according to `clock`
+    pub async fn receive_file_content_at(
+        shared_file: SharedFile,
+        store: &impl BlockStore,
+        clock: &impl Clock,
+    ) -> Result<Vec<u8>, FilesystemError> {
+        if shared_file.is_expired(clock) {
+            return Err(FilesystemError::sharing(SharingError::expired()));
+        }
         let forest = load_forest(&shared_file.forest_cid, store).await?;
 
         // Grab node using share label.
@@ -327,14 +479,15 @@ impl FsMetadata {
                     .map_err(Box::from)?
                     .as_file()
                     .map_err(Box::from)?;
-                Ok(file.get_content(&forest, store).await.map_err(Box::from)?)
+                let content = file.get_content(&forest, store).await.map_err(Box::from)?;
+                decompress_content(&file, content)
             }
         }
     }
 
     /// Get the original root directory
     pub async fn history(
-        &mut self,
+        &self,
         store: &impl BlockStore,
     ) -> Result<PrivateNodeOnPathHistory, FilesystemError> {
         // Get the original private ref
@@ -394,12 +547,33 @@ impl FsMetadata {
         ))
     }
 
+    /// Translate plaintext path segments into the names used by WNFS.
+    /// Needed by anything operating on `root_dir` directly.
+    pub fn wnfs_segments(&self, path_segments: &[String]) -> Vec<String> {
+        match &self.name_key {
+            Some(name_key) => path_segments
+                .iter()
+                .map(|segment| name_key.encrypt(segment))
+                .collect(),
+            None => path_segments.to_vec(),
+        }
+    }
+
+    /// Translate a name used by WNFS back into plaintext
+    fn plaintext_name(&self, name: &str) -> Result<String, FilesystemError> {
+        match &self.name_key {
+            Some(name_key) => name_key.decrypt(name),
+            None => Ok(name.to_string()),
+        }
+    }
+
     /// Make a new directory in the Fs. Store in our metadata store
     pub async fn mkdir(
         &mut self,
         path_segments: &[String],
         metadata_store: &impl RootedBlockStore,
     ) -> Result<(), FilesystemError> {
+        let path_segments = &self.wnfs_segments(path_segments);
         // Search through the PrivateDirectory for a Node that matches the path provided
         let result = self
             .root_dir
@@ -431,6 +605,7 @@ impl FsMetadata {
         path_segments: &[String],
         store: &impl RootedBlockStore,
     ) -> Result<Vec<FsMetadataEntry>, FilesystemError> {
+        let path_segments = &self.wnfs_segments(path_segments);
         let fetched_entries = self
             .root_dir
             .ls(path_segments, true, &self.forest, store)
@@ -441,6 +616,7 @@ impl FsMetadata {
         let mut futures = Vec::new();
 
         for (name, metadata) in fetched_entries.iter() {
+            let plaintext_name = self.plaintext_name(name)?;
             let node_path_segments = path_segments
                 .iter()
                 .chain(std::iter::once(name))
@@ -460,7 +636,7 @@ impl FsMetadata {
                     ))
                     .expect("node not found");
                 // Map the node to an FsMetadataEntry
-                let name = name.to_string();
+                let name = plaintext_name;
                 let entry_type = match entry {
                     PrivateNode::Dir(_) => FsMetadataEntryType::Dir,
                     PrivateNode::File(_) => FsMetadataEntryType::File,
@@ -485,6 +661,152 @@ impl FsMetadata {
         Ok(transformed_entries)
     }
 
+    /// List one page of the directory at the path provided, sorted by name. Only the nodes in the
+    /// page are loaded, so very large directories can be listed a little at a time. Returns the
+    /// page along with whether there are entries after it.
+    pub async fn ls_paginated(
+        &self,
+        path_segments: &[String],
+        offset: usize,
+        limit: usize,
+        store: &impl RootedBlockStore,
+    ) -> Result<(Vec<FsMetadataEntry>, bool), FilesystemError> {
+        let wnfs_segments = self.wnfs_segments(path_segments);
+        let dir = if wnfs_segments.is_empty() {
+            Rc::clone(&self.root_dir)
+        } else {
+            self.root_dir
+                .get_node(&wnfs_segments, true, &self.forest, store)
+                .await
+                .map_err(Box::from)?
+                .ok_or_else(|| FilesystemError::node_not_found(&path_segments.join("/")))?
+                .as_dir()
+                .map_err(Box::from)?
+        };
+
+        // Names are encrypted in WNFS, so order by the plaintext ones. Only the names are read
+        // from the directory itself, no child is loaded until it is known to be in the page.
+        let mut names = dir
+            .get_entries()
+            .map(|name| Ok((self.plaintext_name(name)?, name.clone())))
+            .collect::<Result<Vec<_>, FilesystemError>>()?;
+        names.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let has_more = names.len() > offset.saturating_add(limit);
+
+        let dir = &dir;
+        let page =
+            names
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(plaintext_name, name)| async move {
+                    let (entry_type, metadata) = match dir
+                        .get_node(&[name], true, &self.forest, store)
+                        .await
+                        .map_err(Box::from)?
+                    {
+                        Some(PrivateNode::Dir(dir)) => {
+                            (FsMetadataEntryType::Dir, dir.get_metadata().clone())
+                        }
+                        Some(PrivateNode::File(file)) => {
+                            (FsMetadataEntryType::File, file.get_metadata().clone())
+                        }
+                        None => {
+                            let mut node_path_segments = path_segments.to_vec();
+                            node_path_segments.push(plaintext_name);
+                            return Err(FilesystemError::node_not_found(
+                                &node_path_segments.join("/"),
+                            ));
+                        }
+                    };
+                    Ok::<_, FilesystemError>(FsMetadataEntry {
+                        name: plaintext_name,
+                        entry_type,
+                        metadata,
+                    })
+                });
+        let entries = join_all(page)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, FilesystemError>>()?;
+
+        Ok((entries, has_more))
+    }
+
+    /// List the directory at the path provided along with everything beneath it, descending at
+    /// most `max_depth` levels. Directories beyond that depth are returned without children.
+    #[async_recursion(?Send)]
+    pub async fn tree(
+        &self,
+        path_segments: &[String],
+        max_depth: Option<usize>,
+        store: &impl RootedBlockStore,
+    ) -> Result<Vec<FsTreeEntry>, FilesystemError> {
+        let mut tree = Vec::new();
+        for entry in self.ls(path_segments, store).await? {
+            let children = match entry.entry_type {
+                FsMetadataEntryType::Dir if max_depth != Some(0) => {
+                    let child_path_segments = path_segments
+                        .iter()
+                        .chain(std::iter::once(&entry.name))
+                        .cloned()
+                        .collect::<Vec<String>>();
+                    Some(
+                        self.tree(
+                            &child_path_segments,
+                            max_depth.map(|depth| depth - 1),
+                            store,
+                        )
+                        .await?,
+                    )
+                }
+                _ => None,
+            };
+            tree.push(FsTreeEntry { entry, children });
+        }
+        Ok(tree)
+    }
+
+    /// Find the paths of every Node whose name matches a glob pattern, optionally restricted to
+    /// a single entry type. Only metadata is read, never file content.
+    pub async fn find(
+        &self,
+        pattern: &str,
+        entry_type: Option<FsMetadataEntryType>,
+        case_insensitive: bool,
+        store: &impl RootedBlockStore,
+    ) -> Result<Vec<PathBuf>, FilesystemError> {
+        let pattern = Pattern::new(pattern).map_err(|_| FilesystemError::bad_pattern(pattern))?;
+        let options = MatchOptions {
+            case_sensitive: !case_insensitive,
+            ..MatchOptions::default()
+        };
+        let mut found = Vec::new();
+        // Walk the tree depth first, accumulating the path of each entry along the way
+        let mut stack = self
+            .tree(&[], None, store)
+            .await?
+            .into_iter()
+            .map(|node| (PathBuf::from(&node.entry.name), node))
+            .collect::<Vec<_>>();
+        while let Some((path, node)) = stack.pop() {
+            let type_matches = match (&entry_type, &node.entry.entry_type) {
+                (None, _)
+                | (Some(FsMetadataEntryType::File), FsMetadataEntryType::File)
+                | (Some(FsMetadataEntryType::Dir), FsMetadataEntryType::Dir) => true,
+                _ => false,
+            };
+            if type_matches && pattern.matches_with(&node.entry.name, options) {
+                found.push(path.clone());
+            }
+            for child in node.children.unwrap_or_default() {
+                stack.push((path.join(&child.entry.name), child));
+            }
+        }
+        found.sort();
+        Ok(found)
+    }
+
     /// Mv a file or directory to a new location
     pub async fn mv(
         &mut self,
@@ -494,17 +816,19 @@ impl FsMetadata {
         content_store: &impl BanyanBlockStore,
     ) -> Result<(), FilesystemError> {
         let ds_store = DoubleSplitStore::new(metadata_store, content_store);
+        let src_names = self.wnfs_segments(src_path_segments);
+        let dest_names = self.wnfs_segments(dest_path_segments);
         let result = self
             .root_dir
-            .get_node(src_path_segments, true, &self.forest, metadata_store)
+            .get_node(&src_names, true, &self.forest, metadata_store)
             .await
             .map_err(Box::from)?;
         match result {
             Some(_) => {
                 self.root_dir
                     .basic_mv(
-                        src_path_segments,
-                        dest_path_segments,
+                        &src_names,
+                        &dest_names,
                         true,
                         Utc::now(),
                         &mut self.forest,
@@ -548,8 +872,8 @@ impl FsMetadata {
         // Copy and Link
         self.root_dir
             .cp_link(
-                src_path_segments,
-                dest_path_segments,
+                &self.wnfs_segments(src_path_segments),
+                &self.wnfs_segments(dest_path_segments),
                 true,
                 &mut self.forest,
                 metadata_store,
@@ -575,7 +899,7 @@ impl FsMetadata {
         self.root_dir
             .write_symlink(
                 target_string,
-                path_segments,
+                &self.wnfs_segments(path_segments),
                 true,
                 Utc::now(),
                 &self.forest,
@@ -595,7 +919,12 @@ impl FsMetadata {
     ) -> Result<(), FilesystemError> {
         // Create the subdirectory
         self.root_dir
-            .rm(path_segments, true, &self.forest, store)
+            .rm(
+                &self.wnfs_segments(path_segments),
+                true,
+                &self.forest,
+                store,
+            )
             .await
             .map(|_| ())
             .map_err(|_| FilesystemError::node_not_found(&path_segments.join("/")))
@@ -611,7 +940,12 @@ impl FsMetadata {
         // Compress the data in the file
         let result = self
             .root_dir
-            .get_node(path_segments, true, &self.forest, metadata_store)
+            .get_node(
+                &self.wnfs_segments(path_segments),
+                true,
+                &self.forest,
+                metadata_store,
+            )
             .await
             .expect("node not found");
 
@@ -620,10 +954,11 @@ impl FsMetadata {
 
         // If the node is found and is a file
         if let Some(PrivateNode::File(file)) = result {
-            file.get_content(&self.forest, &split_store)
+            let content = file
+                .get_content(&self.forest, &split_store)
                 .await
-                .map_err(Box::from)
-                .map_err(FilesystemError::wnfs)
+                .map_err(Box::from)?;
+            decompress_content(&file, content)
         } else {
             Err(FilesystemError::node_not_found(&path_segments.join("/")))
         }
@@ -636,35 +971,156 @@ impl FsMetadata {
         metadata_store: &impl RootedBlockStore,
         content_store: &impl BanyanBlockStore,
         content: Vec<u8>,
+    ) -> Result<(), FilesystemError> {
+        self.write_uncompressed(path_segments, metadata_store, content_store, content, None)
+            .await
+    }
+
+    /// Write data to a specific node, compressing it with `codec` before it is encrypted. The
+    /// codec is recorded in the metadata of the file so that reads decompress it again.
+    /// Content which is already compressed, such as images, video or archives, is stored as is.
+    /// Any `record` of the file is stored along with it.
+    pub async fn write_compressed(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        content: Vec<u8>,
+        codec: CompressionCodec,
+        record: Option<FileRecord<'_>>,
+    ) -> Result<(), FilesystemError> {
+        let to_filesystem_error = |err| FilesystemError::wnfs(Box::from(err));
+        // Skip compressing content which a sample of it shows won't get any smaller
+        if !codec
+            .worth_compressing(&content)
+            .map_err(to_filesystem_error)?
+        {
+            return self
+                .write_uncompressed(
+                    path_segments,
+                    metadata_store,
+                    content_store,
+                    content,
+                    record,
+                )
+                .await;
+        }
+        let compressed = codec.compress(&content).map_err(to_filesystem_error)?;
+        if compressed.len() >= content.len() {
+            return self
+                .write_uncompressed(
+                    path_segments,
+                    metadata_store,
+                    content_store,
+                    content,
+                    record,
+                )
+                .await;
+        }
+        let data_size = content.len() as u64;
+        let plaintext_hash = blake3::hash(&content);
+        self.write_content(
+            path_segments,
+            metadata_store,
+            content_store,
+            compressed.as_slice(),
+            data_size,
+            Some((codec, plaintext_hash)),
+            record,
+        )
+        .await
+    }
+
+    /// Write data which is held in memory to a specific node as is, along with any `record`
+    async fn write_uncompressed(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        content: Vec<u8>,
+        record: Option<FileRecord<'_>>,
+    ) -> Result<(), FilesystemError> {
+        let data_size = content.len() as u64;
+        self.write_stream(
+            path_segments,
+            metadata_store,
+            content_store,
+            content.as_slice(),
+            data_size,
+            record,
+        )
+        .await
+    }
+
+    /// Write data to a specific node as it is read from `content`, which yields `data_size`
+    /// bytes. Blocks are encrypted and stored one chunk at a time, so memory use does not
+    /// depend on the size of the content. Any `record` of the file is stored along with it.
+    pub async fn write_stream(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        content: impl AsyncRead + Unpin,
+        data_size: u64,
+        record: Option<FileRecord<'_>>,
+    ) -> Result<(), FilesystemError> {
+        self.write_content(
+            path_segments,
+            metadata_store,
+            content_store,
+            content,
+            data_size,
+            None,
+            record,
+        )
+        .await
+    }
+
+    /// Write `content` to a specific node, recording `data_size` as its size. Content which was
+    /// already compressed comes with its codec and the hash of its plaintext, since the hash
+    /// can't be taken as it is written. The `record` of a prepared file goes into the same
+    /// revision as its content, so that it is only stored once.
+    #[allow(clippy::too_many_arguments)]
+    async fn write_content(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        content: impl AsyncRead + Unpin,
+        data_size: u64,
+        compressed: Option<(CompressionCodec, blake3::Hash)>,
+        record: Option<FileRecord<'_>>,
     ) -> Result<(), FilesystemError> {
         let time = Utc::now();
-        let data_size = content.len();
         let mut rng = thread_rng();
 
         let ds_store = DoubleSplitStore::new(metadata_store, content_store);
+        let names = self.wnfs_segments(path_segments);
 
         let result = self
             .root_dir
-            .open_file_mut(
-                path_segments,
-                true,
-                time,
-                &mut self.forest,
-                &ds_store,
-                &mut rng,
-            )
+            .open_file_mut(&names, true, time, &mut self.forest, &ds_store, &mut rng)
             .await;
 
         if let Ok(file) = result {
+            let mut content = HashingReader::new(content);
             file.set_content(
                 time,
-                content.as_slice(),
+                &mut content,
                 &mut self.forest,
                 content_store,
                 &mut rng,
             )
             .await
             .map_err(Box::from)?;
+            let (codec, plaintext_hash) =
+                compressed.unwrap_or_else(|| (CompressionCodec::None, content.hasher.finalize()));
+            // The plaintext hash lets restores recognize files which are already on disk
+            file.content.metadata.put(
+                CONTENT_HASH_LABEL,
+                Ipld::String(plaintext_hash.to_hex().to_string()),
+            );
+            codec.put(&mut file.content.metadata);
 
             let full_path: std::path::PathBuf = path_segments.iter().collect();
             if let Some(mime) = mime_guess::MimeGuess::from_path(full_path).first() {
@@ -676,6 +1132,9 @@ impl FsMetadata {
             file.content
                 .metadata
                 .put("size", Ipld::Integer(data_size as i128));
+            if let Some(record) = record {
+                record.put(&mut file.content.metadata);
+            }
 
             // Be extra sure to store the private file content back into the content store
             // This is important for retrieving snapshotted files later on
@@ -689,6 +1148,119 @@ impl FsMetadata {
         }
     }
 
+    /// Record the attributes a prepared file had on the filesystem along with whether it is a hard
+    /// link in its metadata, storing a new revision of it only when that changes anything. Files
+    /// which are written are recorded as they are written, this is for copies of them.
+    pub async fn set_file_record(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        record: FileRecord<'_>,
+    ) -> Result<(), FilesystemError> {
+        let Some(PrivateNode::File(file)) = self.get_node(path_segments, metadata_store).await?
+        else {
+            return Err(FilesystemError::node_not_found(&path_segments.join("/")));
+        };
+        let mut metadata = file.get_metadata().clone();
+        record.put(&mut metadata);
+        // Avoid storing a new revision when nothing changes
+        if &metadata == file.get_metadata() {
+            return Ok(());
+        }
+
+        let time = Utc::now();
+        let mut rng = thread_rng();
+        let ds_store = DoubleSplitStore::new(metadata_store, content_store);
+        let names = self.wnfs_segments(path_segments);
+        let file = self
+            .root_dir
+            .open_file_mut(&names, true, time, &mut self.forest, &ds_store, &mut rng)
+            .await
+            .map_err(Box::from)?;
+        record.put(&mut file.content.metadata);
+        file.store(&mut self.forest, &ds_store, &mut rng)
+            .await
+            .map_err(Box::from)?;
+        Ok(())
+    }
+
+    /// Stream the content of the file at `path_segments`. Blocks are fetched and decrypted only
+    /// as the stream is read, so the whole file is never held in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn stream_file_content<'a>(
+        &'a self,
+        path_segments: &[String],
+        metadata_store: &'a impl BanyanBlockStore,
+        content_store: &'a impl BanyanBlockStore,
+    ) -> Result<FileContentStream<'a>, FilesystemError> {
+        match self.get_node(path_segments, metadata_store).await? {
+            Some(PrivateNode::File(file)) => {
+                self.file_content_stream(file, metadata_store, content_store, 1)
+                    .await
+            }
+            _ => Err(FilesystemError::node_not_found(&path_segments.join("/"))),
+        }
+    }
+
+    /// Stream the content of `file`, fetching and decrypting up to `parallelism` blocks ahead
+    /// of the reader. A block which can not be found in either store ends the stream with an
+    /// error when it is reached.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn file_content_stream<'a>(
+        &'a self,
+        file: Rc<PrivateFile>,
+        metadata_store: &'a impl BanyanBlockStore,
+        content_store: &'a impl BanyanBlockStore,
+        parallelism: usize,
+    ) -> Result<FileContentStream<'a>, FilesystemError> {
+        let forest = &self.forest;
+        let block_count = file
+            .get_cids(
+                forest,
+                &DoubleSplitStore::new(content_store, metadata_store),
+            )
+            .await
+            .map_err(Box::from)?
+            .len();
+        // Content small enough to be stored inline has no blocks of its own
+        let blocks = if block_count == 0 {
+            stream::once(async move {
+                let split_store = DoubleSplitStore::new(content_store, metadata_store);
+                file.get_content(forest, &split_store)
+                    .await
+                    .map_err(Box::from)
+                    .map_err(FilesystemError::wnfs)
+            })
+            .boxed_local()
+        } else {
+            stream::iter(0..block_count)
+                .map(move |index| {
+                    let file = Rc::clone(&file);
+                    async move {
+                        let split_store = DoubleSplitStore::new(content_store, metadata_store);
+                        // Content streams start from a given block, only the first is needed
+                        let block = Box::pin(file.stream_content(index, forest, &split_store))
+                            .next()
+                            .await;
+                        match block {
+                            Some(block) => block.map_err(Box::from).map_err(FilesystemError::wnfs),
+                            None => Err(FilesystemError::wnfs(Box::from(format!(
+                                "missing block {index} of file content"
+                            )))),
+                        }
+                    }
+                })
+                .buffered(parallelism.max(1))
+                .boxed_local()
+        };
+        Ok(FileContentStream {
+            blocks,
+            block: Vec::new(),
+            position: 0,
+        })
+    }
+
     /// Get a node from the Fs
     pub async fn get_node(
         &self,
@@ -698,7 +1270,12 @@ impl FsMetadata {
         // Search through the PrivateDirectory for a Node that matches the path provided
         let result = self
             .root_dir
-            .get_node(path_segments, true, &self.forest, store)
+            .get_node(
+                &self.wnfs_segments(path_segments),
+                true,
+                &self.forest,
+                store,
+            )
             .await;
         match result {
             Ok(node) => Ok(node),
@@ -706,6 +1283,81 @@ impl FsMetadata {
         }
     }
 
+    /// Describe a single Node in the Fs, analogous to stat(1). Directories report their number
+    /// of children, or the total size of all files beneath them if `recursive` is set.
+    pub async fn stat(
+        &self,
+        path_segments: &[String],
+        recursive: bool,
+        metadata_store: &impl BlockStore,
+    ) -> Result<NodeStat, FilesystemError> {
+        let not_found = || FilesystemError::node_not_found(&path_segments.join("/"));
+        let node = if path_segments.is_empty() {
+            self.root_dir.as_node()
+        } else {
+            self.root_dir
+                .get_node(
+                    &self.wnfs_segments(path_segments),
+                    true,
+                    &self.forest,
+                    metadata_store,
+                )
+                .await
+                .map_err(|_| not_found())?
+                .ok_or_else(not_found)?
+        };
+
+        let (kind, size, cid, metadata) = match node {
+            PrivateNode::File(file) => {
+                let cid = file
+                    .get_cids(&self.forest, metadata_store)
+                    .await
+                    .map_err(Box::from)?
+                    .into_iter()
+                    .next();
+                let metadata = file.get_metadata().clone();
+                let size = metadata_integer(&metadata, "size").unwrap_or(0) as u64;
+                (FsMetadataEntryType::File, size, cid, metadata)
+            }
+            PrivateNode::Dir(dir) => {
+                let size = if recursive {
+                    let mut size = 0;
+                    let path = path_segments.iter().collect::<PathBuf>();
+                    for (node, _) in self.get_all_children(path, metadata_store).await? {
+                        if let PrivateNode::File(file) = node {
+                            size +=
+                                metadata_integer(file.get_metadata(), "size").unwrap_or(0) as u64;
+                        }
+                    }
+                    size
+                } else {
+                    dir.ls(&[], true, &self.forest, metadata_store)
+                        .await
+                        .map_err(Box::from)?
+                        .len() as u64
+                };
+                (
+                    FsMetadataEntryType::Dir,
+                    size,
+                    None,
+                    dir.get_metadata().clone(),
+                )
+            }
+        };
+
+        Ok(NodeStat {
+            kind,
+            size,
+            modified: metadata_integer(&metadata, "modified"),
+            created: metadata_integer(&metadata, "created"),
+            cid,
+            content_compression: match metadata.0.get("compression") {
+                Some(Ipld::String(compression)) => Some(compression.clone()),
+                _ => None,
+            },
+        })
+    }
+
     /// Get all nodes under the root directory
     pub async fn get_all_nodes(
         &self,
@@ -715,39 +1367,272 @@ impl FsMetadata {
             .await
     }
 
-    #[async_recursion(?Send)]
-    async fn get_all_children(
+    /// Get all Nodes at or below a path, paired with their paths
+    pub async fn get_nodes_under(
         &self,
-        path: PathBuf,
+        path: &Path,
         metadata_store: &impl BlockStore,
     ) -> Result<Vec<(PrivateNode, PathBuf)>, FilesystemError> {
-        let segments = path_to_segments(&path)?;
-        let node = if segments.is_empty() {
-            Some(self.root_dir.as_node())
-        } else {
-            self.root_dir
-                .get_node(&segments, true, &self.forest, metadata_store)
-                .await
-                .map_err(Box::from)?
-        };
+        self.get_all_children(path.to_path_buf(), metadata_store)
+            .await
+    }
 
-        match node {
-            Some(PrivateNode::File(file)) => Ok(vec![(file.as_node(), path.to_path_buf())]),
-            Some(PrivateNode::Dir(dir)) => {
-                // Accumulate a list
-                let mut children = vec![];
-                // List the names of all children
-                let node_names = dir
-                    .ls(&[], true, &self.forest, metadata_store)
-                    .await
-                    .map_err(Box::from)?;
+    /// Get the CIDs of the content blocks of every file at or below a path
+    pub async fn get_content_cids(
+        &self,
+        path: &Path,
+        metadata_store: &impl BlockStore,
+    ) -> Result<BTreeSet<Cid>, FilesystemError> {
+        let mut cids = BTreeSet::new();
+        for (node, _) in self.get_nodes_under(path, metadata_store).await? {
+            if let PrivateNode::File(file) = node {
+                cids.extend(
+                    file.get_cids(&self.forest, metadata_store)
+                        .await
+                        .map_err(Box::from)?,
+                );
+            }
+        }
+        Ok(cids)
+    }
 
-                // Accumulate a list of futures
-                let mut futures = Vec::new();
-                // Add a future for each node name
-                for (node_name, _) in node_names {
-                    futures.push(self.get_all_children(path.join(node_name), metadata_store));
-                }
+    /// Write every block reachable from `root` in `blockstore` into `w` as a plain CARv1, with
+    /// `root` as its only root, the blocks in depth first DAG order, and no CARv2 pragma or
+    /// index. Returns the number of blocks written.
+    pub async fn export_carv1<W: Write + Seek>(
+        &self,
+        root: &Cid,
+        blockstore: &impl BanyanBlockStore,
+        w: &mut W,
+    ) -> Result<usize, FilesystemError> {
+        let header = Header {
+            version: 1,
+            roots: SyncCell::new(vec![*root]),
+        };
+        header.write_bytes(w).map_err(BlockStoreError::car)?;
+
+        let mut visited = HashSet::new();
+        let mut pending = vec![*root];
+        while let Some(cid) = pending.pop() {
+            if !visited.insert(cid) {
+                continue;
+            }
+            let content = BanyanBlockStore::get_block(blockstore, &cid)
+                .await?
+                .to_vec();
+            // Only DAG CBOR blocks link to others, everything else is a leaf
+            if matches!(IpldCodec::try_from(cid.codec()), Ok(IpldCodec::DagCbor)) {
+                let ipld: Ipld = dagcbor::decode(&content).map_err(Box::from)?;
+                let mut links = Vec::new();
+                collect_links(&ipld, &mut links);
+                // Visit links in the order they appear
+                pending.extend(links.into_iter().rev());
+            }
+            let block = Block {
+                varint: (cid.encoded_len() + content.len()) as u128,
+                cid,
+                content,
+            };
+            block.write_bytes(w).map_err(BlockStoreError::car)?;
+        }
+        w.flush().map_err(BlockStoreError::from)?;
+        Ok(visited.len())
+    }
+
+    /// Remove every Block from `content_store` which is no longer reachable from the current
+    /// root, then compact it. Blocks of earlier versions of this Fs are preserved unless
+    /// `keep_history` is false, in which case file content which only earlier versions refer
+    /// to is removed as well.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn gc(
+        &self,
+        metadata_store: &impl BlockStore,
+        content_store: &mut CarV2DiskBlockStore,
+        keep_history: bool,
+    ) -> Result<GcReport, FilesystemError> {
+        let root = content_store
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("root cid"))?;
+
+        // Mark everything linked to from the root. The PrivateForest records every version of
+        // every Node, so this covers the whole history of the Fs.
+        let mut reachable = HashSet::new();
+        let mut pending = vec![root];
+        while let Some(cid) = pending.pop() {
+            if !reachable.insert(cid) {
+                continue;
+            }
+            // Only DAG CBOR blocks link to others, everything else is a leaf
+            if !matches!(IpldCodec::try_from(cid.codec()), Ok(IpldCodec::DagCbor)) {
+                continue;
+            }
+            // The nodes of the PrivateForest are only ever stored in full with the metadata.
+            // Anything an unreadable block links to can't be marked, so stop before sweeping.
+            let content = match BlockStore::get_block(metadata_store, &cid).await {
+                Ok(content) => content.to_vec(),
+                Err(_) => BanyanBlockStore::get_block(&*content_store, &cid)
+                    .await?
+                    .to_vec(),
+            };
+            let ipld: Ipld = dagcbor::decode(&content).map_err(Box::from)?;
+            collect_links(&ipld, &mut pending);
+        }
+
+        if !keep_history {
+            let current = self.get_content_cids(Path::new(""), metadata_store).await?;
+            let mut history = self.history(metadata_store).await?;
+            let mut previous = BTreeSet::new();
+            while let Some(node) = history
+                .get_previous(metadata_store)
+                .await
+                .map_err(Box::from)?
+            {
+                let dir = node.as_dir().map_err(Box::from)?;
+                self.collect_content_cids(&dir, metadata_store, &mut previous)
+                    .await?;
+            }
+            for cid in previous.difference(&current) {
+                reachable.remove(cid);
+            }
+        }
+
+        // Sweep the rest
+        let (kept, removed): (Vec<Cid>, Vec<Cid>) = content_store
+            .all_cids()
+            .await
+            .into_iter()
+            .partition(|cid| *cid == root || reachable.contains(cid));
+        let mut report = GcReport {
+            blocks_kept: kept.len(),
+            blocks_removed: removed.len(),
+            ..Default::default()
+        };
+        if !removed.is_empty() {
+            content_store.remove_blocks(&removed)?;
+            report.bytes_reclaimed = content_store.compact()?;
+        }
+        Ok(report)
+    }
+
+    /// Find the names of Nodes in this Fs which appear verbatim in serialized metadata
+    pub async fn plaintext_names(
+        &self,
+        metadata_store: &impl BlockStore,
+        serialized: &[u8],
+    ) -> Result<PlaintextNames, FilesystemError> {
+        // Every distinct name long enough to be told apart, in the order it is first used
+        let mut file_names = Vec::new();
+        let mut dir_names = Vec::new();
+        let mut seen_files = HashSet::new();
+        let mut seen_dirs = HashSet::new();
+        for (_, path) in self.get_all_nodes(metadata_store).await? {
+            let mut segments = path_to_segments(&path)?;
+            // The last segment names the Node itself, every other one is a directory
+            if let Some(file_name) = segments.pop() {
+                if file_name.len() >= MIN_PLAINTEXT_NAME_LEN && seen_files.insert(file_name.clone())
+                {
+                    file_names.push(file_name);
+                }
+            }
+            for dir_name in segments {
+                if dir_name.len() >= MIN_PLAINTEXT_NAME_LEN && seen_dirs.insert(dir_name.clone()) {
+                    dir_names.push(dir_name);
+                }
+            }
+        }
+
+        // Search for all of them in a single pass over the serialized metadata
+        let patterns = file_names
+            .iter()
+            .chain(dir_names.iter())
+            .collect::<Vec<&String>>();
+        let matcher =
+            AhoCorasick::new(&patterns).map_err(|err| FilesystemError::wnfs(Box::from(err)))?;
+        let leaked = matcher
+            .find_overlapping_iter(serialized)
+            .map(|found| patterns[found.pattern().as_usize()].clone())
+            .collect::<HashSet<String>>();
+        Ok(PlaintextNames {
+            files: file_names
+                .into_iter()
+                .filter(|name| leaked.contains(name))
+                .collect(),
+            dirs: dir_names
+                .into_iter()
+                .filter(|name| leaked.contains(name))
+                .collect(),
+        })
+    }
+
+    /// Collect the CIDs of the content blocks of every file at or below a directory, as they
+    /// were when that version of the directory was stored
+    #[cfg(not(target_arch = "wasm32"))]
+    #[async_recursion(?Send)]
+    async fn collect_content_cids(
+        &self,
+        dir: &Rc<PrivateDirectory>,
+        metadata_store: &impl BlockStore,
+        cids: &mut BTreeSet<Cid>,
+    ) -> Result<(), FilesystemError> {
+        let node_names = dir
+            .ls(&[], false, &self.forest, metadata_store)
+            .await
+            .map_err(Box::from)?;
+        for (node_name, _) in node_names {
+            match dir
+                .get_node(&[node_name], false, &self.forest, metadata_store)
+                .await
+                .map_err(Box::from)?
+            {
+                Some(PrivateNode::File(file)) => cids.extend(
+                    file.get_cids(&self.forest, metadata_store)
+                        .await
+                        .map_err(Box::from)?,
+                ),
+                Some(PrivateNode::Dir(dir)) => {
+                    self.collect_content_cids(&dir, metadata_store, cids)
+                        .await?
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[async_recursion(?Send)]
+    async fn get_all_children(
+        &self,
+        path: PathBuf,
+        metadata_store: &impl BlockStore,
+    ) -> Result<Vec<(PrivateNode, PathBuf)>, FilesystemError> {
+        let segments = self.wnfs_segments(&path_to_segments(&path)?);
+        let node = if segments.is_empty() {
+            Some(self.root_dir.as_node())
+        } else {
+            self.root_dir
+                .get_node(&segments, true, &self.forest, metadata_store)
+                .await
+                .map_err(Box::from)?
+        };
+
+        match node {
+            Some(PrivateNode::File(file)) => Ok(vec![(file.as_node(), path.to_path_buf())]),
+            Some(PrivateNode::Dir(dir)) => {
+                // Accumulate a list
+                let mut children = vec![];
+                // List the names of all children
+                let node_names = dir
+                    .ls(&[], true, &self.forest, metadata_store)
+                    .await
+                    .map_err(Box::from)?;
+
+                // Accumulate a list of futures
+                let mut futures = Vec::new();
+                // Add a future for each node name
+                for (node_name, _) in node_names {
+                    let node_name = self.plaintext_name(&node_name)?;
+                    futures.push(self.get_all_children(path.join(node_name), metadata_store));
+                }
                 // Join on all of them and iterate over results
                 for result in join_all(futures).await {
                     // Extend with children found
@@ -782,16 +1667,337 @@ pub struct FsMetadataEntry {
     pub metadata: Metadata,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// FsMetadataEntry along with the entries beneath it
+pub struct FsTreeEntry {
+    /// The entry itself
+    pub entry: FsMetadataEntry,
+    /// Entries beneath a directory, if it was descended into
+    pub children: Option<Vec<FsTreeEntry>>,
+}
+
+/// The content of a PrivateFile, readable as it is fetched and decrypted block by block
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileContentStream<'a> {
+    /// Decrypted blocks of content, in order
+    blocks: LocalBoxStream<'a, Result<Vec<u8>, FilesystemError>>,
+    /// The block currently being read
+    block: Vec<u8>,
+    /// How much of the current block has been read
+    position: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl tokio::io::AsyncRead for FileContentStream<'_> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        use std::task::Poll;
+        let this = self.get_mut();
+        loop {
+            // Hand out what remains of the current block
+            if this.position < this.block.len() {
+                let len = buf.remaining().min(this.block.len() - this.position);
+                buf.put_slice(&this.block[this.position..this.position + len]);
+                this.position += len;
+                return Poll::Ready(Ok(()));
+            }
+            // Then move on to the next
+            match this.blocks.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(block))) => {
+                    this.block = block;
+                    this.position = 0;
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        err.to_string(),
+                    )))
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Description of a single Node in the Fs
+pub struct NodeStat {
+    /// File / Dir type
+    pub kind: FsMetadataEntryType,
+    /// Size of a file in bytes. For a directory, either its number of children or the
+    /// total size of all files beneath it.
+    pub size: u64,
+    /// Last modification time in seconds since the epoch
+    pub modified: Option<i64>,
+    /// Creation time in seconds since the epoch
+    pub created: Option<i64>,
+    /// CID of the first block of a file's content
+    pub cid: Option<Cid>,
+    /// Compression applied to a file's content, if any
+    pub content_compression: Option<String>,
+}
+
+impl Display for NodeStat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = |timestamp: Option<i64>| {
+            timestamp
+                .and_then(|timestamp| NaiveDateTime::from_timestamp_opt(timestamp, 0))
+                .map(|time| time.to_string())
+                .unwrap_or("Unknown".to_string())
+        };
+        f.write_fmt(format_args!(
+            "{}\ntype:\t\t{}\nsize:\t\t{}\nmodified:\t{}\ncreated:\t{}\ncid:\t\t{}\ncompression:\t{}",
+            "| NODE INFO |".yellow(),
+            match self.kind {
+                FsMetadataEntryType::Dir => "dir",
+                FsMetadataEntryType::File => "file",
+            },
+            self.size,
+            time(self.modified),
+            time(self.created),
+            self.cid
+                .map(|cid| cid.to_string())
+                .unwrap_or("None".to_string()),
+            self.content_compression
+                .clone()
+                .unwrap_or("None".to_string())
+        ))
+    }
+}
+
+/// What garbage collecting a content BlockStore reclaimed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    /// Number of Blocks which were removed
+    pub blocks_removed: usize,
+    /// Number of Blocks which are still reachable
+    pub blocks_kept: usize,
+    /// Bytes freed by compacting the BlockStore
+    pub bytes_reclaimed: u64,
+}
+
+impl Display for GcReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\nblocks removed:\t{}\nblocks kept:\t{}\nbytes reclaimed:\t{}",
+            "| GARBAGE COLLECTION |".yellow(),
+            self.blocks_removed,
+            self.blocks_kept,
+            self.bytes_reclaimed
+        ))
+    }
+}
+
+/// Attributes of a file on the filesystem it was prepared from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    /// Unix mode, including the permission bits
+    pub mode: Option<u32>,
+    /// Last modification time in seconds since the epoch
+    pub mtime: Option<i64>,
+    /// Nanoseconds past the last modification time
+    pub mtime_nsec: Option<i64>,
+    /// Last status change time in seconds since the epoch. This can not be set on restore.
+    pub ctime: Option<i64>,
+}
+
+impl FileAttributes {
+    /// Read the attributes recorded in the metadata of a Node
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            mode: metadata_integer(metadata, MODE_LABEL).and_then(|mode| u32::try_from(mode).ok()),
+            mtime: metadata_integer(metadata, MTIME_LABEL),
+            mtime_nsec: metadata_integer(metadata, MTIME_NSEC_LABEL),
+            ctime: metadata_integer(metadata, CTIME_LABEL),
+        }
+    }
+
+    /// Record the attributes which are known in the metadata of a Node
+    fn put(&self, metadata: &mut Metadata) {
+        let attributes = [
+            (MODE_LABEL, self.mode.map(i64::from)),
+            (MTIME_LABEL, self.mtime),
+            (MTIME_NSEC_LABEL, self.mtime_nsec),
+            (CTIME_LABEL, self.ctime),
+        ];
+        for (label, value) in attributes {
+            if let Some(value) = value {
+                metadata.put(label, Ipld::Integer(value as i128));
+            }
+        }
+    }
+}
+
+/// What is recorded about a prepared file besides its content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileRecord<'a> {
+    /// Attributes the file had on the filesystem it was prepared from
+    pub attributes: FileAttributes,
+    /// Path of the first of a set of hard links to the same file, if the file is one of them
+    pub hardlink: Option<&'a str>,
+}
+
+impl FileRecord<'_> {
+    /// Record the attributes which are known and whether the file is a hard link in the
+    /// metadata of a Node
+    fn put(&self, metadata: &mut Metadata) {
+        self.attributes.put(metadata);
+        if let Some(link) = self.hardlink {
+            metadata.put(HARDLINK_LABEL, Ipld::String(link.to_string()));
+        } else {
+            metadata.0.remove(HARDLINK_LABEL);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&std::fs::Metadata> for FileAttributes {
+    fn from(metadata: &std::fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Self {
+            mode: Some(metadata.mode()),
+            mtime: Some(metadata.mtime()),
+            mtime_nsec: Some(metadata.mtime_nsec()),
+            ctime: Some(metadata.ctime()),
+        }
+    }
+}
+
+/// Hex encoded BLAKE3 hash of the plaintext content of a file, if it was recorded when the
+/// file was written
+pub fn content_hash(metadata: &Metadata) -> Option<&str> {
+    match metadata.0.get(CONTENT_HASH_LABEL) {
+        Some(Ipld::String(hash)) => Some(hash),
+        _ => None,
+    }
+}
+
+/// Undo whatever compression the content of a file was written with
+fn decompress_content(file: &PrivateFile, content: Vec<u8>) -> Result<Vec<u8>, FilesystemError> {
+    match CompressionCodec::from_metadata(file.get_metadata()) {
+        Ok(CompressionCodec::None) => Ok(content),
+        Ok(codec) => codec.decompress(&content),
+        Err(err) => Err(err),
+    }
+    .map_err(|err| FilesystemError::wnfs(Box::from(err)))
+}
+
+/// Reader which hashes everything read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: blake3::Hasher,
+}
+
+impl<R> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for HashingReader<R> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = std::pin::Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let std::task::Poll::Ready(Ok(read)) = &poll {
+            this.hasher.update(&buf[..*read]);
+        }
+        poll
+    }
+}
+
+/// Path of the first of a set of hard links to the same file, if the file was one of them when
+/// it was prepared. Every link in the set records the same path.
+pub fn hardlink(metadata: &Metadata) -> Option<&str> {
+    match metadata.0.get(HARDLINK_LABEL) {
+        Some(Ipld::String(link)) => Some(link),
+        _ => None,
+    }
+}
+
+/// Read an integer out of Node metadata
+fn metadata_integer(metadata: &Metadata, key: &str) -> Option<i64> {
+    match metadata.0.get(key) {
+        Some(Ipld::Integer(integer)) => i64::try_from(*integer).ok(),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+/// Names of Nodes which were found in plaintext within serialized metadata
+pub struct PlaintextNames {
+    /// Directory names
+    pub dirs: Vec<String>,
+    /// File names
+    pub files: Vec<String>,
+}
+
+impl PlaintextNames {
+    /// Whether or not no names were found
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty() && self.files.is_empty()
+    }
+
+    /// Determine whether metadata is fully encrypted given the names found in it, refusing
+    /// metadata which exposes directory names and warning about exposed file names
+    pub fn metadata_encryption(&self) -> Result<bool, FilesystemError> {
+        if !self.dirs.is_empty() {
+            return Err(FilesystemError::plaintext_metadata(&self.dirs));
+        }
+        if !self.files.is_empty() {
+            tracing::warn!(
+                "Filename encryption is not enabled; {} file names are visible in the metadata",
+                self.files.len()
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod test {
     use crate::{
-        blockstore::MemoryBlockStore,
-        filesystem::{error::FilesystemError, metadata::FsMetadata},
+        blockstore::{
+            BanyanBlockStore, BlockStoreError, CarV2DiskBlockStore, CarV2MemoryBlockStore,
+            DoubleSplitStore, MemoryBlockStore, RootedBlockStore,
+        },
+        car::{v1::CarV1, v2::index::indexable::Indexable},
+        filesystem::{
+            content_hash,
+            error::FilesystemError,
+            metadata::{
+                decompress_content, FsMetadata, FsMetadataEntry, FsMetadataEntryType,
+                PlaintextNames,
+            },
+            wnfsio::CompressionCodec,
+        },
         prelude::filesystem::sharing::SharedFile,
+        utils::{testing::clock::MockClock, Clock},
+    };
+    use chrono::Duration;
+    use rand::RngCore;
+    use std::{
+        io::{Cursor, Seek, SeekFrom},
+        path::{Path, PathBuf},
+    };
+    use tokio::io::AsyncReadExt;
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
     };
-    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
-    use wnfs::private::PrivateNode;
+    use wnfs::{libipld::IpldCodec, private::PrivateNode};
 
     async fn _init_save_unlock(
         wrapping_key: &EcEncryptionKey,
@@ -815,6 +2021,119 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn revoke() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let revoked_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+        fs_metadata
+            .share_with(&revoked_key.public_key()?, &metadata_store)
+            .await?;
+        let path = vec!["cat.txt".to_string()];
+        fs_metadata
+            .write(&path, &metadata_store, &content_store, b"meow".to_vec())
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+        let shared_ref = FsMetadata::unlock(revoked_key, &metadata_store)
+            .await?
+            .share_manager
+            .current_ref;
+
+        let fingerprint =
+            hex_fingerprint(revoked_key.public_key()?.fingerprint().await?.as_slice());
+        fs_metadata
+            .revoke(&fingerprint, &metadata_store, &content_store)
+            .await?;
+
+        // The revoked key is locked out of the current version, which is under a new key
+        assert!(FsMetadata::unlock(revoked_key, &metadata_store)
+            .await
+            .is_err());
+        let unlocked = FsMetadata::unlock(wrapping_key, &metadata_store).await?;
+        let current_ref = unlocked.share_manager.current_ref.clone();
+        assert_ne!(
+            current_ref.map(|private_ref| private_ref.temporal_key),
+            shared_ref.map(|private_ref| private_ref.temporal_key)
+        );
+        assert_eq!(
+            unlocked
+                .read(&path, &metadata_store, &content_store)
+                .await?,
+            b"meow".to_vec()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revoke_child_ref() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let revoked_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+        fs_metadata
+            .share_with(&revoked_key.public_key()?, &metadata_store)
+            .await?;
+        let path = vec!["dir".to_string(), "cat.txt".to_string()];
+        fs_metadata
+            .write(&path, &metadata_store, &content_store, b"meow".to_vec())
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        // A reference to just the file, as the revoked key could have kept
+        let file = fs_metadata
+            .get_node(&path, &metadata_store)
+            .await?
+            .expect("no file")
+            .as_file()
+            .map_err(Box::from)?;
+        let mut forest = fs_metadata.forest.clone();
+        let child_ref = file
+            .store(&mut forest, &metadata_store, &mut rand::thread_rng())
+            .await
+            .map_err(Box::from)?;
+
+        let fingerprint =
+            hex_fingerprint(revoked_key.public_key()?.fingerprint().await?.as_slice());
+        fs_metadata
+            .revoke(&fingerprint, &metadata_store, &content_store)
+            .await?;
+        fs_metadata
+            .write(&path, &metadata_store, &content_store, b"woof".to_vec())
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        // Following the reference as far forward as it goes never reaches the new write
+        let forest = FsMetadata::unlock(wrapping_key, &metadata_store)
+            .await?
+            .forest;
+        let split_store = DoubleSplitStore::new(&content_store, &metadata_store);
+        let latest = PrivateNode::load(&child_ref, &forest, &metadata_store)
+            .await
+            .map_err(Box::from)?
+            .search_latest(&forest, &metadata_store)
+            .await
+            .map_err(Box::from)?
+            .as_file()
+            .map_err(Box::from)?;
+        let content = latest
+            .get_content(&forest, &split_store)
+            .await
+            .map_err(Box::from)?;
+        assert_eq!(decompress_content(&latest, content)?, b"meow".to_vec());
+        assert_eq!(
+            fs_metadata
+                .read(&path, &metadata_store, &content_store)
+                .await?,
+            b"woof".to_vec()
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn history() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -835,6 +2154,308 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn plaintext_names() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        fs_metadata
+            .write(
+                &["secret_plans".to_string(), "kitty_plan.txt".to_string()],
+                &metadata_store,
+                &content_store,
+                "hello kitty".as_bytes().to_vec(),
+            )
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        // Serialized WNFS metadata exposes no names
+        let serialized = metadata_store.get_data();
+        assert!(fs_metadata
+            .plaintext_names(&metadata_store, &serialized)
+            .await?
+            .is_empty());
+
+        // Names which do appear in the serialized bytes are reported
+        let mut leaky = serialized.clone();
+        leaky.extend_from_slice(b"kitty_plan.txt");
+        assert_eq!(
+            fs_metadata.plaintext_names(&metadata_store, &leaky).await?,
+            PlaintextNames {
+                dirs: vec![],
+                files: vec!["kitty_plan.txt".to_string()],
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tree() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        fs_metadata
+            .write(
+                &["a".to_string(), "b".to_string(), "kitty.txt".to_string()],
+                &metadata_store,
+                &content_store,
+                "hello kitty".as_bytes().to_vec(),
+            )
+            .await?;
+        fs_metadata
+            .mkdir(&["c".to_string()], &metadata_store)
+            .await?;
+
+        // The full tree nests every entry beneath its directory
+        let tree = fs_metadata.tree(&[], None, &metadata_store).await?;
+        assert_eq!(tree.len(), 2);
+        let a = tree
+            .iter()
+            .find(|node| node.entry.name == "a")
+            .expect("no a");
+        let b = &a.children.as_ref().expect("a not descended")[0];
+        assert_eq!(b.entry.name, "b");
+        let kitty = &b.children.as_ref().expect("b not descended")[0];
+        assert_eq!(kitty.entry.name, "kitty.txt");
+        assert!(matches!(kitty.entry.entry_type, FsMetadataEntryType::File));
+        assert!(kitty.children.is_none());
+
+        // Directories past the maximum depth are not descended into
+        let shallow = fs_metadata.tree(&[], Some(1), &metadata_store).await?;
+        let a = shallow
+            .iter()
+            .find(|node| node.entry.name == "a")
+            .expect("no a");
+        let b = &a.children.as_ref().expect("a not descended")[0];
+        assert!(b.children.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ls_paginated() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init_with_name_encryption(wrapping_key).await?;
+        for name in ["e", "b", "d", "a", "c"] {
+            fs_metadata
+                .write(
+                    &["dir".to_string(), name.to_string()],
+                    &metadata_store,
+                    &content_store,
+                    name.as_bytes().to_vec(),
+                )
+                .await?;
+        }
+        fs_metadata
+            .mkdir(&["dir".to_string(), "f".to_string()], &metadata_store)
+            .await?;
+        let dir = ["dir".to_string()];
+        let names = |entries: &[FsMetadataEntry]| {
+            entries
+                .iter()
+                .map(|entry| entry.name.clone())
+                .collect::<Vec<String>>()
+        };
+
+        // Pages are sorted by plaintext name, and say whether anything follows them
+        let (page, has_more) = fs_metadata
+            .ls_paginated(&dir, 0, 2, &metadata_store)
+            .await?;
+        assert_eq!(names(&page), ["a", "b"]);
+        assert!(has_more);
+        let (page, has_more) = fs_metadata
+            .ls_paginated(&dir, 2, 2, &metadata_store)
+            .await?;
+        assert_eq!(names(&page), ["c", "d"]);
+        assert!(has_more);
+        let (page, has_more) = fs_metadata
+            .ls_paginated(&dir, 4, 2, &metadata_store)
+            .await?;
+        assert_eq!(names(&page), ["e", "f"]);
+        assert!(matches!(page[1].entry_type, FsMetadataEntryType::Dir));
+        assert!(!has_more);
+        // Paging past the end is just empty
+        let (page, has_more) = fs_metadata
+            .ls_paginated(&dir, 10, 2, &metadata_store)
+            .await?;
+        assert!(page.is_empty());
+        assert!(!has_more);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn find() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        for path in [
+            vec!["kitty.txt"],
+            vec!["pets", "puppy.TXT"],
+            vec!["pets", "cats", "tabby.txt"],
+            vec!["pets", "cats", "tabby.png"],
+            vec!["docs.txt", "readme.md"],
+        ] {
+            fs_metadata
+                .write(
+                    &path.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                    &metadata_store,
+                    &content_store,
+                    "hello kitty".as_bytes().to_vec(),
+                )
+                .await?;
+        }
+
+        // Extension globs match at every depth
+        assert_eq!(
+            fs_metadata
+                .find("*.txt", None, false, &metadata_store)
+                .await?,
+            vec![
+                PathBuf::from("docs.txt"),
+                PathBuf::from("kitty.txt"),
+                PathBuf::from("pets/cats/tabby.txt"),
+            ]
+        );
+        // Matching can ignore case
+        assert_eq!(
+            fs_metadata
+                .find("*.txt", None, true, &metadata_store)
+                .await?
+                .len(),
+            4
+        );
+        // Results can be restricted by type
+        assert_eq!(
+            fs_metadata
+                .find(
+                    "*.txt",
+                    Some(FsMetadataEntryType::File),
+                    false,
+                    &metadata_store
+                )
+                .await?,
+            vec![
+                PathBuf::from("kitty.txt"),
+                PathBuf::from("pets/cats/tabby.txt"),
+            ]
+        );
+        assert_eq!(
+            fs_metadata
+                .find("*", Some(FsMetadataEntryType::Dir), false, &metadata_store)
+                .await?,
+            vec![
+                PathBuf::from("docs.txt"),
+                PathBuf::from("pets"),
+                PathBuf::from("pets/cats"),
+            ]
+        );
+        // Invalid patterns are an error
+        assert!(fs_metadata
+            .find("[", None, false, &metadata_store)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stat() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        for (name, content) in [("kitty.txt", "hello kitty"), ("puppy.txt", "hi")] {
+            fs_metadata
+                .write(
+                    &["pets".to_string(), name.to_string()],
+                    &metadata_store,
+                    &content_store,
+                    content.as_bytes().to_vec(),
+                )
+                .await?;
+        }
+
+        let file_stat = fs_metadata
+            .stat(
+                &["pets".to_string(), "kitty.txt".to_string()],
+                false,
+                &metadata_store,
+            )
+            .await?;
+        assert!(matches!(file_stat.kind, FsMetadataEntryType::File));
+        assert_eq!(file_stat.size, 11);
+        assert!(file_stat.cid.is_some());
+        assert!(file_stat.modified.is_some());
+
+        // Directories report their children, or their total size
+        let pets = ["pets".to_string()];
+        let dir_stat = fs_metadata.stat(&pets, false, &metadata_store).await?;
+        assert!(matches!(dir_stat.kind, FsMetadataEntryType::Dir));
+        assert_eq!(dir_stat.size, 2);
+        assert_eq!(
+            fs_metadata.stat(&pets, true, &metadata_store).await?.size,
+            13
+        );
+        assert_eq!(fs_metadata.stat(&[], false, &metadata_store).await?.size, 1);
+
+        // Missing Nodes are an error
+        assert!(fs_metadata
+            .stat(&["cats".to_string()], false, &metadata_store)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn name_encryption() -> Result<(), FilesystemError> {
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init_with_name_encryption(wrapping_key).await?;
+        let dir_name = "secret_plans".to_string();
+        let file_name = "kitty_plan.txt".to_string();
+        fs_metadata
+            .write(
+                &[dir_name.clone(), file_name.clone()],
+                &metadata_store,
+                &content_store,
+                "hello kitty".as_bytes().to_vec(),
+            )
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        // The raw metadata bytes contain none of the original names
+        let serialized = metadata_store.get_data();
+        for name in [&dir_name, &file_name] {
+            assert!(!serialized
+                .windows(name.len())
+                .any(|window| window == name.as_bytes()));
+        }
+        // WNFS itself only ever sees encrypted names
+        let wnfs_names = fs_metadata
+            .root_dir
+            .ls(&[], true, &fs_metadata.forest, &metadata_store)
+            .await
+            .map_err(Box::from)?;
+        assert_eq!(wnfs_names.len(), 1);
+        assert_ne!(wnfs_names[0].0, dir_name);
+
+        // Unlocking recovers the name key and transparently decrypts names
+        let unlocked = FsMetadata::unlock(wrapping_key, &metadata_store).await?;
+        assert_eq!(unlocked.name_key, fs_metadata.name_key);
+        let entries = unlocked.ls(&[dir_name.clone()], &metadata_store).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, file_name);
+        let content = unlocked
+            .read(&[dir_name, file_name], &metadata_store, &content_store)
+            .await?;
+        assert_eq!(content, "hello kitty".as_bytes());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_read() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -863,6 +2484,49 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn export_carv1() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+        fs_metadata
+            .write(
+                &["cat.txt".to_string()],
+                &metadata_store,
+                &content_store,
+                "hello kitty".as_bytes().to_vec(),
+            )
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        let root = metadata_store.get_root().expect("no metadata root");
+        let store = DoubleSplitStore::new(&metadata_store, &content_store);
+        let mut car = Cursor::new(Vec::new());
+        let written = fs_metadata.export_carv1(&root, &store, &mut car).await?;
+
+        // The output is a plain CARv1 with a single root, which comes first
+        car.seek(SeekFrom::Start(0))
+            .map_err(BlockStoreError::from)?;
+        let carv1 = CarV1::read_bytes(None, &mut car).map_err(BlockStoreError::car)?;
+        assert_eq!(carv1.header.version, 1);
+        assert_eq!(*carv1.header.roots.borrow(), vec![root]);
+        let index = carv1.index.borrow();
+        let cids = index.get_all_cids();
+        assert_eq!(cids.len(), written);
+        let first = cids.iter().filter_map(|cid| index.get_offset(cid)).min();
+        assert_eq!(index.get_offset(&root), first);
+        // Including every block of file content
+        for cid in fs_metadata
+            .get_content_cids(Path::new(""), &metadata_store)
+            .await?
+        {
+            assert!(cids.contains(&cid));
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_share_receive() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -898,6 +2562,123 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn share_expiry() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let cat_path = vec!["cat.txt".to_string()];
+        let kitty_bytes = "hello kitty".as_bytes().to_vec();
+        fs_metadata
+            .write(
+                &cat_path,
+                &metadata_store,
+                &content_store,
+                kitty_bytes.clone(),
+            )
+            .await?;
+
+        let clock = MockClock::default();
+        let shared_file = fs_metadata
+            .share_file(&cat_path, &metadata_store, &content_store)
+            .await?
+            .with_expiry(clock.now() + Duration::hours(1));
+        // The expiry survives export
+        let shared_file = SharedFile::import_b64_url(shared_file.export_b64_url()?)?;
+
+        // Still valid before the hour is up
+        clock.advance(Duration::minutes(59));
+        let new_kitty_bytes =
+            FsMetadata::receive_file_content_at(shared_file.clone(), &content_store, &clock)
+                .await?;
+        assert_eq!(kitty_bytes, new_kitty_bytes);
+
+        // But not after
+        clock.advance(Duration::minutes(1));
+        assert!(shared_file.is_expired(&clock));
+        assert!(
+            FsMetadata::receive_file_content_at(shared_file, &content_store, &clock)
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_file_content() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        // Long enough to span several blocks
+        let cat_path = vec!["cat.txt".to_string()];
+        let kitty_bytes: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        fs_metadata
+            .write(
+                &cat_path,
+                &metadata_store,
+                &content_store,
+                kitty_bytes.clone(),
+            )
+            .await?;
+        // And short enough to be stored inline
+        let dog_path = vec!["dog.txt".to_string()];
+        let doggy_bytes = "hello doggy".as_bytes().to_vec();
+        fs_metadata
+            .write(
+                &dog_path,
+                &metadata_store,
+                &content_store,
+                doggy_bytes.clone(),
+            )
+            .await?;
+
+        for (path, bytes) in [(&cat_path, &kitty_bytes), (&dog_path, &doggy_bytes)] {
+            let mut streamed = Vec::new();
+            fs_metadata
+                .stream_file_content(path, &metadata_store, &content_store)
+                .await?
+                .read_to_end(&mut streamed)
+                .await
+                .expect("streamed content");
+            assert_eq!(&streamed, bytes);
+        }
+
+        // Blocks missing locally are fetched from the secondary store as they are reached,
+        // as they would be from the remote
+        let local_store = MemoryBlockStore::default();
+        let fetching_store = DoubleSplitStore::new(&local_store, &content_store);
+        let mut streamed = Vec::new();
+        fs_metadata
+            .stream_file_content(&cat_path, &metadata_store, &fetching_store)
+            .await?
+            .read_to_end(&mut streamed)
+            .await
+            .expect("streamed content");
+        assert_eq!(streamed, kitty_bytes);
+
+        // Blocks that can not be found anywhere fail the read
+        let mut streamed = Vec::new();
+        assert!(fs_metadata
+            .stream_file_content(&cat_path, &metadata_store, &local_store)
+            .await?
+            .read_to_end(&mut streamed)
+            .await
+            .is_err());
+
+        // Only files can be streamed
+        assert!(fs_metadata
+            .stream_file_content(&[], &metadata_store, &content_store)
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore]
     async fn add_read_large() -> Result<(), FilesystemError> {
@@ -1017,6 +2798,96 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn write_compressed() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let kitty_bytes = "hello kitty ".repeat(1024).into_bytes();
+        let path = vec!["cat.txt".to_string()];
+        for codec in [
+            CompressionCodec::Lz4,
+            CompressionCodec::Gzip,
+            CompressionCodec::Zstd { level: 9 },
+            CompressionCodec::None,
+        ] {
+            fs_metadata
+                .write_compressed(
+                    &path,
+                    &metadata_store,
+                    &content_store,
+                    kitty_bytes.clone(),
+                    codec,
+                    None,
+                )
+                .await?;
+            // Reads undo the compression
+            assert_eq!(
+                fs_metadata
+                    .read(&path, &metadata_store, &content_store)
+                    .await?,
+                kitty_bytes
+            );
+            // The recorded size and hash are of the plaintext
+            let Some(PrivateNode::File(file)) =
+                fs_metadata.get_node(&path, &metadata_store).await?
+            else {
+                panic!("no file written");
+            };
+            let stored = CompressionCodec::from_metadata(file.get_metadata())
+                .map_err(|err| FilesystemError::wnfs(Box::from(err)))?;
+            assert_eq!(
+                stored == CompressionCodec::None,
+                codec == CompressionCodec::None
+            );
+            assert_eq!(
+                content_hash(file.get_metadata()),
+                Some(blake3::hash(&kitty_bytes).to_hex().as_str())
+            );
+        }
+
+        // Content which is already compressed is stored as is
+        let mut noise = vec![0; 256 * 1024];
+        rand::thread_rng().fill_bytes(&mut noise);
+        let codec = CompressionCodec::Zstd { level: 3 };
+        assert!(!codec.worth_compressing(&noise).expect("compression failed"));
+        assert!(codec
+            .worth_compressing(&kitty_bytes)
+            .expect("compression failed"));
+        fs_metadata
+            .write_compressed(
+                &path,
+                &metadata_store,
+                &content_store,
+                noise.clone(),
+                codec,
+                None,
+            )
+            .await?;
+        assert_eq!(
+            fs_metadata
+                .read(&path, &metadata_store, &content_store)
+                .await?,
+            noise
+        );
+        let Some(PrivateNode::File(file)) = fs_metadata.get_node(&path, &metadata_store).await?
+        else {
+            panic!("no file written");
+        };
+        assert_eq!(
+            CompressionCodec::from_metadata(file.get_metadata()).ok(),
+            Some(CompressionCodec::None)
+        );
+
+        assert_eq!("zstd:9".parse(), Ok(CompressionCodec::Zstd { level: 9 }));
+        assert_eq!("gzip".parse(), Ok(CompressionCodec::Gzip));
+        assert!("brotli".parse::<CompressionCodec>().is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_rm_read() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -1140,4 +3011,89 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn gc() -> Result<(), FilesystemError> {
+        let path = Path::new("test").join("car").join("metadata_gc.car");
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(BlockStoreError::from)?;
+        }
+        std::fs::create_dir_all(path.parent().expect("no parent"))
+            .map_err(BlockStoreError::from)?;
+        let metadata_store = MemoryBlockStore::default();
+        let mut content_store = CarV2DiskBlockStore::new(&path)?;
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        // Overwrite a file, saving each version
+        let file_path = vec!["cat.txt".to_string()];
+        let mut versions = Vec::new();
+        for _ in 0..2 {
+            let mut content = vec![0; 64 * 1024];
+            rand::thread_rng().fill_bytes(&mut content);
+            fs_metadata
+                .write(&file_path, &metadata_store, &content_store, content.clone())
+                .await?;
+            fs_metadata.save(&metadata_store, &content_store).await?;
+            versions.push(content);
+        }
+        // Nothing links to this
+        let stray = content_store
+            .put_block(vec![7; 512], IpldCodec::Raw)
+            .await?;
+
+        let report = fs_metadata
+            .gc(&metadata_store, &mut content_store, true)
+            .await?;
+        assert!(report.blocks_removed >= 1);
+        assert!(report.bytes_reclaimed >= 512);
+        assert!(content_store.get_block(&stray).await.is_err());
+        // Running again finds nothing more to remove
+        let report = fs_metadata
+            .gc(&metadata_store, &mut content_store, true)
+            .await?;
+        assert_eq!(report.blocks_removed, 0);
+
+        // The previous version is still readable
+        let mut history = fs_metadata.history(&metadata_store).await?;
+        let previous = history
+            .get_previous(&metadata_store)
+            .await
+            .map_err(Box::from)?
+            .expect("no previous version")
+            .as_dir()
+            .map_err(Box::from)?;
+        let previous_file = previous
+            .get_node(&file_path, false, &fs_metadata.forest, &metadata_store)
+            .await
+            .map_err(Box::from)?
+            .expect("no previous file")
+            .as_file()
+            .map_err(Box::from)?;
+        let previous_cids = previous_file
+            .get_cids(&fs_metadata.forest, &metadata_store)
+            .await
+            .map_err(Box::from)?;
+        for cid in &previous_cids {
+            assert!(content_store.get_block(cid).await.is_ok());
+        }
+
+        // Dropping history removes the content of the previous version
+        let report = fs_metadata
+            .gc(&metadata_store, &mut content_store, false)
+            .await?;
+        assert!(report.blocks_removed >= previous_cids.len());
+        assert!(report.bytes_reclaimed >= 64 * 1024);
+        for cid in &previous_cids {
+            assert!(content_store.get_block(cid).await.is_err());
+        }
+        assert_eq!(
+            fs_metadata
+                .read(&file_path, &metadata_store, &content_store)
+                .await?,
+            versions[1]
+        );
+        Ok(())
+    }
 }
@@ -1,9 +1,18 @@
+mod diff;
 mod error;
+mod manifest;
 mod metadata;
+mod names;
 #[allow(unused)]
-pub use metadata::{FsMetadata, FsMetadataEntry, FsMetadataEntryType};
+pub use metadata::{
+    content_hash, hardlink, FileAttributes, FileRecord, FsMetadata, FsMetadataEntry,
+    FsMetadataEntryType, FsTreeEntry, GcReport, NodeStat, PlaintextNames,
+};
 pub mod serialize;
 pub mod sharing;
 pub mod wnfsio;
 
+pub use diff::FsDiff;
 pub use error::FilesystemError;
+pub use manifest::{Manifest, SignedManifest};
+pub use names::NameKey;
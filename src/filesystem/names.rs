@@ -0,0 +1,104 @@
+//! Deterministic encryption of path segments, so that the names WNFS stores do not reveal the
+//! structure of the FileSystem to anyone without access to the root directory.
+//!
+//! Migration note: Drives created before name encryption existed do not have a name key
+//! stored in their root directory, and continue to operate in plaintext-name mode.
+use super::error::FilesystemError;
+use aes_siv::{siv::Aes128Siv, Key, KeyInit};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{thread_rng, RngCore};
+
+/// Associated data binding every encrypted name to its purpose
+const SEGMENT_CONTEXT: &[u8] = b"banyan 2023 path segment encryption";
+
+/// Symmetric AES-SIV key used to encrypt and decrypt path segments
+#[derive(Clone, PartialEq)]
+pub struct NameKey([u8; 32]);
+
+impl std::fmt::Debug for NameKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NameKey(..)")
+    }
+}
+
+impl NameKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        let mut key = [0; 32];
+        thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// Read a key from its raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self(bytes.try_into().ok()?))
+    }
+
+    /// Raw bytes of the key
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Encrypt a path segment. The same segment always encrypts to the same name, so
+    /// encrypted names can still be used to look up Nodes.
+    pub fn encrypt(&self, segment: &str) -> String {
+        let ciphertext = self
+            .cipher()
+            .encrypt([SEGMENT_CONTEXT], segment.as_bytes())
+            .expect("path segment too long to encrypt");
+        URL_SAFE_NO_PAD.encode(ciphertext)
+    }
+
+    /// Decrypt a name produced by `encrypt`
+    pub fn decrypt(&self, name: &str) -> Result<String, FilesystemError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(name)
+            .map_err(|_| FilesystemError::bad_name(name))?;
+        // The synthetic IV doubles as an authentication tag over the plaintext
+        let plaintext = self
+            .cipher()
+            .decrypt([SEGMENT_CONTEXT], &bytes)
+            .map_err(|_| FilesystemError::bad_name(name))?;
+        String::from_utf8(plaintext).map_err(|_| FilesystemError::bad_name(name))
+    }
+
+    /// AES-SIV cipher using this key, split into its MAC and encryption halves
+    fn cipher(&self) -> Aes128Siv {
+        Aes128Siv::new(Key::<Aes128Siv>::from_slice(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NameKey;
+    use crate::filesystem::FilesystemError;
+
+    #[test]
+    fn encrypt_decrypt() -> Result<(), FilesystemError> {
+        let key = NameKey::generate();
+        let name = key.encrypt("kitty.txt");
+        assert_ne!(name, "kitty.txt");
+        assert!(!name.contains('/'));
+        // Encryption is deterministic
+        assert_eq!(name, key.encrypt("kitty.txt"));
+        assert_eq!(key.decrypt(&name)?, "kitty.txt");
+        // Other keys can not decrypt the name
+        assert!(NameKey::generate().decrypt(&name).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_names() {
+        let key = NameKey::generate();
+        let name = key.encrypt("kitty.txt");
+        // Flipping any character of the name is noticed
+        let mut tampered = name.clone().into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).expect("not utf8");
+        assert!(key.decrypt(&tampered).is_err());
+        // As are names too short to hold an IV
+        assert!(key.decrypt("").is_err());
+        assert!(key.decrypt(&name[..8]).is_err());
+    }
+}
@@ -50,6 +50,29 @@ impl EncryptedPrivateRef {
         })
     }
 
+    /// Re-encrypt the temporal key of this Ref from one recipient key to another, without the
+    /// temporal key leaving this function
+    pub async fn rotate_wrapping(
+        &self,
+        old_key: &EcEncryptionKey,
+        new_key: &EcPublicEncryptionKey,
+    ) -> Result<Self, SharingError> {
+        if self.encrypted_temporal_key_string.is_empty() {
+            return Err(SharingError::invalid_data(
+                "encrypted temporal key string is empty",
+            ));
+        }
+        let encrypted_temporal_key =
+            EncryptedSymmetricKey::import(&self.encrypted_temporal_key_string)?;
+        let temporal_key = encrypted_temporal_key.decrypt_with(old_key).await?;
+        let encrypted_temporal_key_string = temporal_key.encrypt_for(new_key).await?.export();
+        Ok(Self {
+            saturated_name_hash: self.saturated_name_hash,
+            encrypted_temporal_key_string,
+            content_cid: self.content_cid,
+        })
+    }
+
     /// Decrypt an EncryptedPrivateRef with a recipient key
     pub async fn decrypt_with(
         self,
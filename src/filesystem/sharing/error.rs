@@ -8,6 +8,27 @@ pub struct SharingError {
     kind: SharingErrorKind,
 }
 
+/// Why a key was unable to unlock a Drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockFailure {
+    /// The key was given access, but it could not decrypt what was shared with it
+    WrongKey,
+    /// The key was never given access to the Drive
+    NoAccess,
+    /// The share manager of the Drive could not be read
+    CorruptShareManager,
+}
+
+impl Display for UnlockFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            UnlockFailure::WrongKey => "wrong key",
+            UnlockFailure::NoAccess => "no access",
+            UnlockFailure::CorruptShareManager => "corrupt share manager",
+        })
+    }
+}
+
 impl SharingError {
     pub fn unauthorized() -> Self {
         Self {
@@ -15,23 +36,63 @@ impl SharingError {
         }
     }
 
+    pub fn wrong_key() -> Self {
+        Self {
+            kind: SharingErrorKind::WrongKey,
+        }
+    }
+
+    pub fn corrupt_share_manager(message: &str) -> Self {
+        Self {
+            kind: SharingErrorKind::CorruptShareManager(message.to_string()),
+        }
+    }
+
     pub fn lost_key() -> Self {
         Self {
             kind: SharingErrorKind::LostKey,
         }
     }
 
+    pub fn expired() -> Self {
+        Self {
+            kind: SharingErrorKind::Expired,
+        }
+    }
+
     pub fn invalid_data(message: &str) -> Self {
         Self {
             kind: SharingErrorKind::InvalidData(message.to_string()),
         }
     }
 
+    pub fn unknown_recipient(fingerprint: &str) -> Self {
+        Self {
+            kind: SharingErrorKind::UnknownRecipient(fingerprint.to_string()),
+        }
+    }
+
+    pub fn last_recipient() -> Self {
+        Self {
+            kind: SharingErrorKind::LastRecipient,
+        }
+    }
+
     pub fn cryptographic(err: TombCryptError) -> Self {
         Self {
             kind: SharingErrorKind::Cryptographic(err),
         }
     }
+
+    /// Why a key was unable to unlock a Drive, if that is what this error describes
+    pub fn unlock_failure(&self) -> Option<UnlockFailure> {
+        match self.kind {
+            SharingErrorKind::UnauthorizedDecryption => Some(UnlockFailure::NoAccess),
+            SharingErrorKind::WrongKey => Some(UnlockFailure::WrongKey),
+            SharingErrorKind::CorruptShareManager(_) => Some(UnlockFailure::CorruptShareManager),
+            _ => None,
+        }
+    }
 }
 
 impl std::error::Error for SharingError {}
@@ -42,8 +103,22 @@ impl Display for SharingError {
             SharingErrorKind::UnauthorizedDecryption => {
                 "You are not authorized to decrypt this Drive, request key access first.".to_owned()
             }
+            SharingErrorKind::WrongKey => {
+                "This key was given access to the Drive, but could not decrypt it. Make sure you are using the right key.".to_owned()
+            }
+            SharingErrorKind::CorruptShareManager(msg) => {
+                format!("The Drive's share manager is corrupt and can not grant access: {msg}")
+            }
             SharingErrorKind::LostKey => "Lost track of a Key".to_owned(),
+            SharingErrorKind::Expired => "This shared file has expired".to_owned(),
             SharingErrorKind::InvalidData(msg) => format!("Invalid data: {msg}"),
+            SharingErrorKind::UnknownRecipient(fingerprint) => {
+                format!("No key with fingerprint {fingerprint} has been given access")
+            }
+            SharingErrorKind::LastRecipient => {
+                "Refusing to revoke the only key with access, which would lock the Drive for good"
+                    .to_owned()
+            }
             SharingErrorKind::Cryptographic(err) => {
                 format!("{} {err}", "CRYPTOGRAPHIC ERROR:".underline())
             }
@@ -56,8 +131,13 @@ impl Display for SharingError {
 #[derive(Debug)]
 pub enum SharingErrorKind {
     UnauthorizedDecryption,
+    WrongKey,
+    CorruptShareManager(String),
     LostKey,
+    Expired,
     InvalidData(String),
+    UnknownRecipient(String),
+    LastRecipient,
     Cryptographic(TombCryptError),
 }
 
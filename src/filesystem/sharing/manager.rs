@@ -51,11 +51,63 @@ impl ShareManager {
         Ok(())
     }
 
+    /// Share our references with several new recipients at once
+    pub async fn share_with_all(
+        &mut self,
+        recipients: &[EcPublicEncryptionKey],
+    ) -> Result<(), SharingError> {
+        for recipient in recipients {
+            self.share_with(recipient).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the recipient with `fingerprint` from both maps and share the current ref again with
+    /// those who remain. This alone does not keep the revoked key from following the ref to
+    /// future versions: the ref must also be rotated, as `FsMetadata::revoke` does. Versions
+    /// from before the revocation remain accessible to the revoked key.
+    pub async fn revoke(&mut self, fingerprint: &str) -> Result<(), SharingError> {
+        if !self.original_map.0.contains_key(fingerprint)
+            && !self.current_map.0.contains_key(fingerprint)
+        {
+            return Err(SharingError::unknown_recipient(fingerprint));
+        }
+        if self
+            .public_fingerprints()
+            .iter()
+            .all(|recipient| recipient == fingerprint)
+        {
+            return Err(SharingError::last_recipient());
+        }
+
+        self.original_map.0.remove(fingerprint);
+        self.current_map.0.remove(fingerprint);
+        if let Some(current_ref) = self.current_ref.clone() {
+            self.current_map.update_ref(&current_ref).await?;
+        }
+        Ok(())
+    }
+
     /// Grab a list of the PEM strings for each Public Key recipient
     pub fn public_fingerprints(&self) -> Vec<String> {
         self.original_map.0.clone().into_keys().collect()
     }
 
+    /// Fingerprints of the recipients both refs are shared with, in sorted order
+    pub fn recipients(&self) -> Vec<String> {
+        self.original_map
+            .fingerprints()
+            .into_iter()
+            .filter(|fingerprint| self.current_map.0.contains_key(fingerprint))
+            .collect()
+    }
+
+    /// Whether or not both refs are shared with the recipient with `fingerprint`
+    pub fn has_recipient(&self, fingerprint: &str) -> bool {
+        self.original_map.0.contains_key(fingerprint)
+            && self.current_map.0.contains_key(fingerprint)
+    }
+
     /// Retrieve the current_ref PrivateRef using a PrivateKey
     async fn current_ref(&self, recipient: &EcEncryptionKey) -> Result<PrivateRef, SharingError> {
         self.current_map.recover_ref(recipient).await
@@ -66,6 +118,13 @@ impl ShareManager {
         self.original_map.recover_ref(recipient).await
     }
 
+    /// Cheaply check whether a key has been given access to both refs, without decrypting
+    /// them. A key which passes may still fail to unlock if what was shared with it is corrupt.
+    pub async fn can_unlock(&self, recipient: &EcEncryptionKey) -> bool {
+        self.current_map.is_shared_with(recipient).await
+            && self.original_map.is_shared_with(recipient).await
+    }
+
     /// Reload both refs into memory
     pub async fn load_refs(&mut self, recipient: &EcEncryptionKey) -> Result<(), SharingError> {
         self.current_ref = Some(self.current_ref(recipient).await?);
@@ -78,13 +78,46 @@ impl EncRefMapper {
         let fingerprint = hex_fingerprint(recipient.fingerprint().await?.as_slice());
         // Grab the encrypted key associated with the fingerprint
         let (_, enc_ref_string) = match self.0.get(&fingerprint) {
-            Some(entry) => entry,
-            None => return Err(SharingError::unauthorized()),
+            // Recipients added before a ref existed have not been shared anything yet
+            Some(entry) if !entry.1.is_empty() => entry,
+            _ => return Err(SharingError::unauthorized()),
         };
-        let enc_ref = serde_json::from_str::<EncryptedPrivateRef>(enc_ref_string)?;
-        let private_ref = enc_ref.decrypt_with(recipient).await?;
+        let enc_ref = serde_json::from_str::<EncryptedPrivateRef>(enc_ref_string)
+            .map_err(|err| SharingError::corrupt_share_manager(&err.to_string()))?;
+        let private_ref = enc_ref
+            .decrypt_with(recipient)
+            .await
+            .map_err(|_| SharingError::wrong_key())?;
         Ok(private_ref)
     }
+
+    /// Whether or not a ref has been shared with a recipient, without decrypting it
+    pub async fn is_shared_with(&self, recipient: &EcEncryptionKey) -> bool {
+        let Ok(fingerprint) = recipient.fingerprint().await else {
+            return false;
+        };
+        self.0
+            .get(&hex_fingerprint(fingerprint.as_slice()))
+            .is_some_and(|(_, enc_ref_string)| !enc_ref_string.is_empty())
+    }
+
+    /// Fingerprints of every recipient in the mapper, in sorted order
+    pub fn fingerprints(&self) -> Vec<String> {
+        let mut fingerprints: Vec<String> = self.0.keys().cloned().collect();
+        fingerprints.sort();
+        fingerprints
+    }
+
+    /// Look up the public key of the recipient with `fingerprint`, if there is one
+    pub async fn recipient(
+        &self,
+        fingerprint: &str,
+    ) -> Result<Option<EcPublicEncryptionKey>, SharingError> {
+        match self.0.get(fingerprint) {
+            Some((der, _)) => Ok(Some(EcPublicEncryptionKey::import_bytes(der).await?)),
+            None => Ok(None),
+        }
+    }
 }
 
 impl EncRefMapper {
@@ -116,16 +149,12 @@ impl EncRefMapper {
             // For each key value pair in the IPLD
             for (fingerprint, ipld) in map {
                 // Get the expected variables, erroring if we fail
-                let map = cast!(ipld, Ipld::Map).ok_or(SharingError::unauthorized())?;
-                let bytes = map
-                    .get(PUBLIC_KEY_LABEL)
-                    .ok_or(SharingError::unauthorized())?;
-                let string = map
-                    .get(ENCRYPTED_PRIVATE_REF_LABEL)
-                    .ok_or(SharingError::unauthorized())?;
-                let public_key = cast!(bytes, Ipld::Bytes).ok_or(SharingError::unauthorized())?;
-                let encrypted_private_ref =
-                    cast!(string, Ipld::String).ok_or(SharingError::unauthorized())?;
+                let malformed = || SharingError::corrupt_share_manager("malformed recipient entry");
+                let map = cast!(ipld, Ipld::Map).ok_or_else(malformed)?;
+                let bytes = map.get(PUBLIC_KEY_LABEL).ok_or_else(malformed)?;
+                let string = map.get(ENCRYPTED_PRIVATE_REF_LABEL).ok_or_else(malformed)?;
+                let public_key = cast!(bytes, Ipld::Bytes).ok_or_else(malformed)?;
+                let encrypted_private_ref = cast!(string, Ipld::String).ok_or_else(malformed)?;
 
                 // Insert the new value into the mapper
                 mapper.0.insert(
@@ -153,7 +182,7 @@ impl<'de> Deserialize<'de> for EncRefMapper {
         D: serde::Deserializer<'de>,
     {
         let ipld = Ipld::deserialize(deserializer)?;
-        Ok(Self::from_ipld(ipld).expect("failed to convert IPLD to EncRefMapper"))
+        Self::from_ipld(ipld).map_err(serde::de::Error::custom)
     }
 }
 
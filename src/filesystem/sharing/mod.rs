@@ -10,18 +10,21 @@ pub mod mapper;
 mod shared_file;
 pub use shared_file::SharedFile;
 
-pub use error::SharingError;
+pub use error::{SharingError, UnlockFailure};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod test {
-    use crate::filesystem::sharing::manager::ShareManager;
+    use crate::filesystem::sharing::{enc_ref::EncryptedPrivateRef, manager::ShareManager};
     use rand::Rng;
     use serial_test::serial;
-    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
+    };
     use wnfs::private::{AesKey, PrivateRef, TemporalKey};
 
-    use super::SharingError;
+    use super::{SharingError, UnlockFailure};
 
     fn random_private_ref() -> PrivateRef {
         let random_bytes = rand::thread_rng().gen::<[u8; 32]>();
@@ -160,4 +163,185 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn share_with_all() -> Result<(), SharingError> {
+        let mut key_manager = ShareManager::default();
+        let original = random_private_ref();
+        let current = random_private_ref();
+        key_manager.set_original_ref(&original).await?;
+        key_manager.set_current_ref(&current).await?;
+
+        // Share with three recipients at once
+        let mut wrapping_keys = Vec::new();
+        let mut public_keys = Vec::new();
+        for _ in 0..3 {
+            let wrapping_key = EcEncryptionKey::generate().await?;
+            public_keys.push(wrapping_key.public_key()?);
+            wrapping_keys.push(wrapping_key);
+        }
+        key_manager.share_with_all(&public_keys).await?;
+
+        // Every one of them can unlock
+        for wrapping_key in &wrapping_keys {
+            let mut unlocked = key_manager.clone();
+            unlocked.original_ref = None;
+            unlocked.current_ref = None;
+            unlocked.load_refs(wrapping_key).await?;
+            assert_eq!(unlocked.original_ref, Some(original.clone()));
+            assert_eq!(unlocked.current_ref, Some(current.clone()));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn revoke() -> Result<(), SharingError> {
+        let mut key_manager = ShareManager::default();
+        let revoked_key = EcEncryptionKey::generate().await?;
+        let remaining_key = EcEncryptionKey::generate().await?;
+        key_manager.share_with(&revoked_key.public_key()?).await?;
+        key_manager.share_with(&remaining_key.public_key()?).await?;
+        key_manager.set_original_ref(&random_private_ref()).await?;
+        key_manager.set_current_ref(&random_private_ref()).await?;
+
+        let fingerprint =
+            hex_fingerprint(revoked_key.public_key()?.fingerprint().await?.as_slice());
+        key_manager.revoke(&fingerprint).await?;
+        // The rotated ref is shared with everyone who remains
+        let rotated = random_private_ref();
+        key_manager.set_current_ref(&rotated).await?;
+
+        let err = key_manager
+            .clone()
+            .load_refs(&revoked_key)
+            .await
+            .expect_err("revoked key unlocked");
+        assert_eq!(err.unlock_failure(), Some(UnlockFailure::NoAccess));
+        let mut unlocked = key_manager.clone();
+        unlocked.load_refs(&remaining_key).await?;
+        assert_eq!(unlocked.current_ref, Some(rotated));
+
+        // Nobody is left without access, and unknown keys can not be revoked
+        assert!(key_manager.revoke(&fingerprint).await.is_err());
+        let fingerprint =
+            hex_fingerprint(remaining_key.public_key()?.fingerprint().await?.as_slice());
+        assert!(key_manager.revoke(&fingerprint).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn unlock_failures() -> Result<(), SharingError> {
+        let mut key_manager = ShareManager::default();
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let other_key = EcEncryptionKey::generate().await?;
+        key_manager.share_with(&wrapping_key.public_key()?).await?;
+        key_manager.share_with(&other_key.public_key()?).await?;
+        key_manager.set_original_ref(&random_private_ref()).await?;
+        key_manager.set_current_ref(&random_private_ref()).await?;
+
+        // The correct key unlocks
+        assert!(key_manager.can_unlock(&wrapping_key).await);
+        key_manager.clone().load_refs(&wrapping_key).await?;
+
+        // A valid key which was never given access
+        let unauthorized_key = EcEncryptionKey::generate().await?;
+        assert!(!key_manager.can_unlock(&unauthorized_key).await);
+        let err = key_manager
+            .clone()
+            .load_refs(&unauthorized_key)
+            .await
+            .expect_err("unauthorized key unlocked");
+        assert_eq!(err.unlock_failure(), Some(UnlockFailure::NoAccess));
+
+        // A key whose shares were encrypted for someone else
+        let mut swapped = key_manager.clone();
+        for map in [&mut swapped.current_map, &mut swapped.original_map] {
+            let mut entries = map.0.values_mut().collect::<Vec<_>>();
+            let (first, rest) = entries.split_at_mut(1);
+            std::mem::swap(&mut first[0].1, &mut rest[0].1);
+        }
+        assert!(swapped.can_unlock(&wrapping_key).await);
+        let err = swapped
+            .load_refs(&wrapping_key)
+            .await
+            .expect_err("wrong key unlocked");
+        assert_eq!(err.unlock_failure(), Some(UnlockFailure::WrongKey));
+
+        // A malformed share
+        let mut malformed = key_manager.clone();
+        for (_, enc_ref_string) in malformed.current_map.0.values_mut() {
+            *enc_ref_string = "not a private ref".to_string();
+        }
+        let err = malformed
+            .load_refs(&wrapping_key)
+            .await
+            .expect_err("malformed share unlocked");
+        assert_eq!(
+            err.unlock_failure(),
+            Some(UnlockFailure::CorruptShareManager)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn rotate_wrapping() -> Result<(), SharingError> {
+        let old_key = EcEncryptionKey::generate().await?;
+        let new_key = EcEncryptionKey::generate().await?;
+        let private_ref = random_private_ref();
+        let encrypted =
+            EncryptedPrivateRef::encrypt_for(&private_ref, &old_key.public_key()?).await?;
+
+        // Only the wrapping of the temporal key changes
+        let rotated = encrypted
+            .rotate_wrapping(&old_key, &new_key.public_key()?)
+            .await?;
+        assert_ne!(
+            rotated.encrypted_temporal_key_string,
+            encrypted.encrypted_temporal_key_string
+        );
+        assert_eq!(rotated.decrypt_with(&new_key).await?, private_ref);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn recipients() -> Result<(), SharingError> {
+        let mut key_manager = ShareManager::default();
+        assert!(key_manager.recipients().is_empty());
+        let public_keys = vec![
+            EcEncryptionKey::generate().await?.public_key()?,
+            EcEncryptionKey::generate().await?.public_key()?,
+        ];
+        key_manager.share_with_all(&public_keys).await?;
+
+        let first = hex_fingerprint(public_keys[0].fingerprint().await?.as_slice());
+        let second = hex_fingerprint(public_keys[1].fingerprint().await?.as_slice());
+        let mut expected = vec![first.clone(), second.clone()];
+        expected.sort();
+        assert_eq!(key_manager.recipients(), expected);
+        assert!(key_manager.has_recipient(&first));
+        assert!(!key_manager.has_recipient("not a fingerprint"));
+
+        // Recipients can be looked up by their fingerprint
+        let found = key_manager
+            .current_map
+            .recipient(&second)
+            .await?
+            .ok_or(SharingError::unknown_recipient(&second))?;
+        assert_eq!(
+            found.export_bytes().await?,
+            public_keys[1].export_bytes().await?
+        );
+        assert!(key_manager.current_map.recipient("").await?.is_none());
+
+        // Revoked recipients are no longer listed
+        key_manager.revoke(&first).await?;
+        assert_eq!(key_manager.recipients(), vec![second]);
+        assert!(!key_manager.has_recipient(&first));
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 use super::SharingError;
+use crate::utils::Clock;
 use base64::{engine::general_purpose::URL_SAFE, Engine as _};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use wnfs::{common::dagcbor, libipld::Cid, private::share::SharePayload};
 
@@ -14,6 +16,9 @@ pub struct SharedFile {
     pub mime_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<u64>,
+    /// Seconds since the epoch after which the file may no longer be received
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
 }
 
 fn serialize_payload<S: Serializer>(
@@ -32,6 +37,18 @@ fn deserialize_payload<'de, D: Deserializer<'de>>(
 }
 
 impl SharedFile {
+    /// Stop the file from being received after `expiry`
+    pub fn with_expiry(mut self, expiry: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expiry.timestamp());
+        self
+    }
+
+    /// Whether or not the file has expired according to `clock`
+    pub fn is_expired(&self, clock: &impl Clock) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| clock.now().timestamp() >= expires_at)
+    }
+
     pub fn export_b64_url(&self) -> Result<String, SharingError> {
         Ok(URL_SAFE.encode(serde_json::to_string(&self)?.as_bytes()))
     }
@@ -1,54 +1,212 @@
 use super::FilesystemError;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
 use std::{
-    io::{BufReader, Read, Write},
+    fmt::Display,
+    io::{BufReader, ErrorKind, Read, Write},
     path::Path,
+    str::FromStr,
 };
+use wnfs::{common::Metadata, libipld::Ipld};
 
-#[derive(Debug, Clone)]
-/// Wrapper for compression information
-struct CompressionScheme {
-    /// The type of compression that is being used
-    pub compression_info: String,
+/// Metadata label recording how the content of a file was compressed
+const COMPRESSION_LABEL: &str = "compression";
+/// Compression level used for zstd when none is specified
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+/// Number of leading bytes of content compressed to judge whether all of it is worth compressing
+const COMPRESSION_SAMPLE_SIZE: usize = 64 * 1024;
+/// Content whose sample doesn't shrink below this fraction of its size is stored as is
+const MAX_COMPRESSION_RATIO: f64 = 0.95;
+
+/// Algorithm the content of a file is compressed with before it is encrypted. The codec is
+/// recorded in the metadata of every file it is used for, so that files written with different
+/// codecs can all be read back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionCodec {
+    /// Content is stored as is
+    #[default]
+    None,
+    /// LZ4 frames
+    Lz4,
+    /// Gzip
+    Gzip,
+    /// Zstandard at a given compression level. Not available in WASM.
+    Zstd {
+        /// Compression level, from 1 to 22
+        level: i32,
+    },
 }
 
-impl CompressionScheme {
-    /// Creates a new `CompressionScheme` struct, specifying the ZSTD compression algorithm
-    pub fn new_lz4_flex() -> Self {
-        CompressionScheme {
-            compression_info: String::from("LZ4_FLEX"),
+impl CompressionCodec {
+    /// Label the codec is recorded in file metadata as, if content is compressed at all
+    fn label(&self) -> Option<&'static str> {
+        match self {
+            CompressionCodec::None => None,
+            CompressionCodec::Lz4 => Some("LZ4_FLEX"),
+            CompressionCodec::Gzip => Some("GZIP"),
+            CompressionCodec::Zstd { .. } => Some("ZSTD"),
+        }
+    }
+
+    /// Determine the codec the content of a file was compressed with from its metadata.
+    /// Files without a recorded codec were stored as is.
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self, std::io::Error> {
+        match metadata.0.get(COMPRESSION_LABEL) {
+            None => Ok(CompressionCodec::None),
+            Some(Ipld::String(label)) => match label.as_str() {
+                "LZ4_FLEX" => Ok(CompressionCodec::Lz4),
+                "GZIP" => Ok(CompressionCodec::Gzip),
+                "ZSTD" => Ok(CompressionCodec::Zstd {
+                    level: DEFAULT_ZSTD_LEVEL,
+                }),
+                _ => Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unsupported compression algorithm: {label}"),
+                )),
+            },
+            Some(_) => Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "malformed compression metadata",
+            )),
+        }
+    }
+
+    /// Record the codec in the metadata of a file, removing any codec it was written with before
+    pub fn put(&self, metadata: &mut Metadata) {
+        match self.label() {
+            Some(label) => metadata.put(COMPRESSION_LABEL, Ipld::String(label.to_string())),
+            None => {
+                metadata.0.remove(COMPRESSION_LABEL);
+            }
         }
     }
 
-    /// Encode a file using the compression algorithm specified in the `CompressionScheme` struct
-    pub fn encode<R, W>(&self, source: &mut R, destination: W) -> Result<(), std::io::Error>
+    /// Compress everything read from `source` into `destination`
+    pub fn encode<R, W>(&self, source: &mut R, mut destination: W) -> Result<(), std::io::Error>
     where
-        R: std::io::Read,
-        W: std::io::Write,
+        R: Read,
+        W: Write,
     {
-        match self.compression_info.as_str() {
-            "LZ4_FLEX" => {
+        match self {
+            CompressionCodec::None => {
+                std::io::copy(source, &mut destination)?;
+                Ok(())
+            }
+            CompressionCodec::Lz4 => {
                 let mut dest = lz4_flex::frame::FrameEncoder::new(destination);
                 std::io::copy(source, &mut dest)?;
-                dest.finish().unwrap();
+                dest.finish()
+                    .map_err(|err| std::io::Error::new(ErrorKind::Other, err))?;
                 Ok(())
             }
-            _ => panic!("unsupported compression algorithm!"),
+            CompressionCodec::Gzip => {
+                let mut dest = GzEncoder::new(destination, Compression::default());
+                std::io::copy(source, &mut dest)?;
+                dest.finish()?;
+                Ok(())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            CompressionCodec::Zstd { level } => {
+                zstd::stream::copy_encode(source, destination, *level)
+            }
+            #[cfg(target_arch = "wasm32")]
+            CompressionCodec::Zstd { .. } => Err(zstd_unsupported()),
         }
     }
 
-    /// Decode a file using the compression algorithm specified in the `CompressionScheme` struct
-    pub fn decode<R, W>(&self, source: R, destination: &mut W) -> Result<(), std::io::Error>
+    /// Decompress everything read from `source` into `destination`
+    pub fn decode<R, W>(&self, mut source: R, destination: &mut W) -> Result<(), std::io::Error>
     where
-        R: std::io::Read,
-        W: std::io::Write,
+        R: Read,
+        W: Write,
     {
-        match self.compression_info.as_str() {
-            "LZ4_FLEX" => {
+        match self {
+            CompressionCodec::None => {
+                std::io::copy(&mut source, destination)?;
+                Ok(())
+            }
+            CompressionCodec::Lz4 => {
                 let mut source = lz4_flex::frame::FrameDecoder::new(source);
                 std::io::copy(&mut source, destination)?;
                 Ok(())
             }
-            _ => panic!("unsupported compression algorithm!"),
+            CompressionCodec::Gzip => {
+                let mut source = GzDecoder::new(source);
+                std::io::copy(&mut source, destination)?;
+                Ok(())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            CompressionCodec::Zstd { .. } => zstd::stream::copy_decode(source, destination),
+            #[cfg(target_arch = "wasm32")]
+            CompressionCodec::Zstd { .. } => Err(zstd_unsupported()),
+        }
+    }
+
+    /// Whether or not compressing `content` is likely to make it meaningfully smaller, judged by
+    /// compressing just the start of it. Content which is already compressed is not.
+    pub fn worth_compressing(&self, content: &[u8]) -> Result<bool, std::io::Error> {
+        if *self == CompressionCodec::None || content.is_empty() {
+            return Ok(false);
+        }
+        let sample = &content[..content.len().min(COMPRESSION_SAMPLE_SIZE)];
+        let compressed = self.compress(sample)?;
+        Ok((compressed.len() as f64) < sample.len() as f64 * MAX_COMPRESSION_RATIO)
+    }
+
+    /// Compress a slice of bytes
+    pub fn compress(&self, buf: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let mut compressed = vec![];
+        self.encode(&mut BufReader::new(buf), &mut compressed)?;
+        Ok(compressed)
+    }
+
+    /// Decompress a slice of bytes
+    pub fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let mut decompressed = vec![];
+        self.decode(buf, &mut decompressed)?;
+        Ok(decompressed)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn zstd_unsupported() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::Unsupported,
+        "zstd compression is not available in WASM",
+    )
+}
+
+impl Display for CompressionCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressionCodec::None => f.write_str("none"),
+            CompressionCodec::Lz4 => f.write_str("lz4"),
+            CompressionCodec::Gzip => f.write_str("gzip"),
+            CompressionCodec::Zstd { level } => write!(f, "zstd:{level}"),
+        }
+    }
+}
+
+impl FromStr for CompressionCodec {
+    type Err = String;
+
+    /// Parse a codec from `none`, `lz4`, `gzip`, `zstd` or `zstd:<level>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("zstd", level)) => level
+                .parse()
+                .map(|level| CompressionCodec::Zstd { level })
+                .map_err(|_| format!("invalid zstd compression level: {level}")),
+            None => match s {
+                "none" => Ok(CompressionCodec::None),
+                "lz4" => Ok(CompressionCodec::Lz4),
+                "gzip" => Ok(CompressionCodec::Gzip),
+                "zstd" => Ok(CompressionCodec::Zstd {
+                    level: DEFAULT_ZSTD_LEVEL,
+                }),
+                _ => Err(format!("unknown compression codec: {s}")),
+            },
+            _ => Err(format!("unknown compression codec: {s}")),
         }
     }
 }
@@ -59,7 +217,7 @@ where
     R: Read,
     W: Write,
 {
-    CompressionScheme::new_lz4_flex().encode(&mut reader, writer)
+    CompressionCodec::Lz4.encode(&mut reader, writer)
 }
 
 /// Decompresses bytes
@@ -68,7 +226,7 @@ where
     R: Read,
     W: Write,
 {
-    CompressionScheme::new_lz4_flex().decode(reader, &mut writer)
+    CompressionCodec::Lz4.decode(reader, &mut writer)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
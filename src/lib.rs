@@ -32,17 +32,26 @@ pub mod prelude {
     }
     pub mod blockstore {
         pub use crate::blockstore::{
-            BanyanApiBlockStore, BanyanBlockStore, CarV2MemoryBlockStore, DoubleSplitStore,
-            MemoryBlockStore, RootedBlockStore,
+            BanyanApiBlockStore, BanyanBlockStore, CachedBlockStore, CarV2MemoryBlockStore,
+            DoubleSplitStore, MemoryBlockStore, ReadOrder, RootedBlockStore,
         };
         #[cfg(not(target_arch = "wasm32"))]
-        pub use crate::blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore};
+        pub use crate::blockstore::{
+            CarV2DiskBlockStore, DiskCachedApiBlockStore, MultiCarV2DiskBlockStore,
+        };
     }
     pub mod car {
+        #[cfg(not(target_arch = "wasm32"))]
+        pub use crate::car::concat;
         pub use crate::car::{v1, v2};
     }
     pub mod filesystem {
-        pub use crate::filesystem::{serialize, sharing, wnfsio, FilesystemError, FsMetadata};
+        pub use crate::filesystem::{
+            serialize, sharing, wnfsio, FilesystemError, FsMetadata, Manifest, SignedManifest,
+        };
+    }
+    pub mod utils {
+        pub use crate::utils::{Clock, SystemClock};
     }
     #[cfg(target_arch = "wasm32")]
     pub mod wasm {
@@ -9,7 +9,13 @@ fn main() {
 use {
     banyan_cli::{
         self,
-        cli::{args::Args, commands::RunnableCommand},
+        cli::{
+            args::Args,
+            commands::{RunnableCommand, TombCommand},
+        },
+        native::utils::{
+            configure_color, set_output_mode, set_progress_mode, OutputMode, ProgressMode,
+        },
     },
     clap::Parser,
     tracing::Level,
@@ -22,10 +28,25 @@ use {
 async fn main() {
     // Parse command line arguments. see args.rs
     let cli = Args::parse();
+    // Completion scripts go straight to stdout, without logging or touching any configuration
+    if let TombCommand::Completions(command) = &cli.command {
+        command.generate(&mut std::io::stdout());
+        return;
+    }
+    // Choose between the interactive progress bar and machine readable progress
+    set_progress_mode(if cli.quiet {
+        ProgressMode::Hidden
+    } else {
+        cli.progress
+    });
+    set_output_mode(cli.output);
+    // Color codes would end up inside the JSON strings
+    let color = configure_color(cli.no_color || cli.output == OutputMode::Json);
 
     let (non_blocking_writer, _guard) = tracing_appender::non_blocking(std::io::stderr());
+    let level = if cli.quiet { Level::ERROR } else { Level::INFO };
     let env_filter = EnvFilter::builder()
-        .with_default_directive(Level::INFO.into())
+        .with_default_directive(level.into())
         .from_env_lossy();
 
     let stderr_layer = tracing_subscriber::fmt::layer()
@@ -33,11 +54,19 @@ async fn main() {
         .with_target(false)
         .with_file(false)
         .with_line_number(false)
+        .with_ansi(color)
         .with_writer(non_blocking_writer)
         .with_filter(env_filter);
 
     tracing_subscriber::registry().with(stderr_layer).init();
 
+    #[cfg(feature = "metrics")]
+    if let Some(address) = cli.metrics_listen {
+        if let Err(err) = banyan_cli::native::metrics::serve(address) {
+            tracing::error!("{err}");
+        }
+    }
+
     // Determine the command being executed run appropriate subcommand
     let _ = cli.command.run().await;
 }
@@ -1,38 +1,85 @@
 use crate::{
-    api::client::{Client, Credentials},
+    api::client::{
+        Client, Credentials, DEFAULT_CONNECT_TIMEOUT, DEFAULT_POOL_SIZE, DEFAULT_REQUEST_TIMEOUT,
+    },
+    blockstore::{BanyanApiBlockStore, DiskCachedApiBlockStore, DEFAULT_CACHE_SIZE_LIMIT},
     native::{
         configuration::{
-            keys::{load_api_key, new_api_key, new_wrapping_key, save_api_key, wrapping_key},
-            xdg::{config_path, default_api_key_path, default_wrapping_key_path},
+            keys::{load_api_key, new_api_key, save_api_key},
+            keystore::{
+                derive_wrapping_key, generate_wrapping_key, load_wrapping_key, FileKeyStore,
+                KeyStore, KeyStoreBackend, PASSPHRASE_SALT_LEN,
+            },
+            xdg::{
+                config_path, default_api_key_path, default_cache_path, default_wrapping_key_path,
+            },
         },
-        sync::LocalBucket,
+        sync::{LocalBucket, LocalBucketStores},
         NativeError,
     },
     utils::get_read,
 };
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use std::{
     fs::{remove_file, OpenOptions},
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey};
 use url::Url;
 use uuid::Uuid;
 
+/// Version of the on disk layout of the GlobalConfig written by this build. Configs written
+/// before versioning was introduced have no version and are treated as version 0.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Environment variable the passphrase is read from when the wrapping key is derived from one
+pub const PASSPHRASE_ENV: &str = "BANYAN_PASSPHRASE";
+
 /// Represents the Global contents of the tomb configuration file in a user's .config
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct GlobalConfig {
     /// Tomb version
     version: String,
+    /// Version of the on disk layout this config was written with
+    #[serde(default)]
+    pub format_version: u32,
     /// Location of wrapping key on disk in PEM format
     pub wrapping_key_path: PathBuf,
+    /// Where the wrapping key is persisted
+    #[serde(default)]
+    pub key_store: KeyStoreBackend,
+    /// Base64 encoded salt the wrapping key is derived from a passphrase with. When set, the
+    /// wrapping key is never stored and the key store is not consulted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_salt: Option<String>,
     /// Location of api key on disk in PEM format
     pub api_key_path: PathBuf,
     /// Remote endpoint
     endpoint: Url,
     /// Remote account id
     remote_user_id: Option<Uuid>,
+    /// Location of the on disk block cache
+    #[serde(default = "default_cache_path")]
+    pub cache_path: PathBuf,
+    /// Maximum size of the on disk block cache in bytes
+    #[serde(default = "default_cache_size_limit")]
+    pub cache_size_limit: u64,
+    /// Seconds allowed for establishing a connection to a remote
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Seconds allowed for a request to a remote to complete, unless it streams content
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
+    /// Number of idle connections kept open per remote host
+    #[serde(default = "default_pool_size")]
+    pub pool_size: usize,
+    /// Bytes per second content may be uploaded or downloaded at, if capped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u64>,
     /// Bucket Configurations
     pub(crate) buckets: Vec<LocalBucket>,
 }
@@ -48,15 +95,40 @@ impl Default for GlobalConfig {
 
         Self {
             version: env!("CARGO_PKG_VERSION").to_string(),
+            format_version: FORMAT_VERSION,
             endpoint,
             wrapping_key_path: default_wrapping_key_path(),
+            key_store: KeyStoreBackend::default(),
+            passphrase_salt: None,
             api_key_path: default_api_key_path(),
             remote_user_id: None,
+            cache_path: default_cache_path(),
+            cache_size_limit: DEFAULT_CACHE_SIZE_LIMIT,
+            connect_timeout: default_connect_timeout(),
+            request_timeout: default_request_timeout(),
+            pool_size: DEFAULT_POOL_SIZE,
+            rate_limit: None,
             buckets: Vec::new(),
         }
     }
 }
 
+fn default_cache_size_limit() -> u64 {
+    DEFAULT_CACHE_SIZE_LIMIT
+}
+
+fn default_connect_timeout() -> u64 {
+    DEFAULT_CONNECT_TIMEOUT.as_secs()
+}
+
+fn default_request_timeout() -> u64 {
+    DEFAULT_REQUEST_TIMEOUT.as_secs()
+}
+
+fn default_pool_size() -> usize {
+    DEFAULT_POOL_SIZE
+}
+
 // Self
 impl GlobalConfig {
     /// Create a new Global Configuration, keys, and save them all
@@ -65,9 +137,10 @@ impl GlobalConfig {
         let config = Self::default();
         config.to_disk()?;
 
-        // Do not blindly overwrite key files if they exist
-        if !config.wrapping_key_path.exists() {
-            let _wrapping_key = new_wrapping_key(&config.wrapping_key_path).await?;
+        // Do not blindly overwrite keys if they exist
+        let wrapping_key_store = config.wrapping_key_store()?;
+        if wrapping_key_store.load().is_err() {
+            let _wrapping_key = generate_wrapping_key(wrapping_key_store.as_ref()).await?;
         }
         if !config.api_key_path.exists() {
             let _api_key = new_api_key(&config.api_key_path).await?;
@@ -77,13 +150,70 @@ impl GlobalConfig {
         Ok(config)
     }
 
-    /// Get the wrapping key
+    /// Get the KeyStore the wrapping key is persisted in
+    pub fn wrapping_key_store(&self) -> Result<Box<dyn KeyStore>, NativeError> {
+        match self.key_store {
+            KeyStoreBackend::File => Ok(Box::new(FileKeyStore::new(&self.wrapping_key_path))),
+            #[cfg(feature = "keyring")]
+            KeyStoreBackend::Keyring => Ok(Box::new(
+                crate::native::configuration::keystore::KeyringKeyStore::new("wrapping_key"),
+            )),
+            #[cfg(not(feature = "keyring"))]
+            KeyStoreBackend::Keyring => Err(NativeError::custom_error(
+                "the keyring key store requires the `keyring` feature",
+            )),
+        }
+    }
+
+    /// Get the wrapping key. In passphrase mode the passphrase is read from `BANYAN_PASSPHRASE`.
     pub async fn wrapping_key(&self) -> Result<EcEncryptionKey, NativeError> {
-        wrapping_key(&self.wrapping_key_path)
+        if self.passphrase_salt.is_some() {
+            let passphrase =
+                std::env::var(PASSPHRASE_ENV).map_err(|_| NativeError::missing_wrapping_key())?;
+            return self.wrapping_key_from_passphrase(&passphrase).await;
+        }
+        load_wrapping_key(self.wrapping_key_store()?.as_ref())
             .await
             .map_err(|_| NativeError::missing_wrapping_key())
     }
 
+    /// Whether the wrapping key is derived from a passphrase rather than stored
+    pub fn passphrase_mode(&self) -> bool {
+        self.passphrase_salt.is_some()
+    }
+
+    /// Switch to deriving the wrapping key from a passphrase, generating the salt for it. Drives
+    /// already configured are locked with the stored wrapping key, so this is refused once any
+    /// exist. Does nothing if passphrase mode is already enabled.
+    pub fn enable_passphrase(&mut self) -> Result<(), NativeError> {
+        if self.passphrase_mode() {
+            return Ok(());
+        }
+        if !self.buckets.is_empty() {
+            return Err(NativeError::custom_error(
+                "passphrase mode can only be enabled before any drives are configured",
+            ));
+        }
+        let mut salt = [0u8; PASSPHRASE_SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        self.passphrase_salt = Some(STANDARD.encode(salt));
+        self.to_disk()
+    }
+
+    /// Derive the wrapping key from a passphrase and the stored salt with Argon2id. See
+    /// [`derive_wrapping_key`] for what this does and does not protect against.
+    pub async fn wrapping_key_from_passphrase(
+        &self,
+        passphrase: &str,
+    ) -> Result<EcEncryptionKey, NativeError> {
+        let salt = self
+            .passphrase_salt
+            .as_ref()
+            .ok_or(NativeError::custom_error("passphrase mode is not enabled"))?;
+        let salt = STANDARD.decode(salt).map_err(|_| NativeError::bad_data())?;
+        derive_wrapping_key(passphrase, &salt).await
+    }
+
     /// Get the api key
     pub async fn api_key(&self) -> Result<EcSignatureKey, NativeError> {
         load_api_key(&self.api_key_path)
@@ -103,6 +233,14 @@ impl GlobalConfig {
     pub async fn get_client(&self) -> Result<Client, NativeError> {
         // Create a new Client
         let mut client = Client::new(self.endpoint.as_ref())?;
+        client.with_timeouts(
+            Duration::from_secs(self.connect_timeout),
+            Duration::from_secs(self.request_timeout),
+        )?;
+        client.with_pool_size(self.pool_size)?;
+        if let Some(rate_limit) = self.rate_limit {
+            client.with_rate_limit(rate_limit);
+        }
         // If there are already credentials
         if let Ok(credentials) = self.get_credentials().await {
             // Set the credentials
@@ -133,6 +271,23 @@ impl GlobalConfig {
         self.to_disk()
     }
 
+    /// Wrap a remote BlockStore in the on disk block cache
+    pub fn get_cache(
+        &self,
+        source: BanyanApiBlockStore,
+    ) -> Result<DiskCachedApiBlockStore, NativeError> {
+        Ok(DiskCachedApiBlockStore::new(
+            source,
+            &self.cache_path,
+            self.cache_size_limit,
+        )?)
+    }
+
+    /// Remove all blocks from the on disk block cache
+    pub fn clear_cache(&self) -> Result<(), NativeError> {
+        Ok(DiskCachedApiBlockStore::<BanyanApiBlockStore>::remove_cache(&self.cache_path)?)
+    }
+
     #[allow(unused)]
     pub fn get_endpoint(&self) -> Url {
         self.endpoint.clone()
@@ -155,13 +310,61 @@ impl GlobalConfig {
         serde_json::to_writer_pretty(writer, &self).map_err(|_| NativeError::bad_data())
     }
 
-    /// Initialize from file on disk
+    /// Initialize from file on disk, upgrading it from older layouts if need be
     pub async fn from_disk() -> Result<Self, NativeError> {
         let file = get_read(&config_path())?;
-        let config = serde_json::from_reader(file).map_err(|_| NativeError::bad_data())?;
+        let value: serde_json::Value =
+            serde_json::from_reader(file).map_err(|_| NativeError::bad_data())?;
+        let (value, migrated) = Self::migrate(value)?;
+        let config: Self = serde_json::from_value(value).map_err(|_| NativeError::bad_data())?;
+        // Persist the upgrade so that it only happens once
+        if migrated {
+            config.to_disk()?;
+        }
         Ok(config)
     }
 
+    /// Where every Bucket keeps its CARs, read from the config on disk without loading any of
+    /// them, so that those too damaged to load can still be found and repaired
+    pub fn bucket_stores_from_disk() -> Result<Vec<LocalBucketStores>, NativeError> {
+        let file = get_read(&config_path())?;
+        let value: serde_json::Value =
+            serde_json::from_reader(file).map_err(|_| NativeError::bad_data())?;
+        let (mut value, _) = Self::migrate(value)?;
+        serde_json::from_value(value["buckets"].take()).map_err(|_| NativeError::bad_data())
+    }
+
+    /// Upgrade the raw contents of a config to the current format version, one version at a
+    /// time. Returns the upgraded contents and whether anything changed. Configs written by a
+    /// newer version than this one are refused rather than misread.
+    fn migrate(mut value: serde_json::Value) -> Result<(serde_json::Value, bool), NativeError> {
+        if !value.is_object() {
+            return Err(NativeError::bad_data());
+        }
+        let format_version = |value: &serde_json::Value| {
+            value
+                .get("format_version")
+                .and_then(|version| version.as_u64())
+                .unwrap_or(0) as u32
+        };
+        let original = format_version(&value);
+        if original > FORMAT_VERSION {
+            return Err(NativeError::unsupported_format(original, FORMAT_VERSION));
+        }
+
+        let mut version = original;
+        while version < FORMAT_VERSION {
+            match version {
+                // Version 0 predates versioning, and has the same layout as version 1
+                0 => {}
+                _ => unreachable!("no migration from format version {version}"),
+            }
+            version += 1;
+            value["format_version"] = serde_json::Value::from(version);
+        }
+        Ok((value, version != original))
+    }
+
     /// Remove a BucketConfig for an origin
     pub fn remove_bucket(&mut self, bucket: &LocalBucket) -> Result<(), NativeError> {
         // Remove bucket data
@@ -204,14 +407,14 @@ impl GlobalConfig {
         self.to_disk()
     }
 
-    /// Create a new bucket
+    /// Create a new bucket locked with a given wrapping key
     async fn create_bucket(
         &mut self,
         name: &str,
         origin: &Path,
+        wrapping_key: &EcEncryptionKey,
     ) -> Result<LocalBucket, NativeError> {
-        let wrapping_key = self.wrapping_key().await?;
-        let mut bucket = LocalBucket::new(origin, &wrapping_key).await?;
+        let mut bucket = LocalBucket::new(origin, wrapping_key).await?;
         bucket.name = name.to_string();
         self.buckets.push(bucket.clone());
         self.to_disk()?;
@@ -235,7 +438,24 @@ impl GlobalConfig {
         if let Some(config) = self.get_bucket(origin) {
             Ok(config.clone())
         } else {
-            Ok(self.create_bucket(name, origin).await?)
+            let wrapping_key = self.wrapping_key().await?;
+            Ok(self.create_bucket(name, origin, &wrapping_key).await?)
+        }
+    }
+
+    /// Create a bucket locked with a key derived from a passphrase if it doesn't exist, return
+    /// the object either way
+    pub async fn get_or_init_bucket_with_passphrase(
+        &mut self,
+        name: &str,
+        origin: &Path,
+        passphrase: &str,
+    ) -> Result<LocalBucket, NativeError> {
+        if let Some(config) = self.get_bucket(origin) {
+            Ok(config.clone())
+        } else {
+            let wrapping_key = self.wrapping_key_from_passphrase(passphrase).await?;
+            Ok(self.create_bucket(name, origin, &wrapping_key).await?)
         }
     }
 }
@@ -244,15 +464,35 @@ impl GlobalConfig {
 mod test {
 
     use serial_test::serial;
-    use std::{fs::remove_file, path::Path};
+    use std::{
+        fs::{create_dir_all, remove_file, OpenOptions},
+        io::{Seek, SeekFrom, Write},
+        path::Path,
+    };
 
-    use crate::native::{
-        configuration::{
-            globalconfig::GlobalConfig,
-            xdg::{config_path, default_api_key_path, default_wrapping_key_path},
+    use crate::{
+        blockstore::CarV2DiskBlockStore,
+        native::{
+            configuration::{
+                globalconfig::{GlobalConfig, FORMAT_VERSION},
+                xdg::{config_path, default_api_key_path, default_wrapping_key_path},
+            },
+            NativeError,
         },
-        NativeError,
     };
+    use tomb_crypt::prelude::{PrivateKey, PublicKey};
+
+    /// A config as written before the format was versioned
+    fn v0_fixture() -> serde_json::Value {
+        serde_json::json!({
+            "version": "0.1.0",
+            "wrapping_key_path": default_wrapping_key_path(),
+            "api_key_path": default_api_key_path(),
+            "endpoint": "http://127.0.0.1:3001/",
+            "remote_user_id": null,
+            "buckets": []
+        })
+    }
 
     #[tokio::test]
     #[serial]
@@ -281,6 +521,38 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn migrate_v0() -> Result<(), NativeError> {
+        std::fs::write(config_path(), v0_fixture().to_string())?;
+        let config = GlobalConfig::from_disk().await?;
+        assert_eq!(config.format_version, FORMAT_VERSION);
+        assert_eq!(config.endpoint.as_str(), "http://127.0.0.1:3001/");
+        assert_eq!(config.wrapping_key_path, default_wrapping_key_path());
+        assert!(config.buckets.is_empty());
+        // The upgrade was saved
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(config_path())?).expect("config is not JSON");
+        assert_eq!(saved["format_version"], FORMAT_VERSION);
+        assert_eq!(GlobalConfig::from_disk().await?, config);
+        remove_file(config_path())?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn refuse_newer_format() -> Result<(), NativeError> {
+        let mut fixture = v0_fixture();
+        fixture["format_version"] = serde_json::Value::from(FORMAT_VERSION + 1);
+        std::fs::write(config_path(), fixture.to_string())?;
+        let err = GlobalConfig::from_disk()
+            .await
+            .expect_err("loaded a config from the future");
+        assert!(err.to_string().contains("upgrade banyan"));
+        remove_file(config_path())?;
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn from_disk_direct() -> Result<(), NativeError> {
@@ -345,4 +617,88 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn find_damaged_stores() -> Result<(), NativeError> {
+        let known_path = config_path();
+        if known_path.exists() {
+            remove_file(&known_path)?;
+        }
+        let origin = Path::new("test").join("find_damaged_stores");
+        create_dir_all(&origin)?;
+        let mut config = GlobalConfig::new().await?;
+        let mut local = config.get_or_init_bucket("damaged", &origin).await?;
+        let wrapping_key = config.wrapping_key().await?;
+        let mut fs = local.unlock_fs(&wrapping_key).await?;
+        local.save_fs(&mut fs).await?;
+        config.update_config(&local)?;
+
+        // Damage the index of the metadata CAR
+        let index_offset = local.metadata.car.header.borrow().index_offset;
+        let mut file = OpenOptions::new().write(true).open(&local.metadata.path)?;
+        file.seek(SeekFrom::Start(index_offset))?;
+        file.write_all(&[0x00; 8])?;
+
+        // The config can no longer be loaded, but where the Bucket keeps its CARs still can
+        assert!(GlobalConfig::from_disk().await.is_err());
+        let stores = GlobalConfig::bucket_stores_from_disk()?;
+        assert_eq!(stores.len(), 1);
+        let paths = stores[0].car_paths()?;
+        assert_eq!(paths[0], local.metadata.path);
+        assert!(paths.contains(&local.content.get_delta()?.path));
+
+        // Nothing is repaired until asked, after which the config loads again
+        assert!(GlobalConfig::from_disk().await.is_err());
+        for path in paths {
+            CarV2DiskBlockStore::repair(&path)?;
+        }
+        assert!(GlobalConfig::from_disk().await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn passphrase_mode() -> Result<(), NativeError> {
+        let known_path = config_path();
+        if known_path.exists() {
+            remove_file(&known_path)?;
+        }
+
+        let mut config = GlobalConfig::new().await?;
+        assert!(config
+            .wrapping_key_from_passphrase("hunter2")
+            .await
+            .is_err());
+        config.enable_passphrase()?;
+        let salt = config.passphrase_salt.clone();
+        // Enabling again keeps the salt, and the salt is persisted
+        config.enable_passphrase()?;
+        assert_eq!(config.passphrase_salt, salt);
+        let reconstructed = GlobalConfig::from_disk().await?;
+        assert_eq!(reconstructed.passphrase_salt, salt);
+
+        let key = config.wrapping_key_from_passphrase("hunter2").await?;
+        let bucket = config
+            .get_or_init_bucket_with_passphrase("passphrase", Path::new("test"), "hunter2")
+            .await?;
+        // The bucket can be unlocked with the key derived again from the saved config
+        let rederived = reconstructed
+            .wrapping_key_from_passphrase("hunter2")
+            .await?;
+        assert_eq!(
+            key.public_key()?.fingerprint().await?,
+            rederived.public_key()?.fingerprint().await?
+        );
+        bucket.unlock_fs(&rederived).await?;
+
+        // Existing drives are locked with the stored key, so passphrase mode can't be adopted
+        let mut other = GlobalConfig::default();
+        other.buckets.push(bucket.clone());
+        assert!(other.enable_passphrase().is_err());
+
+        config.remove_bucket(&bucket)?;
+        remove_file(config_path())?;
+        Ok(())
+    }
 }
@@ -0,0 +1,270 @@
+use crate::native::NativeError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use p384::{
+    pkcs8::{EncodePrivateKey, LineEnding},
+    SecretKey,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{remove_file, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+
+/// Service name under which keys are stored in the OS keychain
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "banyan";
+
+/// Memory cost of the passphrase KDF in KiB. Together with the other parameters this follows
+/// the second recommended Argon2id configuration of RFC 9106, which takes a fraction of a second
+/// on a typical machine while making each guess at a passphrase expensive on dedicated hardware.
+pub const PASSPHRASE_KDF_MEMORY: u32 = 64 * 1024;
+/// Number of passes the passphrase KDF makes over its memory
+pub const PASSPHRASE_KDF_ITERATIONS: u32 = 3;
+/// Degree of parallelism of the passphrase KDF
+pub const PASSPHRASE_KDF_PARALLELISM: u32 = 4;
+/// Number of random bytes salting the passphrase KDF
+pub const PASSPHRASE_SALT_LEN: usize = 16;
+/// Length of a P-384 private scalar
+const SCALAR_LEN: usize = 48;
+
+/// Persistence for a single PEM encoded key
+pub trait KeyStore {
+    /// Load the PEM bytes of the key
+    fn load(&self) -> Result<Vec<u8>, NativeError>;
+    /// Store the PEM bytes of the key, replacing any existing key
+    fn store(&self, pem: &[u8]) -> Result<(), NativeError>;
+    /// Delete the key
+    fn delete(&self) -> Result<(), NativeError>;
+}
+
+/// Which KeyStore implementation keys are persisted with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyStoreBackend {
+    /// PEM files on disk
+    #[default]
+    File,
+    /// The OS keychain. Requires the `keyring` feature.
+    Keyring,
+}
+
+/// KeyStore which keeps a key as a PEM file on disk
+#[derive(Debug, Clone)]
+pub struct FileKeyStore {
+    path: PathBuf,
+}
+
+impl FileKeyStore {
+    /// Create a KeyStore for a key at a given path
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn load(&self) -> Result<Vec<u8>, NativeError> {
+        let mut reader = File::open(&self.path)?;
+        let mut pem_bytes = Vec::new();
+        reader.read_to_end(&mut pem_bytes)?;
+        Ok(pem_bytes)
+    }
+
+    fn store(&self, pem: &[u8]) -> Result<(), NativeError> {
+        let mut writer = File::create(&self.path)?;
+        writer.write_all(pem)?;
+        Ok(())
+    }
+
+    fn delete(&self) -> Result<(), NativeError> {
+        if self.path.exists() {
+            remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// KeyStore which keeps a key in the OS keychain
+#[cfg(feature = "keyring")]
+#[derive(Debug, Clone)]
+pub struct KeyringKeyStore {
+    user: String,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringKeyStore {
+    /// Create a KeyStore for a key with a given name
+    pub fn new(user: &str) -> Self {
+        Self {
+            user: user.to_string(),
+        }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, NativeError> {
+        keyring::Entry::new(KEYRING_SERVICE, &self.user)
+            .map_err(|err| NativeError::custom_error(&err.to_string()))
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl KeyStore for KeyringKeyStore {
+    fn load(&self) -> Result<Vec<u8>, NativeError> {
+        self.entry()?
+            .get_password()
+            .map(String::into_bytes)
+            .map_err(|err| NativeError::custom_error(&err.to_string()))
+    }
+
+    fn store(&self, pem: &[u8]) -> Result<(), NativeError> {
+        let pem = String::from_utf8(pem.to_vec())?;
+        self.entry()?
+            .set_password(&pem)
+            .map_err(|err| NativeError::custom_error(&err.to_string()))
+    }
+
+    fn delete(&self) -> Result<(), NativeError> {
+        match self.entry()?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(NativeError::custom_error(&err.to_string())),
+        }
+    }
+}
+
+/// Load a wrapping key from a KeyStore
+pub async fn load_wrapping_key(store: &dyn KeyStore) -> Result<EcEncryptionKey, NativeError> {
+    let pem_bytes = store.load()?;
+    Ok(EcEncryptionKey::import(&pem_bytes).await?)
+}
+
+/// Generate a new wrapping key and persist it in a KeyStore
+pub async fn generate_wrapping_key(store: &dyn KeyStore) -> Result<EcEncryptionKey, NativeError> {
+    let key = EcEncryptionKey::generate().await?;
+    store.store(&key.export().await?)?;
+    Ok(key)
+}
+
+/// Derive a wrapping key from a passphrase and salt with Argon2id.
+///
+/// The derived bytes are used directly as a P-384 private scalar, so the same passphrase and
+/// salt always produce the same key and nothing secret has to be kept on disk. The trade off is
+/// that the key is only as strong as the passphrase: anyone holding the salt and a copy of the
+/// encrypted metadata can mount an offline guessing attack against it, which the KDF parameters
+/// slow down but cannot prevent. Long, randomly generated passphrases should be used.
+pub async fn derive_wrapping_key(
+    passphrase: &str,
+    salt: &[u8],
+) -> Result<EcEncryptionKey, NativeError> {
+    if passphrase.is_empty() {
+        return Err(NativeError::custom_error(
+            "the passphrase must not be empty",
+        ));
+    }
+    let params = Params::new(
+        PASSPHRASE_KDF_MEMORY,
+        PASSPHRASE_KDF_ITERATIONS,
+        PASSPHRASE_KDF_PARALLELISM,
+        Some(SCALAR_LEN),
+    )
+    .map_err(|err| NativeError::custom_error(&err.to_string()))?;
+    let mut scalar = [0u8; SCALAR_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut scalar)
+        .map_err(|err| NativeError::custom_error(&err.to_string()))?;
+    // Fails only if the output is not below the curve order, which is vanishingly unlikely
+    let secret = SecretKey::from_slice(&scalar)
+        .map_err(|_| NativeError::custom_error("passphrase derived an invalid key"))?;
+    scalar.fill(0);
+    let pem = secret
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|err| NativeError::custom_error(&err.to_string()))?;
+    Ok(EcEncryptionKey::import(pem.as_bytes()).await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        derive_wrapping_key, generate_wrapping_key, load_wrapping_key, FileKeyStore, KeyStore,
+    };
+    use crate::native::NativeError;
+    use std::{cell::RefCell, fs::create_dir_all, path::Path};
+    use tomb_crypt::prelude::{PrivateKey, PublicKey};
+
+    /// KeyStore which only holds a key in memory
+    #[derive(Debug, Default)]
+    struct MockKeyStore {
+        pem: RefCell<Option<Vec<u8>>>,
+    }
+
+    impl KeyStore for MockKeyStore {
+        fn load(&self) -> Result<Vec<u8>, NativeError> {
+            self.pem
+                .borrow()
+                .clone()
+                .ok_or(NativeError::missing_wrapping_key())
+        }
+
+        fn store(&self, pem: &[u8]) -> Result<(), NativeError> {
+            *self.pem.borrow_mut() = Some(pem.to_vec());
+            Ok(())
+        }
+
+        fn delete(&self) -> Result<(), NativeError> {
+            *self.pem.borrow_mut() = None;
+            Ok(())
+        }
+    }
+
+    /// Generate, load, and delete a wrapping key in any KeyStore
+    async fn round_trip(store: &dyn KeyStore) -> Result<(), NativeError> {
+        let key = generate_wrapping_key(store).await?;
+        let loaded = load_wrapping_key(store).await?;
+        assert_eq!(
+            key.public_key()?.fingerprint().await?,
+            loaded.public_key()?.fingerprint().await?
+        );
+        store.delete()?;
+        assert!(load_wrapping_key(store).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn file_key_store() -> Result<(), NativeError> {
+        let dir = Path::new("test").join("keystore");
+        create_dir_all(&dir)?;
+        round_trip(&FileKeyStore::new(&dir.join("wrapping_key.pem"))).await
+    }
+
+    #[tokio::test]
+    async fn mock_key_store() -> Result<(), NativeError> {
+        round_trip(&MockKeyStore::default()).await
+    }
+
+    #[tokio::test]
+    async fn passphrase_derivation() -> Result<(), NativeError> {
+        let salt = [7u8; 16];
+        let fingerprint = |passphrase: &'static str, salt: [u8; 16]| async move {
+            let key = derive_wrapping_key(passphrase, &salt).await?;
+            let fingerprint = key.public_key()?.fingerprint().await?;
+            Ok::<_, NativeError>(fingerprint)
+        };
+        let key = fingerprint("correct horse battery staple", salt).await?;
+        // The same passphrase and salt always derive the same key
+        assert_eq!(
+            key,
+            fingerprint("correct horse battery staple", salt).await?
+        );
+        // Changing either derives a different one
+        assert_ne!(
+            key,
+            fingerprint("correct horse battery stable", salt).await?
+        );
+        assert_ne!(
+            key,
+            fingerprint("correct horse battery staple", [8u8; 16]).await?
+        );
+        assert!(derive_wrapping_key("", &salt).await.is_err());
+        Ok(())
+    }
+}
@@ -2,5 +2,7 @@
 pub mod globalconfig;
 /// Key config
 pub mod keys;
+/// Pluggable key persistence
+pub mod keystore;
 /// XDG config
 pub mod xdg;
@@ -3,6 +3,7 @@ const HOME_ERROR: &str = "cant find home directory";
 const GLOBAL_CONFIG_FILE_NAME: &str = "config.json";
 const DEVICE_API_KEY_FILE_NAME: &str = "device_api_key.pem";
 const DEVICE_WRAPPING_KEY_FILE_NAME: &str = "wrapping_key.pem";
+const BLOCK_CACHE_FILE_NAME: &str = "block_cache.car";
 
 /// Grab config path
 pub fn xdg_config_home() -> PathBuf {
@@ -34,6 +35,21 @@ pub fn xdg_data_home() -> PathBuf {
     path
 }
 
+/// Grab cache path
+pub fn xdg_cache_home() -> PathBuf {
+    // Construct
+    let path = PathBuf::from(format!(
+        "{}/.cache/banyan",
+        env::var("HOME").expect(HOME_ERROR)
+    ));
+    // If the directory doesnt exist yet, make it!
+    if !path.exists() {
+        create_dir_all(&path).expect("failed to create XDG cache home");
+    }
+    // Return
+    path
+}
+
 /// Grab path to config.json File
 pub fn config_path() -> PathBuf {
     xdg_config_home().join(GLOBAL_CONFIG_FILE_NAME)
@@ -48,3 +64,8 @@ pub fn default_api_key_path() -> PathBuf {
 pub fn default_wrapping_key_path() -> PathBuf {
     xdg_config_home().join(DEVICE_WRAPPING_KEY_FILE_NAME)
 }
+
+/// Grab path to the block cache CAR
+pub fn default_cache_path() -> PathBuf {
+    xdg_cache_home().join(BLOCK_CACHE_FILE_NAME)
+}
@@ -37,6 +37,42 @@ impl Display for NativeError {
                 "There is already a unique Drive with these specs".to_owned()
             }
             NativeErrorKind::BadData => "bad data".to_owned(),
+            NativeErrorKind::MissingStorageHost => "unable to determine storage host".to_owned(),
+            NativeErrorKind::MissingRoot(label) => format!(
+                "{} Missing metadata with label \"{label}\"",
+                "FILESYSTEM ERROR:".underline()
+            ),
+            NativeErrorKind::Unauthorized(msg) => format!("{msg}. Check your authentication!"),
+            NativeErrorKind::NotSynced => "this is the wrong metadata".to_owned(),
+            NativeErrorKind::CreationFailed {
+                stage,
+                cause,
+                rolled_back,
+            } => {
+                let rollback = match rolled_back {
+                    None => "nothing was left behind",
+                    Some(true) => "the remote Drive was rolled back",
+                    Some(false) => {
+                        "rolling back the remote Drive failed and it must be deleted manually"
+                    }
+                };
+                format!("failed to create Drive {stage}: {cause}; {rollback}")
+            }
+            NativeErrorKind::TooManyOpenFiles(limit) => format!(
+                "ran out of file handles while reading with up to {limit} files open; \
+                 raise the open file limit (ulimit -n) or lower --max-open-files"
+            ),
+            NativeErrorKind::ReadOnly(name) => format!(
+                "Drive \"{name}\" is read-only; unlock it with `banyan drives unlock` to modify it"
+            ),
+            NativeErrorKind::Diverged(name) => format!(
+                "Drive \"{name}\" and its remote have both changed since they were last synced; \
+                 syncing would overwrite the remote changes. Move the local changes aside and sync \
+                 to download the remote version, or sync with --force to overwrite it"
+            ),
+            NativeErrorKind::UnsupportedFormat { found, supported } => format!(
+                "The configuration was written in format version {found}, but this version of banyan only supports up to {supported}; upgrade banyan to use it"
+            ),
             NativeErrorKind::Custom(msg) => msg.to_owned(),
             NativeErrorKind::Cryptographic(err) => {
                 format!("{} {err}", "CRYPTOGRAPHIC ERROR:".underline())
@@ -103,6 +139,74 @@ impl NativeError {
         }
     }
 
+    /// No storage host is known for this Drive
+    pub fn missing_storage_host() -> Self {
+        Self {
+            kind: NativeErrorKind::MissingStorageHost,
+        }
+    }
+
+    /// A BlockStore is missing the root CID with this label
+    pub fn missing_root(label: &str) -> Self {
+        Self {
+            kind: NativeErrorKind::MissingRoot(label.to_string()),
+        }
+    }
+
+    /// The remote refused a request due to missing or bad credentials
+    pub fn unauthorized(msg: &str) -> Self {
+        Self {
+            kind: NativeErrorKind::Unauthorized(msg.to_owned()),
+        }
+    }
+
+    /// The local metadata does not match the remote metadata being operated on
+    pub fn not_synced() -> Self {
+        Self {
+            kind: NativeErrorKind::NotSynced,
+        }
+    }
+
+    /// Creating a Drive failed at one stage. `rolled_back` is None if no other stage had
+    /// completed, otherwise whether undoing the remote Drive succeeded.
+    pub fn creation_failed(stage: &str, cause: &str, rolled_back: Option<bool>) -> Self {
+        Self {
+            kind: NativeErrorKind::CreationFailed {
+                stage: stage.to_owned(),
+                cause: cause.to_owned(),
+                rolled_back,
+            },
+        }
+    }
+
+    /// The OS refused to open more files while at most `limit` files were being read
+    pub fn too_many_open_files(limit: usize) -> Self {
+        Self {
+            kind: NativeErrorKind::TooManyOpenFiles(limit),
+        }
+    }
+
+    /// The Drive has been locked against modification
+    pub fn read_only(name: &str) -> Self {
+        Self {
+            kind: NativeErrorKind::ReadOnly(name.to_owned()),
+        }
+    }
+
+    /// Local and remote changes to the Drive conflict
+    pub fn diverged(name: &str) -> Self {
+        Self {
+            kind: NativeErrorKind::Diverged(name.to_owned()),
+        }
+    }
+
+    /// Data on disk was written by a newer version than this one understands
+    pub fn unsupported_format(found: u32, supported: u32) -> Self {
+        Self {
+            kind: NativeErrorKind::UnsupportedFormat { found, supported },
+        }
+    }
+
     pub fn custom_error(msg: &str) -> Self {
         Self {
             kind: NativeErrorKind::Custom(msg.to_owned()),
@@ -160,6 +264,22 @@ enum NativeErrorKind {
     MissingRemoteDrive,
     UniqueDriveError,
     BadData,
+    MissingStorageHost,
+    MissingRoot(String),
+    Unauthorized(String),
+    NotSynced,
+    CreationFailed {
+        stage: String,
+        cause: String,
+        rolled_back: Option<bool>,
+    },
+    TooManyOpenFiles(usize),
+    ReadOnly(String),
+    Diverged(String),
+    UnsupportedFormat {
+        found: u32,
+        supported: u32,
+    },
     Custom(String),
     Cryptographic(TombCryptError),
     Filesystem(Box<FilesystemError>),
@@ -0,0 +1,127 @@
+use crate::native::file_scanning::spider_plans::{PreparePipelinePlan, SpiderMetadata};
+use std::{collections::HashMap, os::unix::fs::MetadataExt, path::PathBuf, sync::Arc};
+
+/// Device and inode of the file a path is a hard link to
+type Inode = (u64, u64);
+
+/// The file a spidered path is a hard link to, if other paths link to it as well
+fn inode(metadata: &SpiderMetadata) -> Option<Inode> {
+    let metadata = &metadata.original_metadata;
+    (metadata.is_file() && metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// Merge the FileGroups which contain hard links to the same file, so that its content is
+/// only written once and every link to it is found in one place. Links from outside the
+/// tree being spidered are never seen, so only the links within it end up grouped.
+pub fn merge_hardlinks(plans: Vec<PreparePipelinePlan>) -> Vec<PreparePipelinePlan> {
+    let mut merged = Vec::with_capacity(plans.len());
+    // Index into `merged` of the FileGroup holding the links to each file seen so far
+    let mut groups: HashMap<Inode, usize> = HashMap::new();
+    for plan in plans {
+        let PreparePipelinePlan::FileGroup(metadatas) = plan else {
+            merged.push(plan);
+            continue;
+        };
+        let inodes: Vec<Inode> = metadatas
+            .iter()
+            .filter_map(|metadata| inode(metadata))
+            .collect();
+        match inodes.iter().find_map(|inode| groups.get(inode).copied()) {
+            Some(index) => {
+                if let PreparePipelinePlan::FileGroup(existing) = &mut merged[index] {
+                    existing.extend(metadatas);
+                }
+                for inode in inodes {
+                    groups.insert(inode, index);
+                }
+            }
+            None => {
+                for inode in inodes {
+                    groups.insert(inode, merged.len());
+                }
+                merged.push(PreparePipelinePlan::FileGroup(metadatas));
+            }
+        }
+    }
+    merged
+}
+
+/// For each file of a FileGroup, the location of the first file in the group which is a hard
+/// link to the same file, or None if no other file in the group links to it
+pub fn hardlink_origins(metadatas: &[Arc<SpiderMetadata>]) -> Vec<Option<PathBuf>> {
+    let mut counts: HashMap<Inode, usize> = HashMap::new();
+    let mut origins: HashMap<Inode, &PathBuf> = HashMap::new();
+    for metadata in metadatas {
+        if let Some(inode) = inode(metadata) {
+            *counts.entry(inode).or_default() += 1;
+            origins.entry(inode).or_insert(&metadata.original_location);
+        }
+    }
+    metadatas
+        .iter()
+        .map(|metadata| {
+            inode(metadata)
+                .filter(|inode| counts[inode] > 1)
+                .map(|inode| origins[&inode].clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hardlink_origins, merge_hardlinks};
+    use crate::native::file_scanning::spider_plans::{PreparePipelinePlan, SpiderMetadata};
+    use std::{
+        fs::{self, hard_link},
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    fn spidered(root: &Path, name: &str) -> Arc<SpiderMetadata> {
+        let path = root.join(name);
+        Arc::new(SpiderMetadata {
+            original_location: PathBuf::from(name),
+            original_metadata: fs::metadata(&path).expect("missing test file"),
+            canonicalized_path: path,
+        })
+    }
+
+    #[test]
+    fn merge() {
+        let root = Path::new("test").join("hardlinks").join("merge");
+        let outside = root.with_file_name("merge-outside");
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_file(&outside);
+        fs::create_dir_all(&root).expect("could not create test dir");
+        fs::write(root.join("a"), "linked").expect("could not write test file");
+        hard_link(root.join("a"), root.join("b")).expect("could not link test file");
+        fs::write(root.join("c"), "linked").expect("could not write test file");
+        // Only one path in the tree links to this file
+        fs::write(root.join("d"), "partial").expect("could not write test file");
+        hard_link(root.join("d"), &outside).expect("could not link test file");
+
+        // c is a copy rather than a link, as found by the grouper
+        let plans = vec![
+            PreparePipelinePlan::FileGroup(vec![spidered(&root, "a"), spidered(&root, "c")]),
+            PreparePipelinePlan::FileGroup(vec![spidered(&root, "b")]),
+            PreparePipelinePlan::FileGroup(vec![spidered(&root, "d")]),
+        ];
+        let merged = merge_hardlinks(plans);
+        assert_eq!(merged.len(), 2);
+        let PreparePipelinePlan::FileGroup(group) = &merged[0] else {
+            panic!("expected a file group");
+        };
+        assert_eq!(group.len(), 3);
+        assert_eq!(
+            hardlink_origins(group),
+            vec![Some(PathBuf::from("a")), None, Some(PathBuf::from("a"))]
+        );
+        let PreparePipelinePlan::FileGroup(partial) = &merged[1] else {
+            panic!("expected a file group");
+        };
+        assert_eq!(hardlink_origins(partial), vec![None]);
+
+        fs::remove_dir_all(&root).expect("could not remove test dir");
+        fs::remove_file(&outside).expect("could not remove link");
+    }
+}
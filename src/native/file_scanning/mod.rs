@@ -1,9 +1,11 @@
 mod fclones_logger;
 mod grouper;
+mod hardlinks;
 mod spider;
 
 pub use fclones_logger::FClonesLogger;
 pub use grouper::grouper;
-pub use spider::spider;
+pub use hardlinks::{hardlink_origins, merge_hardlinks};
+pub use spider::{spider, LinkPolicy};
 /// Types related to Spidering
 pub mod spider_plans;
@@ -0,0 +1,131 @@
+//! Operational metrics, served in the Prometheus text format when the `metrics` feature is enabled
+use crate::native::NativeError;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// Number of blocks uploaded to storage hosts
+pub const BLOCKS_UPLOADED: &str = "banyan_blocks_uploaded_total";
+/// Number of bytes sent to or received from remotes, labeled by `direction`
+pub const BYTES_TRANSFERRED: &str = "banyan_bytes_transferred_total";
+/// Time taken by each Drive sync
+pub const SYNC_DURATION: &str = "banyan_sync_duration_seconds";
+/// Number of unsuccessful API responses, labeled by `status`
+pub const API_ERRORS: &str = "banyan_api_errors_total";
+
+/// Direction of a transfer
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    /// Sent to a remote
+    Upload,
+    /// Received from a remote
+    Download,
+}
+
+impl Direction {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+        }
+    }
+}
+
+/// Install the metrics recorder and serve `/metrics` on an address. Must be called from
+/// within a tokio runtime, and only once per process.
+pub fn serve(address: SocketAddr) -> Result<(), NativeError> {
+    PrometheusBuilder::new()
+        .with_http_listener(address)
+        .install()
+        .map_err(|err| NativeError::custom_error(&format!("unable to serve metrics: {err}")))
+}
+
+/// Count blocks uploaded to a storage host
+pub fn blocks_uploaded(count: u64) {
+    metrics::counter!(BLOCKS_UPLOADED).increment(count);
+}
+
+/// Count bytes transferred to or from a remote
+pub fn bytes_transferred(direction: Direction, bytes: u64) {
+    metrics::counter!(BYTES_TRANSFERRED, "direction" => direction.label()).increment(bytes);
+}
+
+/// Count an unsuccessful response from the API
+pub fn api_error(status: u16) {
+    metrics::counter!(API_ERRORS, "status" => status.to_string()).increment(1);
+}
+
+/// Record the time taken by a sync
+pub fn sync_duration(duration: Duration) {
+    metrics::histogram!(SYNC_DURATION).record(duration.as_secs_f64());
+}
+
+/// Records the duration of a sync when dropped, no matter how the sync returned
+#[derive(Debug)]
+pub struct SyncTimer {
+    started: Instant,
+}
+
+impl SyncTimer {
+    /// Start timing a sync
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for SyncTimer {
+    fn drop(&mut self) {
+        sync_duration(self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        blocks_uploaded, bytes_transferred, serve, Direction, SyncTimer, API_ERRORS,
+        BLOCKS_UPLOADED, BYTES_TRANSFERRED, SYNC_DURATION,
+    };
+    use crate::{
+        api::{client::Client, requests::core::buckets::read::ReadAllBuckets},
+        native::NativeError,
+        utils::testing::mock_server::{free_address, mock_server, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn scrape_after_sync() -> Result<(), NativeError> {
+        // Find a free port for the exporter
+        let address = free_address().await;
+        serve(address)?;
+
+        // Simulate a sync which uploads some blocks and then hits an API error
+        {
+            let _timer = SyncTimer::start();
+            blocks_uploaded(3);
+            bytes_transferred(Direction::Upload, 1024);
+            let failing = mock_server(|_| MockResponse::empty("500 Internal Server Error")).await;
+            let mut client = Client::new(&failing)?;
+            client.with_bearer_token("token".to_string());
+            assert!(client.call(ReadAllBuckets).await.is_err());
+        }
+
+        let body = reqwest::get(format!("http://{address}/metrics"))
+            .await
+            .map_err(|err| NativeError::custom_error(&err.to_string()))?
+            .text()
+            .await
+            .map_err(|err| NativeError::custom_error(&err.to_string()))?;
+        for name in [
+            BLOCKS_UPLOADED,
+            BYTES_TRANSFERRED,
+            SYNC_DURATION,
+            API_ERRORS,
+        ] {
+            assert!(body.contains(name), "{name} missing from:\n{body}");
+        }
+        Ok(())
+    }
+}
@@ -25,6 +25,9 @@ extern crate core;
 pub(crate) mod configuration;
 /// Scanning local filesystems
 pub(crate) mod file_scanning;
+/// Prometheus metrics
+#[cfg(feature = "metrics")]
+pub mod metrics;
 /// Operations which can be performed
 pub mod operations;
 pub(crate) mod sync;
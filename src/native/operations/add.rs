@@ -1,5 +1,5 @@
 use crate::{
-    filesystem::wnfsio::{compress_file, path_to_segments},
+    filesystem::wnfsio::{compress_file, path_to_segments, CompressionCodec},
     native::{configuration::globalconfig::GlobalConfig, sync::LocalBucket, NativeError},
 };
 use chrono::Utc;
@@ -12,6 +12,7 @@ pub async fn pipeline(
     input_file: &Path,
     wnfs_path: &Path,
 ) -> Result<String, NativeError> {
+    local.ensure_writable()?;
     // Global config
     let mut global = GlobalConfig::from_disk().await?;
     let wrapping_key = global.clone().wrapping_key().await?;
@@ -23,10 +24,11 @@ pub async fn pipeline(
     // Turn the relative path into a vector of segments
     let time = Utc::now();
     let mut rng = thread_rng();
+    let segments = fs.wnfs_segments(&path_to_segments(wnfs_path)?);
     let file = fs
         .root_dir
         .open_file_mut(
-            &path_to_segments(wnfs_path)?,
+            &segments,
             true,
             time,
             &mut fs.forest,
@@ -46,6 +48,8 @@ pub async fn pipeline(
     )
     .await
     .map_err(Box::from)?;
+    // Reads need to know to decompress the content again
+    CompressionCodec::Lz4.put(&mut file.content.metadata);
 
     // Store all the updated information, now that we've written the file
     local.save_fs(&mut fs).await?;
@@ -12,6 +12,25 @@ pub async fn init(name: &str, path: &Path) -> Result<(), NativeError> {
     global.get_or_init_bucket(name, path).await.map(|_| ())
 }
 
+/// Create a config for this user in passphrase mode, so that the wrapping key is derived from
+/// the passphrase rather than stored, and initialize a bucket locked with it. Passphrase mode can
+/// only be enabled before any other bucket is configured.
+pub async fn init_with_passphrase(
+    name: &str,
+    path: &Path,
+    passphrase: &str,
+) -> Result<(), NativeError> {
+    let mut global = match GlobalConfig::from_disk().await {
+        Ok(global) => global,
+        Err(_) => GlobalConfig::new().await?,
+    };
+    global.enable_passphrase()?;
+    global
+        .get_or_init_bucket_with_passphrase(name, path, passphrase)
+        .await
+        .map(|_| ())
+}
+
 /// Remove all configuration data for a given bucket
 pub async fn deinit(path: &Path) -> Result<(), NativeError> {
     if let Ok(mut global) = GlobalConfig::from_disk().await {
@@ -2,6 +2,8 @@
 pub mod add;
 /// This module contains configuration functions for the cli
 pub mod configure;
+/// This module contains the mv pipeline function, which is the main entry point for relocating nodes within existing WNFS filesystems.
+pub mod mv;
 /// This module contains the encryption pipeline function, which is the main entry point for bundling new data.
 pub mod prepare;
 /// This module contains the add pipeline function, which is the main entry point for removing from existing WNFS filesystems.
@@ -13,39 +15,60 @@ pub mod restore;
 #[cfg(feature = "cli")]
 mod test {
     use crate::{
+        api::{
+            client::{Client, Credentials},
+            models::bucket::BucketType,
+        },
+        blockstore::{BanyanBlockStore, CarV2MemoryBlockStore, RootedBlockStore},
         cli::specifiers::DriveSpecifier,
-        filesystem::wnfsio::{decompress_bytes, path_to_segments},
+        filesystem::{
+            hardlink,
+            wnfsio::{decompress_bytes, path_to_segments, CompressionCodec, DEFAULT_ZSTD_LEVEL},
+            FileAttributes,
+        },
         native::{
             configuration::globalconfig::GlobalConfig,
-            operations::{add, configure, prepare, remove, restore},
+            file_scanning::{spider_plans::PreparePipelinePlan, LinkPolicy},
+            operations::{add, configure, mv, prepare, remove, restore},
             sync::OmniBucket,
+            utils::ProgressTracker,
             NativeError,
         },
         utils::{
             compute_directory_size,
-            testing::local_operations::{test_setup, test_setup_structured, test_teardown},
+            testing::{
+                local_operations::{
+                    test_setup, test_setup_sparse, test_setup_structured, test_teardown,
+                },
+                mock_server::{free_address, mock_server, MockResponse},
+            },
             UtilityError,
         },
     };
 
     use dir_assert::assert_paths;
     use fake_file::{utils::ensure_path_exists_and_is_empty_dir, Strategy, Structure};
+    use filetime::FileTime;
     use fs_extra::dir;
     use serial_test::serial;
     use std::{
         fs::{
-            create_dir_all, read_link, remove_dir_all, remove_file, rename, symlink_metadata, File,
+            create_dir_all, hard_link, read_dir, read_link, remove_dir_all, remove_file, rename,
+            set_permissions, symlink_metadata, File, Permissions,
         },
-        io::Write,
-        os::unix::fs::symlink,
+        io::{Read, Write},
+        os::unix::fs::{symlink, MetadataExt, PermissionsExt},
         path::{Path, PathBuf},
     };
+    use tomb_crypt::prelude::{EcSignatureKey, PrivateKey};
+    use uuid::Uuid;
+    use wnfs::private::PrivateNode;
 
     /// Simplified Prepare call function
-    async fn prepare_pipeline(origin: &Path) -> Result<String, NativeError> {
+    async fn prepare_pipeline(origin: &Path) -> Result<prepare::PrepareStats, NativeError> {
         let name = origin.file_name().unwrap().to_string_lossy().to_string();
         let omni = OmniBucket::create(&name, origin).await?;
-        prepare::pipeline(omni, true).await
+        prepare::pipeline(omni, LinkPolicy::default(), 8, None, None).await
     }
 
     /// Simplified Restore call function
@@ -53,7 +76,14 @@ mod test {
         let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
         let tmp = origin.parent().unwrap().join("tmp");
         rename(origin, &tmp)?;
-        let result = restore::pipeline(omni).await;
+        let result = restore::pipeline(
+            omni,
+            false,
+            restore::DEFAULT_RESTORE_PARALLELISM,
+            restore::WriteStrategy::default(),
+            None,
+        )
+        .await;
         rename(origin, restored)?;
         rename(tmp, origin)?;
         result
@@ -113,6 +143,99 @@ mod test {
         test_teardown(test_name).await
     }
 
+    /// Everything recorded about a prepared Node which must not depend on how it was prepared
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct PreparedNode {
+        path: PathBuf,
+        is_dir: bool,
+        content: Option<Vec<u8>>,
+        mode: Option<u32>,
+        mtime: Option<i64>,
+        hardlink: Option<String>,
+        symlink: Option<String>,
+    }
+
+    /// Prepare an origin with a given parallelism and read back every Node
+    async fn prepared_tree(
+        origin: &Path,
+        parallelism: usize,
+    ) -> Result<Vec<PreparedNode>, UtilityError> {
+        configure::deinit(origin).await?;
+        let name = origin.file_name().unwrap().to_string_lossy().to_string();
+        let omni = OmniBucket::create(&name, origin).await?;
+        prepare::pipeline(omni, LinkPolicy::default(), parallelism, None, None).await?;
+        // Unlock the resulting FileSystem
+        let global = GlobalConfig::from_disk().await?;
+        let wrapping_key = global.wrapping_key().await?;
+        let local = global
+            .get_bucket(origin)
+            .expect("bucket config does not exist for this origin");
+        let fs = local.unlock_fs(&wrapping_key).await?;
+        // Read every Node
+        let mut nodes = Vec::new();
+        for (node, path) in fs.get_all_nodes(&local.metadata).await? {
+            let prepared = match node {
+                PrivateNode::Dir(_) => PreparedNode {
+                    path,
+                    is_dir: true,
+                    content: None,
+                    mode: None,
+                    mtime: None,
+                    hardlink: None,
+                    symlink: None,
+                },
+                PrivateNode::File(file) => {
+                    let attributes = FileAttributes::from_metadata(file.get_metadata());
+                    let symlink = file.symlink_origin().map(|origin| origin.to_string());
+                    let content = match symlink {
+                        Some(_) => None,
+                        None => Some(
+                            fs.read(&path_to_segments(&path)?, &local.metadata, &local.content)
+                                .await?,
+                        ),
+                    };
+                    PreparedNode {
+                        path,
+                        is_dir: false,
+                        content,
+                        mode: attributes.mode,
+                        mtime: attributes.mtime,
+                        hardlink: hardlink(file.get_metadata()).map(str::to_string),
+                        symlink,
+                    }
+                }
+            };
+            nodes.push(prepared);
+        }
+        nodes.sort();
+        Ok(nodes)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn prepare_parallel_matches_serial() -> Result<(), UtilityError> {
+        let test_name = "prepare_parallel_matches_serial";
+        let structure = Structure::new(4, 2, TEST_INPUT_SIZE, Strategy::Simple);
+        let origin = &test_setup_structured(test_name, structure).await?;
+        // Add a real tree of files of all sizes and kinds of content, some of them linked
+        dir::copy("src", origin, &dir::CopyOptions::new()).expect("fs_extra copy");
+        hard_link(origin.join("src").join("lib.rs"), origin.join("lib.rs"))?;
+        symlink("src/main.rs", origin.join("main.rs"))?;
+        set_permissions(
+            origin.join("src").join("main.rs"),
+            Permissions::from_mode(0o755),
+        )?;
+        // Prepare serially, then with many files in flight
+        let serial = prepared_tree(origin, 1).await?;
+        let parallel = prepared_tree(origin, 16).await?;
+        assert!(serial.iter().any(|node| node.hardlink.is_some()));
+        assert!(serial.iter().any(|node| node.symlink.is_some()));
+        assert!(serial.iter().any(|node| node.mode == Some(0o100755)));
+        assert_eq!(serial, parallel);
+        // Teardown
+        test_teardown(test_name).await
+    }
+
     #[tokio::test]
     #[serial]
     async fn restore() -> Result<(), UtilityError> {
@@ -137,6 +260,320 @@ mod test {
         test_teardown(test_name).await
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn create_with_type_defaults() -> Result<(), UtilityError> {
+        let test_name = "create_with_type_defaults";
+        let origin = &test_setup(test_name).await?;
+        configure::deinit(origin).await?;
+        // Backup Drives compress their content
+        let omni =
+            OmniBucket::create_with_type(test_name, origin, BucketType::Backup, false).await?;
+        let local = omni.get_local()?;
+        assert_eq!(local.bucket_type, BucketType::Backup);
+        assert_eq!(
+            local.compression,
+            CompressionCodec::Zstd {
+                level: DEFAULT_ZSTD_LEVEL
+            }
+        );
+        // Interactive Drives keep storing it as is
+        configure::deinit(origin).await?;
+        let omni = OmniBucket::create(test_name, origin).await?;
+        let local = omni.get_local()?;
+        assert_eq!(local.bucket_type, BucketType::Interactive);
+        assert_eq!(local.compression, CompressionCodec::None);
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn create_remote_failure_rolls_back() -> Result<(), UtilityError> {
+        let test_name = "create_remote_failure_rolls_back";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        configure::deinit(origin).await?;
+
+        // Serve an error for every request
+        let url = mock_server(|_| MockResponse::empty("500 Internal Server Error")).await;
+
+        // Authenticate against the failing server
+        let mut global = GlobalConfig::from_disk().await?;
+        let mut client = Client::new(&url).expect("client");
+        client.with_credentials(Credentials {
+            user_id: Uuid::new_v4(),
+            signing_key: EcSignatureKey::generate().await.expect("signing key"),
+        });
+        global.save_client(client).await?;
+
+        // Creation fails without leaving a local Drive behind
+        let err = OmniBucket::create(test_name, origin)
+            .await
+            .expect_err("remote creation should fail");
+        assert!(err.to_string().contains("remotely"));
+        assert!(GlobalConfig::from_disk()
+            .await?
+            .get_bucket(origin)
+            .is_none());
+
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn create_remote_unreachable_local_only() -> Result<(), UtilityError> {
+        let test_name = "create_remote_unreachable_local_only";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        configure::deinit(origin).await?;
+
+        // Find a port nothing is listening on
+        let address = free_address().await;
+
+        // Authenticate against the unreachable server
+        let mut global = GlobalConfig::from_disk().await?;
+        let mut client = Client::new(&format!("http://{address}")).expect("client");
+        client.with_credentials(Credentials {
+            user_id: Uuid::new_v4(),
+            signing_key: EcSignatureKey::generate().await.expect("signing key"),
+        });
+        global.save_client(client).await?;
+
+        // Creation settles for a local only Drive
+        let omni = OmniBucket::create(test_name, origin).await?;
+        assert!(omni.get_local().is_ok());
+        assert!(omni.get_remote().is_err());
+        let local = GlobalConfig::from_disk()
+            .await?
+            .get_bucket(origin)
+            .expect("no local drive");
+        assert!(local.remote_id.is_none());
+
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fetch_subdirectory() -> Result<(), UtilityError> {
+        let test_name = "fetch_subdirectory";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        // Initialize
+        configure::init(test_name, origin).await?;
+        // Prepare locally
+        prepare_pipeline(origin).await?;
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let fs = omni.unlock().await?;
+        let local = omni.get_local()?;
+
+        // Pick a subdirectory and one of its siblings
+        let mut dirs = read_dir(origin)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| PathBuf::from(entry.file_name()));
+        let subdir = dirs.next().expect("no subdirectory");
+        let sibling = dirs.next().expect("no sibling");
+        let cids = fs.get_content_cids(&subdir, &local.metadata).await?;
+        let sibling_cids = fs.get_content_cids(&sibling, &local.metadata).await?;
+        let sibling_only: Vec<_> = sibling_cids.difference(&cids).collect();
+        assert!(!cids.is_empty());
+        assert!(!sibling_only.is_empty());
+
+        // Pull only the subdirectory's blocks into an empty store
+        let destination = CarV2MemoryBlockStore::new().map_err(NativeError::from)?;
+        let fetched = restore::fetch_blocks(&cids, &local.content, &destination).await?;
+        assert_eq!(fetched, cids.len());
+        for cid in &cids {
+            assert!(BanyanBlockStore::get_block(&destination, cid).await.is_ok());
+        }
+        // None of the sibling's content was fetched
+        for cid in sibling_only {
+            assert!(BanyanBlockStore::get_block(&destination, cid)
+                .await
+                .is_err());
+        }
+        // Fetching again finds everything already present
+        assert_eq!(
+            restore::fetch_blocks(&cids, &local.content, &destination).await?,
+            0
+        );
+
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn resume_restore() -> Result<(), UtilityError> {
+        let test_name = "resume_restore";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        // Initialize
+        configure::init(test_name, origin).await?;
+        // Prepare locally
+        prepare_pipeline(origin).await?;
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let fs = omni.unlock().await?;
+        let local = omni.get_local()?;
+        let all_nodes = fs.get_all_nodes(&local.metadata).await?;
+        let file_count = all_nodes.len();
+        assert!(file_count > 2);
+        // Create a new dir to restore in
+        let restored_dir = origin
+            .parent()
+            .expect("origin has no parent")
+            .join(format!("{}_restored", test_name));
+        if restored_dir.exists() {
+            remove_dir_all(&restored_dir)?;
+        }
+        create_dir_all(&restored_dir)?;
+
+        // Interrupt the first run after half of the files
+        let half = file_count / 2;
+        let mut checkpoint = restore::RestoreCheckpoint::open(&restored_dir, false)?;
+        let first_half = all_nodes.iter().take(half).cloned().collect::<Vec<_>>();
+        let first_run = restore::restore_nodes(
+            &fs,
+            first_half.clone(),
+            restored_dir.clone(),
+            &local.metadata,
+            &local.content,
+            &mut checkpoint,
+            restore::DEFAULT_RESTORE_PARALLELISM,
+            restore::WriteStrategy::default(),
+            &ProgressTracker::default(),
+        )
+        .await?;
+        assert_eq!(first_run, half);
+        drop(checkpoint);
+
+        // Damage one of the restored files so the checkpoint can no longer be trusted for it
+        let (_, damaged) = &first_half[0];
+        File::create(restored_dir.join(damaged))?.write_all(b"x")?;
+
+        // Resume, which only writes the remaining files and the damaged one
+        let mut checkpoint = restore::RestoreCheckpoint::open(&restored_dir, true)?;
+        assert_eq!(checkpoint.completed(), half);
+        let second_run = restore::restore_nodes(
+            &fs,
+            all_nodes,
+            restored_dir.clone(),
+            &local.metadata,
+            &local.content,
+            &mut checkpoint,
+            restore::DEFAULT_RESTORE_PARALLELISM,
+            restore::WriteStrategy::default(),
+            &ProgressTracker::default(),
+        )
+        .await?;
+        assert_eq!(second_run, file_count - half + 1);
+        checkpoint.finish()?;
+        assert!(!restore::RestoreCheckpoint::path_for(&restored_dir).exists());
+        // Assert the pre-prepared and restored directories are identical
+        assert_paths(origin, &restored_dir).expect("restored dir does not match origin");
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn export_tar() -> Result<(), UtilityError> {
+        let test_name = "export_tar";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        // Initialize
+        configure::init(test_name, origin).await?;
+        // Prepare locally
+        prepare_pipeline(origin).await?;
+        // Export the Drive to an in memory tar archive
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let archive_bytes = restore::to_tar(omni, Vec::new()).await?;
+        // Every file and directory beneath the origin has an entry
+        let expected_count = jwalk::WalkDir::new(origin).into_iter().count() - 1;
+        let mut archive = tar::Archive::new(archive_bytes.as_slice());
+        let mut entry_count = 0;
+        let mut checked_file = false;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            entry_count += 1;
+            // Compare the content of the first regular file against the original
+            if !checked_file && entry.header().entry_type() == tar::EntryType::Regular {
+                let path = entry.path()?.to_path_buf();
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content)?;
+                assert_eq!(content, std::fs::read(origin.join(path))?);
+                checked_file = true;
+            }
+        }
+        assert_eq!(entry_count, expected_count);
+        assert!(checked_file);
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn cat_file() -> Result<(), UtilityError> {
+        let test_name = "cat_file";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        // Initialize
+        configure::init(test_name, origin).await?;
+        // Prepare locally
+        prepare_pipeline(origin).await?;
+        // Pick any regular file beneath the origin
+        let file_path = jwalk::WalkDir::new(origin)
+            .into_iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_type().is_file())
+            .expect("no files in origin")
+            .path();
+        let relative = file_path.strip_prefix(origin).unwrap();
+        // Write its content into memory
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let mut content = Vec::new();
+        let written = restore::cat(omni, relative, &mut content).await?;
+        assert_eq!(content, std::fs::read(&file_path)?);
+        assert_eq!(written, content.len() as u64);
+        // Directories can not be written out
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        if !parent.as_os_str().is_empty() {
+            assert!(restore::cat(omni, parent, Vec::new()).await.is_err());
+        }
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn read_only() -> Result<(), UtilityError> {
+        let test_name = "read_only";
+        let origin = &test_setup(test_name).await?;
+        prepare_pipeline(origin).await?;
+        let input_file = &origin.join("hello.txt");
+        File::create(input_file)?.write_all(b"This is just example text.")?;
+
+        // Lock the Drive
+        let mut omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        omni.set_read_only(true).await?;
+        let global = GlobalConfig::from_disk().await?;
+        let err = add::pipeline(global.get_bucket(origin).unwrap(), input_file, input_file)
+            .await
+            .expect_err("added a file to a read-only Drive");
+        assert!(err.to_string().contains("read-only"));
+
+        // Unlock it again
+        omni.set_read_only(false).await?;
+        let global = GlobalConfig::from_disk().await?;
+        add::pipeline(global.get_bucket(origin).unwrap(), input_file, input_file).await?;
+
+        test_teardown(test_name).await
+    }
+
     #[tokio::test]
     #[serial]
     async fn add() -> Result<(), UtilityError> {
@@ -245,6 +682,98 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn remove_many() -> Result<(), UtilityError> {
+        let test_name = "remove_many";
+        let origin = &test_setup(test_name).await?;
+        configure::init(test_name, origin).await?;
+        prepare_pipeline(origin).await?;
+        let wnfs_paths = ["0/0", "0/1", "1/0", "missing"].map(PathBuf::from);
+        let global = GlobalConfig::from_disk().await?;
+        let local = global.get_bucket(origin).expect("no bucket at origin");
+        let metadata_root = local.metadata.get_root();
+
+        // Remove everything in one call, reporting the path that was never there
+        let missing = remove::pipeline_many(local, &wnfs_paths).await?;
+        assert_eq!(missing, vec![PathBuf::from("missing")]);
+
+        let global = GlobalConfig::from_disk().await?;
+        let wrapping_key = global.clone().wrapping_key().await?;
+        let local = global.get_bucket(origin).expect("no bucket at origin");
+        // The filesystem was saved exactly once, directly over the prepared version
+        assert_eq!(local.previous_cid, metadata_root);
+        assert_ne!(local.metadata.get_root(), metadata_root);
+        let fs = local.unlock_fs(&wrapping_key).await?;
+        for wnfs_path in &wnfs_paths {
+            let segments = fs.wnfs_segments(&path_to_segments(wnfs_path)?);
+            let node = fs
+                .root_dir
+                .get_node(&segments, true, &fs.forest, &local.metadata)
+                .await
+                .map_err(Box::from)?;
+            assert!(node.is_none());
+        }
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn move_nodes() -> Result<(), UtilityError> {
+        let test_name = "move_nodes";
+        let origin = &test_setup(test_name).await?;
+        configure::init(test_name, origin).await?;
+        prepare_pipeline(origin).await?;
+        let local = |global: &GlobalConfig| global.get_bucket(origin).expect("no bucket at origin");
+        let global = GlobalConfig::from_disk().await?;
+        let wrapping_key = global.clone().wrapping_key().await?;
+        let fs = local(&global).unlock_fs(&wrapping_key).await?;
+        let original = fs
+            .read(
+                &path_to_segments(Path::new("0/0"))?,
+                &local(&global).metadata,
+                &local(&global).content,
+            )
+            .await?;
+
+        // Directories move along with their subtree
+        mv::pipeline(local(&global), Path::new("0"), Path::new("2"), false).await?;
+        // Nothing can be moved inside of itself
+        assert!(
+            mv::pipeline(local(&global), Path::new("2"), Path::new("2/3"), false)
+                .await
+                .is_err()
+        );
+        // Existing nodes are only replaced when asked to
+        let global = GlobalConfig::from_disk().await?;
+        assert!(
+            mv::pipeline(local(&global), Path::new("1/0"), Path::new("2/1"), false)
+                .await
+                .is_err()
+        );
+        mv::pipeline(local(&global), Path::new("1/0"), Path::new("2/1"), true).await?;
+
+        let global = GlobalConfig::from_disk().await?;
+        let local = local(&global);
+        let fs = local.unlock_fs(&wrapping_key).await?;
+        for (path, exists) in [("0", false), ("1/0", false), ("2/0", true), ("2/1", true)] {
+            let node = fs
+                .get_node(&path_to_segments(Path::new(path))?, &local.metadata)
+                .await?;
+            assert_eq!(node.is_some(), exists, "{path}");
+        }
+        // Content is still reachable from its new location
+        let moved = fs
+            .read(
+                &path_to_segments(Path::new("2/0"))?,
+                &local.metadata,
+                &local.content,
+            )
+            .await?;
+        assert_eq!(moved, original);
+        test_teardown(test_name).await
+    }
+
     // Helper function for structure tests
     async fn assert_prepare_restore(test_name: &str) -> Result<(), UtilityError> {
         // Grab directories
@@ -272,6 +801,44 @@ mod test {
 
     const TEST_INPUT_SIZE: usize = 1024;
 
+    #[tokio::test]
+    #[serial]
+    #[cfg(target_os = "linux")]
+    async fn prepare_low_file_limit() -> Result<(), UtilityError> {
+        let test_name = "prepare_low_file_limit";
+        // More files than there will be file descriptors
+        let structure = Structure::new(4, 4, TEST_INPUT_SIZE, Strategy::Simple);
+        let origin = &test_setup_structured(test_name, structure).await?;
+        configure::deinit(origin).await?;
+        let omni = OmniBucket::create(test_name, origin).await?;
+
+        // Lower the soft limit on open files for the duration of the preparation
+        let mut original = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: getrlimit and setrlimit only read and write the structs they are given
+        assert_eq!(
+            unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut original) },
+            0
+        );
+        let lowered = libc::rlimit {
+            rlim_cur: 128.min(original.rlim_cur),
+            rlim_max: original.rlim_max,
+        };
+        assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &lowered) }, 0);
+        // Ask for far more concurrency than there are file descriptors
+        let result = prepare::pipeline(omni, LinkPolicy::default(), 256, None, None).await;
+        assert_eq!(
+            unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &original) },
+            0
+        );
+        result?;
+
+        // Teardown
+        test_teardown(test_name).await
+    }
+
     #[tokio::test]
     #[serial]
     async fn structure_simple() -> Result<(), UtilityError> {
@@ -312,6 +879,156 @@ mod test {
         test_teardown(test_name).await
     }
 
+    /// Peak resident memory of the largest child process waited for so far, in bytes
+    fn peak_child_memory() -> u64 {
+        // SAFETY: `rusage` only holds integers, for which all zeroes is a valid value
+        let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+        // SAFETY: `usage` is a valid `rusage` which nothing else borrows during the call
+        assert_eq!(
+            unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) },
+            0
+        );
+        // Linux reports kilobytes, macOS reports bytes
+        if cfg!(target_os = "macos") {
+            usage.ru_maxrss as u64
+        } else {
+            usage.ru_maxrss as u64 * 1024
+        }
+    }
+
+    /// Size of the sparse file `prepare_sparse_file` prepares, overridden to try out much
+    /// larger files
+    fn streaming_size() -> u64 {
+        std::env::var("BANYAN_TEST_STREAMING_SIZE")
+            .ok()
+            .and_then(|size| size.parse::<u64>().ok())
+            .unwrap_or(prepare::STREAMING_THRESHOLD * 4)
+    }
+
+    /// Prepare a large sparse file. Run on its own by `streaming_big_file`, so that the peak
+    /// memory of its process is that of preparing the file alone.
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn prepare_sparse_file() -> Result<(), UtilityError> {
+        let test_name = "prepare_sparse_file";
+        let size = streaming_size();
+        let origin = &test_setup_sparse(test_name, size).await?;
+        prepare_pipeline(origin).await?;
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let fs = omni.unlock().await?;
+        let local = omni.get_local()?;
+        let stat = fs
+            .stat(&["sparse".to_string()], false, &local.metadata)
+            .await?;
+        assert_eq!(stat.size, size);
+        test_teardown(test_name).await
+    }
+
+    #[test]
+    #[serial]
+    fn streaming_big_file() -> Result<(), UtilityError> {
+        let size = streaming_size();
+        let status = std::process::Command::new(std::env::current_exe()?)
+            .args([
+                "--exact",
+                "native::operations::test::prepare_sparse_file",
+                "--ignored",
+            ])
+            .env("BANYAN_TEST_STREAMING_SIZE", size.to_string())
+            .status()?;
+        assert!(status.success());
+        // The file was never held in memory, no matter how large it is
+        let peak = peak_child_memory();
+        assert!(
+            peak < prepare::STREAMING_THRESHOLD * 2,
+            "peak memory reached {peak} bytes preparing a {size} byte file"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn fsync_restore() -> Result<(), UtilityError> {
+        let test_name = "fsync_restore";
+        let origin = &test_setup(test_name).await?;
+        configure::init(test_name, origin).await?;
+        prepare_pipeline(origin).await?;
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let fs = omni.unlock().await?;
+        let local = omni.get_local()?;
+        let restored_dir = origin
+            .parent()
+            .expect("origin has no parent")
+            .join(format!("{}_restored", test_name));
+        if restored_dir.exists() {
+            remove_dir_all(&restored_dir)?;
+        }
+        create_dir_all(&restored_dir)?;
+
+        // Use a tiny buffer so that content passes through many flushes
+        let strategy = restore::WriteStrategy {
+            buffer_size: 16,
+            fsync: true,
+        };
+        let mut checkpoint = restore::RestoreCheckpoint::open(&restored_dir, false)?;
+        restore::restore_nodes(
+            &fs,
+            fs.get_all_nodes(&local.metadata).await?,
+            restored_dir.clone(),
+            &local.metadata,
+            &local.content,
+            &mut checkpoint,
+            restore::DEFAULT_RESTORE_PARALLELISM,
+            strategy,
+            &ProgressTracker::default(),
+        )
+        .await?;
+        checkpoint.finish()?;
+        assert_paths(origin, &restored_dir).expect("restored dir does not match origin");
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn parallel_restore_matches_serial() -> Result<(), UtilityError> {
+        let test_name = "parallel_restore_matches_serial";
+        let structure = Structure::new(1, 1, 1024 * 1024 * 10, Strategy::Simple);
+        let origin = &test_setup_structured(test_name, structure).await?;
+        configure::init(test_name, origin).await?;
+        prepare_pipeline(origin).await?;
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        let fs = omni.unlock().await?;
+        let local = omni.get_local()?;
+
+        for (node, path) in fs.get_all_nodes(&local.metadata).await? {
+            if let PrivateNode::File(file) = node {
+                let serial = fs
+                    .read(&path_to_segments(&path)?, &local.metadata, &local.content)
+                    .await?;
+                for parallelism in [1, 8] {
+                    let mut parallel = Vec::new();
+                    let progress = ProgressTracker::default();
+                    let size = restore::write_content(
+                        &fs,
+                        &file,
+                        &local.metadata,
+                        &local.content,
+                        parallelism,
+                        &mut parallel,
+                        &progress,
+                    )
+                    .await?;
+                    assert_eq!(size, serial.len() as u64);
+                    assert_eq!(progress.progress().bytes_done, size);
+                    assert!(parallel == serial, "parallel restore differs from serial");
+                }
+            }
+        }
+
+        test_teardown(test_name).await
+    }
+
     /// Ensure that the pipeline can recover duplicate files
     #[tokio::test]
     #[serial]
@@ -339,6 +1056,43 @@ mod test {
         test_teardown(test_name).await
     }
 
+    /// Ensure that the pipeline reports how much content it deduplicated
+    #[tokio::test]
+    #[serial]
+    async fn deduplication_stats() -> Result<(), UtilityError> {
+        let test_name = "deduplication_stats";
+        // Setup the test
+        let origin = &test_setup(test_name).await?;
+        // Nothing is duplicated yet
+        let stats = prepare_pipeline(origin).await?;
+        assert!(stats.unique_blocks > 0);
+        assert_eq!(stats.duplicate_blocks, 0);
+        assert_eq!(stats.bytes_saved, 0);
+        configure::deinit(origin).await?;
+
+        // Duplicate every file
+        let dup_origin = &origin.parent().expect("origin has no parent").join("dups");
+        let original = &dup_origin.join("original");
+        let duplicate = &dup_origin.join("duplicate");
+        create_dir_all(original)?;
+        create_dir_all(duplicate)?;
+        dir::move_dir(origin, original, &dir::CopyOptions::new()).expect("fs_extra move_dir");
+        dir::copy(original, duplicate, &dir::CopyOptions::new()).expect("fs_extra copy");
+        dir::remove(origin).expect("fs_extra remove");
+        rename(dup_origin, origin)?;
+
+        // The copies are not stored again
+        let dup_stats = prepare_pipeline(origin).await?;
+        assert_eq!(dup_stats.unique_blocks, stats.unique_blocks);
+        assert_eq!(dup_stats.duplicate_blocks, stats.unique_blocks);
+        assert!(dup_stats.bytes_saved > 0);
+        assert_eq!(
+            dup_stats.total_blocks,
+            dup_stats.unique_blocks + dup_stats.duplicate_blocks
+        );
+        test_teardown(test_name).await
+    }
+
     // / Ensure that the duplicate data occupies a smaller footprint when prepared
     //TODO (organizedgrime) - This test is a bit longer than I would like, might modify it to be more modular / reusable
     #[tokio::test]
@@ -425,6 +1179,36 @@ mod test {
         test_teardown(test_name).await
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn plans_are_ordered() -> Result<(), UtilityError> {
+        let test_name = "plans_are_ordered";
+        let origin = &test_setup(test_name).await?;
+        let locations = |plans: Vec<PreparePipelinePlan>| {
+            plans
+                .into_iter()
+                .map(|plan| match plan {
+                    PreparePipelinePlan::FileGroup(metadatas) => metadatas
+                        .iter()
+                        .map(|metadata| metadata.original_location.clone())
+                        .collect::<Vec<PathBuf>>(),
+                    PreparePipelinePlan::Directory(metadata)
+                    | PreparePipelinePlan::Symlink(metadata, _) => {
+                        vec![metadata.original_location.clone()]
+                    }
+                })
+                .collect::<Vec<Vec<PathBuf>>>()
+        };
+        // Planning the same filesystem twice yields the same plans in the same order
+        let first = locations(prepare::create_plans(origin, LinkPolicy::default()).await?);
+        let second = locations(prepare::create_plans(origin, LinkPolicy::default()).await?);
+        assert_eq!(first, second);
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+        test_teardown(test_name).await
+    }
+
     #[tokio::test]
     #[serial]
     async fn block_tracking() -> Result<(), UtilityError> {
@@ -719,10 +1503,173 @@ mod test {
         assert_eq!(dir_original, read_link(dir_sym)?);
         assert_eq!(file_original, read_link(file_sym)?);
 
+        // An executable script with a fixed modification time
+        let script = origin.join("script.sh");
+        File::create(&script)?.write_all(b"#!/bin/sh\necho kitty\n")?;
+        set_permissions(&script, Permissions::from_mode(0o750))?;
+        let mtime = FileTime::from_unix_time(1_000_000_000, 123_456_789);
+        filetime::set_file_mtime(&script, mtime)?;
+
         // Run the test on the created filesystem
         assert_prepare_restore(test_name).await?;
 
+        // The script can still be run and looks as old as it was
+        let restored_script = origin
+            .parent()
+            .expect("origin has no parent")
+            .join("restored")
+            .join("script.sh");
+        let metadata = symlink_metadata(restored_script)?;
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+        assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+
         // Teardown
         test_teardown(test_name).await
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn hardlinks() -> Result<(), UtilityError> {
+        let test_name = "hardlinks";
+        let origin = &test_setup(test_name).await?;
+        let parent = origin.parent().expect("origin has no parent");
+        // Three links to the same file, in different directories
+        create_dir_all(origin.join("linked"))?;
+        File::create(origin.join("linked").join("kitty.txt"))?.write_all(b"Hello Kitty!")?;
+        hard_link(
+            origin.join("linked").join("kitty.txt"),
+            origin.join("linked").join("cat.txt"),
+        )?;
+        hard_link(
+            origin.join("linked").join("kitty.txt"),
+            origin.join("kitten.txt"),
+        )?;
+        // A file with its only other link outside of the Drive
+        File::create(origin.join("puppy.txt"))?.write_all(b"Hello Puppy!")?;
+        hard_link(origin.join("puppy.txt"), parent.join("outside.txt"))?;
+
+        prepare_pipeline(origin).await?;
+        let restored = parent.join("restored");
+        restore_pipeline(origin, &restored).await?;
+        assert_paths(origin, &restored).expect("restored dir does not match origin");
+
+        // The links within the Drive are links again, and nothing else joined them
+        let kitty = symlink_metadata(restored.join("linked").join("kitty.txt"))?;
+        assert_eq!(kitty.nlink(), 3);
+        for link in [
+            restored.join("linked").join("cat.txt"),
+            restored.join("kitten.txt"),
+        ] {
+            assert_eq!(symlink_metadata(link)?.ino(), kitty.ino());
+        }
+        assert_eq!(symlink_metadata(restored.join("puppy.txt"))?.nlink(), 1);
+
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn restore_path() -> Result<(), UtilityError> {
+        let test_name = "restore_path";
+        let origin = &test_setup(test_name).await?;
+        create_dir_all(origin.join("wanted").join("nested"))?;
+        File::create(origin.join("wanted").join("nested").join("kitty.txt"))?
+            .write_all(b"Hello Kitty!")?;
+        File::create(origin.join("unwanted.txt"))?.write_all(b"Hello Puppy!")?;
+        prepare_pipeline(origin).await?;
+
+        let target = origin
+            .parent()
+            .expect("origin has no parent")
+            .join("target");
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        restore::pipeline_path(omni, &target, Path::new("wanted")).await?;
+        // Only the requested directory was restored, where it sits within the Drive
+        assert_paths(origin.join("wanted"), target.join("wanted"))
+            .expect("restored dir does not match origin");
+        assert!(!target.join("unwanted.txt").exists());
+
+        // As can a single file
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        restore::pipeline_path(omni, &target, Path::new("unwanted.txt")).await?;
+        assert_eq!(std::fs::read(target.join("unwanted.txt"))?, b"Hello Puppy!");
+
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn incremental_restore() -> Result<(), UtilityError> {
+        let test_name = "incremental_restore";
+        let origin = &test_setup(test_name).await?;
+        File::create(origin.join("kept.txt"))?.write_all(b"unchanged")?;
+        File::create(origin.join("changed.txt"))?.write_all(b"original")?;
+        prepare_pipeline(origin).await?;
+
+        let target = origin
+            .parent()
+            .expect("origin has no parent")
+            .join("target");
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        restore::pipeline_incremental(omni, &target).await?;
+        assert_paths(origin, &target).expect("restored dir does not match origin");
+
+        // Mark a file which should be left alone, and damage one without changing its size
+        let marker = FileTime::from_unix_time(500, 0);
+        filetime::set_file_mtime(target.join("kept.txt"), marker)?;
+        File::create(target.join("changed.txt"))?.write_all(b"damaged!")?;
+
+        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+        restore::pipeline_incremental(omni, &target).await?;
+        assert_paths(origin, &target).expect("restored dir does not match origin");
+        // The unchanged file was never rewritten
+        let kept = symlink_metadata(target.join("kept.txt"))?;
+        assert_eq!(FileTime::from_last_modification_time(&kept), marker);
+
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn escaping_symlinks() -> Result<(), UtilityError> {
+        let test_name = "escaping_symlinks";
+        let origin = &test_setup(test_name).await?;
+
+        // Point from /input/ZZ to a file outside of the input
+        let outside = origin.parent().unwrap().join("outside.txt");
+        File::create(&outside)?.write_all(b"not part of the drive")?;
+        symlink(outside.canonicalize()?, origin.join("ZZ"))?;
+
+        // Find the plan made for the link
+        let link_plan = |plans: Vec<PreparePipelinePlan>| {
+            plans.into_iter().find(|plan| match plan {
+                PreparePipelinePlan::FileGroup(metadatas) => metadatas
+                    .iter()
+                    .any(|metadata| metadata.original_location == Path::new("ZZ")),
+                PreparePipelinePlan::Directory(metadata)
+                | PreparePipelinePlan::Symlink(metadata, _) => {
+                    metadata.original_location == Path::new("ZZ")
+                }
+            })
+        };
+
+        // The link is preserved as a link by default, and when only following links within the root
+        for links in [LinkPolicy::default(), LinkPolicy::FollowWithinRoot] {
+            let plans = prepare::create_plans(origin, links).await?;
+            assert!(matches!(
+                link_plan(plans),
+                Some(PreparePipelinePlan::Symlink(_, _))
+            ));
+        }
+        // Its target is only included when following links unsafely
+        let plans = prepare::create_plans(origin, LinkPolicy::FollowUnsafe).await?;
+        match link_plan(plans) {
+            Some(PreparePipelinePlan::FileGroup(metadatas)) => {
+                assert_eq!(metadatas[0].canonicalized_path, outside.canonicalize()?)
+            }
+            plan => panic!("escaping link was not followed: {plan:?}"),
+        }
+
+        test_teardown(test_name).await
+    }
 }
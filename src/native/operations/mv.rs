@@ -0,0 +1,56 @@
+use crate::{
+    filesystem::{wnfsio::path_to_segments, FilesystemError},
+    native::{configuration::globalconfig::GlobalConfig, sync::LocalBucket, NativeError},
+};
+use std::path::Path;
+
+/// The pipeline for moving a file or directory, along with everything beneath it, to a new
+/// path within a WNFS. Content is relinked rather than rewritten, so nothing is re-chunked.
+/// Moving onto an existing node fails unless `overwrite` is set, in which case it is replaced.
+pub async fn pipeline(
+    mut local: LocalBucket,
+    from_wnfs_path: &Path,
+    to_wnfs_path: &Path,
+    overwrite: bool,
+) -> Result<(), NativeError> {
+    local.ensure_writable()?;
+    // Global config
+    let mut global = GlobalConfig::from_disk().await?;
+    let wrapping_key = global.clone().wrapping_key().await?;
+
+    let mut fs = local.unlock_fs(&wrapping_key).await?;
+    let from_segments = path_to_segments(from_wnfs_path)?;
+    let to_segments = path_to_segments(to_wnfs_path)?;
+    // A directory can not become its own descendant
+    if to_segments.starts_with(&from_segments) {
+        return Err(FilesystemError::move_into_self(&from_segments.join("/")).into());
+    }
+    if fs
+        .get_node(&from_segments, &local.metadata)
+        .await?
+        .is_none()
+    {
+        return Err(FilesystemError::node_not_found(&from_segments.join("/")).into());
+    }
+    if fs.get_node(&to_segments, &local.metadata).await?.is_some() {
+        if !overwrite {
+            return Err(FilesystemError::node_exists(&to_segments.join("/")).into());
+        }
+        // Make room for the node being moved
+        fs.rm(&to_segments, &local.metadata).await?;
+    }
+    fs.mv(
+        &from_segments,
+        &to_segments,
+        &local.metadata,
+        &local.content,
+    )
+    .await?;
+
+    // Store all the updated information, now that we've moved the node
+    local.save_fs(&mut fs).await?;
+
+    // Update global
+    global.update_config(&local)?;
+    Ok(())
+}
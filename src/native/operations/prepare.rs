@@ -1,22 +1,151 @@
 use crate::{
     api::models::metadata::Metadata,
-    blockstore::{BanyanApiBlockStore, DoubleSplitStore, RootedBlockStore},
-    filesystem::{wnfsio::path_to_segments, FsMetadata},
+    blockstore::{
+        BanyanApiBlockStore, BanyanBlockStore, BlockStoreError, DoubleSplitStore, RootedBlockStore,
+    },
+    car::v1::Block,
+    filesystem::{
+        wnfsio::{path_to_segments, CompressionCodec},
+        FileAttributes, FileRecord, FsMetadata,
+    },
     native::{
         configuration::globalconfig::GlobalConfig,
-        file_scanning::{grouper, spider, spider_plans::PreparePipelinePlan},
+        file_scanning::{
+            grouper, hardlink_origins, merge_hardlinks, spider, spider_plans::PreparePipelinePlan,
+            LinkPolicy,
+        },
         sync::OmniBucket,
-        utils::get_progress_bar,
+        utils::{default_max_open_files, get_progress, ProgressCallback, ProgressTracker},
         NativeError,
     },
+    LibipldError,
 };
+use async_trait::async_trait;
+use futures::future::try_join_all;
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     collections::HashSet,
-    fs::File,
-    io::Read,
+    fmt::Display,
+    ops::AddAssign,
     path::{Path, PathBuf},
 };
-use wnfs::private::PrivateNode;
+use tokio::sync::Semaphore;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+use wnfs::{
+    libipld::{Cid, IpldCodec},
+    private::PrivateNode,
+};
+
+/// Files larger than this are streamed into the FileSystem rather than read into memory first
+pub const STREAMING_THRESHOLD: u64 = 1024 * 1024 * 64;
+
+/// How much content deduplication happened during a preparation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrepareStats {
+    /// Number of content blocks written, including those which were already stored
+    pub total_blocks: u64,
+    /// Number of content blocks which had to be stored
+    pub unique_blocks: u64,
+    /// Number of content blocks which were already stored, or belonged to a duplicate file
+    pub duplicate_blocks: u64,
+    /// Number of content bytes which did not need to be stored again
+    pub bytes_saved: u64,
+}
+
+impl AddAssign for PrepareStats {
+    fn add_assign(&mut self, other: Self) {
+        self.total_blocks += other.total_blocks;
+        self.unique_blocks += other.unique_blocks;
+        self.duplicate_blocks += other.duplicate_blocks;
+        self.bytes_saved += other.bytes_saved;
+    }
+}
+
+impl Display for PrepareStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} blocks prepared: {} unique, {} duplicate, {} bytes saved",
+            self.total_blocks, self.unique_blocks, self.duplicate_blocks, self.bytes_saved
+        )
+    }
+}
+
+/// Content BlockStore which skips and counts blocks it already holds
+struct DeduplicatingStore<'a, S: RootedBlockStore> {
+    store: &'a S,
+    stats: RefCell<PrepareStats>,
+}
+
+impl<'a, S: RootedBlockStore> DeduplicatingStore<'a, S> {
+    fn new(store: &'a S) -> Self {
+        Self {
+            store,
+            stats: RefCell::new(PrepareStats::default()),
+        }
+    }
+
+    fn stats(&self) -> PrepareStats {
+        *self.stats.borrow()
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: RootedBlockStore> BanyanBlockStore for DeduplicatingStore<'_, S> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        BanyanBlockStore::get_block(self.store, cid).await
+    }
+
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        let block = Block::new(bytes, codec).map_err(BlockStoreError::car)?;
+        let present = BanyanBlockStore::get_block(self.store, &block.cid)
+            .await
+            .is_ok();
+        let mut stats = self.stats.borrow_mut();
+        stats.total_blocks += 1;
+        if present {
+            stats.duplicate_blocks += 1;
+            stats.bytes_saved += block.content.len() as u64;
+            Ok(block.cid)
+        } else {
+            stats.unique_blocks += 1;
+            drop(stats);
+            BanyanBlockStore::put_block(self.store, block.content, codec).await
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: RootedBlockStore> RootedBlockStore for DeduplicatingStore<'_, S> {
+    fn get_root(&self) -> Option<Cid> {
+        self.store.get_root()
+    }
+
+    fn set_root(&self, root: &Cid) -> Result<(), BlockStoreError> {
+        self.store.set_root(root)
+    }
+
+    async fn all_cids(&self) -> Vec<Cid> {
+        self.store.all_cids().await
+    }
+}
+
+#[async_trait(?Send)]
+impl<S: RootedBlockStore> wnfs::common::BlockStore for DeduplicatingStore<'_, S> {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
 /// Given the input directory, the output directory, the manifest file, and other metadata,
 /// prepare the input directory into the output directory and store a record of how this
 /// operation was performed in the manifest file.
@@ -26,13 +155,29 @@ use wnfs::private::PrivateNode;
 /// * `fs` - FileSystem to modify
 /// * `omni` - Context aware online / offline Drive
 /// * `client` - Means of connecting to the server if need be
-/// * `follow_links` - Whether or not to follow symlinks when bundling.
+/// * `links` - Whether or not to follow symlinks when bundling, and whether they may
+///   lead outside of the Drive's origin.
+/// * `parallelism` - How many files to read from disk concurrently. Their content is still
+///   encrypted and written one file at a time.
+/// * `max_open_files` - How many files may be open at once. Defaults to a limit derived from
+///   the soft limit on open file descriptors.
+/// * `progress` - Notified as files are walked and their content is written.
 ///
 /// # Return Type
-/// Returns `Ok(())` on success, otherwise returns an error.
-pub async fn pipeline(mut omni: OmniBucket, follow_links: bool) -> Result<String, NativeError> {
-    let mut fs = omni.unlock().await?;
+/// Returns how much of the prepared content was deduplicated on success, otherwise returns
+/// an error.
+pub async fn pipeline(
+    mut omni: OmniBucket,
+    links: LinkPolicy,
+    parallelism: usize,
+    max_open_files: Option<usize>,
+    progress: Option<ProgressCallback>,
+) -> Result<PrepareStats, NativeError> {
+    let progress = ProgressTracker::new(progress);
+    let max_open_files = max_open_files.unwrap_or_else(default_max_open_files);
     let mut local = omni.get_local()?;
+    local.ensure_writable()?;
+    let mut fs = omni.unlock().await?;
     let mut global = GlobalConfig::from_disk().await?;
     let mut client = global.get_client().await?;
 
@@ -53,7 +198,9 @@ pub async fn pipeline(mut omni: OmniBucket, follow_links: bool) -> Result<String
     }
 
     // Create bundling plan
-    let bundling_plan = create_plans(&local.origin, follow_links).await?;
+    let bundling_plan = create_plans(&local.origin, links).await?;
+    let (files_total, bytes_total) = plan_totals(&bundling_plan);
+    progress.start(files_total, bytes_total);
 
     // Get all the paths present on disk
     let mut all_disk_paths = <Vec<PathBuf>>::new();
@@ -94,33 +241,55 @@ pub async fn pipeline(mut omni: OmniBucket, follow_links: bool) -> Result<String
         }
     }
 
-    let split_store_local = DoubleSplitStore::new(&local.content, &local.metadata);
+    let content_store = DeduplicatingStore::new(&local.content);
+    let split_store_local = DoubleSplitStore::new(&content_store, &local.metadata);
 
+    let mut stats = PrepareStats::default();
     // If we're online, let's also spin up a BanyanApiBlockStore for getting content
     if let Ok(client) = GlobalConfig::from_disk().await?.get_client().await {
         let api_blockstore = BanyanApiBlockStore::from(client);
         let split_store_remote = DoubleSplitStore::new(&split_store_local, &api_blockstore);
         info!("Using online server as backup to check for file differences...");
-        process_plans(&mut fs, bundling_plan, &local.metadata, &split_store_remote).await?;
+        stats += process_plans(
+            &mut fs,
+            bundling_plan,
+            &local.metadata,
+            &split_store_remote,
+            parallelism,
+            max_open_files,
+            local.compression,
+            &progress,
+        )
+        .await?;
     } else {
         warn!("We notice you're offline or unauthenticated, preparing may fail to detect content changes and require repreparation of old files.");
-        process_plans(&mut fs, bundling_plan, &local.metadata, &split_store_local).await?;
+        stats += process_plans(
+            &mut fs,
+            bundling_plan,
+            &local.metadata,
+            &split_store_local,
+            parallelism,
+            max_open_files,
+            local.compression,
+            &progress,
+        )
+        .await?;
     }
 
+    // Blocks the content store already held were skipped
+    stats += content_store.stats();
+
     local.save_fs(&mut fs).await?;
     global.update_config(&local)?;
     omni.set_local(local);
 
-    Ok(format!(
-        "Prepared data successfully; Encrypted in {}",
-        omni.get_local()?.content.path.display()
-    ))
+    Ok(stats)
 }
 
 /// Create PreparePipelinePlans from an origin dir
 pub async fn create_plans(
     origin: &Path,
-    follow_links: bool,
+    links: LinkPolicy,
 ) -> Result<Vec<PreparePipelinePlan>, NativeError> {
     // HashSet to track files that have already been seen
     let mut seen_files: HashSet<PathBuf> = HashSet::new();
@@ -128,21 +297,32 @@ pub async fn create_plans(
     let mut bundling_plan: Vec<PreparePipelinePlan> = vec![];
 
     info!("🔍 Deduplicating the filesystem at {}", origin.display());
-    // Group the filesystem provided to detect duplicates
-    let group_plans = grouper(origin, follow_links, &mut seen_files)?;
+    // Group the filesystem provided to detect duplicates. Links are never followed here,
+    // the spider follows them once it has checked where they lead.
+    let group_plans = grouper(origin, false, &mut seen_files)?;
     // Extend the bundling plan
     bundling_plan.extend(group_plans);
 
-    // TODO fix setting follow_links / do it right
     info!(
         "📁 Finding directories and symlinks to back up starting at {}",
         origin.display()
     );
 
     // Spider the filesystem provided to include directories and symlinks
-    let spidered_files = spider(origin, follow_links, &mut seen_files).await?;
+    let spidered_files = spider(origin, links, &mut seen_files).await?;
     // Extend the bundling plan
     bundling_plan.extend(spidered_files);
+    // Hard links to the same file are stored as copies of a single file
+    let mut bundling_plan = merge_hardlinks(bundling_plan);
+
+    // Both walks run in parallel, so put the plans in an order which only depends on the
+    // filesystem being prepared
+    for plan in bundling_plan.iter_mut() {
+        if let PreparePipelinePlan::FileGroup(metadatas) = plan {
+            metadatas.sort_by(|a, b| a.original_location.cmp(&b.original_location));
+        }
+    }
+    bundling_plan.sort_by(|a, b| plan_location(a).cmp(plan_location(b)));
 
     info!(
         "💾 Total number of files to prepare: {}",
@@ -152,15 +332,43 @@ pub async fn create_plans(
     Ok(bundling_plan)
 }
 
-/// Given a set of PreparePipelinePlans and required structs, process each
+/// Location in the Drive of the first node a plan describes
+fn plan_location(plan: &PreparePipelinePlan) -> &Path {
+    match plan {
+        PreparePipelinePlan::FileGroup(metadatas) => metadatas
+            .first()
+            .map(|metadata| metadata.original_location.as_path())
+            .unwrap_or(Path::new("")),
+        PreparePipelinePlan::Directory(metadata) | PreparePipelinePlan::Symlink(metadata, _) => {
+            &metadata.original_location
+        }
+    }
+}
+
+/// Given a set of PreparePipelinePlans and required structs, process each.
+/// File content is read from disk `parallelism` files at a time, with no more than
+/// `max_open_files` open at once, while mutations of the FileSystem remain serialized so
+/// that the result is identical to a serial preparation. Content is encrypted and its blocks
+/// are written as the FileSystem is mutated, so those are serialized too. Files larger than
+/// [`STREAMING_THRESHOLD`] are instead streamed from disk when they are written. Content read
+/// into memory is compressed with `compression`, while streamed content is stored as is.
+/// Returns the blocks of the duplicate files which were linked instead of written.
+#[allow(clippy::too_many_arguments)]
 pub async fn process_plans(
     fs: &mut FsMetadata,
     bundling_plan: Vec<PreparePipelinePlan>,
     metadata_store: &impl RootedBlockStore,
     content_store: &impl RootedBlockStore,
-) -> Result<(), NativeError> {
+    parallelism: usize,
+    max_open_files: usize,
+    compression: CompressionCodec,
+    progress: &ProgressTracker,
+) -> Result<PrepareStats, NativeError> {
+    let mut stats = PrepareStats::default();
+    // Pool of file handles shared by every concurrent read
+    let file_handles = Semaphore::new(max_open_files.max(1));
     // Initialize the progress bar using the number of Nodes to process
-    let progress_bar = get_progress_bar(bundling_plan.len() as u64);
+    let progress_bar = get_progress(bundling_plan.len() as u64);
     // Create vectors of direct and indirect plans
     let mut direct_plans: Vec<PreparePipelinePlan> = Vec::new();
     let mut symlink_plans: Vec<PreparePipelinePlan> = Vec::new();
@@ -177,56 +385,142 @@ pub async fn process_plans(
         }
     }
 
-    // First, write data which corresponds to real data
-    for direct_plan in direct_plans {
-        match direct_plan {
-            PreparePipelinePlan::FileGroup(metadatas) => {
-                // Grab the metadata for the first occurrence of this file
-                let first = &metadatas
-                    .first()
-                    .expect("no metadatas present")
-                    .original_location;
-                // Turn the relative path into a vector of segments
-                let path_segments = path_to_segments(first)?;
-                // Load the file from disk
-                let mut file =
-                    File::open(&metadatas.first().expect("no paths").canonicalized_path)?;
-                let mut content = <Vec<u8>>::new();
-                file.read_to_end(&mut content)?;
-                // Add the file contents
-                fs.write(&path_segments, metadata_store, content_store, content)
-                    .await?;
-
-                // Duplicates need to be linked no matter what
-                for meta in &metadatas[1..] {
-                    // Grab the original location
-                    let dup_path_segments = path_to_segments(&meta.original_location)?;
-                    if fs
-                        .get_node(&dup_path_segments, metadata_store)
-                        .await?
-                        .is_none()
-                    {
-                        // Copy
-                        fs.cp(&path_segments, &dup_path_segments, metadata_store)
-                            .await?;
+    // First, write data which corresponds to real data, one batch at a time
+    for batch in direct_plans.chunks(parallelism.max(1)) {
+        // Load the content of every file in this batch concurrently
+        let file_handles = &file_handles;
+        let contents = try_join_all(batch.iter().map(|plan| async move {
+            match plan {
+                PreparePipelinePlan::FileGroup(metadatas) => {
+                    let first = metadatas.first().expect("no paths");
+                    // Leave large files to be streamed
+                    if first.original_metadata.len() > STREAMING_THRESHOLD {
+                        return Ok(None);
                     }
+                    let path = &first.canonicalized_path;
+                    let _handle = file_handles
+                        .acquire()
+                        .await
+                        .expect("file handle pool closed");
+                    tokio::fs::read(path)
+                        .await
+                        .map(Some)
+                        .map_err(|err| read_error(err, max_open_files))
                 }
+                _ => Ok(None),
             }
-            // If this is a directory or symlink
-            PreparePipelinePlan::Directory(meta) => {
-                // Turn the canonicalized path into a vector of segments
-                let path_segments = path_to_segments(&meta.original_location)?;
-                // If the directory does not exist
-                if fs.get_node(&path_segments, metadata_store).await.is_err() {
-                    // Create the subdirectory
-                    fs.mkdir(&path_segments, metadata_store).await?;
+        }))
+        .await?;
+
+        for (direct_plan, content) in batch.iter().zip(contents) {
+            match direct_plan {
+                PreparePipelinePlan::FileGroup(metadatas) => {
+                    // Grab the metadata for the first occurrence of this file
+                    let first = metadatas.first().expect("no metadatas present");
+                    // Turn the relative path into a vector of segments
+                    let path_segments = path_to_segments(&first.original_location)?;
+                    // Remember which of them were hard links to each other, so that restores
+                    // can link them together again
+                    let origins = hardlink_origins(metadatas)
+                        .into_iter()
+                        .map(|origin| {
+                            origin
+                                .as_deref()
+                                .map(path_to_segments)
+                                .transpose()
+                                .map(|segments| segments.map(|segments| segments.join("/")))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let record = |index: usize, metadata: &std::fs::Metadata| FileRecord {
+                        attributes: FileAttributes::from(metadata),
+                        hardlink: origins[index].as_deref(),
+                    };
+                    // Add the file contents
+                    if let Some(content) = content {
+                        fs.write_compressed(
+                            &path_segments,
+                            metadata_store,
+                            content_store,
+                            content,
+                            compression,
+                            Some(record(0, &first.original_metadata)),
+                        )
+                        .await?;
+                    } else {
+                        let _handle = file_handles
+                            .acquire()
+                            .await
+                            .expect("file handle pool closed");
+                        let file = tokio::fs::File::open(&first.canonicalized_path)
+                            .await
+                            .map_err(|err| read_error(err, max_open_files))?;
+                        let size = file.metadata().await?.len();
+                        fs.write_stream(
+                            &path_segments,
+                            metadata_store,
+                            content_store,
+                            file.compat(),
+                            size,
+                            Some(record(0, &first.original_metadata)),
+                        )
+                        .await?;
+                    }
+
+                    // Duplicates need to be linked no matter what
+                    for (index, meta) in metadatas.iter().enumerate().skip(1) {
+                        // Grab the original location
+                        let dup_path_segments = path_to_segments(&meta.original_location)?;
+                        if fs
+                            .get_node(&dup_path_segments, metadata_store)
+                            .await?
+                            .is_none()
+                        {
+                            // Copy
+                            fs.cp(&path_segments, &dup_path_segments, metadata_store)
+                                .await?;
+                            if let Some(PrivateNode::File(file)) =
+                                fs.get_node(&path_segments, metadata_store).await?
+                            {
+                                let blocks = file
+                                    .get_cids(&fs.forest, metadata_store)
+                                    .await
+                                    .map_err(Box::from)?
+                                    .len() as u64;
+                                stats += PrepareStats {
+                                    total_blocks: blocks,
+                                    unique_blocks: 0,
+                                    duplicate_blocks: blocks,
+                                    bytes_saved: first.original_metadata.len(),
+                                };
+                            }
+                        }
+                        // The copy carries the record of the original until it is given its own
+                        fs.set_file_record(
+                            &dup_path_segments,
+                            metadata_store,
+                            content_store,
+                            record(index, &meta.original_metadata),
+                        )
+                        .await?;
+                    }
+                    progress.advance(metadatas.len() as u64, first.original_metadata.len());
                 }
+                // If this is a directory or symlink
+                PreparePipelinePlan::Directory(meta) => {
+                    // Turn the canonicalized path into a vector of segments
+                    let path_segments = path_to_segments(&meta.original_location)?;
+                    // If the directory does not exist
+                    if fs.get_node(&path_segments, metadata_store).await.is_err() {
+                        // Create the subdirectory
+                        fs.mkdir(&path_segments, metadata_store).await?;
+                    }
+                }
+                PreparePipelinePlan::Symlink(_, _) => panic!("this is unreachable code"),
             }
-            PreparePipelinePlan::Symlink(_, _) => panic!("this is unreachable code"),
-        }
 
-        // Denote progress for each loop iteration
-        progress_bar.inc(1);
+            // Denote progress for each loop iteration
+            progress_bar.inc(1);
+        }
     }
 
     // Now that the data exists, we can symlink to it
@@ -238,6 +532,7 @@ pub async fn process_plans(
                 // Symlink it
                 fs.symlink(&symlink_target, &symlink_segments, metadata_store)
                     .await?;
+                progress.advance(1, 0);
             }
             PreparePipelinePlan::Directory(_) | PreparePipelinePlan::FileGroup(_) => {
                 panic!("this is unreachable code")
@@ -248,6 +543,32 @@ pub async fn process_plans(
         progress_bar.inc(1);
     }
 
-    // Return Ok
-    Ok(())
+    Ok(stats)
+}
+
+/// Number of files in a set of plans, and the number of bytes of content to write for them.
+/// Duplicates are counted as files, but their content is only written once.
+fn plan_totals(plans: &[PreparePipelinePlan]) -> (u64, u64) {
+    plans
+        .iter()
+        .fold((0, 0), |(files, bytes), plan| match plan {
+            PreparePipelinePlan::FileGroup(metadatas) => (
+                files + metadatas.len() as u64,
+                bytes
+                    + metadatas
+                        .first()
+                        .map_or(0, |first| first.original_metadata.len()),
+            ),
+            PreparePipelinePlan::Symlink(_, _) => (files + 1, bytes),
+            PreparePipelinePlan::Directory(_) => (files, bytes),
+        })
+}
+
+/// Explain running out of file descriptors, which is otherwise an opaque IO error
+fn read_error(err: std::io::Error, max_open_files: usize) -> NativeError {
+    if err.raw_os_error() == Some(libc::EMFILE) {
+        NativeError::too_many_open_files(max_open_files)
+    } else {
+        NativeError::io(err)
+    }
 }
@@ -2,23 +2,20 @@ use crate::{
     filesystem::wnfsio::path_to_segments,
     native::{configuration::globalconfig::GlobalConfig, sync::LocalBucket, NativeError},
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// The pipeline for removing an individual file from a WNFS
 pub async fn pipeline(mut local: LocalBucket, wnfs_path: &Path) -> Result<(), NativeError> {
+    local.ensure_writable()?;
     // Global config
     let mut global = GlobalConfig::from_disk().await?;
     let wrapping_key = global.clone().wrapping_key().await?;
 
     let mut fs = local.unlock_fs(&wrapping_key).await?;
     // Attempt to remove the node
+    let segments = fs.wnfs_segments(&path_to_segments(wnfs_path)?);
     fs.root_dir
-        .rm(
-            &path_to_segments(wnfs_path)?,
-            true,
-            &fs.forest,
-            &local.metadata,
-        )
+        .rm(&segments, true, &fs.forest, &local.metadata)
         .await
         .map_err(Box::from)?;
 
@@ -29,3 +26,43 @@ pub async fn pipeline(mut local: LocalBucket, wnfs_path: &Path) -> Result<(), Na
     global.update_config(&local)?;
     Ok(())
 }
+
+/// The pipeline for removing many files from a WNFS at once. The filesystem is unlocked and
+/// saved only once, no matter how many paths are removed. Returns the paths which did not exist.
+pub async fn pipeline_many(
+    mut local: LocalBucket,
+    wnfs_paths: &[PathBuf],
+) -> Result<Vec<PathBuf>, NativeError> {
+    local.ensure_writable()?;
+    // Global config
+    let mut global = GlobalConfig::from_disk().await?;
+    let wrapping_key = global.clone().wrapping_key().await?;
+
+    let mut fs = local.unlock_fs(&wrapping_key).await?;
+    let mut missing = Vec::new();
+    for wnfs_path in wnfs_paths {
+        let segments = fs.wnfs_segments(&path_to_segments(wnfs_path)?);
+        // Note the node if it does not exist rather than failing the whole batch
+        let exists = fs
+            .root_dir
+            .get_node(&segments, true, &fs.forest, &local.metadata)
+            .await
+            .map_err(Box::from)?
+            .is_some();
+        if !exists {
+            missing.push(wnfs_path.clone());
+            continue;
+        }
+        fs.root_dir
+            .rm(&segments, true, &fs.forest, &local.metadata)
+            .await
+            .map_err(Box::from)?;
+    }
+
+    // Only store the updated information if something was actually removed
+    if missing.len() < wnfs_paths.len() {
+        local.save_fs(&mut fs).await?;
+        global.update_config(&local)?;
+    }
+    Ok(missing)
+}
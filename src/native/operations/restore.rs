@@ -1,37 +1,288 @@
 use crate::{
-    blockstore::{BanyanApiBlockStore, DoubleSplitStore, RootedBlockStore},
-    filesystem::{wnfsio::path_to_segments, FsMetadata},
+    blockstore::{
+        BanyanApiBlockStore, BanyanBlockStore, DoubleSplitStore, ReadRepairStore, RootedBlockStore,
+    },
+    filesystem::{
+        content_hash, hardlink,
+        wnfsio::{path_to_segments, CompressionCodec},
+        FileAttributes, FilesystemError, FsMetadata,
+    },
     native::{
-        configuration::globalconfig::GlobalConfig, sync::OmniBucket, utils::get_progress_bar,
+        configuration::globalconfig::GlobalConfig,
+        sync::{LocalBucket, OmniBucket},
+        utils::{get_progress, ProgressCallback, ProgressTracker},
         NativeError,
     },
 };
-use std::{fs::File, io::Write, os::unix::fs::symlink, path::PathBuf};
-use wnfs::private::PrivateNode;
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs::{
+        hard_link, remove_file, set_permissions, symlink_metadata, File, OpenOptions, Permissions,
+    },
+    io::{empty, BufRead, BufReader, BufWriter, Write},
+    os::unix::fs::{symlink, PermissionsExt},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use tar::{Builder, EntryType, Header};
+use tokio::io::AsyncReadExt;
+use wnfs::{
+    common::Metadata,
+    libipld::{Cid, Ipld, IpldCodec},
+    private::{PrivateFile, PrivateNode},
+};
+
+/// Number of blocks of a file decrypted at once when none is specified
+pub const DEFAULT_RESTORE_PARALLELISM: usize = 8;
+/// Capacity of the buffer in front of each restored file when none is specified (256 KiB)
+pub const DEFAULT_WRITE_BUFFER_SIZE: usize = 256 * 1024;
+/// Number of bytes written to a file between fsyncs, when fsyncing (64 MiB)
+const FSYNC_INTERVAL: u64 = 64 * 1024 * 1024;
+/// Number of bytes of file content read at once while restoring (64 KiB)
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Number of missing blocks fetched from the server per batch before restoring
+const PREFETCH_BATCH_SIZE: usize = 256;
+/// Bits of a Unix mode which are permissions rather than the type of file
+const PERMISSION_BITS: u32 = 0o7777;
+
+/// How restored files are written to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteStrategy {
+    /// Capacity of the buffer in front of each restored file
+    pub buffer_size: usize,
+    /// Whether to fsync periodically while writing a file, and once it is complete
+    pub fsync: bool,
+}
+
+impl Default for WriteStrategy {
+    fn default() -> Self {
+        Self {
+            buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            fsync: false,
+        }
+    }
+}
+
+/// Buffered writer over a restored file which fsyncs according to a WriteStrategy
+#[derive(Debug)]
+struct RestoredFile {
+    writer: BufWriter<File>,
+    fsync: bool,
+    /// Bytes written since the file was last synced
+    unsynced: u64,
+}
+
+impl RestoredFile {
+    fn create(path: &Path, strategy: WriteStrategy) -> Result<Self, NativeError> {
+        Ok(Self {
+            writer: BufWriter::with_capacity(strategy.buffer_size, File::create(path)?),
+            fsync: strategy.fsync,
+            unsynced: 0,
+        })
+    }
+
+    /// Flush all buffered content, syncing it to disk if requested
+    fn finish(mut self) -> Result<(), NativeError> {
+        self.writer.flush()?;
+        if self.fsync {
+            self.writer.get_ref().sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RestoredFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.unsynced += written as u64;
+        if self.fsync && self.unsynced >= FSYNC_INTERVAL {
+            self.writer.flush()?;
+            self.writer.get_ref().sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
 
 /// Given the manifest file and a destination for our restored data, run the restoring pipeline
 /// on the data referenced in the manifest.
 ///
 /// # Arguments
 ///
-/// * `fs` - FileSystem to modify
 /// * `omni` - Context aware online / offline Drive
-/// * `client` - Means of connecting to the server if need be
+/// * `resume` - Skip files already restored by a previous, interrupted run
+/// * `parallelism` - Maximum number of blocks of a file decrypted at once
+/// * `strategy` - How restored files are written to disk
+/// * `progress` - Notified as files and their content are written out
+///
+/// # Return Type
+/// Returns `Ok(())` on success, otherwise returns an error.
+pub async fn pipeline(
+    omni: OmniBucket,
+    resume: bool,
+    parallelism: usize,
+    strategy: WriteStrategy,
+    progress: Option<ProgressCallback>,
+) -> Result<String, NativeError> {
+    subtree(
+        omni,
+        Path::new(""),
+        None,
+        resume,
+        parallelism,
+        strategy,
+        progress,
+    )
+    .await
+}
+
+/// Run the restoring pipeline on only the part of a Drive at or below a path.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+/// * `path` - Path of the subtree within the Drive
+/// * `target` - Directory to restore into, created if it does not exist. Defaults to the origin
+///   of the Drive.
+/// * `resume` - Skip files already restored by a previous, interrupted run
+/// * `parallelism` - Maximum number of blocks of a file decrypted at once
+/// * `strategy` - How restored files are written to disk
+/// * `progress` - Notified as files and their content are written out
+///
+/// # Return Type
+/// Returns `Ok(())` on success, otherwise returns an error.
+pub async fn subtree(
+    mut omni: OmniBucket,
+    path: &Path,
+    target: Option<&Path>,
+    resume: bool,
+    parallelism: usize,
+    strategy: WriteStrategy,
+    progress: Option<ProgressCallback>,
+) -> Result<String, NativeError> {
+    let restored = match target {
+        Some(target) => {
+            std::fs::create_dir_all(target)?;
+            target.to_path_buf()
+        }
+        None => omni.get_or_init_origin().await?,
+    };
+    let options = RestoreOptions {
+        resume,
+        parallelism,
+        strategy,
+        skip_unchanged: false,
+    };
+    restore_to(omni, path, restored, options, progress).await
+}
+
+/// Restore only the file or directory at `wnfs_path` within a Drive into `target`, rather than
+/// the whole Drive, using the default options of [`subtree`]. Only the content blocks of files in
+/// that subtree are fetched from the server.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+/// * `target` - Directory to restore into, created if it does not exist
+/// * `wnfs_path` - Path of the file or directory within the Drive
+///
+/// # Return Type
+/// Returns `Ok(())` on success, otherwise returns an error.
+pub async fn pipeline_path(
+    omni: OmniBucket,
+    target: &Path,
+    wnfs_path: &Path,
+) -> Result<String, NativeError> {
+    subtree(
+        omni,
+        wnfs_path,
+        Some(target),
+        false,
+        DEFAULT_RESTORE_PARALLELISM,
+        WriteStrategy::default(),
+        None,
+    )
+    .await
+}
+
+/// Restore a Drive into `target`, only writing the files whose content differs from what is
+/// already there. Files are compared using the BLAKE3 hash of their content recorded when they
+/// were prepared, so files prepared before hashes were recorded are always written.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+/// * `target` - Directory holding a previous version of the Drive, created if it does not exist
 ///
 /// # Return Type
 /// Returns `Ok(())` on success, otherwise returns an error.
-pub async fn pipeline(mut omni: OmniBucket) -> Result<String, NativeError> {
+pub async fn pipeline_incremental(omni: OmniBucket, target: &Path) -> Result<String, NativeError> {
+    std::fs::create_dir_all(target)?;
+    let options = RestoreOptions {
+        resume: false,
+        parallelism: DEFAULT_RESTORE_PARALLELISM,
+        strategy: WriteStrategy::default(),
+        skip_unchanged: true,
+    };
+    restore_to(omni, Path::new(""), target.to_path_buf(), options, None).await
+}
+
+/// How a restore is run
+#[derive(Debug, Clone, Copy)]
+struct RestoreOptions {
+    /// Skip files already restored by a previous, interrupted run
+    resume: bool,
+    /// Maximum number of blocks of a file decrypted at once
+    parallelism: usize,
+    /// How restored files are written to disk
+    strategy: WriteStrategy,
+    /// Leave files whose content already matches the Drive alone
+    skip_unchanged: bool,
+}
+
+/// Restore the part of a Drive at or below `path` into the `restored` directory
+async fn restore_to(
+    omni: OmniBucket,
+    path: &Path,
+    restored: PathBuf,
+    options: RestoreOptions,
+    progress: Option<ProgressCallback>,
+) -> Result<String, NativeError> {
+    let RestoreOptions {
+        resume,
+        parallelism,
+        strategy,
+        skip_unchanged,
+    } = options;
+    let progress = ProgressTracker::new(progress);
     let fs = omni.unlock().await?;
     let local = omni.get_local()?;
     let mut global = GlobalConfig::from_disk().await?;
     let mut client = global.get_client().await?;
     // Announce that we're starting
     info!("🚀 Starting restoration pipeline...");
-    let restored = omni.get_or_init_origin().await?;
+    let mut checkpoint = RestoreCheckpoint::open(&restored, resume)?;
+    if checkpoint.completed() > 0 {
+        info!(
+            "⏩ Resuming restoration, {} files were already restored",
+            checkpoint.completed()
+        );
+    }
 
     let metadata_store = &local.metadata;
-    // Get all the nodes in the FileSystem
-    let all_nodes = fs.get_all_nodes(metadata_store).await?;
+    // Get all the nodes in the subtree
+    let mut all_nodes = fs.get_nodes_under(path, metadata_store).await?;
+    if skip_unchanged {
+        let before = all_nodes.len();
+        all_nodes.retain(|(node, path)| !is_unchanged(node, &restored.join(path)));
+        info!("⏭️ Skipping {} unchanged files", before - all_nodes.len());
+    }
     info!(
         "🔐 Restoring all {} files to {}",
         all_nodes.len(),
@@ -39,30 +290,246 @@ pub async fn pipeline(mut omni: OmniBucket) -> Result<String, NativeError> {
     );
 
     if client.is_authenticated().await {
-        let api_store = BanyanApiBlockStore::from(client.to_owned());
-        let split_store = DoubleSplitStore::new(&local.content, &api_store);
+        let api_store = global.get_cache(BanyanApiBlockStore::from(client.to_owned()))?;
+        // Fetch the content missing on disk in batches rather than a block at a time
+        let missing = missing_content(&fs, &all_nodes, &restored, &checkpoint, &local).await?;
+        if !missing.is_empty() {
+            info!("Prefetching {} blocks from the server...", missing.len());
+            match api_store.prefetch(&missing, PREFETCH_BATCH_SIZE).await {
+                Ok(prefetched) => info!("Prefetched {prefetched} blocks"),
+                Err(err) => warn!("Failed to prefetch blocks, fetching them as needed: {err}"),
+            }
+        }
+        // Corrupt local blocks are repaired from the server as they are read
+        let repair_store = ReadRepairStore::new(&local.content, &api_store);
         info!("Using online server as backup to grab file content...");
-        restore_nodes(&fs, all_nodes, restored, metadata_store, &split_store).await?;
+        restore_nodes(
+            &fs,
+            all_nodes,
+            restored,
+            metadata_store,
+            &repair_store,
+            &mut checkpoint,
+            parallelism,
+            strategy,
+            &progress,
+        )
+        .await?;
     } else {
         warn!("We notice you're offline or unauthenticated, reconstructing may fail if encrypted data is not already present on disk.");
-        restore_nodes(&fs, all_nodes, restored, metadata_store, &local.content).await?;
+        restore_nodes(
+            &fs,
+            all_nodes,
+            restored,
+            metadata_store,
+            &local.content,
+            &mut checkpoint,
+            parallelism,
+            strategy,
+            &progress,
+        )
+        .await?;
     }
+    // Everything has been restored, so there is nothing left to resume
+    checkpoint.finish()?;
 
     global.update_config(&local)?;
 
     Ok("🎉 Data has been successfully reconstructed!".to_string())
 }
 
-/// Restore all nodes
+/// Whether or not a file on disk already holds exactly the content of a Node
+fn is_unchanged(node: &PrivateNode, on_disk: &Path) -> bool {
+    let PrivateNode::File(file) = node else {
+        return false;
+    };
+    let metadata = file.get_metadata();
+    let Some(expected) = content_hash(metadata) else {
+        return false;
+    };
+    // Symlinks are cheap to recreate, and anything of the wrong size can not match
+    match symlink_metadata(on_disk) {
+        Ok(existing) if existing.is_file() && existing.len() == file_size(metadata) => {}
+        _ => return false,
+    }
+    let mut hasher = blake3::Hasher::new();
+    match File::open(on_disk).and_then(|reader| hasher.update_reader(reader).map(|_| ())) {
+        Ok(()) => hasher.finalize().to_hex().as_str() == expected,
+        Err(_) => false,
+    }
+}
+
+/// The content blocks of files still to be restored which are not on disk
+async fn missing_content(
+    fs: &FsMetadata,
+    all_nodes: &[(PrivateNode, PathBuf)],
+    restored: &Path,
+    checkpoint: &RestoreCheckpoint,
+    local: &LocalBucket,
+) -> Result<Vec<Cid>, NativeError> {
+    let mut missing = BTreeSet::new();
+    for (node, path) in all_nodes {
+        if let PrivateNode::File(file) = node {
+            if file.symlink_origin().is_some() || checkpoint.is_complete(restored, path) {
+                continue;
+            }
+            let cids = file
+                .get_cids(&fs.forest, &local.metadata)
+                .await
+                .map_err(Box::from)?;
+            missing.extend(cids.into_iter().filter(|cid| !local.content.contains(cid)));
+        }
+    }
+    Ok(missing.into_iter().collect())
+}
+
+/// Copy blocks from a source BlockStore into a destination, returning how many were copied.
+/// Blocks the destination already has are not requested from the source.
+pub async fn fetch_blocks(
+    cids: &BTreeSet<Cid>,
+    source: &impl BanyanBlockStore,
+    destination: &impl BanyanBlockStore,
+) -> Result<usize, NativeError> {
+    let mut fetched = 0;
+    for cid in cids {
+        if destination.get_block(cid).await.is_ok() {
+            continue;
+        }
+        let bytes = source.get_block(cid).await?.to_vec();
+        let codec = IpldCodec::try_from(cid.codec()).map_err(|_| NativeError::bad_data())?;
+        // The destination hashes the block itself, so make sure it arrived intact
+        if destination.put_block(bytes, codec).await? != *cid {
+            return Err(NativeError::bad_data());
+        }
+        fetched += 1;
+    }
+    Ok(fetched)
+}
+
+/// Entry of a RestoreCheckpoint
+#[derive(Debug, Serialize, Deserialize)]
+struct CheckpointEntry {
+    /// Path of the file within the Drive
+    path: PathBuf,
+    /// Number of bytes written out for the file
+    size: u64,
+}
+
+/// Record of the files a restore has already written out, so that an interrupted restore can
+/// skip them when it is resumed. Stored as JSON lines next to the restored directory.
+#[derive(Debug)]
+pub struct RestoreCheckpoint {
+    /// Location of the checkpoint on disk
+    path: PathBuf,
+    /// Sizes of the files restored so far
+    completed: HashMap<PathBuf, u64>,
+    /// Checkpoint file being appended to
+    file: File,
+}
+
+impl RestoreCheckpoint {
+    /// Location of the checkpoint for a restore into a given directory
+    pub fn path_for(restored: &Path) -> PathBuf {
+        let name = restored
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        restored.with_file_name(format!(".{name}.restore-checkpoint"))
+    }
+
+    /// Open the checkpoint for a restore into a given directory.
+    /// Unless `resume` is set, any existing checkpoint is discarded.
+    pub fn open(restored: &Path, resume: bool) -> Result<Self, NativeError> {
+        let path = Self::path_for(restored);
+        let mut completed = HashMap::new();
+        if resume && path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                // A line cut short by the interruption is simply not trusted
+                if let Ok(entry) = serde_json::from_str::<CheckpointEntry>(&line?) {
+                    completed.insert(entry.path, entry.size);
+                }
+            }
+        }
+        let mut options = OpenOptions::new();
+        options.create(true);
+        if resume {
+            options.append(true);
+        } else {
+            options.write(true).truncate(true);
+        }
+        Ok(Self {
+            file: options.open(&path)?,
+            path,
+            completed,
+        })
+    }
+
+    /// Number of files restored by previous runs
+    pub fn completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Whether or not a file was restored by a previous run and is still intact on disk
+    pub fn is_complete(&self, restored: &Path, path: &Path) -> bool {
+        match (
+            self.completed.get(path),
+            symlink_metadata(restored.join(path)),
+        ) {
+            (Some(size), Ok(metadata)) => metadata.is_symlink() || metadata.len() == *size,
+            _ => false,
+        }
+    }
+
+    /// Record that a file has been fully restored
+    pub fn record(&mut self, path: &Path, size: u64) -> Result<(), NativeError> {
+        let entry = CheckpointEntry {
+            path: path.to_path_buf(),
+            size,
+        };
+        let line = serde_json::to_string(&entry).map_err(|_| NativeError::bad_data())?;
+        writeln!(self.file, "{line}")?;
+        self.completed.insert(entry.path, size);
+        Ok(())
+    }
+
+    /// Delete the checkpoint once the restore has completed
+    pub fn finish(self) -> Result<(), NativeError> {
+        drop(self.file);
+        remove_file(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Restore all nodes, skipping files which the checkpoint shows are already restored.
+/// Up to `parallelism` blocks of each file are decrypted at once, and files are written
+/// according to `strategy`. Files which were hard links to each other when they were prepared
+/// are linked to the first of them to be restored, so their content is only written once.
+/// Returns the number of files which were written out.
+#[allow(clippy::too_many_arguments)]
 pub async fn restore_nodes(
     fs: &FsMetadata,
     all_nodes: Vec<(PrivateNode, PathBuf)>,
     restored: PathBuf,
     metadata_store: &impl RootedBlockStore,
     content_store: &impl RootedBlockStore,
-) -> Result<(), NativeError> {
+    checkpoint: &mut RestoreCheckpoint,
+    parallelism: usize,
+    strategy: WriteStrategy,
+    progress: &ProgressTracker,
+) -> Result<usize, NativeError> {
+    let mut written = 0;
+    // Where the first file restored of each set of hard links was written to
+    let mut hardlinks: HashMap<String, PathBuf> = HashMap::new();
+    let (files_total, bytes_total) =
+        all_nodes
+            .iter()
+            .fold((0, 0), |(files, bytes), (node, _)| match node {
+                PrivateNode::File(file) => (files + 1, bytes + file_size(file.get_metadata())),
+                PrivateNode::Dir(_) => (files, bytes),
+            });
+    progress.start(files_total, bytes_total);
     // Initialize the progress bar using the number of Nodes to process
-    let progress_bar = get_progress_bar(all_nodes.len() as u64);
+    let progress_bar = get_progress(all_nodes.len() as u64);
     // For each node path tuple in the FS Metadata
     for (node, path) in all_nodes {
         match node {
@@ -72,16 +539,45 @@ pub async fn restore_nodes(
                 progress_bar.inc(1);
             }
             PrivateNode::File(file) => {
+                let link = hardlink(file.get_metadata()).map(str::to_string);
                 let built_path = restored.join(path.clone());
+                // Files restored by a previous run do not need to be read again
+                if checkpoint.is_complete(&restored, &path) {
+                    if let Some(link) = link {
+                        hardlinks.entry(link).or_insert(built_path);
+                    }
+                    progress.advance(1, file_size(file.get_metadata()));
+                    progress_bar.inc(1);
+                    continue;
+                }
 
-                let content = fs
-                    .read(&path_to_segments(&path)?, metadata_store, content_store)
-                    .await?;
+                // If another link to this file was already restored, link to it
+                if let Some(linked) = link.as_ref().and_then(|link| hardlinks.get(link)) {
+                    if symlink_metadata(&built_path).is_ok() {
+                        remove_file(&built_path)?;
+                    }
+                    if let Some(parent) = built_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    hard_link(linked, &built_path)?;
+                    let size = symlink_metadata(&built_path)?.len();
+                    checkpoint.record(&path, size)?;
+                    progress.advance(1, size);
+                    written += 1;
+                    progress_bar.inc(1);
+                    continue;
+                }
 
                 // If this file is a symlink
                 if let Some(origin) = file.symlink_origin() {
+                    // Replace any link left behind by an interrupted run
+                    if symlink_metadata(&built_path).is_ok() {
+                        remove_file(&built_path)?;
+                    }
                     // Write out the symlink
                     symlink(origin, built_path)?;
+                    checkpoint.record(&path, 0)?;
+                    progress.advance(1, 0);
                 } else {
                     // If the parent does not yet exist
                     if let Some(parent) = built_path.parent() {
@@ -91,15 +587,274 @@ pub async fn restore_nodes(
                         }
                     }
                     // Create the file at the desired location
-                    let mut output_file = File::create(built_path)?;
+                    let mut output_file = RestoredFile::create(&built_path, strategy)?;
 
                     // Write out the content to disk
-                    output_file.write_all(&content)?;
+                    let size = write_content(
+                        fs,
+                        &file,
+                        metadata_store,
+                        content_store,
+                        parallelism,
+                        &mut output_file,
+                        progress,
+                    )
+                    .await?;
+                    // Only record the file once its content has been flushed
+                    output_file.finish()?;
+                    apply_attributes(&built_path, file.get_metadata())?;
+                    checkpoint.record(&path, size)?;
+                    if let Some(link) = link {
+                        hardlinks.insert(link, built_path);
+                    }
+                    progress.advance(1, 0);
                 }
 
+                written += 1;
                 progress_bar.inc(1);
             }
         }
     }
+    Ok(written)
+}
+
+/// Decrypt the content of a file into a writer, returning the number of bytes written.
+/// Up to `parallelism` blocks are fetched and decrypted at once, and they are written out
+/// in order as soon as they are ready, so no more than that many blocks are held in memory.
+/// Compressed content is decompressed in memory, which is only ever used for files small
+/// enough to have been read into memory when they were prepared.
+/// Every chunk written is recorded in `progress`.
+pub async fn write_content(
+    fs: &FsMetadata,
+    file: &PrivateFile,
+    metadata_store: &impl RootedBlockStore,
+    content_store: &impl RootedBlockStore,
+    parallelism: usize,
+    w: &mut impl Write,
+    progress: &ProgressTracker,
+) -> Result<u64, NativeError> {
+    let mut content = fs
+        .file_content_stream(
+            Rc::new(file.clone()),
+            metadata_store,
+            content_store,
+            parallelism,
+        )
+        .await?;
+    let codec = CompressionCodec::from_metadata(file.get_metadata())?;
+    if codec != CompressionCodec::None {
+        let mut compressed = Vec::new();
+        content.read_to_end(&mut compressed).await?;
+        let decompressed = codec.decompress(&compressed)?;
+        w.write_all(&decompressed)?;
+        progress.advance(0, decompressed.len() as u64);
+        return Ok(decompressed.len() as u64);
+    }
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut written = 0;
+    loop {
+        let len = content.read(&mut chunk).await?;
+        if len == 0 {
+            break;
+        }
+        w.write_all(&chunk[..len])?;
+        written += len as u64;
+        progress.advance(0, len as u64);
+    }
+    Ok(written)
+}
+
+/// Stream the content of a single file in a Drive into a writer without touching the local
+/// filesystem, fetching any blocks missing on disk from the remote when authenticated.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+/// * `path` - Path of the file within the Drive
+/// * `w` - Destination of the file content
+///
+/// # Return Type
+/// Returns the number of bytes written on success, otherwise returns an error.
+pub async fn cat<W: Write>(omni: OmniBucket, path: &Path, mut w: W) -> Result<u64, NativeError> {
+    let fs = omni.unlock().await?;
+    let local = omni.get_local()?;
+    let global = GlobalConfig::from_disk().await?;
+    let mut client = global.get_client().await?;
+
+    let metadata_store = &local.metadata;
+    let file = match fs
+        .get_node(&path_to_segments(path)?, metadata_store)
+        .await?
+    {
+        Some(PrivateNode::File(file)) => file,
+        _ => {
+            return Err(FilesystemError::node_not_found(&path.display().to_string()).into());
+        }
+    };
+    let progress = ProgressTracker::new(None);
+
+    let written = if client.is_authenticated().await {
+        let api_store = global.get_cache(BanyanApiBlockStore::from(client.to_owned()))?;
+        let split_store = DoubleSplitStore::new(&local.content, &api_store);
+        write_content(
+            &fs,
+            &file,
+            metadata_store,
+            &split_store,
+            DEFAULT_RESTORE_PARALLELISM,
+            &mut w,
+            &progress,
+        )
+        .await?
+    } else {
+        write_content(
+            &fs,
+            &file,
+            metadata_store,
+            &local.content,
+            DEFAULT_RESTORE_PARALLELISM,
+            &mut w,
+            &progress,
+        )
+        .await?
+    };
+    w.flush()?;
+    Ok(written)
+}
+
+/// Stream the contents of a Drive into a tar archive without touching the local filesystem.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+/// * `w` - Destination of the tar archive
+///
+/// # Return Type
+/// Returns the writer on success, otherwise returns an error.
+pub async fn to_tar<W: Write>(mut omni: OmniBucket, w: W) -> Result<W, NativeError> {
+    let fs = omni.unlock().await?;
+    let local = omni.get_local()?;
+    let global = GlobalConfig::from_disk().await?;
+    let mut client = global.get_client().await?;
+
+    let metadata_store = &local.metadata;
+    // Get all the nodes in the FileSystem
+    let all_nodes = fs.get_all_nodes(metadata_store).await?;
+    info!("🔐 Exporting all {} files to tar", all_nodes.len());
+
+    if client.is_authenticated().await {
+        let api_store = global.get_cache(BanyanApiBlockStore::from(client.to_owned()))?;
+        let split_store = DoubleSplitStore::new(&local.content, &api_store);
+        info!("Using online server as backup to grab file content...");
+        tar_nodes(&fs, all_nodes, metadata_store, &split_store, w).await
+    } else {
+        warn!("We notice you're offline or unauthenticated, exporting may fail if encrypted data is not already present on disk.");
+        tar_nodes(&fs, all_nodes, metadata_store, &local.content, w).await
+    }
+}
+
+/// Write all nodes into a tar archive
+pub async fn tar_nodes<W: Write>(
+    fs: &FsMetadata,
+    all_nodes: Vec<(PrivateNode, PathBuf)>,
+    metadata_store: &impl RootedBlockStore,
+    content_store: &impl RootedBlockStore,
+    w: W,
+) -> Result<W, NativeError> {
+    let mut builder = Builder::new(w);
+    // Directories which have already been given an entry
+    let mut written_dirs = HashSet::new();
+    for (node, path) in all_nodes {
+        // Make sure every ancestor directory has an entry before its children
+        let mut ancestors: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .map(PathBuf::from)
+            .collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            if written_dirs.insert(ancestor.clone()) {
+                let mut header = Header::new_gnu();
+                header.set_entry_type(EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                header.set_mtime(0);
+                builder.append_data(&mut header, &ancestor, empty())?;
+            }
+        }
+
+        match node {
+            PrivateNode::Dir(dir) => {
+                if written_dirs.insert(path.clone()) {
+                    let mut header = Header::new_gnu();
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    header.set_mtime(modified(dir.get_metadata()));
+                    builder.append_data(&mut header, &path, empty())?;
+                }
+            }
+            PrivateNode::File(file) => {
+                let attributes = FileAttributes::from_metadata(file.get_metadata());
+                let mut header = Header::new_gnu();
+                header.set_mtime(
+                    attributes
+                        .mtime
+                        .map(|mtime| mtime.max(0) as u64)
+                        .unwrap_or_else(|| modified(file.get_metadata())),
+                );
+                // If this file is a symlink
+                if let Some(origin) = file.symlink_origin() {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_mode(0o777);
+                    header.set_size(0);
+                    builder.append_link(&mut header, &path, origin)?;
+                } else {
+                    let content = fs
+                        .read(&path_to_segments(&path)?, metadata_store, content_store)
+                        .await?;
+                    header.set_entry_type(EntryType::Regular);
+                    header.set_mode(
+                        attributes
+                            .mode
+                            .map(|mode| mode & PERMISSION_BITS)
+                            .unwrap_or(0o644),
+                    );
+                    header.set_size(content.len() as u64);
+                    builder.append_data(&mut header, &path, content.as_slice())?;
+                }
+            }
+        }
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Apply the permissions and modification time a restored file had when it was prepared
+fn apply_attributes(path: &Path, metadata: &Metadata) -> Result<(), NativeError> {
+    let attributes = FileAttributes::from_metadata(metadata);
+    if let Some(mode) = attributes.mode {
+        set_permissions(path, Permissions::from_mode(mode & PERMISSION_BITS))?;
+    }
+    if let Some(mtime) = attributes.mtime {
+        let nanos = attributes.mtime_nsec.unwrap_or(0) as u32;
+        filetime::set_file_mtime(path, FileTime::from_unix_time(mtime, nanos))?;
+    }
     Ok(())
 }
+
+/// Size of a file's content in bytes, if it was recorded
+fn file_size(metadata: &Metadata) -> u64 {
+    match metadata.0.get("size") {
+        Some(Ipld::Integer(size)) => *size as u64,
+        _ => 0,
+    }
+}
+
+/// Modification time of a node in seconds since the epoch, if it was recorded
+fn modified(metadata: &Metadata) -> u64 {
+    match metadata.0.get("modified") {
+        Some(Ipld::Integer(modified)) => *modified as u64,
+        _ => 0,
+    }
+}
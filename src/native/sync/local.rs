@@ -1,9 +1,14 @@
 use crate::{
-    api::models::storage_ticket::StorageTicket,
-    blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore},
-    filesystem::{FilesystemError, FsMetadata},
-    native::configuration::xdg::xdg_data_home,
-    prelude::blockstore::RootedBlockStore,
+    api::models::{
+        bucket::{BucketType, StorageClass},
+        storage_ticket::StorageTicket,
+    },
+    blockstore::{
+        BlockStoreError, CarV2DiskBlockStore, CarV2MemoryBlockStore, MultiCarV2DiskBlockStore,
+    },
+    filesystem::{wnfsio::CompressionCodec, FilesystemError, FsMetadata, SignedManifest},
+    native::{configuration::xdg::xdg_data_home, NativeError},
+    prelude::blockstore::{BanyanBlockStore, RootedBlockStore},
 };
 use colored::Colorize;
 use rand::{distributions::Alphanumeric, Rng};
@@ -11,7 +16,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeSet,
     fmt::Display,
-    fs::{create_dir_all, remove_dir_all},
+    fs::{create_dir_all, remove_dir_all, File},
     path::{Path, PathBuf},
 };
 use tomb_crypt::prelude::EcEncryptionKey;
@@ -20,6 +25,7 @@ use wnfs::{libipld::Cid, private::PrivateNodeOnPathHistory};
 
 const BUCKET_METADATA_FILE_NAME: &str = "metadata.car";
 const BUCKET_CONTENT_DIR_NAME: &str = "content";
+const BUCKET_MANIFESTS_DIR_NAME: &str = "manifests";
 
 fn bucket_data_home(local_id: &str) -> PathBuf {
     xdg_data_home().join(local_id)
@@ -33,6 +39,50 @@ fn bucket_content_path(name: &str) -> PathBuf {
     xdg_data_home().join(name).join(BUCKET_CONTENT_DIR_NAME)
 }
 
+/// A version of the metadata which the server is known to have
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+pub struct PushedMetadata {
+    /// Root CID of the metadata
+    pub metadata_cid: Cid,
+    /// Size of the metadata CARv1 data at the time
+    pub data_size: u64,
+}
+
+/// A content upload which the staging service only received part of
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PendingUpload {
+    /// Metadata the content was uploaded for
+    pub metadata_id: Uuid,
+    /// Root CID of the content at the time
+    pub root_cid: Cid,
+    /// Number of bytes of the content CAR the staging service acknowledged
+    pub offset: u64,
+}
+
+/// Where a LocalBucket keeps its CARs, as recorded in configuration. Unlike a LocalBucket this
+/// can be read even when the CARs are too damaged to load.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalBucketStores {
+    /// The name of this bucket
+    pub name: String,
+    /// The filesystem origin of this bucket
+    pub origin: PathBuf,
+    /// The remote id of this bucket, if it has one
+    pub remote_id: Option<Uuid>,
+    metadata: PathBuf,
+    #[serde(deserialize_with = "MultiCarV2DiskBlockStore::deserialize_path")]
+    content: PathBuf,
+}
+
+impl LocalBucketStores {
+    /// The path of the metadata CAR followed by those of every content CAR
+    pub fn car_paths(&self) -> Result<Vec<PathBuf>, BlockStoreError> {
+        let mut paths = vec![self.metadata.clone()];
+        paths.extend(MultiCarV2DiskBlockStore::car_paths(&self.content)?);
+        Ok(paths)
+    }
+}
+
 // TODO: This is maybe better concieved of as a Bucket
 /// Configuration for an individual Bucket / FileSystem
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
@@ -55,6 +105,24 @@ pub struct LocalBucket {
     pub content: MultiCarV2DiskBlockStore,
     /// Previous root cid of the metadata BlockStore, if there is one
     pub previous_cid: Option<Cid>,
+    /// Last version of the metadata the server is known to have, used to push deltas
+    #[serde(default)]
+    pub(crate) pushed_metadata: Option<PushedMetadata>,
+    /// Storage class of the remote bucket, as last known
+    #[serde(default)]
+    pub(crate) storage_class: Option<StorageClass>,
+    /// Type of workflow this bucket was created for
+    #[serde(default)]
+    pub(crate) bucket_type: BucketType,
+    /// Whether this bucket is protected from modification
+    #[serde(default)]
+    pub(crate) read_only: bool,
+    /// Content upload to continue on the next sync, if the last one was interrupted
+    #[serde(default)]
+    pub(crate) pending_upload: Option<PendingUpload>,
+    /// Codec file content is compressed with when it is prepared
+    #[serde(default)]
+    pub compression: CompressionCodec,
 }
 
 impl Display for LocalBucket {
@@ -119,9 +187,26 @@ impl LocalBucket {
             metadata,
             content,
             previous_cid: None,
+            pushed_metadata: None,
+            storage_class: None,
+            bucket_type: BucketType::default(),
+            read_only: false,
+            pending_upload: None,
+            compression: CompressionCodec::default(),
         })
     }
 
+    /// Start encrypting the names of every Node in the filesystem. Nodes already written would
+    /// keep their plaintext names, so this replaces the filesystem with an empty one, and is only
+    /// meant for Drives which were just created.
+    pub(crate) async fn encrypt_names(
+        &mut self,
+        wrapping_key: &EcEncryptionKey,
+    ) -> Result<(), FilesystemError> {
+        let mut fs = FsMetadata::init_with_name_encryption(wrapping_key).await?;
+        fs.save(&self.metadata, &self.content).await
+    }
+
     pub(crate) fn remove_data(&self) -> Result<(), std::io::Error> {
         // Remove dir if it exists
         if bucket_data_home(&self.local_id).exists() {
@@ -130,6 +215,33 @@ impl LocalBucket {
         Ok(())
     }
 
+    /// Attach a signed manifest to a snapshot by storing it alongside the bucket data
+    pub(crate) fn save_manifest(
+        &self,
+        snapshot_id: Uuid,
+        manifest: &SignedManifest,
+    ) -> Result<PathBuf, std::io::Error> {
+        let manifests = bucket_data_home(&self.local_id).join(BUCKET_MANIFESTS_DIR_NAME);
+        create_dir_all(&manifests)?;
+        let path = manifests.join(format!("{snapshot_id}.json"));
+        serde_json::to_writer_pretty(File::create(&path)?, manifest)?;
+        Ok(path)
+    }
+
+    /// Number of bytes of prepared content and metadata stored on disk for this bucket
+    pub(crate) fn data_size(&self) -> u64 {
+        fs_extra::dir::get_size(bucket_data_home(&self.local_id)).unwrap_or(0)
+    }
+
+    /// Refuse to continue if this bucket has been locked against modification
+    pub fn ensure_writable(&self) -> Result<(), NativeError> {
+        if self.read_only {
+            Err(NativeError::read_only(&self.name))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Shortcut for unlocking a filesystem
     pub async fn unlock_fs(
         &self,
@@ -144,6 +256,47 @@ impl LocalBucket {
         fs.save(&self.metadata, &self.content).await
     }
 
+    /// Record that the server now has the current version of the metadata
+    pub(crate) fn mark_metadata_pushed(&mut self) {
+        self.pushed_metadata = self.metadata.get_root().map(|metadata_cid| PushedMetadata {
+            metadata_cid,
+            data_size: self.metadata.data_size(),
+        });
+    }
+
+    /// Build a CAR of only the metadata blocks written since the last push, along with the
+    /// metadata CID the server should apply it over. Returns None if the server's version is
+    /// not a common ancestor of the local metadata, in which case the full CAR must be pushed.
+    pub(crate) async fn metadata_delta(
+        &self,
+    ) -> Result<Option<(Cid, CarV2MemoryBlockStore)>, BlockStoreError> {
+        let Some(pushed) = self.pushed_metadata else {
+            return Ok(None);
+        };
+        // The pushed version must still be part of the local metadata
+        if pushed.data_size > self.metadata.data_size()
+            || BanyanBlockStore::get_block(&self.metadata, &pushed.metadata_cid)
+                .await
+                .is_err()
+        {
+            return Ok(None);
+        }
+        Ok(Some((
+            pushed.metadata_cid,
+            self.metadata.delta_since(pushed.data_size)?,
+        )))
+    }
+
+    /// Whether or not the root block of the content BlockStore can be read locally
+    pub async fn has_content_root(&self) -> bool {
+        match self.content.get_root() {
+            Some(root) => BanyanBlockStore::get_block(&self.content, &root)
+                .await
+                .is_ok(),
+            None => false,
+        }
+    }
+
     /// Shortcut for serialize::load_history
     pub async fn get_history(
         &self,
@@ -156,7 +309,11 @@ impl LocalBucket {
 
 #[cfg(test)]
 mod test {
-    use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+    use crate::{
+        blockstore::RootedBlockStore,
+        native::{configuration::globalconfig::GlobalConfig, NativeError},
+        utils::testing::local_operations::test_setup_bucket,
+    };
     use chrono::Utc;
     use rand::thread_rng;
     use serial_test::serial;
@@ -231,4 +388,78 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn one_file_delta_is_small() -> Result<(), NativeError> {
+        let (_, mut local, mut fs) = test_setup_bucket("one_file_delta_is_small").await?;
+
+        // Without a pushed version there is nothing to apply a delta over
+        assert!(local.metadata_delta().await?.is_none());
+
+        // Build up a sizeable tree and push it
+        for i in 0..50 {
+            fs.write(
+                &[format!("dir_{}", i % 5), format!("file_{i}.txt")],
+                &local.metadata,
+                &local.content,
+                format!("file number {i}").into_bytes(),
+            )
+            .await?;
+        }
+        local.save_fs(&mut fs).await?;
+        local.mark_metadata_pushed();
+        let pushed = local.pushed_metadata.expect("no pushed metadata");
+
+        // Change a single file
+        fs.write(
+            &["dir_0".to_string(), "file_0.txt".to_string()],
+            &local.metadata,
+            &local.content,
+            "changed".as_bytes().to_vec(),
+        )
+        .await?;
+        local.save_fs(&mut fs).await?;
+
+        let (base_cid, delta) = local.metadata_delta().await?.expect("no delta");
+        assert_eq!(base_cid, pushed.metadata_cid);
+        assert_eq!(delta.get_root(), local.metadata.get_root());
+        assert!(delta.data_size() < local.metadata.data_size() / 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn encrypt_names() -> Result<(), NativeError> {
+        let (global, mut local, _) = test_setup_bucket("local_encrypt_names").await?;
+        let wrapping_key = global.wrapping_key().await?;
+        local.encrypt_names(&wrapping_key).await?;
+
+        let mut fs = local.unlock_fs(&wrapping_key).await?;
+        assert!(fs.name_key.is_some());
+        fs.write(
+            &["kitty.txt".to_string()],
+            &local.metadata,
+            &local.content,
+            "meow".as_bytes().to_vec(),
+        )
+        .await?;
+        local.save_fs(&mut fs).await?;
+
+        // The name is stored encrypted, but still lists and reads as plaintext
+        let fs = local.unlock_fs(&wrapping_key).await?;
+        let names = fs
+            .root_dir
+            .ls(&[], true, &fs.forest, &local.metadata)
+            .await
+            .map_err(Box::from)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 1);
+        assert_ne!(names[0], "kitty.txt");
+        let entries = fs.ls(&[], &local.metadata).await?;
+        assert_eq!(entries[0].name, "kitty.txt");
+        Ok(())
+    }
 }
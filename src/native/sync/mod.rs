@@ -12,27 +12,46 @@ use crate::{
         client::Client,
         error::ApiError,
         models::{
-            bucket::{Bucket, BucketType, StorageClass},
-            metadata::Metadata,
+            bucket::Bucket,
+            metadata::{Metadata, MetadataState},
             storage_ticket::StorageTicket,
         },
-        requests::staging::upload::content::UploadContent,
+        requests::{
+            capabilities::{METADATA_DELTA, RESUMABLE_UPLOAD},
+            core::blocks::locate::LocationRequest,
+            staging::upload::{content::UploadContent, progress::UploadProgress},
+        },
+    },
+    blockstore::{
+        BanyanApiBlockStore, CarV2MemoryBlockStore, DagTree, DoubleSplitStore, RootedBlockStore,
+        LOCATE_BATCH_SIZE,
     },
-    blockstore::{BanyanApiBlockStore, CarV2MemoryBlockStore, RootedBlockStore},
-    filesystem::{FilesystemError, FsMetadata},
+    filesystem::{FsDiff, FsMetadata},
     native::{configuration::globalconfig::GlobalConfig, operations::restore, NativeError},
 };
+use bytesize::ByteSize;
 use colored::Colorize;
 use futures_util::StreamExt;
-pub use local::LocalBucket;
-pub use omni::OmniBucket;
-use std::{collections::BTreeSet, fmt::Display};
+pub use local::{LocalBucket, LocalBucketStores, PendingUpload, PushedMetadata};
+pub use omni::{Deletion, DeletionReport, DriveInfo, OmniBucket, ReconcileReport};
+use reqwest::Url;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 use tokio::io::AsyncWriteExt;
 use tomb_crypt::prelude::{PrivateKey, PublicKey};
-use wnfs::{common::BlockStore, libipld::Ipld};
+use uuid::Uuid;
+use wnfs::{
+    common::BlockStore,
+    libipld::{Cid, Ipld},
+};
 
 /// Sync State
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SyncState {
     /// Initial / Default state
     Unknown,
@@ -48,6 +67,8 @@ pub enum SyncState {
     AllSynced,
     /// Local bucket is ahead of the remote
     Ahead,
+    /// Local and remote have both changed since the last version they shared, or share none
+    Diverged,
 }
 
 impl Display for SyncState {
@@ -60,13 +81,173 @@ impl Display for SyncState {
             SyncState::MetadataSynced => "Metadata Synced; File System not reconstructed".blue(),
             SyncState::AllSynced => "Drive is in sync with remote".green(),
             SyncState::Ahead => "Drive is ahead of remote".red(),
+            SyncState::Diverged => "Drive and remote have diverged".red(),
         };
 
         f.write_fmt(format_args!("{}", description))
     }
 }
 
+/// What syncing a Bucket would do, as computed by `OmniBucket::sync_bucket_dry_run`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "action")]
+pub enum SyncPlan {
+    /// Sync state is not yet known, and would be determined first
+    DetermineState,
+    /// Continue an interrupted content upload
+    ResumeUpload {
+        /// Storage host receiving the content
+        host: String,
+        /// Bytes of the content CAR the storage host already has
+        offset: u64,
+        /// Bytes of the content CAR left to upload
+        remaining_bytes: u64,
+    },
+    /// Download the current metadata from the remote
+    DownloadMetadata,
+    /// Push the metadata and then upload the most recent content CAR
+    Upload {
+        /// Whether or not the remote Bucket has to be created first
+        create_remote: bool,
+        /// Bytes of metadata to push; only those written since the last push if the server
+        /// has a prior version
+        metadata_bytes: u64,
+        /// Bytes of content to upload
+        content_bytes: u64,
+    },
+    /// Fetch the content from the network and write the files into the origin
+    Reconstruct {
+        /// Where the files would be written
+        origin: PathBuf,
+    },
+    /// Nothing to do
+    AlreadySynced,
+    /// Local and remote changes conflict, so nothing would be synced without `--force`
+    Diverged,
+}
+
+impl Display for SyncPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}\n", "| SYNC DRY RUN |".yellow()))?;
+        match self {
+            SyncPlan::DetermineState => f.write_str("would determine the sync state"),
+            SyncPlan::ResumeUpload {
+                host,
+                offset,
+                remaining_bytes,
+            } => f.write_fmt(format_args!(
+                "would resume uploading content to {host} from byte {offset}\nupload:\t\t{}",
+                ByteSize(*remaining_bytes)
+            )),
+            SyncPlan::DownloadMetadata => f.write_str("would download metadata from remote"),
+            SyncPlan::Upload {
+                create_remote,
+                metadata_bytes,
+                content_bytes,
+            } => {
+                if *create_remote {
+                    f.write_str("would create the Drive remotely\n")?;
+                }
+                f.write_fmt(format_args!(
+                    "would push metadata and upload content\nmetadata:\t{}\ncontent:\t{}",
+                    ByteSize(*metadata_bytes),
+                    ByteSize(*content_bytes)
+                ))
+            }
+            SyncPlan::Reconstruct { origin } => f.write_fmt(format_args!(
+                "would download content and reconstruct files in {}",
+                origin.display()
+            )),
+            SyncPlan::AlreadySynced => f.write_str("nothing to do; Drive is already synced"),
+            SyncPlan::Diverged => f.write_str(
+                "would refuse to sync; the Drive and remote have diverged (use --force to overwrite the remote)",
+            ),
+        }
+    }
+}
+
 impl OmniBucket {
+    /// Create a fresh grant for this Client against a storage host
+    async fn regrant(&self, host: &str, client: &mut Client) -> Result<StorageTicket, NativeError> {
+        let storage_ticket = StorageTicket {
+            host: host.to_string(),
+            authorization: self.get_remote()?.get_grants_token(client).await?,
+        };
+        storage_ticket.create_grant(client).await?;
+        Ok(storage_ticket)
+    }
+
+    /// Continue an interrupted content upload from where the staging service left off, so long as
+    /// the content has not changed since. Returns None if there was no upload to continue.
+    async fn resume_upload(
+        &mut self,
+        global: &mut GlobalConfig,
+        client: &mut Client,
+    ) -> Result<Option<String>, NativeError> {
+        let Ok(mut local) = self.get_local() else {
+            return Ok(None);
+        };
+        let (Some(pending), Some(storage_ticket)) =
+            (local.pending_upload.clone(), local.storage_ticket.clone())
+        else {
+            return Ok(None);
+        };
+        // Content prepared since then needs new metadata, and so a fresh upload
+        if local.content.get_root() != Some(pending.root_cid) {
+            local.pending_upload = None;
+            global.update_config(&local)?;
+            self.set_local(local);
+            return Ok(None);
+        }
+
+        // Hosts which can't continue an upload take all of it again
+        let offset = match resumable_host(&storage_ticket.host, client).await {
+            Some(_) => pending.offset,
+            None => 0,
+        };
+        info!("Resuming upload from byte {offset}...");
+        let upload_result = local
+            .content
+            .upload_from(
+                storage_ticket.host.clone(),
+                pending.metadata_id,
+                offset,
+                client,
+            )
+            .await;
+        local.pending_upload = match upload_result {
+            Ok(()) => None,
+            Err(_) => Some(
+                pending_upload(
+                    &storage_ticket.host,
+                    pending.metadata_id,
+                    pending.root_cid,
+                    client,
+                )
+                .await,
+            ),
+        };
+        let resumed = local.pending_upload.is_none();
+        global.update_config(&local)?;
+        self.set_local(local);
+
+        if resumed {
+            self.sync_state = SyncState::AllSynced;
+            let metadata = Metadata::read_current(self.get_id()?, client).await?;
+            Ok(Some(format!(
+                "{}\n{}",
+                "<< SUCCESSFULLY UPLOADED CONTENT >>".green(),
+                metadata
+            )))
+        } else {
+            Ok(Some(format!(
+                "{}\n{}",
+                "<< FAILED TO PUSH CONTENT >>".red(),
+                "<< SYNC AGAIN TO CONTINUE THE UPLOAD >>".blue()
+            )))
+        }
+    }
+
     /// Determine the Sync State of an omni bucket
     pub async fn determine_sync_state(&mut self) -> Result<(), NativeError> {
         let bucket_id = match self.get_id() {
@@ -92,24 +273,34 @@ impl OmniBucket {
             if local_metadata_cid == Some(current_remote.metadata_cid) {
                 // If the block is also persisted locally in content
                 if local_content_cid == Some(current_remote.root_cid) {
-                    self.sync_state = SyncState::AllSynced
+                    self.sync_state = SyncState::AllSynced;
+                    // Make sure the content can actually be read before declaring it synced
+                    self.verify_content().await?;
                 } else {
                     self.sync_state = SyncState::MetadataSynced;
                 }
                 Ok(())
             } else {
                 let all_metadatas = Metadata::read_all(bucket_id, &mut client).await?;
-                // If the current Metadata id exists in the list of remotely persisted ones
-                if all_metadatas
-                    .iter()
-                    .any(|metadata| Some(metadata.metadata_cid.clone()) == local_metadata_cid)
-                {
-                    self.sync_state = SyncState::Behind;
-                    Ok(())
-                } else {
-                    self.sync_state = SyncState::Ahead;
-                    Ok(())
-                }
+                let remote_history = all_metadatas
+                    .into_iter()
+                    .map(|metadata| (metadata.metadata_cid, metadata.previous_cid))
+                    .collect::<BTreeMap<String, Option<String>>>();
+                // The versions the local metadata is known to descend from
+                let local_ancestors = local
+                    .pushed_metadata
+                    .map(|pushed| pushed.metadata_cid)
+                    .into_iter()
+                    .chain(local.previous_cid)
+                    .map(|cid| cid.to_string())
+                    .collect::<Vec<String>>();
+                self.sync_state = compare_histories(
+                    local_metadata_cid.as_deref(),
+                    &local_ancestors,
+                    &current_remote.metadata_cid,
+                    &remote_history,
+                );
+                Ok(())
             }
         } else {
             self.sync_state = SyncState::Unlocalized;
@@ -117,33 +308,135 @@ impl OmniBucket {
         }
     }
 
-    /// Sync
-    #[allow(unused)]
-    pub async fn sync_bucket(&mut self) -> Result<String, NativeError> {
+    /// Check that the content root of the Drive can be fetched, either from disk or from a
+    /// storage host. If it can not, the user is warned and an `AllSynced` state is downgraded to
+    /// `MetadataSynced`.
+    pub async fn verify_content(&mut self) -> Result<bool, NativeError> {
+        let local = self.get_local()?;
+        let available = local.has_content_root().await || self.remote_has_content_root().await;
+        if !available {
+            warn!("The content of this Drive is not available; sync again to download it.");
+            if self.sync_state == SyncState::AllSynced {
+                self.sync_state = SyncState::MetadataSynced;
+            }
+        }
+        Ok(available)
+    }
+
+    /// Ask the remote whether any storage host holds the content root of the Drive
+    async fn remote_has_content_root(&self) -> bool {
+        let Some(root) = self
+            .get_local()
+            .ok()
+            .and_then(|local| local.content.get_root())
+        else {
+            return false;
+        };
+        let Ok(global) = GlobalConfig::from_disk().await else {
+            return false;
+        };
+        let Ok(mut client) = global.get_client().await else {
+            return false;
+        };
+        if !client.is_authenticated().await {
+            return false;
+        }
+        match client
+            .call(LocationRequest {
+                cids: BTreeSet::from([root]),
+            })
+            .await
+        {
+            // Blocks which could not be located are listed under "NA"
+            Ok(locations) => locations
+                .iter()
+                .any(|(host, cids)| host != "NA" && cids.contains(&root.to_string())),
+            Err(_) => false,
+        }
+    }
+
+    /// Work out what `sync_bucket` would do from the current sync state, without touching the
+    /// network or disk. Byte counts are taken from the local CAR indexes.
+    pub fn sync_bucket_dry_run(&self) -> Result<SyncPlan, NativeError> {
+        let local = self.get_local();
+        Ok(match &self.sync_state {
+            SyncState::Unknown => SyncPlan::DetermineState,
+            SyncState::Unlocalized | SyncState::Behind => SyncPlan::DownloadMetadata,
+            SyncState::Unpublished | SyncState::Ahead => {
+                let local = local?;
+                // An interrupted upload of unchanged content is continued rather than redone
+                if let (Some(pending), Some(storage_ticket)) =
+                    (&local.pending_upload, &local.storage_ticket)
+                {
+                    if local.content.get_root() == Some(pending.root_cid) {
+                        let size = local.content.get_delta()?.data_size();
+                        return Ok(SyncPlan::ResumeUpload {
+                            host: storage_ticket.host.clone(),
+                            offset: pending.offset,
+                            remaining_bytes: size.saturating_sub(pending.offset),
+                        });
+                    }
+                }
+                let metadata_size = local.metadata.data_size();
+                let metadata_bytes = match local.pushed_metadata {
+                    Some(pushed) if pushed.data_size <= metadata_size => {
+                        metadata_size - pushed.data_size
+                    }
+                    _ => metadata_size,
+                };
+                SyncPlan::Upload {
+                    create_remote: self.get_id().is_err(),
+                    metadata_bytes,
+                    content_bytes: local.content.get_delta()?.data_size(),
+                }
+            }
+            SyncState::MetadataSynced => SyncPlan::Reconstruct {
+                origin: local?.origin,
+            },
+            SyncState::AllSynced => SyncPlan::AlreadySynced,
+            SyncState::Diverged => SyncPlan::Diverged,
+        })
+    }
+
+    /// Sync. If `force` is set, an already synced Bucket will have its metadata and content pushed again
+    pub async fn sync_bucket(&mut self, force: bool) -> Result<String, NativeError> {
+        #[cfg(feature = "metrics")]
+        let _timer = crate::native::metrics::SyncTimer::start();
         let mut global = GlobalConfig::from_disk().await?;
         let mut client = global.get_client().await?;
+        // Treat a synced Bucket as though it were ahead in order to re-run the upload path
+        if force && self.sync_state == SyncState::AllSynced {
+            info!("Forcing a re-push of this Drive...");
+            self.sync_state = SyncState::Ahead;
+        }
+        // Pushing over changes made elsewhere would lose them, so only do it when asked to
+        if force && self.sync_state == SyncState::Diverged {
+            warn!("Overwriting remote changes to this Drive...");
+            self.sync_state = SyncState::Ahead;
+        }
         match &self.sync_state {
             // Download the Bucket
             SyncState::Unlocalized | SyncState::Behind => {
                 let current = Metadata::read_current(self.get_id()?, &mut client).await?;
-                let mut byte_stream = current.pull(&mut client).await?;
+                let metadata = download_metadata(&current, &mut client).await?;
 
                 self.get_or_init_origin().await.ok();
 
-                let mut buffer = <Vec<u8>>::new();
-                // Write every chunk to it
-                while let Some(chunk) = byte_stream.next().await {
-                    tokio::io::copy(&mut chunk.map_err(ApiError::http)?.as_ref(), &mut buffer)
-                        .await?;
-                }
-                // Attempt to create a CARv2 BlockStore from the data
-                let metadata = CarV2MemoryBlockStore::try_from(buffer)?;
                 // Grab the metadata file
                 let mut metadata_file =
                     tokio::fs::File::create(&self.get_local()?.metadata.path).await?;
                 metadata_file.write_all(&metadata.get_data()).await?;
                 // Write that data out to the metadatas
 
+                // The server has exactly this version, so future pushes can be deltas over it
+                let mut local = self.get_local()?;
+                local.pushed_metadata = metadata.get_root().map(|metadata_cid| PushedMetadata {
+                    metadata_cid,
+                    data_size: metadata.data_size(),
+                });
+                global.update_config(&local)?;
+                self.set_local(local);
+
                 info!("{}", "<< METADATA RECONSTRUCTED >>".green());
                 self.sync_state = SyncState::MetadataSynced;
                 Ok(format!(
@@ -153,6 +446,12 @@ impl OmniBucket {
             }
             // Upload the Bucket
             SyncState::Unpublished | SyncState::Ahead => {
+                // Pick up an interrupted upload rather than starting over
+                if !force {
+                    if let Some(result) = self.resume_upload(&mut global, &mut client).await? {
+                        return Ok(result);
+                    }
+                }
                 let mut local = self.get_local()?;
                 let wrapping_key = global.wrapping_key().await?;
                 let fs = local.unlock_fs(&wrapping_key).await?;
@@ -164,8 +463,8 @@ impl OmniBucket {
                     let (remote, _) = Bucket::create(
                         local.name.clone(),
                         pem,
-                        BucketType::Interactive,
-                        StorageClass::Hot,
+                        local.bucket_type,
+                        local.bucket_type.defaults().storage_class,
                         &mut client,
                     )
                     .await?;
@@ -181,18 +480,52 @@ impl OmniBucket {
                 let local_content_cid = local
                     .content
                     .get_root()
-                    .ok_or(FilesystemError::missing_metadata("root cid"))?;
+                    .ok_or(NativeError::missing_root("root cid"))?;
                 let local_metadata_cid = local
                     .metadata
                     .get_root()
-                    .ok_or(FilesystemError::missing_metadata("metdata cid"))?;
-                let delta = local.content.get_delta()?;
+                    .ok_or(NativeError::missing_root("metdata cid"))?;
+                // Content which rolled over into new CARs would never have them uploaded
+                local.content.check_uploadable()?;
+                let delta_data_size = local.content.get_delta()?.data_size();
+                #[cfg(feature = "metrics")]
+                let delta_blocks = local
+                    .content
+                    .get_delta()?
+                    .car
+                    .car
+                    .index
+                    .borrow()
+                    .get_all_cids()
+                    .len() as u64;
+
+                // Make sure the metadata does not expose the structure of the FileSystem
+                let metadata_bytes = tokio::fs::read(&local.metadata.path).await?;
+                let plaintext = fs.plaintext_names(&local.metadata, &metadata_bytes).await?;
+                let metadata_encrypted = plaintext.metadata_encryption()?;
+
+                // Only send the blocks the server is missing if it has a prior version and knows
+                // how to apply them over it; otherwise the whole metadata CAR is pushed
+                let metadata_delta = if client.supports(None, METADATA_DELTA).await {
+                    local.metadata_delta().await?
+                } else {
+                    None
+                };
+                let (delta_base_cid, metadata_stream) = match metadata_delta {
+                    Some((base_cid, metadata_delta)) => {
+                        (Some(base_cid.to_string()), metadata_delta.get_data().into())
+                    }
+                    None => (
+                        None,
+                        tokio::fs::File::open(&local.metadata.path).await?.into(),
+                    ),
+                };
 
                 // Push the metadata
                 let (metadata, host, authorization) = Metadata::push(
                     PushMetadata {
                         bucket_id,
-                        expected_data_size: delta.data_size(),
+                        expected_data_size: delta_data_size,
                         root_cid: local_content_cid.to_string(),
                         metadata_cid: local_metadata_cid.to_string(),
                         previous_cid: local.previous_cid.map(|cid| cid.to_string()),
@@ -203,14 +536,18 @@ impl OmniBucket {
                             .iter()
                             .map(|v| v.to_string())
                             .collect(),
-                        metadata_stream: tokio::fs::File::open(&local.metadata.path).await?.into(),
+                        metadata_encrypted,
+                        delta_base_cid,
+                        metadata_stream,
                     },
                     &mut client,
                 )
                 .await?;
+                local.mark_metadata_pushed();
 
                 // Empty the list of deleted blocks, now that it's the server's problem
                 local.deleted_block_cids = BTreeSet::new();
+                let previous_ticket = local.storage_ticket.clone();
 
                 if host.is_none() && authorization.is_none() {
                     local.storage_ticket = None;
@@ -228,6 +565,9 @@ impl OmniBucket {
                         };
                         storage_ticket.create_grant(&mut client).await?;
                         local.storage_ticket = Some(storage_ticket.clone());
+                        // Record the storage host before uploading, so that if the upload is
+                        // cut short the next sync can finish it against the same metadata
+                        global.update_config(&local)?;
                         local
                             .content
                             .upload(storage_ticket.host, metadata.id, &mut client)
@@ -235,8 +575,27 @@ impl OmniBucket {
                     }
                     // Already granted, still upload
                     (Some(host), None) => {
+                        // The remote may have lost our grant, so create a new one
+                        if force {
+                            local.storage_ticket = Some(self.regrant(&host, &mut client).await?);
+                        }
                         local.content.upload(host, metadata.id, &mut client).await
                     }
+                    // Forced re-push, or metadata pushed by an interrupted sync whose content
+                    // never finished uploading, to the last known storage host
+                    (None, None)
+                        if previous_ticket.is_some()
+                            && (force || metadata.state != MetadataState::Current) =>
+                    {
+                        let storage_ticket = self
+                            .regrant(&previous_ticket.expect("checked above").host, &mut client)
+                            .await?;
+                        local.storage_ticket = Some(storage_ticket.clone());
+                        local
+                            .content
+                            .upload(storage_ticket.host, metadata.id, &mut client)
+                            .await
+                    }
                     // No uploading required
                     _ => {
                         global.update_config(&local)?;
@@ -245,12 +604,28 @@ impl OmniBucket {
                     }
                 };
 
+                // Remember how much of the content made it, so the next sync can continue
+                local.pending_upload = match (&upload_result, local.storage_ticket.clone()) {
+                    (Err(_), Some(storage_ticket)) => Some(
+                        pending_upload(
+                            &storage_ticket.host,
+                            metadata.id,
+                            local_content_cid,
+                            &mut client,
+                        )
+                        .await,
+                    ),
+                    _ => None,
+                };
+
                 global.update_config(&local)?;
                 self.set_local(local);
 
                 match upload_result {
                     // Upload succeeded
                     Ok(()) => {
+                        #[cfg(feature = "metrics")]
+                        crate::native::metrics::blocks_uploaded(delta_blocks);
                         self.sync_state = SyncState::AllSynced;
                         Metadata::read_current(bucket_id, &mut client)
                             .await
@@ -276,14 +651,18 @@ impl OmniBucket {
             SyncState::MetadataSynced => {
                 let local = self.get_local()?;
                 let api_blockstore_client = client.clone();
-                let mut api_blockstore = BanyanApiBlockStore::from(api_blockstore_client);
+                let api_blockstore = BanyanApiBlockStore::from(api_blockstore_client);
                 let metadata_root_cid = local
                     .metadata
                     .get_root()
-                    .ok_or(FilesystemError::missing_metadata("root cid"))?;
+                    .ok_or(NativeError::missing_root("root cid"))?;
                 let mut cids = BTreeSet::new();
                 cids.insert(metadata_root_cid);
-                api_blockstore.find_cids(cids).await?;
+                api_blockstore
+                    .find_cids_with_progress(cids, LOCATE_BATCH_SIZE, |resolved, total| {
+                        info!("Resolving blocks {resolved}/{total}")
+                    })
+                    .await?;
                 // If getting a block is an error
                 if api_blockstore.get_block(&metadata_root_cid).await.is_err() {
                     // Grab storage host
@@ -291,9 +670,7 @@ impl OmniBucket {
                         .clone()
                         .storage_ticket
                         .map(|ticket| ticket.host)
-                        .ok_or(NativeError::custom_error(
-                            "unable to determine storage host",
-                        ))?;
+                        .ok_or(NativeError::missing_storage_host())?;
                     // Get authorization
                     let authorization = self.get_remote()?.get_grants_token(&mut client).await?;
                     // Create a grant for this Client so that future BlockStore calls will succeed
@@ -304,14 +681,22 @@ impl OmniBucket {
                     storage_ticket.create_grant(&mut client).await?;
                 }
 
-                // Open the FileSystem
-                let fs = FsMetadata::unlock(&global.wrapping_key().await?, &local.metadata).await?;
                 // Reconstruct the data on disk
-                let restoration_result = restore::pipeline(self.clone()).await;
+                let restoration_result = restore::pipeline(
+                    self.clone(),
+                    false,
+                    restore::DEFAULT_RESTORE_PARALLELISM,
+                    restore::WriteStrategy::default(),
+                    None,
+                )
+                .await;
                 // If we succeed at reconstructing
                 if restoration_result.is_ok() {
                     // Save the metadata in the content store as well
-                    let metadata_cid = local.metadata.get_root().unwrap();
+                    let metadata_cid = local
+                        .metadata
+                        .get_root()
+                        .ok_or(NativeError::missing_root("metadata cid"))?;
                     let ipld = local
                         .metadata
                         .get_deserializable::<Ipld>(&metadata_cid)
@@ -322,8 +707,10 @@ impl OmniBucket {
                         .put_serializable(&ipld)
                         .await
                         .map_err(Box::from)?;
-                    local.content.set_root(&content_cid);
-                    assert_eq!(metadata_cid, content_cid);
+                    local.content.set_root(&content_cid)?;
+                    if metadata_cid != content_cid {
+                        return Err(NativeError::not_synced());
+                    }
                     // We're now all synced up
                     self.sync_state = SyncState::AllSynced;
                 }
@@ -335,6 +722,7 @@ impl OmniBucket {
                 "{}",
                 "This Bucket data is already synced :)".green()
             )),
+            SyncState::Diverged => Err(NativeError::diverged(&self.get_local()?.name)),
             SyncState::Unknown => {
                 self.determine_sync_state().await?;
                 Ok(format!(
@@ -344,4 +732,624 @@ impl OmniBucket {
             }
         }
     }
+
+    /// Pull the content of a single subdirectory, fetching only the blocks of the files
+    /// at or below `path` rather than the entire content tree
+    pub async fn sync_bucket_path(&mut self, path: &Path) -> Result<String, NativeError> {
+        if self.sync_state == SyncState::Unknown {
+            self.determine_sync_state().await?;
+        }
+        // The metadata is needed to know which blocks belong to the path
+        if matches!(self.sync_state, SyncState::Unlocalized | SyncState::Behind) {
+            self.sync_bucket(false).await?;
+        }
+        match self.sync_state {
+            SyncState::MetadataSynced => {}
+            SyncState::AllSynced => {
+                return Ok(format!(
+                    "{}",
+                    "This Bucket data is already synced :)".green()
+                ))
+            }
+            _ => {
+                return Err(NativeError::custom_error(&format!(
+                    "a Drive must have its metadata synced to pull a path, but it is {}",
+                    self.sync_state
+                )))
+            }
+        }
+
+        let global = GlobalConfig::from_disk().await?;
+        let mut client = global.get_client().await?;
+        let local = self.get_local()?;
+        let fs = FsMetadata::unlock(&global.wrapping_key().await?, &local.metadata).await?;
+        // Walk the forest from the node at the path to find its content
+        let cids = fs.get_content_cids(path, &local.metadata).await?;
+        info!("Fetching {} blocks below {}...", cids.len(), path.display());
+
+        let api_blockstore = BanyanApiBlockStore::from(client.clone());
+        api_blockstore
+            .find_cids_with_progress(cids.clone(), LOCATE_BATCH_SIZE, |resolved, total| {
+                info!("Resolving blocks {resolved}/{total}")
+            })
+            .await?;
+        // Make sure this Client is allowed to read from the storage host
+        if let (Some(cid), Some(ticket)) = (cids.first(), &local.storage_ticket) {
+            if api_blockstore.get_block(cid).await.is_err() {
+                self.regrant(&ticket.host, &mut client).await?;
+            }
+        }
+        let fetched = restore::fetch_blocks(&cids, &api_blockstore, &local.content).await?;
+        // Persist the index of the blocks that were just written
+        local.content.get_delta()?.to_disk()?;
+        info!("Fetched {fetched} new blocks");
+
+        restore::subtree(
+            self.clone(),
+            path,
+            None,
+            false,
+            restore::DEFAULT_RESTORE_PARALLELISM,
+            restore::WriteStrategy::default(),
+            None,
+        )
+        .await
+    }
+
+    /// Compute the files which changed between two versions of the remote metadata of this
+    /// Drive, given by their metadata ids, from the older to the newer
+    pub async fn diff(&self, from: Uuid, to: Uuid) -> Result<FsDiff, NativeError> {
+        let global = GlobalConfig::from_disk().await?;
+        let mut client = global.get_client().await?;
+        let wrapping_key = global.wrapping_key().await?;
+        let bucket_id = self.get_id()?;
+
+        let from_metadata = Metadata::read(bucket_id, from, &mut client).await?;
+        let to_metadata = Metadata::read(bucket_id, to, &mut client).await?;
+        let from_store = download_metadata(&from_metadata, &mut client).await?;
+        let to_store = download_metadata(&to_metadata, &mut client).await?;
+        let from_fs = FsMetadata::unlock(&wrapping_key, &from_store).await?;
+        let to_fs = FsMetadata::unlock(&wrapping_key, &to_store).await?;
+        Ok(from_fs.diff(&from_store, &to_fs, &to_store).await?)
+    }
+
+    /// List the Blocks of the local copy of this Drive reachable from a root, by default the
+    /// root of its content. Blocks only kept with the metadata are followed as well.
+    pub async fn inspect(&self, root: Option<Cid>) -> Result<DagTree, NativeError> {
+        let local = self.get_local()?;
+        let root = root
+            .or(local.content.get_root())
+            .ok_or(NativeError::missing_root("content root"))?;
+        let store = DoubleSplitStore::new(&local.content, &local.metadata);
+        Ok(DagTree::build(&root, &store).await?)
+    }
+}
+
+/// Compare the local metadata with the remote history of a Drive, given as the previous
+/// metadata CID of every remote version. Local metadata the remote already has is behind it.
+/// Otherwise the local metadata is ahead if the current remote version is among its ancestors,
+/// found by walking back from the versions it is known to descend from. In every other case,
+/// including when the two share no history at all, both sides have changed and diverged.
+fn compare_histories(
+    local_cid: Option<&str>,
+    local_ancestors: &[String],
+    remote_cid: &str,
+    remote_history: &BTreeMap<String, Option<String>>,
+) -> SyncState {
+    if local_cid.is_some_and(|cid| remote_history.contains_key(cid)) {
+        return SyncState::Behind;
+    }
+    if local_ancestors
+        .iter()
+        .any(|cid| ancestry(cid, remote_history).contains(remote_cid))
+    {
+        return SyncState::Ahead;
+    }
+    SyncState::Diverged
+}
+
+/// A metadata CID and every version it descends from, guarding against cycles in bad data
+fn ancestry(cid: &str, history: &BTreeMap<String, Option<String>>) -> BTreeSet<String> {
+    let mut ancestry = BTreeSet::new();
+    let mut next = Some(cid.to_string());
+    while let Some(cid) = next {
+        if !ancestry.insert(cid.clone()) {
+            break;
+        }
+        next = history.get(&cid).cloned().flatten();
+    }
+    ancestry
+}
+
+/// Download a version of the metadata of a Drive into memory
+async fn download_metadata(
+    metadata: &Metadata,
+    client: &mut Client,
+) -> Result<CarV2MemoryBlockStore, NativeError> {
+    let rate_limit = client.rate_limit();
+    let mut byte_stream = metadata.pull(client).await?;
+    let mut buffer = <Vec<u8>>::new();
+    // Write every chunk to it
+    while let Some(chunk) = byte_stream.next().await {
+        let chunk = chunk.map_err(ApiError::http)?;
+        if let Some(rate_limit) = &rate_limit {
+            rate_limit.acquire(chunk.len() as u64).await;
+        }
+        tokio::io::copy(&mut chunk.as_ref(), &mut buffer).await?;
+    }
+    #[cfg(feature = "metrics")]
+    crate::native::metrics::bytes_transferred(
+        crate::native::metrics::Direction::Download,
+        buffer.len() as u64,
+    );
+    // Attempt to create a CARv2 BlockStore from the data
+    Ok(CarV2MemoryBlockStore::try_from(buffer)?)
+}
+
+/// The staging host at `host`, if it says it can continue an interrupted upload
+async fn resumable_host(host: &str, client: &mut Client) -> Option<Url> {
+    let host_url = Url::parse(host).ok()?;
+    client
+        .supports(Some(&host_url), RESUMABLE_UPLOAD)
+        .await
+        .then_some(host_url)
+}
+
+/// Record how much of an interrupted upload the staging service acknowledged. If it can not
+/// say, the next attempt starts from the beginning of the content again.
+async fn pending_upload(
+    host: &str,
+    metadata_id: Uuid,
+    root_cid: Cid,
+    client: &mut Client,
+) -> PendingUpload {
+    let offset = match resumable_host(host, client).await {
+        Some(host_url) => client
+            .call(UploadProgress {
+                host_url,
+                metadata_id,
+            })
+            .await
+            .map(|progress| progress.bytes_received)
+            .unwrap_or(0),
+        None => 0,
+    };
+    PendingUpload {
+        metadata_id,
+        root_cid,
+        offset,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare_histories, OmniBucket, PendingUpload, SyncPlan, SyncState};
+    use crate::{
+        api::{
+            client::{Client, Credentials},
+            models::{
+                bucket::{Bucket, BucketType, StorageClass},
+                storage_ticket::StorageTicket,
+            },
+        },
+        blockstore::{MultiCarV2DiskBlockStore, RootedBlockStore},
+        filesystem::PlaintextNames,
+        native::{configuration::globalconfig::GlobalConfig, NativeError},
+        utils::testing::{
+            local_operations::test_setup_bucket,
+            mock_server::{mock_server, MockRequest, MockResponse},
+        },
+    };
+    use serial_test::serial;
+    use std::{
+        collections::BTreeMap,
+        fs::{create_dir_all, remove_dir_all},
+        path::Path,
+        sync::{Arc, Mutex},
+    };
+    use tomb_crypt::prelude::{EcSignatureKey, PrivateKey};
+    use uuid::Uuid;
+    use wnfs::libipld::Cid;
+
+    #[test]
+    fn diverged_histories() {
+        // The remote went a -> b -> c
+        let remote_history = BTreeMap::from([
+            ("a".to_string(), None),
+            ("b".to_string(), Some("a".to_string())),
+            ("c".to_string(), Some("b".to_string())),
+        ]);
+        let ancestors = |cids: &[&str]| cids.iter().map(|cid| cid.to_string()).collect::<Vec<_>>();
+
+        // Local metadata the remote has seen is behind
+        assert_eq!(
+            compare_histories(Some("b"), &ancestors(&["a"]), "c", &remote_history),
+            SyncState::Behind
+        );
+        // Local changes made on top of the current remote version are ahead
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["c"]), "c", &remote_history),
+            SyncState::Ahead
+        );
+        // Local changes made on top of an older version conflict with the newer remote ones
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["a", "x"]), "c", &remote_history),
+            SyncState::Diverged
+        );
+        // Without any shared history neither side can be trusted over the other
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["x"]), "c", &remote_history),
+            SyncState::Diverged
+        );
+        // Local changes on top of a version pushed after the current one are still ahead
+        let mut pushed_history = remote_history.clone();
+        pushed_history.insert("y".to_string(), Some("c".to_string()));
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["y"]), "c", &pushed_history),
+            SyncState::Ahead
+        );
+        // Cycles in the history end the walk
+        let cyclic = BTreeMap::from([
+            ("a".to_string(), Some("b".to_string())),
+            ("b".to_string(), Some("a".to_string())),
+        ]);
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["x"]), "a", &cyclic),
+            SyncState::Diverged
+        );
+        assert_eq!(
+            compare_histories(Some("d"), &ancestors(&["b"]), "a", &cyclic),
+            SyncState::Ahead
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn metadata_only_mount() -> Result<(), NativeError> {
+        let test_name = "metadata_only_mount";
+        let (_, mut local, _) = test_setup_bucket(test_name).await?;
+
+        // With content present the Drive stays synced
+        let mut omni = OmniBucket::from_local(&local);
+        omni.sync_state = SyncState::AllSynced;
+        assert!(omni.verify_content().await?);
+        assert_eq!(omni.sync_state, SyncState::AllSynced);
+
+        // Replace the content with an empty store that only knows the root
+        let content_root = local.content.get_root().expect("no content root");
+        local.content = MultiCarV2DiskBlockStore::new(&local.origin.join("empty_content"))?;
+        local.content.add_delta()?;
+        local.content.set_root(&content_root)?;
+
+        // Without content the Drive is only metadata synced
+        let mut omni = OmniBucket::from_local(&local);
+        omni.sync_state = SyncState::AllSynced;
+        assert!(!omni.verify_content().await?);
+        assert_eq!(omni.sync_state, SyncState::MetadataSynced);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn dry_run_sync() -> Result<(), NativeError> {
+        let test_name = "dry_run_sync";
+        let (_, local, _) = test_setup_bucket(test_name).await?;
+
+        // A Drive which was never pushed would be created and uploaded in full
+        let omni = OmniBucket::from_local(&local);
+        let SyncPlan::Upload {
+            create_remote,
+            metadata_bytes,
+            content_bytes,
+        } = omni.sync_bucket_dry_run()?
+        else {
+            panic!("unpublished Drive would not be uploaded");
+        };
+        assert!(create_remote);
+        assert_eq!(metadata_bytes, local.metadata.data_size());
+        assert_eq!(content_bytes, local.content.get_delta()?.data_size());
+        // Nothing changed as a result
+        assert_eq!(omni.sync_state, SyncState::Unpublished);
+        let global = GlobalConfig::from_disk().await?;
+        assert!(global
+            .get_bucket(&local.origin)
+            .is_some_and(|bucket| bucket.remote_id.is_none()));
+
+        let mut omni = OmniBucket::from_local(&local);
+        omni.sync_state = SyncState::MetadataSynced;
+        assert_eq!(
+            omni.sync_bucket_dry_run()?,
+            SyncPlan::Reconstruct {
+                origin: local.origin.clone()
+            }
+        );
+        omni.sync_state = SyncState::AllSynced;
+        assert_eq!(omni.sync_bucket_dry_run()?, SyncPlan::AlreadySynced);
+        omni.sync_state = SyncState::Behind;
+        assert_eq!(omni.sync_bucket_dry_run()?, SyncPlan::DownloadMetadata);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn dry_run_delete() -> Result<(), NativeError> {
+        let test_name = "dry_run_delete";
+        let (global, local, _) = test_setup_bucket(test_name).await?;
+        let wrapping_key = global.wrapping_key().await?;
+
+        let omni = OmniBucket::from_local(&local);
+        let report = omni.deletion_report().await?;
+        assert_eq!(report.name.as_deref(), Some(test_name));
+        assert!(report.local_size.unwrap_or(0) > 0);
+        assert!(report.remote_id.is_none());
+        assert!(report.to_string().contains("Nothing was deleted"));
+
+        // The Drive is still fully intact
+        let global = GlobalConfig::from_disk().await?;
+        assert!(global.get_bucket(&local.origin).is_some());
+        assert!(local.metadata.path.exists());
+        assert!(local.unlock_fs(&wrapping_key).await.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn delete_reports_removed() -> Result<(), NativeError> {
+        let test_name = "delete_reports_removed";
+        let (_, local, _) = test_setup_bucket(test_name).await?;
+
+        // Only the local half exists, so only it can be deleted
+        let omni = OmniBucket::from_local(&local);
+        let deletion = omni.delete(true, true).await?;
+        assert!(deletion.deleted_locally);
+        assert!(!deletion.deleted_remotely);
+        assert!(deletion.removed.local_size.unwrap_or(0) > 0);
+        assert!(deletion.removed.remote_id.is_none());
+        assert!(!deletion.to_string().contains("Nothing was deleted"));
+        let global = GlobalConfig::from_disk().await?;
+        assert!(global.get_bucket(&local.origin).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn stale_pending_upload() -> Result<(), NativeError> {
+        let test_name = "stale_pending_upload";
+        let (mut global, mut local, _) = test_setup_bucket(test_name).await?;
+
+        // An upload was interrupted, but the content has changed since
+        local.storage_ticket = Some(StorageTicket {
+            host: "http://127.0.0.1:1".to_string(),
+            authorization: String::new(),
+        });
+        local.pending_upload = Some(PendingUpload {
+            metadata_id: Uuid::new_v4(),
+            root_cid: Cid::default(),
+            offset: 1024,
+        });
+        global.update_config(&local)?;
+
+        // So it is abandoned rather than resumed
+        let mut omni = OmniBucket::from_local(&local);
+        let mut client = global.get_client().await?;
+        assert!(omni
+            .resume_upload(&mut global, &mut client)
+            .await?
+            .is_none());
+        assert!(omni.get_local()?.pending_upload.is_none());
+        let global = GlobalConfig::from_disk().await?;
+        let local = global.get_bucket(&local.origin).expect("bucket missing");
+        assert!(local.pending_upload.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn forced_push() -> Result<(), NativeError> {
+        let test_name = "forced_push";
+        let (mut global, mut local, _) = test_setup_bucket(test_name).await?;
+
+        // The remote already has this exact version, current and stored
+        let bucket_id = Uuid::new_v4();
+        let metadata = format!(
+            r#"{{"id":"{}","root_cid":"{}","metadata_cid":"{}","previous_cid":null,"data_size":0,"state":"current","created_at":0,"updated_at":0,"snapshot_id":null}}"#,
+            Uuid::new_v4(),
+            local.content.get_root().expect("no content root"),
+            local.metadata.get_root().expect("no metadata root"),
+        );
+        let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+        let received = requests.clone();
+        let url = mock_server(move |request| {
+            received.lock().unwrap().push(request.clone());
+            if request.is(
+                "GET",
+                &format!("/api/v1/buckets/{bucket_id}/metadata/current"),
+            ) {
+                MockResponse::ok(metadata.clone())
+            } else if request.is("GET", &format!("/api/v1/buckets/{bucket_id}/metadata")) {
+                MockResponse::ok(format!("[{metadata}]"))
+            } else if request.is("GET", "/api/v1/buckets/") {
+                MockResponse::ok(r#"{"authorization_token":"token"}"#)
+            } else if request.is("POST", "/") {
+                MockResponse::empty("200 OK")
+            } else {
+                MockResponse::empty("404 Not Found")
+            }
+        })
+        .await;
+        let mut client = Client::new(&url).expect("client");
+        client.with_credentials(Credentials {
+            user_id: Uuid::new_v4(),
+            signing_key: EcSignatureKey::generate().await.expect("signing key"),
+        });
+        global.save_client(client).await?;
+        local.remote_id = Some(bucket_id);
+        local.storage_ticket = Some(StorageTicket {
+            host: url,
+            authorization: String::new(),
+        });
+        global.update_config(&local)?;
+
+        let mut omni = OmniBucket::from_local(&local);
+        omni.set_remote(Bucket {
+            id: bucket_id,
+            name: test_name.to_string(),
+            r#type: BucketType::Interactive,
+            storage_class: StorageClass::Hot,
+            read_only: false,
+        });
+        omni.sync_state = SyncState::AllSynced;
+
+        // A normal sync leaves the remote alone
+        omni.sync_bucket(false).await?;
+        assert!(requests.lock().unwrap().is_empty());
+
+        // A forced one grants this client storage again and uploads the content anew
+        omni.sync_bucket(true).await?;
+        assert_eq!(omni.sync_state, SyncState::AllSynced);
+        let requests = requests.lock().unwrap();
+        let posted = |path: &str| {
+            requests
+                .iter()
+                .filter(|request| request.is("POST", path))
+                .count()
+        };
+        assert_eq!(posted("/api/v1/client_grant"), 1);
+        assert_eq!(posted("/api/v1/upload"), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn reconcile_and_adopt() -> Result<(), NativeError> {
+        let test_name = "reconcile_and_adopt";
+        let root = Path::new("test").join(test_name);
+        // Where a remote Drive named `../reconcile_and_adopt` is adopted to
+        let adopted_origin = Path::new(env!("HOME"))
+            .join("tomb")
+            .join(format!(".._{test_name}"));
+        let mut global = GlobalConfig::from_disk().await?;
+        let tracked = |global: &GlobalConfig| {
+            global
+                .buckets
+                .iter()
+                .filter(|local| local.origin == adopted_origin || local.origin.starts_with(&root))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        for local in tracked(&global) {
+            global.remove_bucket(&local)?;
+        }
+        for path in [&root, &adopted_origin] {
+            if path.exists() {
+                remove_dir_all(path)?;
+            }
+        }
+
+        // One Drive of each kind, and a remote one whose name tries to leave the tomb directory
+        let (linked_id, deleted_id, escaping_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for (name, remote_id) in [
+            ("linked", Some(linked_id)),
+            ("deleted", Some(deleted_id)),
+            ("local", None),
+        ] {
+            let origin = root.join(name);
+            create_dir_all(&origin)?;
+            let mut local = global.get_or_init_bucket(name, &origin).await?;
+            local.remote_id = remote_id;
+            global.update_config(&local)?;
+        }
+        let escaping_name = format!("../{test_name}");
+        let buckets = format!(
+            r#"[{{"id":"{linked_id}","name":"linked","type":"interactive","storage_class":"hot"}},{{"id":"{escaping_id}","name":"{escaping_name}","type":"interactive","storage_class":"hot"}}]"#
+        );
+        let url = mock_server(move |request| {
+            if request.request_line().starts_with("GET /api/v1/buckets ") {
+                MockResponse::ok(buckets.clone())
+            } else {
+                MockResponse::empty("404 Not Found")
+            }
+        })
+        .await;
+        let mut client = Client::new(&url).expect("client");
+        client.with_credentials(Credentials {
+            user_id: Uuid::new_v4(),
+            signing_key: EcSignatureKey::generate().await.expect("signing key"),
+        });
+        global.save_client(client.clone()).await?;
+
+        // Every Drive is sorted by where it is tracked
+        let mut report = OmniBucket::reconcile(&mut client).await?;
+        let ids = |drives: &[OmniBucket]| {
+            drives
+                .iter()
+                .filter_map(|drive| drive.get_id().ok())
+                .collect::<Vec<Uuid>>()
+        };
+        let names = |drives: &[OmniBucket]| {
+            drives
+                .iter()
+                .filter_map(|drive| drive.info().name)
+                .collect::<Vec<String>>()
+        };
+        assert!(ids(&report.linked).contains(&linked_id));
+        assert!(ids(&report.remote_deleted).contains(&deleted_id));
+        assert!(names(&report.local_only).contains(&"local".to_string()));
+        assert_eq!(ids(&report.remote_only), vec![escaping_id]);
+
+        // Adopting a remote only Drive tracks it locally within the tomb directory
+        report.adopt_remote_only().await?;
+        assert!(report.remote_only.is_empty());
+        let adopted = report
+            .linked
+            .iter()
+            .find(|drive| drive.get_id().ok() == Some(escaping_id))
+            .expect("remote only drive was not adopted");
+        let origin = adopted.get_local()?.origin;
+        assert_eq!(origin, adopted_origin);
+        assert!(origin.is_dir());
+        let mut global = GlobalConfig::from_disk().await?;
+        let local = global.get_bucket(&origin).expect("adopted drive missing");
+        assert_eq!(local.remote_id, Some(escaping_id));
+
+        // Adopting it again changes nothing, but another Drive can not take over its directory
+        let mut adopted = adopted.clone();
+        adopted.adopt().await?;
+        let mut namesake = adopted.get_remote()?;
+        namesake.id = Uuid::new_v4();
+        assert!(OmniBucket::from_remote(&namesake).adopt().await.is_err());
+
+        // Teardown
+        for local in tracked(&global) {
+            global.remove_bucket(&local)?;
+        }
+        remove_dir_all(&origin)?;
+        remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn encrypted_metadata() -> Result<(), NativeError> {
+        assert!(PlaintextNames::default().metadata_encryption()?);
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_filenames_warn() -> Result<(), NativeError> {
+        let plaintext = PlaintextNames {
+            dirs: vec![],
+            files: vec!["secret_plans.txt".to_string()],
+        };
+        assert!(!plaintext.metadata_encryption()?);
+        Ok(())
+    }
+
+    #[test]
+    fn plaintext_dirs_refused() {
+        let plaintext = PlaintextNames {
+            dirs: vec!["secret_plans".to_string()],
+            files: vec![],
+        };
+        assert!(plaintext.metadata_encryption().is_err());
+    }
 }
@@ -1,7 +1,13 @@
 #[cfg(feature = "cli")]
 use crate::cli::specifiers::DriveSpecifier;
 use crate::{
-    api::models::bucket::{Bucket as RemoteBucket, BucketType, StorageClass},
+    api::{
+        client::Client,
+        models::{
+            bucket::{Bucket as RemoteBucket, BucketType, StorageClass},
+            metadata::Metadata,
+        },
+    },
     native::{
         configuration::globalconfig::GlobalConfig,
         sync::{LocalBucket, SyncState},
@@ -9,7 +15,9 @@ use crate::{
     },
     prelude::filesystem::FsMetadata,
 };
+use bytesize::ByteSize;
 use colored::{ColoredString, Colorize};
+use serde::{Serialize, Serializer};
 use std::{
     collections::HashMap,
     fmt::Display,
@@ -125,8 +133,20 @@ impl OmniBucket {
         self.remote = Some(remote);
     }
 
-    /// Create a new bucket
+    /// Create a new interactive bucket
     pub async fn create(name: &str, origin: &Path) -> Result<OmniBucket, NativeError> {
+        Self::create_with_type(name, origin, BucketType::Interactive, false).await
+    }
+
+    /// Create a new bucket, using the defaults of its type, optionally encrypting the names of
+    /// everything stored in it
+    pub async fn create_with_type(
+        name: &str,
+        origin: &Path,
+        bucket_type: BucketType,
+        encrypt_names: bool,
+    ) -> Result<OmniBucket, NativeError> {
+        let defaults = bucket_type.defaults();
         let mut global = GlobalConfig::from_disk().await?;
 
         let mut omni = OmniBucket {
@@ -136,6 +156,7 @@ impl OmniBucket {
         };
 
         // If this bucket already exists both locally and remotely
+        let existed_locally = global.get_bucket(origin).is_some();
         if let Some(bucket) = global.get_bucket(origin) {
             if bucket.remote_id.is_some() {
                 // Prevent the user from re-creating it
@@ -148,59 +169,225 @@ impl OmniBucket {
         let public_key = wrapping_key.public_key()?;
         let pem = String::from_utf8(public_key.export().await?)?;
 
-        // Initialize remotely
-        if let Ok((remote, _)) = RemoteBucket::create(
-            name.to_string(),
-            pem,
-            BucketType::Interactive,
-            StorageClass::Hot,
-            &mut global.get_client().await?,
-        )
-        .await
-        {
-            // Update in obj
-            omni.set_remote(remote);
+        // Initialize remotely, settling for a local only Drive if there are no credentials or
+        // the remote can't be reached
+        let mut client = global.get_client().await?;
+        if client.is_authenticated().await {
+            match RemoteBucket::create(
+                name.to_string(),
+                pem,
+                bucket_type,
+                defaults.storage_class,
+                &mut client,
+            )
+            .await
+            {
+                // Update in obj
+                Ok((remote, _)) => omni.set_remote(remote),
+                Err(err) if err.is_unreachable() => {
+                    tracing::warn!(
+                        "creating {name} locally only, as the remote is unreachable: {err}"
+                    )
+                }
+                // The remote refused the Drive, which it would do again when syncing
+                Err(err) => {
+                    return Err(NativeError::creation_failed(
+                        "remotely",
+                        &err.to_string(),
+                        None,
+                    ))
+                }
+            }
         }
 
         // Initialize locally
-        if let Ok(mut local) = global.get_or_init_bucket(name, origin).await {
-            // If a remote bucket was made successfully
-            if let Ok(remote) = omni.get_remote() {
-                // Also save that in the local obj
-                local.remote_id = Some(remote.id);
+        let local = match global.get_or_init_bucket(name, origin).await {
+            Ok(mut local) => {
+                local.bucket_type = bucket_type;
+                // Leave the codec of an existing Drive as it was chosen
+                if !existed_locally {
+                    local.compression = defaults.compression;
+                }
+                // If a remote bucket was made successfully
+                if let Ok(remote) = omni.get_remote() {
+                    // Also save that in the local obj
+                    local.remote_id = Some(remote.id);
+                    local.storage_class = Some(remote.storage_class);
+                }
+                // Only a Drive created by this call is still empty enough to encrypt its names
+                let encrypted = if encrypt_names && !existed_locally {
+                    local
+                        .encrypt_names(&wrapping_key)
+                        .await
+                        .map_err(NativeError::from)
+                } else {
+                    Ok(())
+                };
+                // Update in global
+                encrypted.and_then(|_| global.update_config(&local).map(|_| local))
+            }
+            Err(err) => Err(err),
+        };
+
+        match local {
+            Ok(local) => omni.local = Some(local),
+            Err(err) => {
+                // Undo the local half if this call created it
+                if !existed_locally {
+                    if let Some(local) = global.get_bucket(origin) {
+                        let _ = global.remove_bucket(&local);
+                    }
+                }
+                // Undo the remote half, if there is one
+                let rolled_back = match omni.get_remote() {
+                    Ok(remote) => Some(
+                        RemoteBucket::delete_by_id(&mut client, remote.id)
+                            .await
+                            .is_ok(),
+                    ),
+                    Err(_) => None,
+                };
+                return Err(NativeError::creation_failed(
+                    "locally",
+                    &err.to_string(),
+                    rolled_back,
+                ));
             }
-            // Update in global and obj
-            global.update_config(&local.clone())?;
-            omni.local = Some(local);
         }
 
         Ok(omni)
     }
 
-    /// Delete an individual Bucket
+    /// Summarize what is known about this Bucket locally and remotely
+    pub fn info(&self) -> DriveInfo {
+        let local = self.local.as_ref();
+        let remote = self.remote.as_ref();
+        DriveInfo {
+            name: remote
+                .map(|remote| remote.name.clone())
+                .or_else(|| local.map(|local| local.name.clone())),
+            drive_id: self.get_id().ok(),
+            origin: local.map(|local| local.origin.clone()),
+            drive_type: remote
+                .map(|remote| remote.r#type)
+                .or_else(|| local.map(|local| local.bucket_type)),
+            storage_class: remote
+                .map(|remote| remote.storage_class.clone())
+                .or_else(|| local.and_then(|local| local.storage_class.clone())),
+            storage_host: local
+                .and_then(|local| local.storage_ticket.as_ref())
+                .map(|ticket| ticket.host.clone()),
+            locally_tracked: local.is_some(),
+            remotely_tracked: remote.is_some(),
+            read_only: local.is_some_and(|local| local.read_only)
+                || remote.is_some_and(|remote| remote.read_only),
+            sync_state: self.sync_state.clone(),
+        }
+    }
+
+    /// Delete an individual Bucket, from whichever of the requested places it is tracked in,
+    /// reporting what was removed
     pub async fn delete(
         &self,
         local_deletion: bool,
-        mut remote_deletion: bool,
+        remote_deletion: bool,
+    ) -> Result<Deletion, NativeError> {
+        let mut removed = self.deletion_report().await?;
+        let mut global = GlobalConfig::from_disk().await?;
+        let deleted_locally = match self.get_local() {
+            Ok(local) if local_deletion => {
+                global.remove_bucket(&local)?;
+                true
+            }
+            _ => false,
+        };
+        let deleted_remotely = match self.get_remote() {
+            Ok(remote) if remote_deletion => {
+                RemoteBucket::delete_by_id(&mut global.get_client().await?, remote.id)
+                    .await
+                    .is_ok()
+            }
+            _ => false,
+        };
+
+        // Only report the data which is actually gone
+        if !deleted_locally {
+            removed.local_size = None;
+        }
+        if !deleted_remotely {
+            removed.remote_id = None;
+            removed.metadata_versions = None;
+            removed.snapshots = None;
+        }
+        Ok(Deletion {
+            deleted_locally,
+            deleted_remotely,
+            removed,
+        })
+    }
+
+    /// Move the remote data of this Bucket to another storage class, recording it locally too
+    pub async fn set_storage_class(
+        &mut self,
+        storage_class: StorageClass,
     ) -> Result<String, NativeError> {
         let mut global = GlobalConfig::from_disk().await?;
-        if local_deletion {
-            global.remove_bucket(&self.get_local()?)?;
+        let mut client = global.get_client().await?;
+        let bucket_id = self.get_remote()?.id;
+        RemoteBucket::set_storage_class(&mut client, bucket_id, storage_class.clone()).await?;
+
+        if let Some(remote) = self.remote.as_mut() {
+            remote.storage_class = storage_class.clone();
+        }
+        if let Some(local) = self.local.as_mut() {
+            local.storage_class = Some(storage_class.clone());
+            global.update_config(local)?;
         }
 
-        if remote_deletion {
-            remote_deletion =
-                RemoteBucket::delete_by_id(&mut global.get_client().await?, self.get_remote()?.id)
-                    .await
-                    .is_ok();
+        let mut message = format!(
+            "{}\ndrive_id:\t\t{}\nstorage_class:\t\t{}",
+            "| STORAGE CLASS UPDATED |".green(),
+            bucket_id,
+            storage_class
+        );
+        if storage_class == StorageClass::Cold {
+            message = format!(
+                "{message}\n{}",
+                "Reads from cold storage may be slower, and data may need to be rehydrated before it can be read".yellow()
+            );
         }
+        Ok(message)
+    }
 
-        Ok(format!(
-            "{}\ndeleted locally:\t{}\ndeleted remotely:\t{}",
-            "<< BUCKET DELETION >>".blue(),
-            bool_colorized(local_deletion),
-            bool_colorized(remote_deletion)
-        ))
+    /// Describe what deleting this Bucket would remove, without removing anything
+    pub async fn deletion_report(&self) -> Result<DeletionReport, NativeError> {
+        let local = self.get_local().ok();
+        let remote = self.get_remote().ok();
+        let mut report = DeletionReport {
+            name: remote
+                .as_ref()
+                .map(|remote| remote.name.clone())
+                .or(local.as_ref().map(|local| local.name.clone())),
+            local_size: local.as_ref().map(LocalBucket::data_size),
+            remote_id: remote.as_ref().map(|remote| remote.id),
+            metadata_versions: None,
+            snapshots: None,
+        };
+
+        if let Some(remote) = remote {
+            let mut client = GlobalConfig::from_disk().await?.get_client().await?;
+            report.metadata_versions = Metadata::read_all(remote.id, &mut client)
+                .await
+                .map(|metadatas| metadatas.len())
+                .ok();
+            report.snapshots = remote
+                .list_snapshots(&mut client)
+                .await
+                .map(|snapshots| snapshots.len())
+                .ok();
+        }
+
+        Ok(report)
     }
 
     /// List all available Buckets
@@ -248,14 +435,73 @@ impl OmniBucket {
         Ok(map.into_values().collect::<Vec<OmniBucket>>())
     }
 
+    /// Sort every Bucket tracked locally or remotely by whether it is tracked in both places.
+    /// Local Buckets whose remote id no longer names a remote Bucket are reported apart from
+    /// those which were never published.
+    pub async fn reconcile(client: &mut Client) -> Result<ReconcileReport, NativeError> {
+        let local_buckets = GlobalConfig::from_disk().await?.buckets;
+        let mut remote_buckets: HashMap<Uuid, RemoteBucket> = RemoteBucket::read_all(client)
+            .await
+            .map_err(NativeError::api)?
+            .into_iter()
+            .map(|remote| (remote.id, remote))
+            .collect();
+
+        let mut report = ReconcileReport::default();
+        for local in local_buckets {
+            match local.remote_id {
+                Some(remote_id) => match remote_buckets.remove(&remote_id) {
+                    Some(remote) => {
+                        let mut omni = OmniBucket {
+                            local: Some(local),
+                            remote: Some(remote),
+                            sync_state: SyncState::Unknown,
+                        };
+                        let _ = omni.determine_sync_state().await;
+                        report.linked.push(omni);
+                    }
+                    None => report.remote_deleted.push(OmniBucket::from_local(&local)),
+                },
+                None => report.local_only.push(OmniBucket::from_local(&local)),
+            }
+        }
+        report.remote_only = remote_buckets
+            .values()
+            .map(OmniBucket::from_remote)
+            .collect();
+        Ok(report)
+    }
+
+    /// Track a Bucket which only exists remotely in the local configuration, with an origin in
+    /// the default tomb directory. None of its files are pulled until it is synced.
+    pub async fn adopt(&mut self) -> Result<(), NativeError> {
+        let remote = self.get_remote()?;
+        if self.local.is_some() {
+            return Ok(());
+        }
+        let mut global = GlobalConfig::from_disk().await?;
+        let origin = default_origin(&remote);
+        // Never take over a directory another Bucket is already tracking
+        if global.get_bucket(&origin).is_some() {
+            return Err(NativeError::unique_error());
+        }
+        create_dir_all(&origin)?;
+        let mut local = global.get_or_init_bucket(&remote.name, &origin).await?;
+        local.remote_id = Some(remote.id);
+        local.bucket_type = remote.r#type;
+        local.storage_class = Some(remote.storage_class.clone());
+        local.read_only = remote.read_only;
+        global.update_config(&local)?;
+        self.local = Some(local);
+        Ok(())
+    }
+
     /// Get the origin for this bucket or create one in the default tomb directory if a local bucket does not yet exist
     pub async fn get_or_init_origin(&mut self) -> Result<PathBuf, NativeError> {
         if let Ok(local) = self.get_local() {
             Ok(local.origin)
         } else {
-            let new_local_origin = PathBuf::from(env!("HOME"))
-                .join("tomb")
-                .join(self.get_remote()?.name);
+            let new_local_origin = default_origin(&self.get_remote()?);
             // Remove existing contents and create a enw directory
             remove_dir_all(&new_local_origin).ok();
             create_dir_all(&new_local_origin)?;
@@ -274,6 +520,32 @@ impl OmniBucket {
         }
     }
 
+    /// Protect the bucket from modification, or lift that protection, both locally and remotely
+    pub async fn set_read_only(&mut self, read_only: bool) -> Result<String, NativeError> {
+        let mut global = GlobalConfig::from_disk().await?;
+        if let Some(remote) = self.remote.as_mut() {
+            let mut client = global.get_client().await?;
+            let mut updated = remote.clone();
+            updated.read_only = read_only;
+            updated.update(&mut client).await?;
+            *remote = updated;
+        }
+        if let Some(local) = self.local.as_mut() {
+            local.read_only = read_only;
+            global.update_config(local)?;
+        }
+
+        Ok(format!(
+            "{}\n{}",
+            if read_only {
+                "<< DRIVE LOCKED >>".green()
+            } else {
+                "<< DRIVE UNLOCKED >>".green()
+            },
+            self
+        ))
+    }
+
     /// Unlock FsMetadata
     pub async fn unlock(&self) -> Result<FsMetadata, NativeError> {
         let local = self.get_local()?;
@@ -294,6 +566,178 @@ fn bool_colorized(value: bool) -> ColoredString {
     }
 }
 
+/// Where a remote Bucket is pulled to by default, in the tomb directory. Its name is only used
+/// as a single directory within it, so names such as `../x` can never point anywhere else.
+fn default_origin(remote: &RemoteBucket) -> PathBuf {
+    let name = remote
+        .name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '\0' => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let name = match name.as_str() {
+        "" | "." | ".." => remote.id.to_string(),
+        _ => name,
+    };
+    PathBuf::from(env!("HOME")).join("tomb").join(name)
+}
+
+impl Serialize for OmniBucket {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.info().serialize(serializer)
+    }
+}
+
+/// Everything known about a Bucket, in a form suited to machine readable output
+#[derive(Debug, Clone, Serialize)]
+pub struct DriveInfo {
+    /// Name of the Bucket
+    pub name: Option<String>,
+    /// Remote id, if there is one
+    pub drive_id: Option<Uuid>,
+    /// Local filesystem root, if the Bucket is tracked locally
+    pub origin: Option<PathBuf>,
+    /// Workflow the Bucket was created for
+    pub drive_type: Option<BucketType>,
+    /// Storage class of the remote data, as last known
+    pub storage_class: Option<StorageClass>,
+    /// Storage host holding the content, if there is one
+    pub storage_host: Option<String>,
+    /// Whether the Bucket is tracked locally
+    pub locally_tracked: bool,
+    /// Whether the Bucket is tracked remotely
+    pub remotely_tracked: bool,
+    /// Whether the Bucket is protected from modification
+    pub read_only: bool,
+    /// How the local and remote copies relate
+    pub sync_state: SyncState,
+}
+
+/// Which halves of a Bucket were deleted
+#[derive(Debug, Clone, Serialize)]
+pub struct Deletion {
+    /// Whether the local Bucket was deleted
+    pub deleted_locally: bool,
+    /// Whether the remote Bucket was deleted
+    pub deleted_remotely: bool,
+    /// What was removed along with the deleted halves
+    pub removed: DeletionReport,
+}
+
+impl Display for Deletion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\ndeleted locally:\t{}\ndeleted remotely:\t{}\n{}",
+            "<< BUCKET DELETION >>".blue(),
+            bool_colorized(self.deleted_locally),
+            bool_colorized(self.deleted_remotely),
+            self.removed.details()
+        ))
+    }
+}
+
+/// What deleting a Bucket would remove
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletionReport {
+    /// Name of the Bucket
+    pub name: Option<String>,
+    /// Bytes of prepared data stored locally, if the Bucket is tracked locally
+    pub local_size: Option<u64>,
+    /// Remote id, if the Bucket is tracked remotely
+    pub remote_id: Option<Uuid>,
+    /// Number of remote metadata versions, if they could be counted
+    pub metadata_versions: Option<usize>,
+    /// Number of remote snapshots, if they could be counted
+    pub snapshots: Option<usize>,
+}
+
+impl DeletionReport {
+    /// The data the report covers, one line each
+    pub fn details(&self) -> String {
+        let unknown = || format!("{}", "None".yellow());
+        format!(
+            "name:\t\t\t{}\nlocal_size:\t\t{}\ndrive_id:\t\t{}\nmetadata_versions:\t{}\nsnapshots:\t\t{}",
+            self.name.clone().unwrap_or_else(unknown),
+            self.local_size
+                .map(|size| ByteSize(size).to_string())
+                .unwrap_or_else(unknown),
+            self.remote_id
+                .map(|id| id.to_string())
+                .unwrap_or_else(unknown),
+            self.metadata_versions
+                .map(|count| count.to_string())
+                .unwrap_or_else(unknown),
+            self.snapshots
+                .map(|count| count.to_string())
+                .unwrap_or_else(unknown),
+        )
+    }
+}
+
+impl Display for DeletionReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\n{}\n{}",
+            "| DELETION DRY RUN |".yellow(),
+            self.details(),
+            "Nothing was deleted".blue()
+        ))
+    }
+}
+
+/// Where each known Bucket is tracked
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReconcileReport {
+    /// Buckets tracked both locally and remotely
+    pub linked: Vec<OmniBucket>,
+    /// Local Buckets which have never been published
+    pub local_only: Vec<OmniBucket>,
+    /// Local Buckets whose remote counterpart no longer exists
+    pub remote_deleted: Vec<OmniBucket>,
+    /// Remote Buckets with no local configuration
+    pub remote_only: Vec<OmniBucket>,
+}
+
+impl ReconcileReport {
+    /// Adopt every remote only Bucket into the local configuration, moving them all to `linked`
+    pub async fn adopt_remote_only(&mut self) -> Result<(), NativeError> {
+        for mut omni in std::mem::take(&mut self.remote_only) {
+            omni.adopt().await?;
+            self.linked.push(omni);
+        }
+        Ok(())
+    }
+}
+
+impl Display for ReconcileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sections = [
+            ("linked", &self.linked),
+            ("local only", &self.local_only),
+            ("remote deleted", &self.remote_deleted),
+            ("remote only", &self.remote_only),
+        ];
+        f.write_fmt(format_args!("{}", "| DRIVE RECONCILIATION |".yellow()))?;
+        for (label, drives) in sections {
+            f.write_fmt(format_args!("\n{label}:\t{}", drives.len()))?;
+            for drive in drives {
+                let info = drive.info();
+                f.write_fmt(format_args!(
+                    "\n  {}\t{}",
+                    info.name.unwrap_or_default(),
+                    info.drive_id
+                        .map(|id| id.to_string())
+                        .or(info.origin.map(|origin| origin.display().to_string()))
+                        .unwrap_or_default()
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Display for OmniBucket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut info = format!(
@@ -328,8 +772,11 @@ impl Display for OmniBucket {
             (Err(_), Err(_)) => {}
         }
 
+        let read_only = self.local.as_ref().is_some_and(|local| local.read_only)
+            || self.remote.as_ref().is_some_and(|remote| remote.read_only);
         f.write_fmt(format_args!(
-            "{info}\nsync_status:\t\t{}\n",
+            "{info}\nread_only:\t\t{}\nsync_status:\t\t{}\n",
+            bool_colorized(read_only),
             self.sync_state
         ))
     }
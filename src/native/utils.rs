@@ -1,4 +1,276 @@
+use chrono::Utc;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::{
+    cell::Cell,
+    fmt::Display,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+/// How progress through long running tasks is reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// Interactive progress bar
+    #[default]
+    Bar,
+    /// One JSON object per progress event written to stderr
+    Jsonl,
+    /// No progress is reported
+    Hidden,
+}
+
+/// How the results of commands are written out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Human readable prose
+    #[default]
+    Text,
+    /// A single JSON document written to stdout
+    Json,
+}
+
+/// File descriptors left free for everything other than reading files
+const OPEN_FILES_MARGIN: u64 = 32;
+/// Soft limit on open files assumed when it can not be read from the OS
+const FALLBACK_OPEN_FILES_LIMIT: u64 = 256;
+/// Upper bound on the default number of open files, for effectively unlimited soft limits
+const MAX_DEFAULT_OPEN_FILES: u64 = 4096;
+
+/// Progress mode chosen for this process
+static PROGRESS_MODE: OnceLock<ProgressMode> = OnceLock::new();
+/// Output mode chosen for this process
+static OUTPUT_MODE: OnceLock<OutputMode> = OnceLock::new();
+/// Whether a command of this process writes its own output to stdout
+static STDOUT_CLAIMED: AtomicBool = AtomicBool::new(false);
+/// Sequence number of the next progress event
+static PROGRESS_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Set the progress mode for this process. Only the first call has any effect.
+pub fn set_progress_mode(mode: ProgressMode) {
+    let _ = PROGRESS_MODE.set(mode);
+}
+
+/// Get the progress mode for this process
+pub fn progress_mode() -> ProgressMode {
+    PROGRESS_MODE.get().copied().unwrap_or_default()
+}
+
+/// Set the output mode for this process. Only the first call has any effect.
+pub fn set_output_mode(mode: OutputMode) {
+    let _ = OUTPUT_MODE.set(mode);
+}
+
+/// Get the output mode for this process
+pub fn output_mode() -> OutputMode {
+    OUTPUT_MODE.get().copied().unwrap_or_default()
+}
+
+/// Note that a command writes its own output to stdout, such as the content of a file or a
+/// stream of JSON lines, so that its result is reported on stderr rather than mixed into it
+pub fn claim_stdout() {
+    STDOUT_CLAIMED.store(true, Ordering::SeqCst);
+}
+
+/// Whether a command of this process writes its own output to stdout
+pub fn stdout_claimed() -> bool {
+    STDOUT_CLAIMED.load(Ordering::SeqCst)
+}
+
+/// Render the result of a command according to the output mode of this process
+pub fn render<T: Serialize + Display>(result: &T) -> String {
+    render_as(output_mode(), result)
+}
+
+/// Render the result of a command as prose or as JSON
+pub fn render_as<T: Serialize + Display>(mode: OutputMode, result: &T) -> String {
+    match mode {
+        OutputMode::Text => result.to_string(),
+        OutputMode::Json => serde_json::to_string_pretty(result)
+            .unwrap_or_else(|err| serde_json::json!({ "error": err.to_string() }).to_string()),
+    }
+}
+
+/// Decide whether output is colored for this process, disabling colors everywhere if `no_color`
+/// is set or the `NO_COLOR` environment variable is present and not empty. Returns whether or
+/// not colors remain enabled.
+pub fn configure_color(no_color: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color || no_color_env {
+        colored::control::set_override(false);
+        false
+    } else {
+        true
+    }
+}
+
+/// Default number of files which may be open at once, derived from the soft limit on open
+/// file descriptors minus a margin for the handles the process already holds
+pub fn default_max_open_files() -> usize {
+    let soft_limit = soft_open_files_limit().unwrap_or(FALLBACK_OPEN_FILES_LIMIT);
+    soft_limit
+        .saturating_sub(OPEN_FILES_MARGIN)
+        .clamp(1, MAX_DEFAULT_OPEN_FILES) as usize
+}
+
+/// Soft limit on the number of open file descriptors of this process
+#[cfg(unix)]
+fn soft_open_files_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes into the struct it is given
+    let result = unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+    (result == 0).then_some(limit.rlim_cur as u64)
+}
+
+/// Soft limit on the number of open file descriptors of this process
+#[cfg(not(unix))]
+fn soft_open_files_limit() -> Option<u64> {
+    None
+}
+
+/// A single step of progress through a task
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressEvent {
+    /// Monotonically increasing sequence number
+    pub seq: u64,
+    /// Milliseconds since the epoch
+    pub timestamp: i64,
+    /// Number of items processed so far
+    pub position: u64,
+    /// Total number of items in the task
+    pub total: u64,
+}
+
+impl ProgressEvent {
+    /// Create the next event in the sequence
+    pub fn next(position: u64, total: u64) -> Self {
+        Self {
+            seq: PROGRESS_SEQUENCE.fetch_add(1, Ordering::SeqCst),
+            timestamp: Utc::now().timestamp_millis(),
+            position,
+            total,
+        }
+    }
+}
+
+/// Reports progress through a task according to the progress mode
+#[derive(Debug)]
+pub enum Progress {
+    /// Interactive progress bar
+    Bar(ProgressBar),
+    /// JSON lines written to stderr
+    Jsonl {
+        /// Number of items processed so far
+        position: AtomicU64,
+        /// Total number of items in the task
+        total: u64,
+    },
+}
+
+impl Progress {
+    /// Advance the progress by `delta` items
+    pub fn inc(&self, delta: u64) {
+        match self {
+            Self::Bar(progress_bar) => progress_bar.inc(delta),
+            Self::Jsonl { position, total } => {
+                let position = position.fetch_add(delta, Ordering::SeqCst) + delta;
+                let event = ProgressEvent::next(position, *total);
+                if let Ok(line) = serde_json::to_string(&event) {
+                    // Write the whole line at once so events are never split
+                    let _ = writeln!(std::io::stderr().lock(), "{line}");
+                }
+            }
+        }
+    }
+}
+
+/// How far a prepare or restore has gotten through its files and their content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OperationProgress {
+    /// Number of files processed so far
+    pub files_done: u64,
+    /// Total number of files to process
+    pub files_total: u64,
+    /// Number of content bytes processed so far
+    pub bytes_done: u64,
+    /// Total number of content bytes to process
+    pub bytes_total: u64,
+}
+
+/// Callback notified each time a prepare or restore makes progress
+pub type ProgressCallback = Box<dyn Fn(OperationProgress) + Send>;
+
+/// Tracks progress through a prepare or restore, notifying an optional callback as it goes
+#[derive(Default)]
+pub struct ProgressTracker {
+    progress: Cell<OperationProgress>,
+    callback: Option<ProgressCallback>,
+}
+
+impl std::fmt::Debug for ProgressTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressTracker")
+            .field("progress", &self.progress.get())
+            .finish()
+    }
+}
+
+impl ProgressTracker {
+    /// Track progress, notifying `callback` of every change if there is one
+    pub fn new(callback: Option<ProgressCallback>) -> Self {
+        Self {
+            progress: Cell::new(OperationProgress::default()),
+            callback,
+        }
+    }
+
+    /// Set how many files and bytes there are to process, starting over from nothing done
+    pub fn start(&self, files_total: u64, bytes_total: u64) {
+        self.update(OperationProgress {
+            files_total,
+            bytes_total,
+            ..Default::default()
+        });
+    }
+
+    /// Record that `files` more files and `bytes` more content bytes were processed
+    pub fn advance(&self, files: u64, bytes: u64) {
+        let mut progress = self.progress.get();
+        progress.files_done += files;
+        progress.bytes_done += bytes;
+        self.update(progress);
+    }
+
+    /// The progress made so far
+    pub fn progress(&self) -> OperationProgress {
+        self.progress.get()
+    }
+
+    fn update(&self, progress: OperationProgress) {
+        self.progress.set(progress);
+        if let Some(callback) = &self.callback {
+            callback(progress);
+        }
+    }
+}
+
+/// Create a progress reporter for a task of `count` items using the progress mode
+pub fn get_progress(count: u64) -> Progress {
+    match progress_mode() {
+        ProgressMode::Bar => Progress::Bar(get_progress_bar(count)),
+        ProgressMode::Hidden => Progress::Bar(ProgressBar::hidden()),
+        ProgressMode::Jsonl => Progress::Jsonl {
+            position: AtomicU64::new(0),
+            total: count,
+        },
+    }
+}
 
 /// Create a progress bar for displaying progress through a task with a predetermined style
 pub fn get_progress_bar(count: u64) -> ProgressBar {
@@ -14,3 +286,44 @@ pub fn get_progress_bar(count: u64) -> ProgressBar {
     );
     progress_bar
 }
+
+#[cfg(test)]
+mod test {
+    use super::{OperationProgress, ProgressEvent, ProgressTracker};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn tracker_notifies_callback() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let tracker = ProgressTracker::new(Some(Box::new(move |progress| {
+            recorded.lock().expect("poisoned").push(progress)
+        })));
+        tracker.start(2, 10);
+        tracker.advance(1, 4);
+        tracker.advance(1, 6);
+        let events = events.lock().expect("poisoned");
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events.last(),
+            Some(&OperationProgress {
+                files_done: 2,
+                files_total: 2,
+                bytes_done: 10,
+                bytes_total: 10,
+            })
+        );
+        assert_eq!(tracker.progress(), events[2]);
+    }
+
+    #[test]
+    fn events_are_sequenced() {
+        let first = ProgressEvent::next(1, 2);
+        let second = ProgressEvent::next(2, 2);
+        assert!(second.seq > first.seq);
+        assert!(second.timestamp >= first.timestamp);
+        let json = serde_json::to_value(&second).expect("serialize");
+        assert_eq!(json["position"], 2);
+        assert_eq!(json["total"], 2);
+    }
+}
@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time, so that logic which depends on the time can be tested
+pub trait Clock {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Reads the time from the system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -13,6 +13,8 @@ pub use io::{get_read, get_read_write, get_write};
 pub use io::compute_directory_size;
 
 mod cast;
+mod clock;
+pub use clock::{Clock, SystemClock};
 pub mod varint;
 
 mod error;
@@ -0,0 +1,40 @@
+use crate::utils::Clock;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Mutex;
+
+/// A Clock which only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// Create a new MockClock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Move the time to `now`
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("mock clock poisoned") = now;
+    }
+
+    /// Move the time forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().expect("mock clock poisoned") += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("mock clock poisoned")
+    }
+}
@@ -1,6 +1,16 @@
-use crate::{native::operations::configure, utils::UtilityError};
+use crate::{
+    filesystem::FsMetadata,
+    native::{
+        configuration::globalconfig::GlobalConfig, operations::configure, sync::LocalBucket,
+        NativeError,
+    },
+    utils::UtilityError,
+};
 use fake_file::{utils::ensure_path_exists_and_is_empty_dir, Strategy, Structure};
-use std::{fs::remove_dir_all, path::PathBuf};
+use std::{
+    fs::{create_dir_all, remove_dir_all},
+    path::{Path, PathBuf},
+};
 
 /// Set up temporary filesystem for test cases
 pub async fn test_setup(test_name: &str) -> Result<PathBuf, UtilityError> {
@@ -8,6 +18,23 @@ pub async fn test_setup(test_name: &str) -> Result<PathBuf, UtilityError> {
     test_setup_structured(test_name, Structure::new(2, 2, 2000, Strategy::Simple)).await
 }
 
+/// Set up an empty Drive named `test_name` in a fresh temporary directory, with its empty
+/// filesystem already saved
+pub async fn test_setup_bucket(
+    test_name: &str,
+) -> Result<(GlobalConfig, LocalBucket, FsMetadata), NativeError> {
+    let origin = Path::new("test").join(test_name);
+    if origin.exists() {
+        remove_dir_all(&origin)?;
+    }
+    create_dir_all(&origin)?;
+    let mut global = GlobalConfig::from_disk().await?;
+    let mut local = global.get_or_init_bucket(test_name, &origin).await?;
+    let mut fs = local.unlock_fs(&global.wrapping_key().await?).await?;
+    local.save_fs(&mut fs).await?;
+    Ok((global, local, fs))
+}
+
 /// Set up a temporary filesystem for test cases according to specified structure
 pub async fn test_setup_structured(
     test_name: &str,
@@ -34,6 +61,15 @@ pub async fn test_setup_structured(
     Ok(input_path.clone())
 }
 
+/// Set up a temporary filesystem for test cases which also contains a sparse file of `size`
+/// bytes, taking up almost no space on disk no matter how large it is
+pub async fn test_setup_sparse(test_name: &str, size: u64) -> Result<PathBuf, UtilityError> {
+    let input_path =
+        test_setup_structured(test_name, Structure::new(1, 1, 2000, Strategy::Simple)).await?;
+    std::fs::File::create(input_path.join("sparse"))?.set_len(size)?;
+    Ok(input_path)
+}
+
 /// Remove contents of temporary dir
 pub async fn test_teardown(test_name: &str) -> Result<(), UtilityError> {
     Ok(std::fs::remove_dir_all(
@@ -0,0 +1,217 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A request received by a mock server
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    /// Request line and headers
+    pub head: String,
+    /// Raw body
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// The request line, e.g. `GET /api/v1/buckets HTTP/1.1`
+    pub fn request_line(&self) -> &str {
+        self.head.lines().next().unwrap_or_default()
+    }
+
+    /// Whether this request has the given method and a path starting with `path`
+    pub fn is(&self, method: &str, path: &str) -> bool {
+        let mut parts = self.request_line().split_whitespace();
+        parts.next() == Some(method) && parts.next().is_some_and(|p| p.starts_with(path))
+    }
+
+    /// The body as text
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    /// Whether the body contains some bytes
+    pub fn contains(&self, needle: &[u8]) -> bool {
+        self.body
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+}
+
+/// A response for a mock server to send
+#[derive(Debug, Clone)]
+pub(crate) struct MockResponse {
+    status: String,
+    body: Vec<u8>,
+    trickle: Option<Duration>,
+    stall: bool,
+}
+
+impl MockResponse {
+    /// Respond with a status, e.g. `404 Not Found`, and a body
+    pub fn new(status: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: status.to_string(),
+            body: body.into(),
+            trickle: None,
+            stall: false,
+        }
+    }
+
+    /// Respond with a `200 OK` and a body
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::new("200 OK", body)
+    }
+
+    /// Respond with a status and no body
+    pub fn empty(status: &str) -> Self {
+        Self::new(status, Vec::new())
+    }
+
+    /// Never respond, holding the connection open until the client gives up
+    pub fn stalled() -> Self {
+        Self {
+            stall: true,
+            ..Self::empty("200 OK")
+        }
+    }
+
+    /// Send the body a byte at a time, waiting `delay` before each
+    pub fn trickled(self, delay: Duration) -> Self {
+        Self {
+            trickle: Some(delay),
+            ..self
+        }
+    }
+
+    async fn write(&self, stream: &mut TcpStream) {
+        if self.stall {
+            let mut buffer = [0; 1024];
+            while let Ok(read) = stream.read(&mut buffer).await {
+                if read == 0 {
+                    break;
+                }
+            }
+            return;
+        }
+        let head = format!(
+            "HTTP/1.1 {}\r\nconnection: close\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+            self.status,
+            self.body.len()
+        );
+        let _ = stream.write_all(head.as_bytes()).await;
+        match self.trickle {
+            Some(delay) => {
+                for byte in &self.body {
+                    tokio::time::sleep(delay).await;
+                    let _ = stream.write_all(&[*byte]).await;
+                }
+            }
+            None => {
+                let _ = stream.write_all(&self.body).await;
+            }
+        }
+    }
+}
+
+/// An HTTP server on a free local port, bound before it serves so that its URL can be handed
+/// to the handler
+pub(crate) struct MockServer {
+    listener: TcpListener,
+    /// Base URL of the server, without a trailing slash
+    pub url: String,
+}
+
+impl MockServer {
+    /// Bind a free local port
+    pub async fn bind() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind");
+        let url = format!(
+            "http://{}",
+            listener.local_addr().expect("no local address")
+        );
+        Self { listener, url }
+    }
+
+    /// Answer every request with whatever `handler` returns for it, returning the URL
+    pub fn serve(
+        self,
+        handler: impl Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+    ) -> String {
+        let handler = Arc::new(handler);
+        let listener = self.listener;
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let request = read_request(&mut stream).await;
+                    handler(request).write(&mut stream).await;
+                });
+            }
+        });
+        self.url
+    }
+}
+
+/// Spawn an HTTP server answering every request with whatever `handler` returns for it,
+/// returning its URL
+pub(crate) async fn mock_server(
+    handler: impl Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+) -> String {
+    MockServer::bind().await.serve(handler)
+}
+
+/// Find a local address which nothing is listening on
+pub(crate) async fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind")
+        .local_addr()
+        .expect("no local address")
+}
+
+/// Read a whole HTTP request
+async fn read_request(stream: &mut TcpStream) -> MockRequest {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let read = stream.read(&mut buffer).await.unwrap_or(0);
+        request.extend_from_slice(&buffer[..read]);
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_string();
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            let chunked = head
+                .to_ascii_lowercase()
+                .contains("transfer-encoding: chunked");
+            let body = &request[end + 4..];
+            // Streamed bodies are complete once their last chunk arrives, and multipart ones once
+            // their closing boundary does
+            let complete = if chunked {
+                body.ends_with(b"0\r\n\r\n")
+            } else {
+                body.len() >= content_length || body.ends_with(b"--\r\n")
+            };
+            if complete || read == 0 {
+                return MockRequest {
+                    head,
+                    body: body.to_vec(),
+                };
+            }
+        }
+        if read == 0 {
+            return MockRequest {
+                head: String::new(),
+                body: Vec::new(),
+            };
+        }
+    }
+}
@@ -1,4 +1,8 @@
 #[allow(dead_code)]
 pub(crate) mod blockstores;
 #[allow(dead_code)]
+pub(crate) mod clock;
+#[allow(dead_code)]
 pub(crate) mod local_operations;
+#[allow(dead_code)]
+pub(crate) mod mock_server;
@@ -13,13 +13,14 @@ use js_sys::Array;
 use std::{
     convert::{From, TryFrom},
     str::FromStr,
+    time::Duration,
 };
 use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey, PublicKey};
 use tracing::{error, info};
 pub use types::{
-    to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError, WasmBucket, WasmBucketKey,
-    WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount, WasmNodeMetadata,
-    WasmSharedFile, WasmSnapshot,
+    to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError, WasmAccountInfo, WasmBucket,
+    WasmBucketKey, WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmFsTreeEntry,
+    WasmMount, WasmNodeMetadata, WasmNodeStat, WasmSharedFile, WasmSnapshot,
 };
 use uuid::Uuid;
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
@@ -27,6 +28,25 @@ use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
 /// Special Result type for WASM builds
 pub type TombResult<T> = Result<T, js_sys::Error>;
 
+/// How often a bucket key is checked on while waiting for it to be approved
+const KEY_APPROVAL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Find the bucket key with a given fingerprint among every bucket of the current account
+async fn find_bucket_key(client: &mut Client, fingerprint: &str) -> TombResult<Option<BucketKey>> {
+    let buckets = Bucket::read_all(client)
+        .await
+        .map_err(to_js_error_with_msg("read all buckets"))?;
+    for bucket in buckets {
+        let keys = BucketKey::read_all(bucket.id, client)
+            .await
+            .map_err(to_js_error_with_msg("read bucket keys"))?;
+        if let Some(key) = keys.into_iter().find(|key| key.fingerprint == fingerprint) {
+            return Ok(Some(key));
+        }
+    }
+    Ok(None)
+}
+
 /// Wrapper around a Client
 #[derive(Debug)]
 #[wasm_bindgen]
@@ -100,6 +120,15 @@ impl TombWasm {
             .map_err(to_js_error_with_msg("retrieve usage limit"))
     }
 
+    /// Get the identity, plan and usage of the current account at once
+    #[wasm_bindgen(js_name = getAccountInfo)]
+    pub async fn get_account_info(&mut self) -> TombResult<WasmAccountInfo> {
+        Account::read_current(self.client())
+            .await
+            .map(WasmAccountInfo::from)
+            .map_err(to_js_error_with_msg("retrieve account info"))
+    }
+
     /// List the buckets for the current account
     #[wasm_bindgen(js_name = listBuckets)]
     pub async fn list_buckets(&mut self) -> TombResult<Array> {
@@ -121,6 +150,7 @@ impl TombWasm {
     /// # Arguments
     ///
     /// * `bucket_id` - The id of the bucket to list snapshots for
+    /// * `since` - Optional RFC 3339 timestamp, only snapshots created after it are listed
     ///
     /// # Returns an array WasmSnapshots
     ///
@@ -135,16 +165,27 @@ impl TombWasm {
     /// ]
     /// ```
     #[wasm_bindgen(js_name = listBucketSnapshots)]
-    pub async fn list_bucket_snapshots(&mut self, bucket_id: String) -> TombResult<Array> {
+    pub async fn list_bucket_snapshots(
+        &mut self,
+        bucket_id: String,
+        since: Option<String>,
+    ) -> TombResult<Array> {
         info!("list_bucket_snapshots()");
         // Parse the bucket id
         let bucket_id =
             Uuid::parse_str(&bucket_id).map_err(to_wasm_error_with_msg("parse UUID"))?;
 
         // Call the API
-        let snapshots = Bucket::list_snapshots_by_bucket_id(self.client(), bucket_id)
-            .await
-            .map_err(to_wasm_error_with_msg("list snapshots for bucket"))?;
+        let snapshots = match since {
+            Some(since) => {
+                let since = chrono::DateTime::parse_from_rfc3339(&since)
+                    .map_err(to_wasm_error_with_msg("parse RFC 3339 timestamp"))?
+                    .timestamp();
+                Bucket::list_snapshots_by_bucket_id_since(self.client(), bucket_id, since).await
+            }
+            None => Bucket::list_snapshots_by_bucket_id(self.client(), bucket_id).await,
+        }
+        .map_err(to_wasm_error_with_msg("list snapshots for bucket"))?;
 
         // Convert the snapshots
         snapshots
@@ -335,6 +376,50 @@ impl TombWasm {
             .map_err(to_js_error_with_msg("end regwait"))
     }
 
+    /// Wait for the bucket key with a given fingerprint to be approved, checking on it every
+    /// second for up to `timeout_ms` milliseconds
+    /// # Arguments
+    /// * fingerprint - The fingerprint of the bucket key being waited on
+    /// * timeout_ms - How long to wait for approval before giving up
+    /// # Returns
+    /// The approved WasmBucketKey
+    #[wasm_bindgen(js_name = waitForKeyApproval)]
+    pub async fn wait_for_key_approval(
+        &mut self,
+        fingerprint: String,
+        timeout_ms: u32,
+    ) -> TombResult<WasmBucketKey> {
+        let deadline = js_sys::Date::now() + timeout_ms as f64;
+        // Bucket the key belongs to, once it has been found
+        let mut bucket_id = None;
+        loop {
+            let key = match bucket_id {
+                Some(bucket_id) => BucketKey::read_all(bucket_id, self.client())
+                    .await
+                    .map_err(to_js_error_with_msg("read bucket keys"))?
+                    .into_iter()
+                    .find(|key| key.fingerprint == fingerprint),
+                None => find_bucket_key(self.client(), &fingerprint).await?,
+            };
+            if let Some(key) = key {
+                if key.approved {
+                    return Ok(WasmBucketKey(key));
+                }
+                bucket_id = Some(key.bucket_id);
+            }
+
+            let remaining = deadline - js_sys::Date::now();
+            if remaining <= 0.0 {
+                return Err(TombWasmError::new(&format!(
+                    "bucket key {fingerprint} was not approved within {timeout_ms}ms"
+                ))
+                .into());
+            }
+            let delay = remaining.min(KEY_APPROVAL_POLL_INTERVAL.as_millis() as f64);
+            gloo_timers::future::sleep(Duration::from_millis(delay as u64)).await;
+        }
+    }
+
     /* Bucket Mounting interface */
 
     /// Mount a bucket as a File System that can be managed by the user
@@ -377,17 +462,16 @@ impl TombWasm {
             Ok(mut mount) => {
                 info!("mount()/{}/pulled mount, unlocking", &bucket_id);
 
-                // Unlock the mount
-                let unlock_result = mount.unlock(&key).await;
-
-                // TODO: This should be checking against a defined error type,
-                // but it's pretty safe to assume here that a failure here means the key just
-                // doesn't have access to the bucket.
-
-                // Check the result
-                match unlock_result {
+                // Unlock the mount. A key without access to the bucket still gets a locked
+                // mount, and `unlockFailure` on it tells the caller why it is locked.
+                match mount.unlock(&key).await {
                     Ok(_) => info!("mount()/{}/unlocked mount", &bucket_id),
-                    Err(_) => info!("mount()/{}/could not unlock mount", &bucket_id),
+                    Err(err) => match mount.unlock_failure() {
+                        Some(failure) => {
+                            info!("mount()/{}/could not unlock mount: {}", &bucket_id, failure)
+                        }
+                        None => error!("mount()/{}/could not unlock mount: {}", &bucket_id, err),
+                    },
                 };
 
                 mount
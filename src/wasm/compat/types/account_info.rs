@@ -0,0 +1,39 @@
+use crate::api::models::account::AccountInfo;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+pub struct WasmAccountInfo(pub(crate) AccountInfo);
+
+#[wasm_bindgen]
+impl WasmAccountInfo {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.0.id.to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn email(&self) -> Option<String> {
+        self.0.email.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tier(&self) -> Option<String> {
+        self.0.tier.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn usage(&self) -> f64 {
+        self.0.usage as f64
+    }
+
+    #[wasm_bindgen(getter = usageLimit)]
+    pub fn usage_limit(&self) -> f64 {
+        self.0.usage_limit as f64
+    }
+}
+
+impl From<AccountInfo> for WasmAccountInfo {
+    fn from(info: AccountInfo) -> Self {
+        Self(info)
+    }
+}
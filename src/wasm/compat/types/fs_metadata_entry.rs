@@ -1,13 +1,42 @@
 use crate::{
-    filesystem::{FsMetadataEntry, FsMetadataEntryType},
+    filesystem::{FsMetadataEntry, FsMetadataEntryType, FsTreeEntry},
     value,
     wasm::{TombWasmError, WasmNodeMetadata},
 };
-use js_sys::{Object, Reflect};
+use js_sys::{Array, Object, Reflect};
 use wasm_bindgen::prelude::{JsCast, JsValue};
 
 pub struct WasmFsMetadataEntry(pub(crate) FsMetadataEntry);
 
+/// WasmFsMetadataEntry along with the entries beneath it
+pub struct WasmFsTreeEntry(pub(crate) FsTreeEntry);
+
+impl From<FsTreeEntry> for WasmFsTreeEntry {
+    fn from(fs_tree_entry: FsTreeEntry) -> Self {
+        Self(fs_tree_entry)
+    }
+}
+
+impl TryFrom<WasmFsTreeEntry> for JsValue {
+    type Error = js_sys::Error;
+
+    fn try_from(tree_entry: WasmFsTreeEntry) -> Result<Self, Self::Error> {
+        let object = JsValue::try_from(WasmFsMetadataEntry(tree_entry.0.entry))?;
+
+        // Only directories which were descended into have children
+        if let Some(children) = tree_entry.0.children {
+            let children = children
+                .into_iter()
+                .map(|child| JsValue::try_from(WasmFsTreeEntry(child)))
+                .collect::<Result<Array, Self::Error>>()?;
+            Reflect::set(&object, &JsValue::from_str("children"), &children)
+                .map_err(|_| TombWasmError::new("children property on object"))?;
+        }
+
+        Ok(object)
+    }
+}
+
 impl From<FsMetadataEntry> for WasmFsMetadataEntry {
     fn from(fs_metadata_entry: FsMetadataEntry) -> Self {
         Self(fs_metadata_entry)
@@ -1,3 +1,4 @@
+mod account_info;
 mod bucket;
 mod bucket_key;
 mod bucket_metadata;
@@ -6,16 +7,19 @@ mod error;
 mod fs_metadata_entry;
 mod mount;
 mod node_metadata;
+mod node_stat;
 mod shared_file;
 mod snapshot;
 
+pub use account_info::WasmAccountInfo;
 pub use bucket::WasmBucket;
 pub use bucket_key::WasmBucketKey;
 pub use bucket_metadata::WasmBucketMetadata;
 pub use bucketmount::WasmBucketMount;
 pub use error::{to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError};
-pub use fs_metadata_entry::WasmFsMetadataEntry;
+pub use fs_metadata_entry::{WasmFsMetadataEntry, WasmFsTreeEntry};
 pub use mount::WasmMount;
 pub use node_metadata::WasmNodeMetadata;
+pub use node_stat::WasmNodeStat;
 pub use shared_file::WasmSharedFile;
 pub use snapshot::WasmSnapshot;
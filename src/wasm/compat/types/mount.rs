@@ -1,5 +1,5 @@
 use futures_util::StreamExt;
-use js_sys::{Array, ArrayBuffer, Uint8Array};
+use js_sys::{Array, ArrayBuffer, Object, Reflect, Uint8Array};
 use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use std::io::Cursor;
@@ -18,13 +18,13 @@ use crate::{
         requests::staging::upload::content::UploadContent,
     },
     blockstore::{BanyanApiBlockStore, CarV2MemoryBlockStore as BlockStore, RootedBlockStore},
-    filesystem::FsMetadata,
+    filesystem::{sharing::UnlockFailure, FsMetadata},
     prelude::{
         api::requests::core::buckets::metadata::push::PushMetadata, blockstore::DoubleSplitStore,
     },
     wasm::{
         to_wasm_error_with_msg, TombResult, TombWasmError, WasmBucket, WasmBucketMetadata,
-        WasmFsMetadataEntry, WasmSharedFile, WasmSnapshot,
+        WasmFsMetadataEntry, WasmFsTreeEntry, WasmNodeStat, WasmSharedFile, WasmSnapshot,
     },
 };
 
@@ -41,6 +41,8 @@ pub struct WasmMount {
     fs_metadata: Option<FsMetadata>,
 
     locked: bool,
+    /// Why the last attempt to unlock failed, if it did
+    unlock_failure: Option<UnlockFailure>,
     /// Whether or not a change requires a call to save
     dirty: bool,
     /// Whether or not data has been appended to the content blockstore
@@ -86,6 +88,7 @@ impl WasmMount {
             fs_metadata: Some(fs_metadata),
 
             locked: false,
+            unlock_failure: None,
             dirty: true,
             append: false,
 
@@ -142,6 +145,7 @@ impl WasmMount {
             bucket,
             metadata: Some(metadata.to_owned()),
             locked: true,
+            unlock_failure: None,
             dirty: false,
             append: false,
             deleted_block_cids: BTreeSet::new(),
@@ -273,6 +277,20 @@ impl WasmMount {
             metadata_cid.to_string(),
             data_size
         );
+        // Make sure the metadata does not expose the structure of the FileSystem
+        let plaintext = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .plaintext_names(
+                &self.metadata_blockstore,
+                &self.metadata_blockstore.get_data(),
+            )
+            .await
+            .map_err(to_wasm_error_with_msg("check metadata encryption"))?;
+        let metadata_encrypted = plaintext
+            .metadata_encryption()
+            .map_err(to_wasm_error_with_msg("check metadata encryption"))?;
         let (metadata, host, authorization) = Metadata::push(
             PushMetadata {
                 bucket_id: self.bucket.id,
@@ -287,6 +305,8 @@ impl WasmMount {
                     .share_manager
                     .public_fingerprints(),
                 deleted_block_cids: self.deleted_block_cids.clone(),
+                metadata_encrypted,
+                delta_base_cid: None,
                 metadata_stream: Cursor::new(self.metadata_blockstore.get_data()),
             },
             &mut self.client,
@@ -367,14 +387,19 @@ impl WasmMount {
         assert_eq!(metadata_cid.to_string(), metadata.metadata_cid);
         assert_eq!(root_cid.to_string(), metadata.root_cid);
 
-        // Now try unlocking the metadata
-        let fs_metadata = FsMetadata::unlock(key, &self.metadata_blockstore)
-            .await
-            .map_err(to_wasm_error_with_msg("unlock FsMetadata"))?;
+        // Now try unlocking the metadata, remembering why it failed if it did
+        let fs_metadata = match FsMetadata::unlock(key, &self.metadata_blockstore).await {
+            Ok(fs_metadata) => fs_metadata,
+            Err(err) => {
+                self.unlock_failure = err.unlock_failure();
+                return Err(to_wasm_error_with_msg("unlock FsMetadata")(err));
+            }
+        };
 
         info!("unlock()/{} - unlocked", self.bucket.id,);
 
         self.locked = false;
+        self.unlock_failure = None;
         self.fs_metadata = Some(fs_metadata);
 
         Ok(())
@@ -402,6 +427,19 @@ impl WasmMount {
         self.locked
     }
 
+    /// Returns whether or not the bucket is protected from modification
+    #[wasm_bindgen(js_name = readOnly)]
+    pub fn read_only(&self) -> bool {
+        self.bucket.read_only
+    }
+
+    /// Returns why the last attempt to unlock the bucket failed, if it did:
+    /// "wrong key", "no access" or "corrupt share manager"
+    #[wasm_bindgen(js_name = unlockFailure)]
+    pub fn unlock_failure(&self) -> Option<String> {
+        self.unlock_failure.map(|failure| failure.to_string())
+    }
+
     /// Returns the Bucket behind the mount
     pub fn bucket(&self) -> WasmBucket {
         WasmBucket::from(self.bucket.clone())
@@ -496,6 +534,210 @@ impl WasmMount {
             .collect()
     }
 
+    /// List one page of the contents of the bucket at a provided path, sorted by name. Only the
+    /// page is loaded, which keeps listing very large directories from exhausting memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path_segments` - The path to ls (as an Array)
+    /// * `offset` - How many entries to skip
+    /// * `limit` - The most entries to return
+    ///
+    /// # Returns
+    ///
+    /// An object in the form of:
+    ///
+    /// ```json
+    /// {
+    ///   "entries": [/* the same objects returned by ls */],
+    ///   "has_more": true
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * `Bucket is locked` - If the bucket is locked
+    #[wasm_bindgen(js_name = lsPaginated)]
+    pub async fn ls_paginated(
+        &mut self,
+        path_segments: Array,
+        offset: u32,
+        limit: u32,
+    ) -> TombResult<JsValue> {
+        // Read the array as a Vec<String>
+        let path_segments = path_segments
+            .iter()
+            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "ls_paginated()/{}/{} - offset {offset}, limit {limit}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(
+                TombWasmError::new("unable to list directory contents of a locked bucket").into(),
+            );
+        };
+
+        let (fs_metadata_entries, has_more) = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ls_paginated(
+                &path_segments,
+                offset as usize,
+                limit as usize,
+                &self.metadata_blockstore,
+            )
+            .await
+            .map_err(to_wasm_error_with_msg("list directory entries"))?;
+
+        let entries = fs_metadata_entries
+            .into_iter()
+            .map(|entry| JsValue::try_from(WasmFsMetadataEntry::from(entry)))
+            .collect::<Result<Array, js_sys::Error>>()
+            .map_err(|err| {
+                TombWasmError::new(&format!(
+                    "unable to convert directory entries to JS objects: {err:?}"
+                ))
+            })?;
+        let page = Object::new();
+        Reflect::set(&page, &JsValue::from_str("entries"), &entries)
+            .map_err(|_| TombWasmError::new("entries property on object"))?;
+        Reflect::set(
+            &page,
+            &JsValue::from_str("has_more"),
+            &JsValue::from_bool(has_more),
+        )
+        .map_err(|_| TombWasmError::new("has_more property on object"))?;
+        Ok(page.into())
+    }
+
+    /// List the contents of the bucket at a provided path along with everything beneath it
+    ///
+    /// # Arguments
+    ///
+    /// * `path_segments` - The path to start from (as an Array)
+    /// * `max_depth` - How many levels of directories to descend into. Directories past this
+    ///   depth have no `children`, and can be expanded later by calling `tree` on their path.
+    ///
+    /// # Returns
+    ///
+    /// The same objects returned by `ls`, where directories also have a `children` Array of
+    /// the same form
+    ///
+    /// # Errors
+    ///
+    /// * `Bucket is locked` - If the bucket is locked
+    pub async fn tree(
+        &mut self,
+        path_segments: Array,
+        max_depth: Option<u32>,
+    ) -> TombResult<Array> {
+        // Read the array as a Vec<String>
+        let path_segments = path_segments
+            .iter()
+            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "tree()/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(
+                TombWasmError::new("unable to list directory contents of a locked bucket").into(),
+            );
+        };
+
+        let tree = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .tree(
+                &path_segments,
+                max_depth.map(|depth| depth as usize),
+                &self.metadata_blockstore,
+            )
+            .await
+            .map_err(to_wasm_error_with_msg("list directory tree"))?;
+
+        tree.into_iter()
+            .map(|entry| {
+                JsValue::try_from(WasmFsTreeEntry::from(entry)).map_err(|err| {
+                    TombWasmError::new(&format!(
+                        "unable to convert directory tree to JS objects: {err:?}"
+                    ))
+                    .into()
+                })
+            })
+            .collect()
+    }
+
+    /// Describe a single file or directory in the bucket
+    ///
+    /// # Arguments
+    ///
+    /// * `path_segments` - The path to stat (as an Array)
+    /// * `recursive` - Report the total size of a directory's contents instead of its number of children
+    ///
+    /// # Returns
+    ///
+    /// An object in the form of:
+    ///
+    /// ```json
+    /// {
+    ///   "type": "(file | dir)",
+    ///   "size": 0,
+    ///   "modified": 0,
+    ///   "created": 0,
+    ///   "cid": "string",
+    ///   "content_compression": "string"
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `Could not stat` - If there is no node at the path
+    pub async fn stat(&mut self, path_segments: Array, recursive: bool) -> TombResult<JsValue> {
+        // Read the array as a Vec<String>
+        let path_segments = path_segments
+            .iter()
+            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "stat()/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(TombWasmError::new("unable to stat a node in a locked bucket").into());
+        };
+
+        let node_stat = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .stat(&path_segments, recursive, &self.metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("stat"))?;
+
+        JsValue::try_from(WasmNodeStat::from(node_stat)).map_err(|err| {
+            TombWasmError::new(&format!(
+                "unable to convert node stat to a JS object: {err:?}"
+            ))
+            .into()
+        })
+    }
+
     /// Mkdir
     /// # Arguments
     /// * `path_segments` - The path to mkdir (as an Array)
@@ -521,6 +763,9 @@ impl WasmMount {
         if self.locked() {
             panic!("Bucket is locked");
         };
+        if self.bucket.read_only {
+            return Err(TombWasmError::new("Bucket is read-only").into());
+        }
 
         info!(
             "mkdir()/{}/{} - mkdir",
@@ -591,6 +836,9 @@ impl WasmMount {
         if self.locked() {
             panic!("Bucket is locked");
         };
+        if self.bucket.read_only {
+            return Err(TombWasmError::new("Bucket is read-only").into());
+        }
 
         let content = Uint8Array::new(&content_buffer).to_vec();
 
@@ -723,6 +971,9 @@ impl WasmMount {
         if self.locked() {
             panic!("Bucket is locked");
         };
+        if self.bucket.read_only {
+            return Err(TombWasmError::new("Bucket is read-only").into());
+        }
 
         self.fs_metadata
             .as_mut()
@@ -776,6 +1027,9 @@ impl WasmMount {
         if self.locked() {
             panic!("Bucket is locked");
         };
+        if self.bucket.read_only {
+            return Err(TombWasmError::new("Bucket is read-only").into());
+        }
 
         let fs = self
             .fs_metadata
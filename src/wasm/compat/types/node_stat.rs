@@ -0,0 +1,52 @@
+use crate::{
+    filesystem::{FsMetadataEntryType, NodeStat},
+    value,
+};
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::JsValue;
+
+pub struct WasmNodeStat(pub(crate) NodeStat);
+
+impl From<NodeStat> for WasmNodeStat {
+    fn from(node_stat: NodeStat) -> Self {
+        Self(node_stat)
+    }
+}
+
+impl TryFrom<WasmNodeStat> for JsValue {
+    type Error = js_sys::Error;
+
+    fn try_from(node_stat: WasmNodeStat) -> Result<Self, Self::Error> {
+        let stat = node_stat.0;
+        let object = Object::new();
+
+        let kind = match stat.kind {
+            FsMetadataEntryType::File => "file",
+            FsMetadataEntryType::Dir => "dir",
+        };
+        Reflect::set(&object, &value!("type"), &value!(kind))?;
+        Reflect::set(&object, &value!("size"), &value!(stat.size as f64))?;
+
+        if let Some(modified) = stat.modified {
+            Reflect::set(&object, &value!("modified"), &value!(modified as f64))?;
+        }
+
+        if let Some(created) = stat.created {
+            Reflect::set(&object, &value!("created"), &value!(created as f64))?;
+        }
+
+        if let Some(cid) = stat.cid {
+            Reflect::set(&object, &value!("cid"), &value!(cid.to_string()))?;
+        }
+
+        if let Some(compression) = stat.content_compression {
+            Reflect::set(
+                &object,
+                &value!("content_compression"),
+                &value!(compression),
+            )?;
+        }
+
+        Ok(value!(object))
+    }
+}
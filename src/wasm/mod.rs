@@ -5,8 +5,8 @@ mod version;
 /// Expose all the compatibility types directly
 pub use compat::{
     to_wasm_error_with_msg, TombResult, TombWasm, TombWasmError, WasmBucket, WasmBucketKey,
-    WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount, WasmNodeMetadata,
-    WasmSharedFile, WasmSnapshot,
+    WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmFsTreeEntry, WasmMount,
+    WasmNodeMetadata, WasmNodeStat, WasmSharedFile, WasmSnapshot,
 };
 use std::sync::Once;
 use time::macros::format_description;
@@ -11,7 +11,7 @@ mod test {
                 WasmFsMetadataEntry,
             },
         },
-        js_sys::{Array, Uint8Array},
+        js_sys::{Array, Reflect, Uint8Array},
         std::convert::TryFrom,
         tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey},
         tracing::info,
@@ -324,6 +324,51 @@ mod test {
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn tree() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: tree()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        assert!(!mount.locked());
+
+        mount.mkdir(js_array(&["a", "b"]).into()).await?;
+        mount.mkdir(js_array(&["c"]).into()).await?;
+
+        // The full tree nests every entry beneath its directory
+        let tree = mount.tree(js_array(&[]).into(), None).await?;
+        assert_eq!(tree.length(), 2);
+        let a = tree.get(0);
+        assert_eq!(
+            WasmFsMetadataEntry::try_from(a.clone()).unwrap().name(),
+            "a"
+        );
+        let a_children: Array = Reflect::get(&a, &JsValue::from_str("children"))
+            .unwrap()
+            .into();
+        assert_eq!(a_children.length(), 1);
+        let b = a_children.get(0);
+        assert_eq!(
+            WasmFsMetadataEntry::try_from(b.clone()).unwrap().name(),
+            "b"
+        );
+        let b_children: Array = Reflect::get(&b, &JsValue::from_str("children"))
+            .unwrap()
+            .into();
+        assert_eq!(b_children.length(), 0);
+
+        // Directories past the maximum depth have no children
+        let shallow = mount.tree(js_array(&[]).into(), Some(0)).await?;
+        assert_eq!(shallow.length(), 2);
+        assert!(
+            Reflect::get(&shallow.get(0), &JsValue::from_str("children"))
+                .unwrap()
+                .is_undefined()
+        );
+        Ok(())
+    }
+
     #[wasm_bindgen_test]
     async fn mkdir_remount() -> TombResult<()> {
         let mut client = authenticated_client().await?;